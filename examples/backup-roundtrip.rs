@@ -0,0 +1,156 @@
+// Backup round trip
+//
+// Creates a temporary chunk store below /tmp, stores a few chunks as
+// dynamic index together with a manifest (the same on-disk layout a backup
+// creates), and reads the snapshot back, checking index checksum and
+// content. The chunk store is removed again afterwards.
+//
+// Needs neither a running server nor root privileges:
+//
+//   cargo run --example backup-roundtrip
+
+use std::convert::TryInto;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, format_err, Error};
+
+use proxmox_backup::backup::{
+    BackupDir, BackupManifest, BufferedDynamicReader, ChunkStore, CryptMode, DataBlob,
+    DataChunkBuilder, DynamicIndexReader, DynamicIndexWriter, IndexFile, ReadChunk,
+    MANIFEST_BLOB_NAME,
+};
+
+const ARCHIVE_NAME: &str = "data.didx";
+
+// read chunks directly from the chunk store
+struct StoreChunkReader {
+    store: Arc<ChunkStore>,
+}
+
+impl ReadChunk for StoreChunkReader {
+    fn read_raw_chunk(&self, digest: &[u8; 32]) -> Result<DataBlob, Error> {
+        let (path, _digest_str) = self.store.chunk_path(digest);
+        DataBlob::load_from_reader(&mut std::fs::File::open(path)?)
+    }
+
+    fn read_chunk(&self, digest: &[u8; 32]) -> Result<Vec<u8>, Error> {
+        self.read_raw_chunk(digest)?.decode(None, Some(digest))
+    }
+}
+
+// some chunks with distinct content, the last one is a duplicate
+fn test_chunks() -> Vec<Vec<u8>> {
+    let mut chunks = Vec::new();
+    for i in 0..3u8 {
+        chunks.push((0..256*1024u32).map(|n| (n as u8).wrapping_mul(i + 1)).collect());
+    }
+    chunks.push(chunks[0].clone());
+    chunks
+}
+
+fn backup(store: &Arc<ChunkStore>, snapshot: &BackupDir, chunks: &[Vec<u8>]) -> Result<(), Error> {
+
+    let snapshot_path = snapshot.relative_path();
+    std::fs::create_dir_all(store.relative_path(&snapshot_path))?;
+
+    let mut writer = DynamicIndexWriter::create(
+        Arc::clone(store),
+        &snapshot_path.join(ARCHIVE_NAME),
+        None,
+    )?;
+
+    let mut offset = 0;
+    for chunk in chunks {
+        let (blob, digest) = DataChunkBuilder::new(chunk).compress(true).build()?;
+        let (is_duplicate, _encoded_size) = store.insert_chunk(&blob, &digest)?;
+        println!(
+            "chunk {} ({} bytes){}",
+            proxmox::tools::digest_to_hex(&digest),
+            chunk.len(),
+            if is_duplicate { " - duplicate" } else { "" },
+        );
+        offset += chunk.len() as u64;
+        writer.add_chunk(offset, &digest)?;
+    }
+
+    let csum = writer.close()?;
+    println!("stored {} ({} bytes)", ARCHIVE_NAME, offset);
+
+    let mut manifest = BackupManifest::new(snapshot.clone());
+    manifest.add_file(ARCHIVE_NAME.to_string(), offset, csum, CryptMode::None)?;
+
+    let manifest = manifest.to_string(None)?;
+    let blob = DataBlob::encode(manifest.as_bytes(), None, true)?;
+    std::fs::write(
+        store.relative_path(&snapshot_path.join(MANIFEST_BLOB_NAME)),
+        blob.raw_data(),
+    )?;
+    println!("backup finished");
+
+    Ok(())
+}
+
+fn restore(store: &Arc<ChunkStore>, snapshot: &BackupDir) -> Result<Vec<u8>, Error> {
+
+    let snapshot_path = store.relative_path(&snapshot.relative_path());
+
+    let mut file = std::fs::File::open(snapshot_path.join(MANIFEST_BLOB_NAME))?;
+    let manifest: BackupManifest = DataBlob::load_from_reader(&mut file)?.try_into()?;
+
+    let index = DynamicIndexReader::open(&snapshot_path.join(ARCHIVE_NAME))?;
+    let (csum, size) = index.compute_csum();
+    manifest.verify_file(ARCHIVE_NAME, &csum, size)?;
+
+    let chunk_reader = StoreChunkReader { store: Arc::clone(store) };
+    let mut reader = BufferedDynamicReader::new(index, chunk_reader);
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    Ok(data)
+}
+
+fn run(path: &Path) -> Result<(), Error> {
+
+    let user = nix::unistd::User::from_uid(nix::unistd::Uid::current())?
+        .ok_or_else(|| format_err!("unable to lookup current user"))?;
+
+    let store = Arc::new(ChunkStore::create("roundtrip", path, user.uid, user.gid)?);
+    println!("created chunk store at {:?}", path);
+
+    let chunks = test_chunks();
+    let snapshot = BackupDir::new("host", "roundtrip", proxmox::tools::time::epoch_i64())?;
+
+    backup(&store, &snapshot, &chunks)?;
+
+    let data = restore(&store, &snapshot)?;
+    if data != chunks.concat() {
+        bail!("restored data does not match stored data");
+    }
+    println!("restored {} bytes, content matches", data.len());
+
+    Ok(())
+}
+
+fn main() {
+    let path = match nix::unistd::mkdtemp("/tmp/pbs-example-roundtrip-XXXXXX") {
+        Ok(path) => path,
+        Err(err) => {
+            eprintln!("ERROR: unable to create temporary directory - {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let result = run(&path);
+
+    if let Err(err) = std::fs::remove_dir_all(&path) {
+        eprintln!("unable to remove {:?} - {}", path, err);
+    }
+
+    if let Err(err) = result {
+        eprintln!("ERROR: {}", err);
+        std::process::exit(1);
+    }
+    println!("DONE");
+}
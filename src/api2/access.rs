@@ -327,7 +327,7 @@ pub fn list_permissions(
     path: Option<String>,
     rpcenv: &dyn RpcEnvironment,
 ) -> Result<HashMap<String, HashMap<String, bool>>, Error> {
-    let current_auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let current_auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let user_info = CachedUserInfo::new()?;
     let user_privs = user_info.lookup_privs(&current_auth_id, &["access"]);
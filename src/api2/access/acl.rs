@@ -99,7 +99,7 @@ pub fn read_acl(
     exact: bool,
     mut rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<AclListItem>, Error> {
-    let auth_id = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let user_info = CachedUserInfo::new()?;
 
@@ -176,7 +176,7 @@ pub fn update_acl(
     digest: Option<String>,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
-    let current_auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let current_auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let user_info = CachedUserInfo::new()?;
 
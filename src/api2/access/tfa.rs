@@ -7,7 +7,7 @@ use proxmox::api::{api, Permission, Router, RpcEnvironment};
 use proxmox::tools::tfa::totp::Totp;
 use proxmox::{http_bail, http_err};
 
-use crate::api2::types::{Authid, Userid, PASSWORD_SCHEMA};
+use crate::api2::types::{Authid, parse_auth_id_checked, Userid, PASSWORD_SCHEMA};
 use crate::config::acl::{PRIV_PERMISSIONS_MODIFY, PRIV_SYS_AUDIT};
 use crate::config::cached_user_info::CachedUserInfo;
 use crate::config::tfa::{TfaInfo, TfaUserData};
@@ -24,7 +24,7 @@ fn tfa_update_auth(
     password: Option<String>,
     must_exist: bool,
 ) -> Result<(), Error> {
-    let authid: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let authid: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     if authid.user() != Userid::root_userid() {
         let password = password.ok_or_else(|| http_err!(UNAUTHORIZED, "missing password"))?;
@@ -316,7 +316,7 @@ struct TfaUser {
 )]
 /// List user TFA configuration.
 fn list_tfa(rpcenv: &mut dyn RpcEnvironment) -> Result<Vec<TfaUser>, Error> {
-    let authid: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let authid: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let top_level_privs = user_info.lookup_privs(&authid, &["access", "users"]);
@@ -247,7 +247,7 @@ pub fn create_user(
 
     if let Some(password) = password {
         let user_info = CachedUserInfo::new()?;
-        let current_auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+        let current_auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
         if realm == "pam" && !user_info.is_superuser(&current_auth_id) {
             bail!("only superuser can edit pam credentials!");
         }
@@ -409,7 +409,7 @@ pub fn update_user(
 
     if let Some(password) = password {
         let user_info = CachedUserInfo::new()?;
-        let current_auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+        let current_auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
         let self_service = current_auth_id.user() == &userid;
         let target_realm = userid.realm();
         if !self_service && target_realm == "pam" && !user_info.is_superuser(&current_auth_id) {
@@ -66,9 +66,7 @@ fn check_backup_owner(
     owner: &Authid,
     auth_id: &Authid,
 ) -> Result<(), Error> {
-    let correct_owner = owner == auth_id
-        || (owner.is_token() && &Authid::from(owner.user().clone()) == auth_id);
-    if !correct_owner {
+    if !auth_id.owns(owner) {
         bail!("backup owner check failed ({} != {})", auth_id, owner);
     }
     Ok(())
@@ -154,7 +152,7 @@ pub fn list_groups(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<GroupListItem>, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
     let user_privs = user_info.lookup_privs(&auth_id, &["datastore", &store]);
 
@@ -260,7 +258,7 @@ pub fn list_snapshot_files(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<BackupContent>, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let datastore = DataStore::lookup_datastore(&store)?;
 
     let snapshot = BackupDir::new(backup_type, backup_id, backup_time)?;
@@ -308,7 +306,7 @@ pub fn delete_snapshot(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Value, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let snapshot = BackupDir::new(backup_type, backup_id, backup_time)?;
     let datastore = DataStore::lookup_datastore(&store)?;
@@ -360,7 +358,7 @@ pub fn list_snapshots (
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<SnapshotListItem>, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
     let user_privs = user_info.lookup_privs(&auth_id, &["datastore", &store]);
 
@@ -373,7 +371,7 @@ pub fn list_snapshots (
     let groups = match (backup_type, backup_id) {
         (Some(backup_type), Some(backup_id)) => {
             let mut groups = Vec::with_capacity(1);
-            groups.push(BackupGroup::new(backup_type, backup_id));
+            groups.push(BackupGroup::new(backup_type, backup_id)?);
             groups
         },
         (Some(backup_type), None) => {
@@ -563,7 +561,7 @@ pub fn status(
     let datastore = DataStore::lookup_datastore(&store)?;
     let storage = crate::tools::disks::disk_usage(&datastore.base_path())?;
     let (counts, gc_status) = if verbose {
-        let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+        let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
         let user_info = CachedUserInfo::new()?;
 
         let store_privs = user_info.lookup_privs(&auth_id, &["datastore", &store]);
@@ -630,7 +628,7 @@ pub fn verify(
 ) -> Result<Value, Error> {
     let datastore = DataStore::lookup_datastore(&store)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let worker_id;
 
     let mut backup_dir = None;
@@ -649,7 +647,7 @@ pub fn verify(
         }
         (Some(backup_type), Some(backup_id), None) => {
             worker_id = format!("{}:{}/{}", store, backup_type, backup_id);
-            let group = BackupGroup::new(backup_type, backup_id);
+            let group = BackupGroup::new(backup_type, backup_id)?;
 
             check_priv_or_backup_owner(&datastore, &group, &auth_id, PRIV_DATASTORE_VERIFY)?;
 
@@ -797,11 +795,11 @@ pub fn prune(
     let backup_type = tools::required_string_param(&param, "backup-type")?;
     let backup_id = tools::required_string_param(&param, "backup-id")?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let dry_run = param["dry-run"].as_bool().unwrap_or(false);
 
-    let group = BackupGroup::new(backup_type, backup_id);
+    let group = BackupGroup::new(backup_type, backup_id)?;
 
     let datastore = DataStore::lookup_datastore(&store)?;
 
@@ -922,7 +920,7 @@ pub fn start_garbage_collection(
 ) -> Result<Value, Error> {
 
     let datastore = DataStore::lookup_datastore(&store)?;
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let job =  Job::new("garbage_collection", &store)
         .map_err(|_| format_err!("garbage collection already running"))?;
@@ -983,7 +981,7 @@ pub fn get_datastore_list(
 
     let (config, _digest) = datastore::config()?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let mut list = Vec::new();
@@ -996,6 +994,9 @@ pub fn get_datastore_list(
                 DataStoreListItem {
                     store: store.clone(),
                     comment: data["comment"].as_str().map(String::from),
+                    total: None,
+                    used: None,
+                    avail: None,
                 }
             );
         }
@@ -1035,7 +1036,7 @@ pub fn download_file(
         let store = tools::required_string_param(&param, "store")?;
         let datastore = DataStore::lookup_datastore(store)?;
 
-        let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+        let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
         let file_name = tools::required_string_param(&param, "file-name")?.to_owned();
 
@@ -1105,7 +1106,7 @@ pub fn download_file_decoded(
         let store = tools::required_string_param(&param, "store")?;
         let datastore = DataStore::lookup_datastore(store)?;
 
-        let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+        let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
         let file_name = tools::required_string_param(&param, "file-name")?.to_owned();
 
@@ -1227,7 +1228,7 @@ pub fn upload_backup_log(
 
         let backup_dir = BackupDir::new(backup_type, backup_id, backup_time)?;
 
-        let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+        let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
         let owner = datastore.get_owner(backup_dir.group())?;
         check_backup_owner(&owner, &auth_id)?;
 
@@ -1296,7 +1297,7 @@ pub fn catalog(
 ) -> Result<Vec<ArchiveEntry>, Error> {
     let datastore = DataStore::lookup_datastore(&store)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let backup_dir = BackupDir::new(backup_type, backup_id, backup_time)?;
 
@@ -1366,7 +1367,7 @@ pub fn pxar_file_download(
         let store = tools::required_string_param(&param, "store")?;
         let datastore = DataStore::lookup_datastore(&store)?;
 
-        let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+        let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
         let filepath = tools::required_string_param(&param, "filepath")?.to_owned();
 
@@ -1525,7 +1526,7 @@ pub fn get_notes(
 ) -> Result<String, Error> {
     let datastore = DataStore::lookup_datastore(&store)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let backup_dir = BackupDir::new(backup_type, backup_id, backup_time)?;
 
     check_priv_or_backup_owner(&datastore, backup_dir.group(), &auth_id, PRIV_DATASTORE_AUDIT)?;
@@ -1576,7 +1577,7 @@ pub fn set_notes(
 ) -> Result<(), Error> {
     let datastore = DataStore::lookup_datastore(&store)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let backup_dir = BackupDir::new(backup_type, backup_id, backup_time)?;
 
     check_priv_or_backup_owner(&datastore, backup_dir.group(), &auth_id, PRIV_DATASTORE_MODIFY)?;
@@ -1621,9 +1622,9 @@ pub fn set_backup_owner(
 
     let datastore = DataStore::lookup_datastore(&store)?;
 
-    let backup_group = BackupGroup::new(backup_type, backup_id);
+    let backup_group = BackupGroup::new(backup_type, backup_id)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let user_info = CachedUserInfo::new()?;
 
@@ -13,6 +13,7 @@ use crate::{
             DATASTORE_SCHEMA,
             JOB_ID_SCHEMA,
             Authid,
+            parse_auth_id_checked,
         },
         pull::do_sync_job,
         config::sync::{
@@ -63,7 +64,7 @@ pub fn list_sync_jobs(
     mut rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<SyncJobStatus>, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, digest) = sync::config()?;
@@ -117,7 +118,7 @@ pub fn run_sync_job(
     _info: &ApiMethod,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<String, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, _digest) = sync::config()?;
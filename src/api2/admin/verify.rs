@@ -12,6 +12,7 @@ use crate::{
         DATASTORE_SCHEMA,
         JOB_ID_SCHEMA,
         Authid,
+        parse_auth_id_checked,
     },
     server::{
         do_verification_job,
@@ -61,7 +62,7 @@ pub fn list_verification_jobs(
     _param: Value,
     mut rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<VerificationJobStatus>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let required_privs = PRIV_DATASTORE_AUDIT | PRIV_DATASTORE_VERIFY;
@@ -119,7 +120,7 @@ pub fn run_verification_job(
     _info: &ApiMethod,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<String, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, _digest) = verify::config()?;
@@ -1,5 +1,7 @@
 //! Backup protocol (HTTP2 upgrade)
 
+use std::path::Path;
+
 use anyhow::{bail, format_err, Error};
 use futures::*;
 use hyper::header::{HeaderValue, UPGRADE};
@@ -7,7 +9,7 @@ use hyper::http::request::Parts;
 use hyper::{Body, Response, Request, StatusCode};
 use serde_json::{json, Value};
 
-use proxmox::{sortable, identity, list_subdirs_api_method};
+use proxmox::{http_err, sortable, identity, list_subdirs_api_method};
 use proxmox::api::{ApiResponseFuture, ApiHandler, ApiMethod, Router, RpcEnvironment, Permission};
 use proxmox::api::router::SubdirMap;
 use proxmox::api::schema::*;
@@ -41,6 +43,11 @@ pub const API_METHOD_UPGRADE_BACKUP: ApiMethod = ApiMethod::new(
             ("backup-time", false, &BACKUP_TIME_SCHEMA),
             ("debug", true, &BooleanSchema::new("Enable verbose debug logging.").schema()),
             ("benchmark", true, &BooleanSchema::new("Job is a benchmark (do not keep data).").schema()),
+            ("ignore-clock-skew", true, &BooleanSchema::new(
+                "Only warn (instead of failing) if the backup-time is too far ahead of the server clock.")
+             .default(false)
+             .schema()
+            ),
         ]),
     )
 ).access(
@@ -58,10 +65,11 @@ fn upgrade_to_backup_protocol(
 ) -> ApiResponseFuture {
 
 async move {
-    let debug = param["debug"].as_bool().unwrap_or(false);
-    let benchmark = param["benchmark"].as_bool().unwrap_or(false);
+    let debug = tools::optional_bool_param(&param, "debug")?.unwrap_or(false);
+    let benchmark = tools::optional_bool_param(&param, "benchmark")?.unwrap_or(false);
+    let ignore_clock_skew = tools::optional_bool_param(&param, "ignore-clock-skew")?.unwrap_or(false);
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let store = tools::required_string_param(&param, "store")?.to_owned();
 
@@ -70,10 +78,19 @@ async move {
 
     let datastore = DataStore::lookup_datastore(&store)?;
 
+    let (node_config, _digest) = crate::config::node::config()?;
+
     let backup_type = tools::required_string_param(&param, "backup-type")?;
     let backup_id = tools::required_string_param(&param, "backup-id")?;
     let backup_time = tools::required_integer_param(&param, "backup-time")?;
 
+    let clock_skew_warning = check_backup_time_skew(
+        backup_time,
+        proxmox::tools::time::epoch_i64(),
+        node_config.max_backup_time_skew(),
+        ignore_clock_skew,
+    )?;
+
     let protocols = parts
         .headers
         .get("UPGRADE")
@@ -92,7 +109,7 @@ async move {
 
     let env_type = rpcenv.env_type();
 
-    let backup_group = BackupGroup::new(backup_type, backup_id);
+    let backup_group = BackupGroup::new(backup_type, backup_id)?;
 
     let worker_type = if backup_type == "host" && backup_id == "benchmark" {
         if !benchmark {
@@ -110,10 +127,7 @@ async move {
     let (owner, _group_guard) = datastore.create_locked_backup_group(&backup_group, &auth_id)?;
 
     // permission check
-    let correct_owner = owner == auth_id
-        || (owner.is_token()
-            && Authid::from(owner.user().clone()) == auth_id);
-    if !correct_owner && worker_type != "benchmark" {
+    if !auth_id.owns(&owner) && worker_type != "benchmark" {
         // only the owner is allowed to create additional snapshots
         bail!("backup owner check failed ({} != {})", auth_id, owner);
     }
@@ -122,18 +136,10 @@ async move {
         let info = BackupInfo::last_backup(&datastore.base_path(), &backup_group, true).unwrap_or(None);
         if let Some(info) = info {
             let (manifest, _) = datastore.load_manifest(&info.backup_dir)?;
-            let verify = manifest.unprotected["verify_state"].clone();
-            match serde_json::from_value::<SnapshotVerifyState>(verify) {
-                Ok(verify) => {
-                    match verify.state {
-                        VerifyState::Ok => Some(info),
-                        VerifyState::Failed => None,
-                    }
-                },
-                Err(_) => {
-                    // no verify state found, treat as valid
-                    Some(info)
-                }
+            match manifest.verify_state() {
+                Some(verify) if verify.state == VerifyState::Failed => None,
+                // ok or no verify state found, treat as valid
+                _ => Some(info),
             }
         } else {
             None
@@ -167,6 +173,14 @@ async move {
 
         env.log(format!("starting new {} on datastore '{}': {:?}", worker_type, store, path));
 
+        if let Some(warning) = clock_skew_warning {
+            worker.warn(warning);
+        }
+
+        if let Ok(true) = tools::disks::is_network_filesystem(&env.datastore.base_path()) {
+            env.log("note: datastore is located on a network file system, locking (flock) may not be reliable");
+        }
+
         let service = H2Service::new(env.clone(), worker.clone(), &BACKUP_API_ROUTER, debug);
 
         let abort_future = worker.abort_future();
@@ -180,11 +194,7 @@ async move {
 
                 let mut http = hyper::server::conn::Http::new();
                 http.http2_only(true);
-                // increase window size: todo - find optiomal size
-                let window_size = 32*1024*1024; // max = (1 << 31) - 2
-                http.http2_initial_stream_window_size(window_size);
-                http.http2_initial_connection_window_size(window_size);
-                http.http2_max_frame_size(4*1024*1024);
+                node_config.setup_http2(&mut http);
 
                 let env3 = env2.clone();
                 http.serve_connection(conn, service)
@@ -218,7 +228,10 @@ async move {
             };
             if benchmark {
                 env.log("benchmark finished successfully");
-                tools::runtime::block_in_place(|| env.remove_backup())?;
+                cleanup_benchmark(
+                    || tools::runtime::block_in_place(|| env.remove_backup()),
+                    |msg| env.log(msg),
+                );
                 return Ok(());
             }
 
@@ -268,11 +281,44 @@ async move {
     }.boxed()
 }
 
+/// Check that 'backup_time' is not more than 'max_skew' seconds ahead of 'now'.
+///
+/// A client clock far in the future would make all later (correct) backups
+/// look older than the last one. Fails if the skew is exceeded, unless
+/// 'ignore' is set - then a warning message is returned instead.
+fn check_backup_time_skew(
+    backup_time: i64,
+    now: i64,
+    max_skew: u32,
+    ignore: bool,
+) -> Result<Option<String>, Error> {
+    let skew = backup_time - now;
+    if skew <= max_skew as i64 {
+        return Ok(None);
+    }
+
+    let msg = format!(
+        "backup-time is {} seconds ahead of the server clock (max. allowed skew {}s)",
+        skew,
+        max_skew,
+    );
+
+    if !ignore {
+        bail!("{} - check the client clock or use 'ignore-clock-skew'", msg);
+    }
+
+    Ok(Some(msg))
+}
+
 const BACKUP_API_SUBDIRS: SubdirMap = &[
     (
         "blob", &Router::new()
             .upload(&API_METHOD_UPLOAD_BLOB)
     ),
+    (
+        "checkpoint", &Router::new()
+            .post(&API_METHOD_WRITER_CHECKPOINT)
+    ),
     (
         "dynamic_chunk", &Router::new()
             .upload(&API_METHOD_UPLOAD_DYNAMIC_CHUNK)
@@ -308,6 +354,10 @@ const BACKUP_API_SUBDIRS: SubdirMap = &[
             .post(&API_METHOD_CREATE_FIXED_INDEX)
             .put(&API_METHOD_FIXED_APPEND)
     ),
+    (
+        "known_chunks", &Router::new()
+            .post(&API_METHOD_KNOWN_CHUNKS)
+    ),
     (
         "previous", &Router::new()
             .download(&API_METHOD_DOWNLOAD_PREVIOUS)
@@ -333,10 +383,30 @@ pub const API_METHOD_CREATE_DYNAMIC_INDEX: ApiMethod = ApiMethod::new(
         "Create dynamic chunk index file.",
         &sorted!([
             ("archive-name", false, &crate::api2::types::BACKUP_ARCHIVE_NAME_SCHEMA),
+            ("reuse-csum", true, &StringSchema::new("If set, compare last backup's \
+                csum and reuse index for incremental backup if it matches.").schema()),
         ]),
     )
 );
 
+/// Remove the data written by a benchmark run.
+///
+/// The benchmark itself already succeeded at this point, so a failing cleanup is only logged and
+/// does not fail the task. The leftover snapshot needs to be removed manually, its chunks are
+/// only freed by garbage collection after that.
+fn cleanup_benchmark<R, L>(remove: R, log: L)
+where
+    R: FnOnce() -> Result<(), Error>,
+    L: Fn(String),
+{
+    if let Err(err) = remove() {
+        log(format!(
+            "removing benchmark data failed: {} - please remove the benchmark snapshot manually",
+            err,
+        ));
+    }
+}
+
 fn create_dynamic_index(
     param: Value,
     _info: &ApiMethod,
@@ -346,6 +416,7 @@ fn create_dynamic_index(
     let env: &BackupEnvironment = rpcenv.as_ref();
 
     let name = tools::required_string_param(&param, "archive-name")?.to_owned();
+    let reuse_csum = param["reuse-csum"].as_str();
 
     let archive_name = name.clone();
     if !archive_name.ends_with(".didx") {
@@ -353,10 +424,56 @@ fn create_dynamic_index(
     }
 
     let mut path = env.backup_dir.relative_path();
-    path.push(archive_name);
+    path.push(&archive_name);
 
-    let index = env.datastore.create_dynamic_writer(&path)?;
-    let wid = env.register_dynamic_writer(index, name)?;
+    // do incremental backup if csum is set
+    let mut reader = None;
+    if let Some(csum) = reuse_csum {
+        let last_backup = match &env.last_backup {
+            Some(info) => info,
+            None => {
+                bail!("cannot reuse index - no valid previous backup exists");
+            }
+        };
+
+        let mut last_path = last_backup.backup_dir.relative_path();
+        last_path.push(&archive_name);
+
+        let index = match env.datastore.open_dynamic_reader(last_path) {
+            Ok(index) => index,
+            Err(_) => {
+                bail!("cannot reuse index - no previous backup exists for archive");
+            }
+        };
+
+        let (old_csum, _) = index.compute_csum();
+        let old_csum = proxmox::tools::digest_to_hex(&old_csum);
+        if old_csum != csum {
+            bail!("expected csum ({}) doesn't match last backup's ({}), cannot do incremental backup",
+                csum, old_csum);
+        }
+
+        reader = Some(index);
+    }
+
+    let mut index = env.datastore.create_dynamic_writer(&path)?;
+
+    if let Some(reader) = reader {
+        // allow appends to reference the reused chunks
+        env.register_previous_index(&reader)?;
+        let (chunk_count, offset) = index.clone_data_from(&reader)?;
+
+        let wid = env.register_dynamic_writer(index, name, offset, true)?;
+
+        env.log(format!(
+            "created new incremental dynamic index {} ({:?}), reusing {} chunks ({} bytes)",
+            wid, path, chunk_count, offset,
+        ));
+
+        return Ok(json!(wid));
+    }
+
+    let wid = env.register_dynamic_writer(index, name, 0, false)?;
 
     env.log(format!("created new dynamic index {} ({:?})", wid, path));
 
@@ -512,6 +629,50 @@ fn dynamic_append (
     Ok(Value::Null)
 }
 
+#[sortable]
+pub const API_METHOD_KNOWN_CHUNKS: ApiMethod = ApiMethod::new(
+    &ApiHandler::Sync(&known_chunks),
+    &ObjectSchema::new(
+        "Return the chunks of the given list which are already known to this backup session. \
+         Unknown digests are simply omitted from the returned list.",
+        &sorted!([
+            (
+                "digest-list",
+                false,
+                &ArraySchema::new("Chunk digest list.", &CHUNK_DIGEST_SCHEMA)
+                    .max_length(KNOWN_CHUNKS_MAX_DIGESTS)
+                    .schema()
+            ),
+        ]),
+    )
+);
+
+// Only reports chunks known to this session (uploaded, or registered from the
+// previous snapshot), so clients cannot probe for chunks of other backups.
+fn known_chunks (
+    param: Value,
+    _info: &ApiMethod,
+    rpcenv: &mut dyn RpcEnvironment,
+) -> Result<Value, Error> {
+
+    let digest_list = tools::required_array_param(&param, "digest-list")?;
+
+    let env: &BackupEnvironment = rpcenv.as_ref();
+
+    let mut known = Vec::new();
+    for item in digest_list {
+        let digest_str = item.as_str().unwrap();
+        let digest = proxmox::tools::hex_to_digest(digest_str)?;
+        if env.lookup_chunk(&digest).is_some() {
+            known.push(digest_str.to_string());
+        }
+    }
+
+    env.debug(format!("known_chunks: {} of {} chunks known", known.len(), digest_list.len()));
+
+    Ok(json!(known))
+}
+
 #[sortable]
 pub const API_METHOD_FIXED_APPEND: ApiMethod = ApiMethod::new(
     &ApiHandler::Sync(&fixed_append),
@@ -617,8 +778,8 @@ fn close_dynamic_index (
 ) -> Result<Value, Error> {
 
     let wid = tools::required_integer_param(&param, "wid")? as usize;
-    let chunk_count = tools::required_integer_param(&param, "chunk-count")? as u64;
-    let size = tools::required_integer_param(&param, "size")? as u64;
+    let chunk_count = tools::required_u64_param(&param, "chunk-count")?;
+    let size = tools::required_u64_param(&param, "size")?;
     let csum_str = tools::required_string_param(&param, "csum")?;
     let csum = proxmox::tools::hex_to_digest(csum_str)?;
 
@@ -631,6 +792,53 @@ fn close_dynamic_index (
     Ok(Value::Null)
 }
 
+#[sortable]
+pub const API_METHOD_WRITER_CHECKPOINT: ApiMethod = ApiMethod::new(
+    &ApiHandler::Sync(&writer_checkpoint),
+    &ObjectSchema::new(
+        "Get chunk count and digest list checksum of an open index writer.",
+        &sorted!([
+            (
+                "wid",
+                false,
+                &IntegerSchema::new("Dynamic or fixed writer ID.")
+                    .minimum(1)
+                    .maximum(256)
+                    .schema()
+            ),
+            (
+                "csum",
+                true,
+                &StringSchema::new("Expected digest list checksum. Fails early on mismatch.").schema()
+            ),
+        ]),
+    )
+);
+
+fn writer_checkpoint (
+    param: Value,
+    _info: &ApiMethod,
+    rpcenv: &mut dyn RpcEnvironment,
+) -> Result<Value, Error> {
+
+    let wid = tools::required_integer_param(&param, "wid")? as usize;
+
+    let env: &BackupEnvironment = rpcenv.as_ref();
+
+    let (chunk_count, csum) = env.writer_checkpoint(wid)?;
+
+    if let Some(expected) = param["csum"].as_str() {
+        if proxmox::tools::hex_to_digest(expected)? != csum {
+            bail!("writer '{}' checkpoint failed - got unexpected checksum (chunk count {})", wid, chunk_count);
+        }
+    }
+
+    Ok(json!({
+        "chunk-count": chunk_count,
+        "csum": proxmox::tools::digest_to_hex(&csum),
+    }))
+}
+
 #[sortable]
 pub const API_METHOD_CLOSE_FIXED_INDEX: ApiMethod = ApiMethod::new(
     &ApiHandler::Sync(&close_fixed_index),
@@ -671,8 +879,8 @@ fn close_fixed_index (
 ) -> Result<Value, Error> {
 
     let wid = tools::required_integer_param(&param, "wid")? as usize;
-    let chunk_count = tools::required_integer_param(&param, "chunk-count")? as u64;
-    let size = tools::required_integer_param(&param, "size")? as u64;
+    let chunk_count = tools::required_u64_param(&param, "chunk-count")?;
+    let size = tools::required_u64_param(&param, "size")?;
     let csum_str = tools::required_string_param(&param, "csum")?;
     let csum = proxmox::tools::hex_to_digest(csum_str)?;
 
@@ -693,10 +901,10 @@ fn finish_backup (
 
     let env: &BackupEnvironment = rpcenv.as_ref();
 
-    env.finish_backup()?;
+    let dedup_stat = env.finish_backup()?;
     env.log("successfully finished backup");
 
-    Ok(Value::Null)
+    Ok(dedup_stat)
 }
 
 #[sortable]
@@ -750,8 +958,14 @@ fn download_previous(
             None => bail!("no valid previous backup"),
         };
 
-        let mut path = env.datastore.snapshot_path(&last_backup.backup_dir);
-        path.push(&archive_name);
+        let snapshot_path = env.datastore.snapshot_path(&last_backup.backup_dir);
+        let path = tools::fs::ensure_path_within(&snapshot_path, Path::new(&archive_name))
+            .map_err(|err| match err.downcast_ref::<std::io::Error>() {
+                Some(io_err) if io_err.kind() == std::io::ErrorKind::NotFound => {
+                    http_err!(NOT_FOUND, "archive '{}' not found in previous backup", archive_name)
+                }
+                _ => err,
+            })?;
 
         {
             let index: Option<Box<dyn IndexFile>> = match archive_type(&archive_name)? {
@@ -768,11 +982,11 @@ fn download_previous(
             if let Some(index) = index {
                 env.log(format!("register chunks in '{}' from previous backup.", archive_name));
 
-                for pos in 0..index.index_count() {
-                    let info = index.chunk_info(pos).unwrap();
-                    let size = info.range.end - info.range.start;
-                    env.register_chunk(info.digest, size as u32)?;
-                }
+                let (count, size) = env.register_previous_index(index.as_ref())?;
+                env.log(format!(
+                    "registered {} chunks ({} bytes) in '{}' from previous backup.",
+                    count, size, archive_name,
+                ));
             }
         }
 
@@ -780,3 +994,43 @@ fn download_previous(
         crate::api2::helpers::create_download_response(path).await
     }.boxed()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_cleanup_benchmark() {
+        let log = RefCell::new(Vec::new());
+        cleanup_benchmark(|| Ok(()), |msg| log.borrow_mut().push(msg));
+        assert!(log.borrow().is_empty());
+
+        // a failing cleanup must not be propagated, only logged
+        cleanup_benchmark(
+            || bail!("unable to remove directory"),
+            |msg| log.borrow_mut().push(msg),
+        );
+        let log = log.into_inner();
+        assert_eq!(log.len(), 1);
+        assert!(log[0].starts_with("removing benchmark data failed: unable to remove directory"));
+    }
+
+    #[test]
+    fn test_backup_time_skew() -> Result<(), Error> {
+        let now = 1_600_000_000;
+
+        assert_eq!(check_backup_time_skew(now - 3600, now, 300, false)?, None);
+        assert_eq!(check_backup_time_skew(now + 300, now, 300, false)?, None);
+
+        // a client clock one year in the future
+        let far_future = now + 365*24*3600;
+        let err = check_backup_time_skew(far_future, now, 300, false).unwrap_err();
+        assert!(err.to_string().contains("31536000 seconds ahead"));
+
+        let warning = check_backup_time_skew(far_future, now, 300, true)?;
+        assert!(warning.unwrap().contains("31536000 seconds ahead"));
+
+        Ok(())
+    }
+}
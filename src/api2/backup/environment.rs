@@ -1,13 +1,13 @@
 use anyhow::{bail, format_err, Error};
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use nix::dir::Dir;
 
 use ::serde::{Serialize};
 use serde_json::{json, Value};
 
 use proxmox::tools::digest_to_hex;
-use proxmox::tools::fs::{replace_file, CreateOptions};
+use proxmox::tools::fs::replace_file;
 use proxmox::api::{RpcEnvironment, RpcEnvironmentType};
 
 use crate::api2::types::Authid;
@@ -48,6 +48,18 @@ impl std::ops::Add for UploadStatistic {
     }
 }
 
+/// Deduplication statistics of all chunks appended to index writers
+///
+/// A chunk counts as new if it was uploaded (and not already present
+/// in the datastore) during this backup, everything else was reused.
+#[derive(Copy, Clone, Default, Serialize)]
+struct DedupStatistic {
+    new_chunks: u64,
+    new_bytes: u64,
+    reused_chunks: u64,
+    reused_bytes: u64,
+}
+
 struct DynamicWriterState {
     name: String,
     index: DynamicIndexWriter,
@@ -65,6 +77,7 @@ struct FixedWriterState {
     small_chunk_count: usize, // allow 0..1 small chunks (last chunk may be smaller)
     upload_stat: UploadStatistic,
     incremental: bool,
+    csum: Option<openssl::sha::Sha256>, // running checksum, only for sequential appends
 }
 
 // key=digest, value=length
@@ -77,8 +90,13 @@ struct SharedBackupState {
     dynamic_writers: HashMap<usize, DynamicWriterState>,
     fixed_writers: HashMap<usize, FixedWriterState>,
     known_chunks: KnownChunksMap,
+    previous_chunk_count: u64, // chunks registered from previous backup
+    reused_previous_index: bool, // an index of the previous backup was cloned
     backup_size: u64, // sums up size of all files
     backup_stat: UploadStatistic,
+    uploaded_chunks: HashSet<[u8; 32]>, // newly written, not yet appended
+    dedup_stat: DedupStatistic,
+    benchmark: bool, // do not record dedup statistics
 }
 
 impl SharedBackupState {
@@ -91,11 +109,90 @@ impl SharedBackupState {
         Ok(())
     }
 
+    // Remember a chunk written by this backup, so that appending it
+    // can be accounted as new data
+    fn record_uploaded_chunk(&mut self, digest: [u8; 32], is_duplicate: bool) {
+        if !self.benchmark && !is_duplicate {
+            self.uploaded_chunks.insert(digest);
+        }
+    }
+
+    // Update dedup statistics for a chunk appended to an index
+    fn record_appended_chunk(&mut self, digest: &[u8; 32], size: u32) {
+        if self.benchmark {
+            return;
+        }
+        // only the first reference to an uploaded chunk is new data
+        if self.uploaded_chunks.remove(digest) {
+            self.dedup_stat.new_chunks += 1;
+            self.dedup_stat.new_bytes += size as u64;
+        } else {
+            self.dedup_stat.reused_chunks += 1;
+            self.dedup_stat.reused_bytes += size as u64;
+        }
+    }
+
     // Get an unique integer ID
     pub fn next_uid(&mut self) -> usize {
         self.uid_counter += 1;
         self.uid_counter
     }
+
+    // Append a chunk to a dynamic writer, chunks need to be appended in order
+    fn append_dynamic_chunk(&mut self, wid: usize, offset: u64, size: u32, digest: &[u8; 32]) -> Result<(), Error> {
+        let mut data = match self.dynamic_writers.get_mut(&wid) {
+            Some(data) => data,
+            None => bail!("dynamic writer '{}' not registered", wid),
+        };
+
+        if data.offset != offset {
+            bail!("dynamic writer '{}' append chunk failed - got strange chunk offset ({} != {})",
+                  data.name, data.offset, offset);
+        }
+
+        data.offset += size as u64;
+        data.chunk_count += 1;
+
+        data.index.add_chunk(data.offset, digest)?;
+
+        self.record_appended_chunk(digest, size);
+
+        Ok(())
+    }
+
+    // Remove and close a dynamic writer, returns its state
+    //
+    // Like for fixed writers, 'chunk_count' only counts the chunks appended
+    // during this backup, while 'size' is the size of the whole index, which
+    // includes the data cloned from the previous backup for incremental writers.
+    fn close_dynamic_writer(
+        &mut self,
+        wid: usize,
+        chunk_count: u64,
+        size: u64,
+        csum: [u8; 32],
+    ) -> Result<DynamicWriterState, Error> {
+        let mut data = match self.dynamic_writers.remove(&wid) {
+            Some(data) => data,
+            None => bail!("dynamic writer '{}' not registered", wid),
+        };
+
+        if data.chunk_count != chunk_count {
+            bail!("dynamic writer '{}' close failed - unexpected chunk count ({} != {})", data.name, data.chunk_count, chunk_count);
+        }
+
+        if data.offset != size {
+            bail!("dynamic writer '{}' close failed - unexpected file size ({} != {})", data.name, data.offset, size);
+        }
+
+        let expected_csum = data.index.close()?;
+
+        if csum != expected_csum {
+            bail!("dynamic writer '{}' close failed - got unexpected checksum", data.name);
+        }
+
+        Ok(data)
+    }
 }
 
 
@@ -130,8 +227,14 @@ impl BackupEnvironment {
             dynamic_writers: HashMap::new(),
             fixed_writers: HashMap::new(),
             known_chunks: HashMap::new(),
+            previous_chunk_count: 0,
+            reused_previous_index: false,
             backup_size: 0,
             backup_stat: UploadStatistic::new(),
+            uploaded_chunks: HashSet::new(),
+            dedup_stat: DedupStatistic::default(),
+            // benchmark data is discarded, so dedup numbers are meaningless
+            benchmark: worker.upid().worker_type == "benchmark",
         };
 
         Self {
@@ -162,6 +265,26 @@ impl BackupEnvironment {
         Ok(())
     }
 
+    /// Register all chunks referenced by an index of the previous backup.
+    ///
+    /// Returns the number of registered chunks and their total size.
+    pub fn register_previous_index(&self, index: &dyn IndexFile) -> Result<(u64, u64), Error> {
+        let (count, size) = register_index_chunks(index, |digest, size| {
+            self.register_chunk(digest, size)
+        })?;
+
+        let mut state = self.state.lock().unwrap();
+        state.previous_chunk_count += count;
+
+        Ok((count, size))
+    }
+
+    /// Number of chunks registered from the previous backup so far.
+    pub fn previous_chunk_count(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.previous_chunk_count
+    }
+
     /// Register fixed length chunks after upload.
     ///
     /// Like `register_chunk()`, but additionally record statistics for
@@ -202,6 +325,7 @@ impl BackupEnvironment {
 
         // register chunk
         state.known_chunks.insert(digest, size);
+        state.record_uploaded_chunk(digest, is_duplicate);
 
         Ok(())
     }
@@ -235,6 +359,7 @@ impl BackupEnvironment {
 
         // register chunk
         state.known_chunks.insert(digest, size);
+        state.record_uploaded_chunk(digest, is_duplicate);
 
         Ok(())
     }
@@ -249,15 +374,22 @@ impl BackupEnvironment {
     }
 
     /// Store the writer with an unique ID
-    pub fn register_dynamic_writer(&self, index: DynamicIndexWriter, name: String) -> Result<usize, Error> {
+    ///
+    /// Incremental writers already contain the data of the previous backup,
+    /// so appending starts at 'offset'.
+    pub fn register_dynamic_writer(&self, index: DynamicIndexWriter, name: String, offset: u64, incremental: bool) -> Result<usize, Error> {
         let mut state = self.state.lock().unwrap();
 
         state.ensure_unfinished()?;
 
         let uid = state.next_uid();
 
+        if incremental {
+            state.reused_previous_index = true;
+        }
+
         state.dynamic_writers.insert(uid, DynamicWriterState {
-            index, name, offset: 0, chunk_count: 0, upload_stat: UploadStatistic::new(),
+            index, name, offset, chunk_count: 0, upload_stat: UploadStatistic::new(),
         });
 
         Ok(uid)
@@ -271,8 +403,14 @@ impl BackupEnvironment {
 
         let uid = state.next_uid();
 
+        if incremental {
+            state.reused_previous_index = true;
+        }
+
         state.fixed_writers.insert(uid, FixedWriterState {
             index, name, chunk_count: 0, size, chunk_size, small_chunk_count: 0, upload_stat: UploadStatistic::new(), incremental,
+            // incremental writers already contain digests we never see
+            csum: if incremental { None } else { Some(openssl::sha::Sha256::new()) },
         });
 
         Ok(uid)
@@ -284,23 +422,7 @@ impl BackupEnvironment {
 
         state.ensure_unfinished()?;
 
-        let mut data = match state.dynamic_writers.get_mut(&wid) {
-            Some(data) => data,
-            None => bail!("dynamic writer '{}' not registered", wid),
-        };
-
-
-        if data.offset != offset {
-            bail!("dynamic writer '{}' append chunk failed - got strange chunk offset ({} != {})",
-                  data.name, data.offset, offset);
-        }
-
-        data.offset += size as u64;
-        data.chunk_count += 1;
-
-        data.index.add_chunk(data.offset, digest)?;
-
-        Ok(())
+        state.append_dynamic_chunk(wid, offset, size, digest)
     }
 
     /// Append chunk to fixed writer
@@ -317,13 +439,56 @@ impl BackupEnvironment {
         let end = (offset as usize) + (size as usize);
         let idx = data.index.check_chunk_alignment(end, size as usize)?;
 
+        if (idx as u64) == data.chunk_count {
+            if let Some(ref mut csum) = data.csum {
+                csum.update(digest);
+            }
+        } else {
+            data.csum = None; // out of order, the final checksum is still verified on close
+        }
+
         data.chunk_count += 1;
 
         data.index.add_digest(idx, digest)?;
 
+        state.record_appended_chunk(digest, size);
+
         Ok(())
     }
 
+    /// Returns the chunk count and the checksum of the digests appended so far
+    ///
+    /// This allows clients to detect a corrupted digest list early, the
+    /// checksum passed on close is still verified.
+    pub fn writer_checkpoint(&self, wid: usize) -> Result<(u64, [u8; 32]), Error> {
+        let state = self.state.lock().unwrap();
+
+        state.ensure_unfinished()?;
+
+        if let Some(data) = state.dynamic_writers.get(&wid) {
+            return Ok((data.chunk_count, data.index.current_csum()?));
+        }
+
+        match state.fixed_writers.get(&wid) {
+            Some(data) => match data.csum {
+                Some(ref csum) => Ok((data.chunk_count, csum.clone().finish())),
+                None => bail!("fixed writer '{}' - no running checksum (incremental or out of order append)", data.name),
+            },
+            None => bail!("writer '{}' not registered", wid),
+        }
+    }
+
+    fn log_dedup_stat(&self, stat: &DedupStatistic) {
+        let total_bytes = stat.new_bytes + stat.reused_bytes;
+        self.log(format!(
+            "Deduplication: {} new chunks ({} bytes), {} reused chunks ({} bytes)",
+            stat.new_chunks, stat.new_bytes, stat.reused_chunks, stat.reused_bytes,
+        ));
+        if stat.new_bytes > 0 {
+            self.log(format!("Deduplication factor: {:.2}", (total_bytes as f64)/(stat.new_bytes as f64)));
+        }
+    }
+
     fn log_upload_stat(&self, archive_name:  &str, csum: &[u8; 32], uuid: &[u8; 16], size: u64, chunk_count: u64, upload_stat: &UploadStatistic) {
         self.log(format!("Upload statistics for '{}'", archive_name));
         self.log(format!("UUID: {}", digest_to_hex(uuid)));
@@ -357,33 +522,23 @@ impl BackupEnvironment {
     }
 
     /// Close dynamic writer
-    pub fn dynamic_writer_close(&self, wid: usize, chunk_count: u64, size: u64, csum: [u8; 32]) -> Result<(), Error> {
+    ///
+    /// 'chunk_count' is the number of chunks appended during this backup,
+    /// 'size' the size of the whole index (see `fixed_writer_close`).
+    pub fn dynamic_writer_close(
+        &self,
+        wid: usize,
+        chunk_count: u64,
+        size: u64,
+        csum: [u8; 32],
+    ) -> Result<(), Error> {
         let mut state = self.state.lock().unwrap();
 
         state.ensure_unfinished()?;
 
-        let mut data = match state.dynamic_writers.remove(&wid) {
-            Some(data) => data,
-            None => bail!("dynamic writer '{}' not registered", wid),
-        };
-
-        if data.chunk_count != chunk_count {
-            bail!("dynamic writer '{}' close failed - unexpected chunk count ({} != {})", data.name, data.chunk_count, chunk_count);
-        }
-
-        if data.offset != size {
-            bail!("dynamic writer '{}' close failed - unexpected file size ({} != {})", data.name, data.offset, size);
-        }
-
-        let uuid = data.index.uuid;
+        let data = state.close_dynamic_writer(wid, chunk_count, size, csum)?;
 
-        let expected_csum = data.index.close()?;
-
-        if csum != expected_csum {
-            bail!("dynamic writer '{}' close failed - got unexpected checksum", data.name);
-        }
-
-        self.log_upload_stat(&data.name, &csum, &uuid, size, chunk_count, &data.upload_stat);
+        self.log_upload_stat(&data.name, &csum, &data.index.uuid, size, chunk_count, &data.upload_stat);
 
         state.file_counter += 1;
         state.backup_size += size;
@@ -393,7 +548,17 @@ impl BackupEnvironment {
     }
 
     /// Close fixed writer
-    pub fn fixed_writer_close(&self, wid: usize, chunk_count: u64, size: u64, csum: [u8; 32]) -> Result<(), Error> {
+    ///
+    /// 'chunk_count' is the number of chunks appended during this backup,
+    /// which for incremental writers excludes the chunks of the previous
+    /// backup. 'size' is the size of the whole image.
+    pub fn fixed_writer_close(
+        &self,
+        wid: usize,
+        chunk_count: u64,
+        size: u64,
+        csum: [u8; 32],
+    ) -> Result<(), Error> {
         let mut state = self.state.lock().unwrap();
 
         state.ensure_unfinished()?;
@@ -426,6 +591,12 @@ impl BackupEnvironment {
             bail!("fixed writer '{}' close failed - got unexpected checksum", data.name);
         }
 
+        if let Some(running_csum) = data.csum.take() {
+            if chunk_count == (data.index.index_length() as u64) && running_csum.finish() != expected_csum {
+                bail!("fixed writer '{}' close failed - running checksum does not match index", data.name);
+            }
+        }
+
         self.log_upload_stat(&data.name, &expected_csum, &uuid, size, chunk_count, &data.upload_stat);
 
         state.file_counter += 1;
@@ -448,7 +619,7 @@ impl BackupEnvironment {
         let blob = DataBlob::load_from_reader(&mut &data[..])?;
 
         let raw_data = blob.raw_data();
-        replace_file(&path, raw_data, CreateOptions::new())?;
+        replace_file(&path, raw_data, self.datastore.file_create_options())?;
 
         self.log(format!("add blob {:?} ({} bytes, comp: {})", path, orig_len, blob_len));
 
@@ -461,7 +632,9 @@ impl BackupEnvironment {
     }
 
     /// Mark backup as finished
-    pub fn finish_backup(&self) -> Result<(), Error> {
+    ///
+    /// Returns the deduplication statistics of the appended chunks.
+    pub fn finish_backup(&self) -> Result<Value, Error> {
         let mut state = self.state.lock().unwrap();
 
         state.ensure_unfinished()?;
@@ -475,10 +648,21 @@ impl BackupEnvironment {
             bail!("backup does not contain valid files (file count == 0)");
         }
 
+        // record the snapshot this backup builds upon, if any
+        let base_snapshot = match &self.last_backup {
+            Some(base) if state.previous_chunk_count > 0 || state.reused_previous_index => {
+                Some(base.backup_dir.to_string())
+            }
+            _ => None,
+        };
+
         // check for valid manifest and store stats
         let stats = serde_json::to_value(state.backup_stat)?;
         self.datastore.update_manifest(&self.backup_dir, |manifest| {
             manifest.unprotected["chunk_upload_stats"] = stats;
+            if let Some(base_snapshot) = base_snapshot {
+                manifest.unprotected["base_snapshot"] = base_snapshot.into();
+            }
         }).map_err(|err| format_err!("unable to update manifest blob - {}", err))?;
 
         if let Some(base) = &self.last_backup {
@@ -494,7 +678,12 @@ impl BackupEnvironment {
         // marks the backup as successful
         state.finished = true;
 
-        Ok(())
+        let dedup = state.dedup_stat;
+        if !state.benchmark {
+            self.log_dedup_stat(&dedup);
+        }
+
+        Ok(serde_json::to_value(dedup)?)
     }
 
     /// If verify-new is set on the datastore, this will run a new verify task
@@ -617,3 +806,187 @@ impl AsRef<BackupEnvironment> for Box<dyn RpcEnvironment> {
         self.as_any().downcast_ref::<BackupEnvironment>().unwrap()
     }
 }
+
+// Call `register` for every chunk of `index`, returns the chunk count and total size.
+fn register_index_chunks<F>(index: &dyn IndexFile, mut register: F) -> Result<(u64, u64), Error>
+where
+    F: FnMut([u8; 32], u32) -> Result<(), Error>,
+{
+    let mut count = 0;
+    let mut size = 0;
+
+    for pos in 0..index.index_count() {
+        let info = index.chunk_info(pos).unwrap();
+        let chunk_size = info.size();
+        register(info.digest, chunk_size as u32)?;
+        count += 1;
+        size += chunk_size;
+    }
+
+    Ok((count, size))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct TestIndex {
+        chunks: Vec<([u8; 32], u64)>, // digest, end offset
+    }
+
+    impl IndexFile for TestIndex {
+        fn index_count(&self) -> usize {
+            self.chunks.len()
+        }
+
+        fn index_digest(&self, pos: usize) -> Option<&[u8; 32]> {
+            self.chunks.get(pos).map(|(digest, _)| digest)
+        }
+
+        fn index_bytes(&self) -> u64 {
+            self.chunks.last().map(|(_, end)| *end).unwrap_or(0)
+        }
+
+        fn chunk_info(&self, pos: usize) -> Option<ChunkReadInfo> {
+            let (digest, end) = self.chunks.get(pos)?;
+            let start = if pos == 0 { 0 } else { self.chunks[pos - 1].1 };
+            Some(ChunkReadInfo { range: start..*end, digest: *digest })
+        }
+
+        fn index_ctime(&self) -> i64 {
+            0
+        }
+
+        fn index_size(&self) -> usize {
+            self.chunks.len() * 40
+        }
+
+        fn chunk_from_offset(&self, _offset: u64) -> Option<(usize, u64)> {
+            None
+        }
+
+        fn compute_csum(&self) -> ([u8; 32], u64) {
+            ([0u8; 32], self.index_bytes())
+        }
+    }
+
+    #[test]
+    fn test_register_index_chunks() -> Result<(), Error> {
+        let index = TestIndex {
+            chunks: vec![([1u8; 32], 4096), ([2u8; 32], 6144), ([1u8; 32], 10240)],
+        };
+
+        let mut known_chunks = KnownChunksMap::new();
+        let (count, size) = register_index_chunks(&index, |digest, size| {
+            known_chunks.insert(digest, size);
+            Ok(())
+        })?;
+
+        assert_eq!(count, index.index_count() as u64);
+        assert_eq!(size, index.index_bytes());
+        assert_eq!(known_chunks.len(), 2);
+        assert_eq!(known_chunks.get(&[2u8; 32]), Some(&2048));
+
+        // errors from the register callback are propagated
+        assert!(register_index_chunks(&index, |_, _| bail!("finished")).is_err());
+
+        Ok(())
+    }
+
+    fn test_state(benchmark: bool) -> SharedBackupState {
+        SharedBackupState {
+            finished: false,
+            uid_counter: 0,
+            file_counter: 0,
+            dynamic_writers: HashMap::new(),
+            fixed_writers: HashMap::new(),
+            known_chunks: HashMap::new(),
+            previous_chunk_count: 0,
+            reused_previous_index: false,
+            backup_size: 0,
+            backup_stat: UploadStatistic::new(),
+            uploaded_chunks: HashSet::new(),
+            dedup_stat: DedupStatistic::default(),
+            benchmark,
+        }
+    }
+
+    #[test]
+    fn test_incremental_dynamic_writer_close() -> Result<(), Error> {
+        let path = nix::unistd::mkdtemp("/tmp/pbs-backup-env-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let user = nix::unistd::User::from_uid(nix::unistd::Uid::current())?.unwrap();
+            let store = Arc::new(ChunkStore::create("test", &path, user.uid, user.gid)?);
+
+            let chunks = [(100u64, [1u8; 32]), (250, [2u8; 32]), (300, [3u8; 32])];
+
+            // index of the previous backup with the first two chunks
+            let mut previous = DynamicIndexWriter::create(
+                Arc::clone(&store), std::path::Path::new("previous.didx"), None)?;
+            for (end, digest) in &chunks[..2] {
+                previous.add_chunk(*end, digest)?;
+            }
+            previous.close()?;
+            let previous = DynamicIndexReader::open(&path.join("previous.didx"))?;
+
+            let mut index = DynamicIndexWriter::create(
+                Arc::clone(&store), std::path::Path::new("current.didx"), None)?;
+            let (cloned_count, offset) = index.clone_data_from(&previous)?;
+            assert_eq!((cloned_count, offset), (2, 250));
+
+            let mut state = test_state(false);
+            let wid = state.next_uid();
+            state.dynamic_writers.insert(wid, DynamicWriterState {
+                index, name: "current.didx".into(), offset, chunk_count: 0, upload_stat: UploadStatistic::new(),
+            });
+
+            // appends continue after the cloned data
+            assert!(state.append_dynamic_chunk(wid, 0, 50, &chunks[2].1).is_err());
+            state.append_dynamic_chunk(wid, 250, 50, &chunks[2].1)?;
+
+            let mut csum = openssl::sha::Sha256::new();
+            for (end, digest) in &chunks {
+                csum.update(&end.to_le_bytes());
+                csum.update(digest);
+            }
+            let csum = csum.finish();
+
+            // only the appended chunk is counted, the size covers the whole index
+            state.close_dynamic_writer(wid, 1, 300, csum)?;
+
+            let index = DynamicIndexReader::open(&path.join("current.didx"))?;
+            assert_eq!(index.index_count(), 3);
+            assert_eq!(index.compute_csum(), (csum, 300));
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&path)?;
+
+        result
+    }
+
+    #[test]
+    fn test_dedup_statistic() {
+        let mut state = test_state(false);
+
+        state.record_uploaded_chunk([1u8; 32], false);
+        state.record_uploaded_chunk([2u8; 32], true); // already in datastore
+
+        state.record_appended_chunk(&[1u8; 32], 100);
+        state.record_appended_chunk(&[1u8; 32], 100); // second reference
+        state.record_appended_chunk(&[2u8; 32], 50);
+        state.record_appended_chunk(&[3u8; 32], 10); // from previous backup
+
+        assert_eq!(state.dedup_stat.new_chunks, 1);
+        assert_eq!(state.dedup_stat.new_bytes, 100);
+        assert_eq!(state.dedup_stat.reused_chunks, 3);
+        assert_eq!(state.dedup_stat.reused_bytes, 160);
+
+        let mut state = test_state(true);
+        state.record_uploaded_chunk([1u8; 32], false);
+        state.record_appended_chunk(&[1u8; 32], 100);
+        assert_eq!(state.dedup_stat.new_chunks + state.dedup_stat.reused_chunks, 0);
+    }
+}
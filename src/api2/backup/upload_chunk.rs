@@ -1,6 +1,7 @@
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use anyhow::{bail, format_err, Error};
 use futures::*;
@@ -14,13 +15,18 @@ use proxmox::api::schema::*;
 
 use crate::api2::types::*;
 use crate::backup::*;
+use crate::server::WorkerTask;
 use crate::tools;
 
 use super::environment::*;
 
+// paused backups get aborted after this time
+const PAUSE_TIMEOUT: Duration = Duration::from_secs(2*3600);
+
 pub struct UploadChunk {
     stream: Body,
     store: Arc<DataStore>,
+    worker: Arc<WorkerTask>,
     digest: [u8; 32],
     size: u32,
     encoded_size: u32,
@@ -28,8 +34,15 @@ pub struct UploadChunk {
 }
 
 impl UploadChunk {
-    pub fn new(stream: Body,  store: Arc<DataStore>, digest: [u8; 32], size: u32, encoded_size: u32) -> Self {
-        Self { stream, store, size, encoded_size, raw_data: Some(vec![]), digest }
+    pub fn new(
+        stream: Body,
+        store: Arc<DataStore>,
+        worker: Arc<WorkerTask>,
+        digest: [u8; 32],
+        size: u32,
+        encoded_size: u32,
+    ) -> Self {
+        Self { stream, store, worker, size, encoded_size, raw_data: Some(vec![]), digest }
     }
 }
 
@@ -67,7 +80,10 @@ impl Future for UploadChunk {
                                 // always comput CRC at server side
                                 chunk.set_crc(chunk.compute_crc());
 
-                                this.store.insert_chunk(&chunk, &this.digest)
+                                let worker = &this.worker;
+                                this.store.insert_chunk_with_retry(&chunk, &this.digest, |msg| {
+                                    worker.warn(format!("insert chunk failed - {}", msg));
+                                })
                             })
 
                         } {
@@ -130,8 +146,11 @@ fn upload_fixed_chunk(
 
         let env: &BackupEnvironment = rpcenv.as_ref();
 
+        // do not read new chunks while paused
+        env.worker.wait_resumed(PAUSE_TIMEOUT).await?;
+
         let (digest, size, compressed_size, is_duplicate) =
-            UploadChunk::new(req_body, env.datastore.clone(), digest, size, encoded_size).await?;
+            UploadChunk::new(req_body, env.datastore.clone(), env.worker.clone(), digest, size, encoded_size).await?;
 
         env.register_fixed_chunk(wid, digest, size, compressed_size, is_duplicate)?;
         let digest_str = proxmox::tools::digest_to_hex(&digest);
@@ -188,8 +207,11 @@ fn upload_dynamic_chunk(
 
         let env: &BackupEnvironment = rpcenv.as_ref();
 
+        // do not read new chunks while paused
+        env.worker.wait_resumed(PAUSE_TIMEOUT).await?;
+
         let (digest, size, compressed_size, is_duplicate) =
-            UploadChunk::new(req_body, env.datastore.clone(), digest, size, encoded_size)
+            UploadChunk::new(req_body, env.datastore.clone(), env.worker.clone(), digest, size, encoded_size)
             .await?;
 
         env.register_dynamic_chunk(wid, digest, size, compressed_size, is_duplicate)?;
@@ -21,6 +21,7 @@ use crate::{
     },
     api2::types::{
         Authid,
+        parse_auth_id_checked,
         PROXMOX_CONFIG_DIGEST_SCHEMA,
         CHANGER_NAME_SCHEMA,
         SCSI_CHANGER_PATH_SCHEMA,
@@ -147,7 +148,7 @@ pub fn list_changers(
     _param: Value,
     mut rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<ScsiTapeChanger>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, digest) = config::drive::config()?;
@@ -36,7 +36,7 @@ pub fn list_datastores(
 
     let (config, digest) = datastore::config()?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     rpcenv["digest"] = proxmox::tools::digest_to_hex(&digest).into();
@@ -109,6 +109,14 @@ pub fn list_datastores(
                 optional: true,
                 schema: PRUNE_SCHEMA_KEEP_YEARLY,
             },
+            "file-mode": {
+                optional: true,
+                schema: DATASTORE_FILE_MODE_SCHEMA,
+            },
+            "chunk-write-retries": {
+                optional: true,
+                schema: DATASTORE_CHUNK_WRITE_RETRIES_SCHEMA,
+            },
         },
     },
     access: {
@@ -199,6 +207,10 @@ pub enum DeletableProperty {
     notify_user,
     /// Delete the notify property
     notify,
+    /// Delete the file-mode property
+    file_mode,
+    /// Delete the chunk-write-retries property
+    chunk_write_retries,
 }
 
 #[api(
@@ -258,6 +270,14 @@ pub enum DeletableProperty {
                 optional: true,
                 default: false,
             },
+            "file-mode": {
+                optional: true,
+                schema: DATASTORE_FILE_MODE_SCHEMA,
+            },
+            "chunk-write-retries": {
+                optional: true,
+                schema: DATASTORE_CHUNK_WRITE_RETRIES_SCHEMA,
+            },
             delete: {
                 description: "List of properties to delete.",
                 type: Array,
@@ -292,6 +312,8 @@ pub fn update_datastore(
     verify_new: Option<bool>,
     notify: Option<String>,
     notify_user: Option<Userid>,
+    file_mode: Option<String>,
+    chunk_write_retries: Option<u32>,
     delete: Option<Vec<DeletableProperty>>,
     digest: Option<String>,
 ) -> Result<(), Error> {
@@ -323,6 +345,8 @@ pub fn update_datastore(
                 DeletableProperty::verify_new => { data.verify_new = None; },
                 DeletableProperty::notify => { data.notify = None; },
                 DeletableProperty::notify_user => { data.notify_user = None; },
+                DeletableProperty::file_mode => { data.file_mode = None; },
+                DeletableProperty::chunk_write_retries => { data.chunk_write_retries = None; },
             }
         }
     }
@@ -368,6 +392,10 @@ pub fn update_datastore(
 
     if notify_user.is_some() { data.notify_user = notify_user; }
 
+    if file_mode.is_some() { data.file_mode = file_mode; }
+
+    if chunk_write_retries.is_some() { data.chunk_write_retries = chunk_write_retries; }
+
     config.set_data(&name, "datastore", &data)?;
 
     datastore::save_config(&config)?;
@@ -1,5 +1,6 @@
 use anyhow::{bail, format_err, Error};
-use serde_json::Value;
+use futures::stream::StreamExt;
+use serde_json::{Map, Value};
 use ::serde::{Deserialize, Serialize};
 
 use proxmox::api::{api, ApiMethod, Router, RpcEnvironment, Permission};
@@ -32,7 +33,7 @@ pub fn list_remotes(
     _info: &ApiMethod,
     mut rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<remote::Remote>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, digest) = remote::config()?;
@@ -85,6 +86,14 @@ pub fn list_remotes(
                 optional: true,
                 schema: CERT_FINGERPRINT_SHA256_SCHEMA,
             },
+            timeout: {
+                optional: true,
+                schema: remote::REMOTE_TIMEOUT_SCHEMA,
+            },
+            "rate-limit": {
+                optional: true,
+                schema: remote::REMOTE_RATE_LIMIT_SCHEMA,
+            },
         },
     },
     access: {
@@ -150,6 +159,11 @@ pub enum DeletableProperty {
     fingerprint,
     /// Delete the port property.
     port,
+    /// Delete the timeout property.
+    timeout,
+    /// Delete the rate-limit property.
+    #[serde(rename = "rate-limit")]
+    rate_limit,
 }
 
 #[api(
@@ -184,6 +198,14 @@ pub enum DeletableProperty {
                 optional: true,
                 schema: CERT_FINGERPRINT_SHA256_SCHEMA,
             },
+            timeout: {
+                optional: true,
+                schema: remote::REMOTE_TIMEOUT_SCHEMA,
+            },
+            "rate-limit": {
+                optional: true,
+                schema: remote::REMOTE_RATE_LIMIT_SCHEMA,
+            },
             delete: {
                 description: "List of properties to delete.",
                 type: Array,
@@ -212,6 +234,8 @@ pub fn update_remote(
     auth_id: Option<Authid>,
     password: Option<String>,
     fingerprint: Option<String>,
+    timeout: Option<u64>,
+    rate_limit: Option<u64>,
     delete: Option<Vec<DeletableProperty>>,
     digest: Option<String>,
 ) -> Result<(), Error> {
@@ -233,6 +257,8 @@ pub fn update_remote(
                 DeletableProperty::comment => { data.comment = None; },
                 DeletableProperty::fingerprint => { data.fingerprint = None; },
                 DeletableProperty::port => { data.port = None; },
+                DeletableProperty::timeout => { data.timeout = None; },
+                DeletableProperty::rate_limit => { data.rate_limit = None; },
             }
         }
     }
@@ -247,6 +273,8 @@ pub fn update_remote(
     }
     if let Some(host) = host { data.host = host; }
     if port.is_some() { data.port = port; }
+    if timeout.is_some() { data.timeout = timeout; }
+    if rate_limit.is_some() { data.rate_limit = rate_limit; }
     if let Some(auth_id) = auth_id { data.auth_id = auth_id; }
     if let Some(password) = password { data.password = password; }
 
@@ -309,9 +337,15 @@ pub fn delete_remote(name: String, digest: Option<String>) -> Result<(), Error>
     Ok(())
 }
 
+fn remote_client_options(remote: &remote::Remote) -> HttpClientOptions {
+    HttpClientOptions::new_non_interactive(remote.password.clone(), remote.fingerprint.clone())
+        .timeout(remote.timeout.map(std::time::Duration::from_secs))
+        .rate_limit(remote.rate_limit)
+}
+
 /// Helper to get client for remote.cfg entry
 pub async fn remote_client(remote: remote::Remote) -> Result<HttpClient, Error> {
-    let options = HttpClientOptions::new_non_interactive(remote.password.clone(), remote.fingerprint.clone());
+    let options = remote_client_options(&remote);
 
     let client = HttpClient::new(
         &remote.host,
@@ -326,12 +360,81 @@ pub async fn remote_client(remote: remote::Remote) -> Result<HttpClient, Error>
 }
 
 
+/// Remote privileges needed to sync from or back up to a remote datastore.
+const REMOTE_DATASTORE_ACCESS_PRIVS: &[&str] = &["Datastore.Read", "Datastore.Backup"];
+
+// Check if a permission map (as returned by /access/permissions) grants access to
+// `store`. Entries for the datastore path itself already contain inherited privileges,
+// parent paths only count if the privilege is propagated.
+fn remote_datastore_accessible(permissions: &Map<String, Value>, store: &str) -> bool {
+    let has_priv = |path: &str, need_propagate: bool| -> bool {
+        match permissions.get(path).and_then(Value::as_object) {
+            Some(privs) => REMOTE_DATASTORE_ACCESS_PRIVS.iter().any(|name| {
+                match privs.get(*name).and_then(Value::as_bool) {
+                    Some(propagate) => propagate || !need_propagate,
+                    None => false,
+                }
+            }),
+            None => false,
+        }
+    };
+
+    let store_path = format!("/datastore/{}", store);
+    if permissions.contains_key(&store_path) {
+        return has_priv(&store_path, false);
+    }
+
+    has_priv("/datastore", true) || has_priv("/", true)
+}
+
+/// Filter a remote datastore list by the remote user's permissions.
+///
+/// If `permissions` is not a permission map (e.g. the remote does not expose privilege
+/// information), the list is returned unchanged.
+pub fn filter_accessible_datastores(
+    list: Vec<DataStoreListItem>,
+    permissions: &Value,
+) -> Vec<DataStoreListItem> {
+    let permissions = match permissions.as_object() {
+        Some(permissions) => permissions,
+        None => return list,
+    };
+
+    list.into_iter()
+        .filter(|item| remote_datastore_accessible(permissions, &item.store))
+        .collect()
+}
+
+/// Fill in the storage usage of a datastore list item from a remote datastore status.
+fn set_datastore_usage(item: &mut DataStoreListItem, status: &Value) {
+    if let Ok(status) = serde_json::from_value::<StorageStatus>(status.clone()) {
+        item.total = Some(status.total);
+        item.used = Some(status.used);
+        item.avail = Some(status.avail);
+    }
+}
+
+// number of parallel datastore status requests when scanning a remote
+const SCAN_USAGE_CONCURRENCY: usize = 4;
+
 #[api(
     input: {
         properties: {
             name: {
                 schema: REMOTE_ID_SCHEMA,
             },
+            accessible: {
+                description: "Only list datastores the remote credentials can read from or back up to.",
+                type: bool,
+                optional: true,
+                default: false,
+            },
+            usage: {
+                description: "Include storage usage of the datastores (if permitted on the remote).",
+                type: bool,
+                optional: true,
+                default: false,
+            },
         },
     },
     access: {
@@ -344,7 +447,11 @@ pub async fn remote_client(remote: remote::Remote) -> Result<HttpClient, Error>
     },
 )]
 /// List datastores of a remote.cfg entry
-pub async fn scan_remote_datastores(name: String) -> Result<Vec<DataStoreListItem>, Error> {
+pub async fn scan_remote_datastores(
+    name: String,
+    accessible: Option<bool>,
+    usage: Option<bool>,
+) -> Result<Vec<DataStoreListItem>, Error> {
     let (remote_config, _digest) = remote::config()?;
     let remote: remote::Remote = remote_config.lookup("remote", &name)?;
 
@@ -367,10 +474,47 @@ pub async fn scan_remote_datastores(name: String) -> Result<Vec<DataStoreListIte
         None => bail!("remote {} did not return any datastore list data", &name),
     };
 
-    match parse_res {
-        Ok(parsed) => Ok(parsed),
+    let mut list = match parse_res {
+        Ok(parsed) => parsed,
         Err(_) => bail!("Failed to parse remote scan api result."),
+    };
+
+    if accessible.unwrap_or(false) {
+        // older remotes may not provide this - do not filter then
+        match client.get("api2/json/access/permissions", None).await {
+            Ok(res) => list = filter_accessible_datastores(list, &res["data"]),
+            Err(err) => {
+                log::warn!("unable to query permissions on remote '{}' - {}", name, err);
+            }
+        }
     }
+
+    if usage.unwrap_or(false) {
+        let client = &client;
+        let results: Vec<_> = futures::stream::iter(list.iter().map(|item| item.store.clone()))
+            .map(|store| async move {
+                let path = format!("api2/json/admin/datastore/{}/status", store);
+                client.get(&path, None).await
+            })
+            .buffered(SCAN_USAGE_CONCURRENCY)
+            .collect()
+            .await;
+
+        for (item, result) in list.iter_mut().zip(results) {
+            match result {
+                Ok(res) => set_datastore_usage(item, &res["data"]),
+                // missing privileges or old remotes - simply leave the fields unset
+                Err(err) => log::warn!(
+                    "unable to query usage of datastore '{}' on remote '{}' - {}",
+                    item.store,
+                    name,
+                    err,
+                ),
+            }
+        }
+    }
+
+    Ok(list)
 }
 
 const SCAN_ROUTER: Router = Router::new()
@@ -386,3 +530,123 @@ pub const ROUTER: Router = Router::new()
     .get(&API_METHOD_LIST_REMOTES)
     .post(&API_METHOD_CREATE_REMOTE)
     .match_all("name", &ITEM_ROUTER);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn store_list(names: &[&str]) -> Vec<DataStoreListItem> {
+        names.iter()
+            .map(|name| DataStoreListItem {
+                store: name.to_string(),
+                comment: None,
+                total: None,
+                used: None,
+                avail: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_remote_client_timeout() -> Result<(), Error> {
+        // accepts connections (via the listen backlog), but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+
+        let remote = remote::Remote {
+            name: "unresponsive".to_string(),
+            comment: None,
+            host: "127.0.0.1".to_string(),
+            port: Some(port),
+            auth_id: Authid::root_auth_id().clone(),
+            password: "secret".to_string(),
+            fingerprint: None,
+            timeout: Some(1),
+            rate_limit: None,
+        };
+
+        let start = std::time::Instant::now();
+        let result = crate::tools::runtime::main(remote_client(remote));
+        assert!(result.is_err());
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+        drop(listener);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_client_rate_limit() -> Result<(), Error> {
+        let mut remote = remote::Remote {
+            name: "limited".to_string(),
+            comment: None,
+            host: "localhost".to_string(),
+            port: None,
+            auth_id: Authid::root_auth_id().clone(),
+            password: "secret".to_string(),
+            fingerprint: None,
+            timeout: None,
+            rate_limit: None,
+        };
+        assert_eq!(remote_client_options(&remote).get_rate_limit(), None);
+
+        remote.rate_limit = Some(1024 * 1024);
+        assert_eq!(remote_client_options(&remote).get_rate_limit(), Some(1024 * 1024));
+
+        // the schema rejects too small values
+        let schema = &remote::REMOTE_RATE_LIMIT_SCHEMA;
+        assert!(proxmox::api::schema::parse_simple_value("10", schema).is_err());
+        assert!(proxmox::api::schema::parse_simple_value("1048576", schema).is_ok());
+
+        Ok(())
+    }
+
+    fn store_names(list: &[DataStoreListItem]) -> Vec<&str> {
+        list.iter().map(|item| item.store.as_str()).collect()
+    }
+
+    #[test]
+    fn test_filter_accessible_datastores() {
+        let permissions = json!({
+            "/": { "Sys.Audit": true },
+            "/datastore": { "Datastore.Backup": false },
+            "/datastore/store1": { "Datastore.Read": false },
+            "/datastore/store2": { "Datastore.Audit": true },
+        });
+        let list = filter_accessible_datastores(
+            store_list(&["store1", "store2", "store3"]), &permissions);
+        assert_eq!(store_names(&list), vec!["store1"]);
+
+        // propagated privilege on a parent path
+        let permissions = json!({
+            "/datastore": { "Datastore.Backup": true },
+            "/datastore/store2": { "Datastore.Audit": true },
+        });
+        let list = filter_accessible_datastores(
+            store_list(&["store1", "store2", "store3"]), &permissions);
+        assert_eq!(store_names(&list), vec!["store1", "store3"]);
+
+        // no privilege info - do not filter
+        let list = filter_accessible_datastores(
+            store_list(&["store1", "store2"]), &Value::Null);
+        assert_eq!(store_names(&list), vec!["store1", "store2"]);
+    }
+
+    #[test]
+    fn test_set_datastore_usage() {
+        let mut list = store_list(&["store1", "store2"]);
+
+        let status = json!({
+            "total": 1000,
+            "used": 400,
+            "avail": 600,
+            "gc-status": { "upid": null },
+        });
+        set_datastore_usage(&mut list[0], &status);
+        assert_eq!((list[0].total, list[0].used, list[0].avail), (Some(1000), Some(400), Some(600)));
+
+        // remote without status information
+        set_datastore_usage(&mut list[1], &Value::Null);
+        assert_eq!((list[1].total, list[1].used, list[1].avail), (None, None, None));
+    }
+}
@@ -8,6 +8,7 @@ use proxmox::tools::fs::open_file_locked;
 use crate::{
     api2::types::{
         Authid,
+        parse_auth_id_checked,
         Userid,
         JOB_ID_SCHEMA,
         DATASTORE_SCHEMA,
@@ -26,6 +27,7 @@ use crate::{
         },
         tape_job::{
             TAPE_JOB_CFG_LOCKFILE,
+            TAPE_VERIFY_SAMPLE_RATE_SCHEMA,
             TapeBackupJobConfig,
         }
     },
@@ -50,7 +52,7 @@ pub fn list_tape_backup_jobs(
     _param: Value,
     mut rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<TapeBackupJobConfig>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, digest) = config::tape_job::config()?;
@@ -152,6 +154,8 @@ pub enum DeletableProperty {
     LatestOnly,
     /// Delete the 'notify-user' property
     NotifyUser,
+    /// Delete the 'verify-sample-rate' property
+    VerifySampleRate,
 }
 
 #[api(
@@ -192,6 +196,10 @@ pub enum DeletableProperty {
                 optional: true,
                 type: Userid,
             },
+            "verify-sample-rate": {
+                schema: TAPE_VERIFY_SAMPLE_RATE_SCHEMA,
+                optional: true,
+            },
             comment: {
                 optional: true,
                 schema: SINGLE_LINE_COMMENT_SCHEMA,
@@ -228,6 +236,7 @@ pub fn update_tape_backup_job(
     export_media_set: Option<bool>,
     latest_only: Option<bool>,
     notify_user: Option<Userid>,
+    verify_sample_rate: Option<u64>,
     comment: Option<String>,
     schedule: Option<String>,
     delete: Option<Vec<DeletableProperty>>,
@@ -251,6 +260,7 @@ pub fn update_tape_backup_job(
                 DeletableProperty::ExportMediaSet => { data.setup.export_media_set = None; },
                 DeletableProperty::LatestOnly => { data.setup.latest_only = None; },
                 DeletableProperty::NotifyUser => { data.setup.notify_user = None; },
+                DeletableProperty::VerifySampleRate => { data.setup.verify_sample_rate = None; },
                 DeletableProperty::Schedule => { data.schedule = None; },
                 DeletableProperty::Comment => { data.comment = None; },
             }
@@ -265,6 +275,7 @@ pub fn update_tape_backup_job(
     if export_media_set.is_some() { data.setup.export_media_set = export_media_set; }
     if latest_only.is_some() { data.setup.latest_only = latest_only; }
     if notify_user.is_some() { data.setup.notify_user = notify_user; }
+    if verify_sample_rate.is_some() { data.setup.verify_sample_rate = verify_sample_rate; }
 
     let schedule_changed = data.schedule != schedule;
     if schedule.is_some() { data.schedule = schedule; }
@@ -10,7 +10,7 @@ use crate::server::WorkerTask;
 use crate::tools::{apt, http::SimpleHttp, subscription};
 
 use crate::config::acl::{PRIV_SYS_AUDIT, PRIV_SYS_MODIFY};
-use crate::api2::types::{Authid, APTUpdateInfo, NODE_SCHEMA, UPID_SCHEMA};
+use crate::api2::types::{Authid, parse_auth_id_checked, APTLockInfo, APTUpdateInfo, NODE_SCHEMA, UPID_SCHEMA};
 
 #[api(
     input: {
@@ -46,7 +46,40 @@ fn apt_update_available(_param: Value) -> Result<Value, Error> {
     Ok(json!(cache.package_status))
 }
 
+#[api(
+    input: {
+        properties: {
+            node: {
+                schema: NODE_SCHEMA,
+            },
+        },
+    },
+    returns: {
+        description: "State of the APT/dpkg lock files.",
+        type: Array,
+        items: {
+            type: APTLockInfo,
+        },
+    },
+    access: {
+        permission: &Permission::Privilege(&[], PRIV_SYS_AUDIT, false),
+    },
+)]
+/// Query if APT/dpkg is locked, for example by a running update.
+fn apt_lock_status() -> Result<Vec<APTLockInfo>, Error> {
+    apt::apt_lock_status()
+}
+
 fn do_apt_update(worker: &WorkerTask, quiet: bool) -> Result<(), Error> {
+    for lock in apt::apt_lock_status()? {
+        if lock.locked {
+            match lock.pid {
+                Some(pid) => bail!("an update is already running - '{}' is locked by pid {}", lock.path, pid),
+                None => bail!("an update is already running - '{}' is locked", lock.path),
+            }
+        }
+    }
+
     if !quiet { worker.log("starting apt-get update") }
 
     // TODO: set proxy /etc/apt/apt.conf.d/76pbsproxy like PVE
@@ -112,7 +145,7 @@ pub fn apt_update_database(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<String, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let to_stdout = rpcenv.env_type() == RpcEnvironmentType::CLI;
 
     let upid_str = WorkerTask::new_thread("aptupdate", None, auth_id, to_stdout, move |worker| {
@@ -183,12 +216,13 @@ fn apt_get_changelog(
     let name = crate::tools::required_string_param(&param, "name")?.to_owned();
     let version = param["version"].as_str();
 
-    let pkg_info = apt::list_installed_apt_packages(|data| {
-        match version {
-            Some(version) => version == data.active_version,
-            None => data.active_version == data.candidate_version
-        }
-    }, Some(&name));
+    let pkg_info = match version {
+        Some(version) => apt::list_installed_apt_packages(
+            |data| version == data.active_version,
+            Some(&name),
+        ),
+        None => apt::list_installed_apt_packages(apt::by_name(&name), Some(&name)),
+    };
 
     if pkg_info.is_empty() {
         bail!("Package '{}' not found", name);
@@ -197,8 +231,23 @@ fn apt_get_changelog(
     let mut client = SimpleHttp::new(None); // TODO: pass proxy_config
 
     let changelog_url = &pkg_info[0].change_log_url;
-    // FIXME: use 'apt-get changelog' for proxmox packages as well, once repo supports it
-    if changelog_url.starts_with("http://download.proxmox.com/") {
+
+    // configured mirrors take precedence over the default hosts
+    let (node_config, _digest) = crate::config::node::config()?;
+    let mirror = if changelog_url.is_empty() {
+        None
+    } else {
+        node_config.changelog_mirror(&pkg_info[0].origin)?
+    };
+
+    if let Some(mirror) = mirror {
+        let url = apt::changelog_url_with_mirror(changelog_url, &mirror)?;
+        let changelog = crate::tools::runtime::block_on(client.get_string(&url, None))
+            .map_err(|err| format_err!("Error downloading changelog from '{}': {}", url, err))?;
+        Ok(json!(changelog))
+
+    } else if changelog_url.starts_with("http://download.proxmox.com/") {
+        // FIXME: use 'apt-get changelog' for proxmox packages as well, once repo supports it
         let changelog = crate::tools::runtime::block_on(client.get_string(changelog_url, None))
             .map_err(|err| format_err!("Error downloading changelog from '{}': {}", changelog_url, err))?;
         Ok(json!(changelog))
@@ -285,15 +334,18 @@ pub fn get_versions() -> Result<Vec<APTUpdateInfo>, Error> {
             section: "unknown".into(),
             change_log_url: "unknown".into(),
             extra_info,
+            download_size: None,
         }
     }
 
     let is_kernel = |name: &str| name.starts_with("pve-kernel-");
 
+    let installed = apt::installed_only();
+
     let mut packages: Vec<APTUpdateInfo> = Vec::new();
     let pbs_packages = apt::list_installed_apt_packages(
         |filter| {
-            filter.installed_version == Some(filter.active_version)
+            installed(filter)
                 && (is_kernel(filter.package) || PACKAGES.contains(&filter.package))
         },
         None,
@@ -354,6 +406,7 @@ pub fn get_versions() -> Result<Vec<APTUpdateInfo>, Error> {
 
 const SUBDIRS: SubdirMap = &[
     ("changelog", &Router::new().get(&API_METHOD_APT_GET_CHANGELOG)),
+    ("lock", &Router::new().get(&API_METHOD_APT_LOCK_STATUS)),
     ("update", &Router::new()
         .get(&API_METHOD_APT_UPDATE_AVAILABLE)
         .post(&API_METHOD_APT_UPDATE_DATABASE)
@@ -181,6 +181,10 @@ pub fn read_interface(iface: String) -> Result<Value, Error> {
                 maximum: 65535,
                 default: 1500,
             },
+            hwaddress: {
+                schema: MAC_ADDRESS_SCHEMA,
+                optional: true,
+            },
             bridge_ports: {
                 schema: NETWORK_INTERFACE_LIST_SCHEMA,
                 optional: true,
@@ -202,6 +206,10 @@ pub fn read_interface(iface: String) -> Result<Value, Error> {
                 type: BondXmitHashPolicy,
                 optional: true,
             },
+            bond_fail_over_mac: {
+                type: BondFailOverMac,
+                optional: true,
+            },
             slaves: {
                 schema: NETWORK_INTERFACE_LIST_SCHEMA,
                 optional: true,
@@ -226,11 +234,13 @@ pub fn create_interface(
     cidr6: Option<String>,
     gateway6: Option<String>,
     mtu: Option<u64>,
+    hwaddress: Option<String>,
     bridge_ports: Option<String>,
     bridge_vlan_aware: Option<bool>,
     bond_mode: Option<LinuxBondMode>,
     bond_primary: Option<String>,
     bond_xmit_hash_policy: Option<BondXmitHashPolicy>,
+    bond_fail_over_mac: Option<BondFailOverMac>,
     slaves: Option<String>,
     param: Value,
 ) -> Result<(), Error> {
@@ -253,6 +263,7 @@ pub fn create_interface(
     if method.is_some() { interface.method = method; }
     if method6.is_some() { interface.method6 = method6; }
     if mtu.is_some() { interface.mtu = mtu; }
+    if hwaddress.is_some() { interface.hwaddress = hwaddress; }
     if comments.is_some() { interface.comments = comments; }
     if comments6.is_some() { interface.comments6 = comments6; }
 
@@ -299,6 +310,12 @@ pub fn create_interface(
                     }
                     interface.bond_primary = bond_primary;
                 }
+                if bond_fail_over_mac.is_some() {
+                    if mode != LinuxBondMode::active_backup {
+                        bail!("bond_fail_over_mac is only valid with Active/Backup mode");
+                    }
+                    interface.bond_fail_over_mac = bond_fail_over_mac;
+                }
                 if bond_xmit_hash_policy.is_some() {
                     if mode != LinuxBondMode::ieee802_3ad &&
                        mode != LinuxBondMode::balance_xor
@@ -358,6 +375,8 @@ pub enum DeletableProperty {
     comments6,
     /// Delete mtu.
     mtu,
+    /// Delete hwaddress (MAC address override).
+    hwaddress,
     /// Delete autostart flag
     autostart,
     /// Delete bridge ports (set to 'none')
@@ -371,6 +390,8 @@ pub enum DeletableProperty {
     bond_primary,
     /// Delete bond transmit hash policy
     bond_xmit_hash_policy,
+    /// Delete bond fail_over_mac policy
+    bond_fail_over_mac,
 }
 
 
@@ -434,6 +455,10 @@ pub enum DeletableProperty {
                 maximum: 65535,
                 default: 1500,
             },
+            hwaddress: {
+                schema: MAC_ADDRESS_SCHEMA,
+                optional: true,
+            },
             bridge_ports: {
                 schema: NETWORK_INTERFACE_LIST_SCHEMA,
                 optional: true,
@@ -455,6 +480,10 @@ pub enum DeletableProperty {
                 type: BondXmitHashPolicy,
                 optional: true,
             },
+            bond_fail_over_mac: {
+                type: BondFailOverMac,
+                optional: true,
+            },
             slaves: {
                 schema: NETWORK_INTERFACE_LIST_SCHEMA,
                 optional: true,
@@ -491,11 +520,13 @@ pub fn update_interface(
     cidr6: Option<String>,
     gateway6: Option<String>,
     mtu: Option<u64>,
+    hwaddress: Option<String>,
     bridge_ports: Option<String>,
     bridge_vlan_aware: Option<bool>,
     bond_mode: Option<LinuxBondMode>,
     bond_primary: Option<String>,
     bond_xmit_hash_policy: Option<BondXmitHashPolicy>,
+    bond_fail_over_mac: Option<BondFailOverMac>,
     slaves: Option<String>,
     delete: Option<Vec<DeletableProperty>>,
     digest: Option<String>,
@@ -535,12 +566,14 @@ pub fn update_interface(
                 DeletableProperty::comments => { interface.comments = None; },
                 DeletableProperty::comments6 => { interface.comments6 = None; },
                 DeletableProperty::mtu => { interface.mtu = None; },
+                DeletableProperty::hwaddress => { interface.hwaddress = None; },
                 DeletableProperty::autostart => { interface.autostart = false; },
                 DeletableProperty::bridge_ports => { interface.set_bridge_ports(Vec::new())?; }
                 DeletableProperty::bridge_vlan_aware => { interface.bridge_vlan_aware = None; }
                 DeletableProperty::slaves => { interface.set_bond_slaves(Vec::new())?; }
                 DeletableProperty::bond_primary => { interface.bond_primary = None; }
                 DeletableProperty::bond_xmit_hash_policy => { interface.bond_xmit_hash_policy = None }
+                DeletableProperty::bond_fail_over_mac => { interface.bond_fail_over_mac = None }
             }
         }
     }
@@ -549,6 +582,7 @@ pub fn update_interface(
     if method.is_some() { interface.method = method; }
     if method6.is_some() { interface.method6 = method6; }
     if mtu.is_some() { interface.mtu = mtu; }
+    if hwaddress.is_some() { interface.hwaddress = hwaddress; }
     if let Some(ports) = bridge_ports {
         let ports = split_interface_list(&ports)?;
         interface.set_bridge_ports(ports)?;
@@ -566,6 +600,12 @@ pub fn update_interface(
             }
             interface.bond_primary = bond_primary;
         }
+        if bond_fail_over_mac.is_some() {
+            if mode != LinuxBondMode::active_backup {
+                bail!("bond_fail_over_mac is only valid with Active/Backup mode");
+            }
+            interface.bond_fail_over_mac = bond_fail_over_mac;
+        }
         if bond_xmit_hash_policy.is_some() {
             if mode != LinuxBondMode::ieee802_3ad &&
                mode != LinuxBondMode::balance_xor
@@ -654,6 +694,11 @@ pub fn delete_interface(iface: String, digest: Option<String>) -> Result<(), Err
 
     let _interface = config.lookup(&iface)?; // check if interface exists
 
+    let users = config.interfaces_using_port(&iface);
+    if !users.is_empty() {
+        bail!("interface '{}' is still used by: {}", iface, users.join(", "));
+    }
+
     config.interfaces.remove(&iface);
 
     network::save_config(&config)?;
@@ -681,7 +726,7 @@ pub async fn reload_network_config(
 
     network::assert_ifupdown2_installed()?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let upid_str = WorkerTask::spawn("srvreload", Some(String::from("networking")), auth_id, true, |_worker| async {
 
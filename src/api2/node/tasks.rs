@@ -103,8 +103,7 @@ fn check_job_store(upid: &UPID, store: &str) -> bool {
 
 fn check_task_access(auth_id: &Authid, upid: &UPID) -> Result<(), Error> {
     let task_auth_id = &upid.auth_id;
-    if auth_id == task_auth_id
-        || (task_auth_id.is_token() && &Authid::from(task_auth_id.user().clone()) == auth_id) {
+    if auth_id.owns(task_auth_id) {
         // task owner can always read
         Ok(())
     } else {
@@ -191,7 +190,7 @@ async fn get_task_status(
 
     let upid = extract_upid(&param)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     check_task_access(&auth_id, &upid)?;
 
     let mut result = json!({
@@ -268,11 +267,11 @@ async fn read_task_log(
 
     let upid = extract_upid(&param)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     check_task_access(&auth_id, &upid)?;
 
-    let test_status = param["test-status"].as_bool().unwrap_or(false);
+    let test_status = tools::optional_bool_param(&param, "test-status")?.unwrap_or(false);
 
     let start = param["start"].as_u64().unwrap_or(0);
     let mut limit = param["limit"].as_u64().unwrap_or(50);
@@ -338,7 +337,7 @@ fn stop_task(
 
     let upid = extract_upid(&param)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     if auth_id != upid.auth_id {
         let user_info = CachedUserInfo::new()?;
@@ -350,6 +349,80 @@ fn stop_task(
     Ok(Value::Null)
 }
 
+#[api(
+    protected: true,
+    input: {
+        properties: {
+            node: {
+                schema: NODE_SCHEMA,
+            },
+            upid: {
+                schema: UPID_SCHEMA,
+            },
+        },
+    },
+    access: {
+        description: "Users can pause there own tasks, or need Sys.Modify on /system/tasks.",
+        permission: &Permission::Anybody,
+    },
+)]
+/// Pause a running task (only supported by backup tasks).
+async fn pause_task(
+    param: Value,
+    rpcenv: &mut dyn RpcEnvironment,
+) -> Result<Value, Error> {
+
+    let upid = extract_upid(&param)?;
+
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
+
+    if auth_id != upid.auth_id {
+        let user_info = CachedUserInfo::new()?;
+        user_info.check_privs(&auth_id, &["system", "tasks"], PRIV_SYS_MODIFY, false)?;
+    }
+
+    server::pause_worker(upid).await?;
+
+    Ok(Value::Null)
+}
+
+#[api(
+    protected: true,
+    input: {
+        properties: {
+            node: {
+                schema: NODE_SCHEMA,
+            },
+            upid: {
+                schema: UPID_SCHEMA,
+            },
+        },
+    },
+    access: {
+        description: "Users can resume there own tasks, or need Sys.Modify on /system/tasks.",
+        permission: &Permission::Anybody,
+    },
+)]
+/// Resume a paused task.
+async fn resume_task(
+    param: Value,
+    rpcenv: &mut dyn RpcEnvironment,
+) -> Result<Value, Error> {
+
+    let upid = extract_upid(&param)?;
+
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
+
+    if auth_id != upid.auth_id {
+        let user_info = CachedUserInfo::new()?;
+        user_info.check_privs(&auth_id, &["system", "tasks"], PRIV_SYS_MODIFY, false)?;
+    }
+
+    server::resume_worker(upid).await?;
+
+    Ok(Value::Null)
+}
+
 #[api(
     input: {
         properties: {
@@ -440,7 +513,7 @@ pub fn list_tasks(
     mut rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<TaskListItem>, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
     let user_privs = user_info.lookup_privs(&auth_id, &["system", "tasks"]);
 
@@ -525,6 +598,14 @@ const UPID_API_SUBDIRS: SubdirMap = &sorted!([
         "log", &Router::new()
             .get(&API_METHOD_READ_TASK_LOG)
     ),
+    (
+        "pause", &Router::new()
+            .post(&API_METHOD_PAUSE_TASK)
+    ),
+    (
+        "resume", &Router::new()
+            .post(&API_METHOD_RESUME_TASK)
+    ),
     (
         "status", &Router::new()
             .get(&API_METHOD_GET_TASK_STATUS)
@@ -170,7 +170,7 @@ async fn pull (
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<String, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let delete = remove_vanished.unwrap_or(true);
 
     check_pull_privs(&auth_id, &store, &remote, &remote_store, delete)?;
@@ -37,6 +37,7 @@ use crate::{
             BACKUP_ID_SCHEMA,
             CHUNK_DIGEST_SCHEMA,
             Authid,
+            parse_auth_id_checked,
         },
     },
     backup::{
@@ -97,9 +98,9 @@ fn upgrade_to_backup_reader_protocol(
 ) -> ApiResponseFuture {
 
     async move {
-        let debug = param["debug"].as_bool().unwrap_or(false);
+        let debug = tools::optional_bool_param(&param, "debug")?.unwrap_or(false);
 
-        let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+        let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
         let store = tools::required_string_param(&param, "store")?.to_owned();
 
         let user_info = CachedUserInfo::new()?;
@@ -115,6 +116,8 @@ fn upgrade_to_backup_reader_protocol(
 
         let datastore = DataStore::lookup_datastore(&store)?;
 
+        let (node_config, _digest) = crate::config::node::config()?;
+
         let backup_type = tools::required_string_param(&param, "backup-type")?;
         let backup_id = tools::required_string_param(&param, "backup-id")?;
         let backup_time = tools::required_integer_param(&param, "backup-time")?;
@@ -138,10 +141,7 @@ fn upgrade_to_backup_reader_protocol(
         let backup_dir = BackupDir::new(backup_type, backup_id, backup_time)?;
         if !priv_read {
             let owner = datastore.get_owner(backup_dir.group())?;
-            let correct_owner = owner == auth_id
-                || (owner.is_token()
-                    && Authid::from(owner.user().clone()) == auth_id);
-            if !correct_owner {
+            if !auth_id.owns(&owner) {
                 bail!("backup owner check failed!");
             }
         }
@@ -184,11 +184,7 @@ fn upgrade_to_backup_reader_protocol(
 
                 let mut http = hyper::server::conn::Http::new();
                 http.http2_only(true);
-                // increase window size: todo - find optiomal size
-                let window_size = 32*1024*1024; // max = (1 << 31) - 2
-                http.http2_initial_stream_window_size(window_size);
-                http.http2_initial_connection_window_size(window_size);
-                http.http2_max_frame_size(4*1024*1024);
+                node_config.setup_http2(&mut http);
 
                 http.serve_connection(conn, service)
                     .map_err(Error::from).await
@@ -19,6 +19,7 @@ use crate::api2::types::{
     RRDMode,
     RRDTimeFrameResolution,
     Authid,
+    parse_auth_id_checked,
 };
 
 use crate::backup::{DataStore};
@@ -85,7 +86,7 @@ pub fn datastore_status(
 
     let (config, _digest) = datastore::config()?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let mut list = Vec::new();
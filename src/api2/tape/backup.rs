@@ -49,6 +49,7 @@ use crate::{
     },
     api2::types::{
         Authid,
+        parse_auth_id_checked,
         UPID_SCHEMA,
         JOB_ID_SCHEMA,
         MediaPoolConfig,
@@ -122,7 +123,7 @@ pub fn list_tape_backup_jobs(
     _param: Value,
     mut rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<TapeBackupJobStatus>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, digest) = config::tape_job::config()?;
@@ -286,7 +287,7 @@ pub fn run_tape_backup_job(
     id: String,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<String, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     let (config, _digest) = config::tape_job::config()?;
     let backup_job: TapeBackupJobConfig = config.lookup("backup", &id)?;
@@ -330,7 +331,7 @@ pub fn backup(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Value, Error> {
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
 
     check_backup_permission(
         &auth_id,
@@ -415,6 +416,10 @@ fn backup_worker(
 
     let mut pool_writer = PoolWriter::new(pool, &setup.drive, worker, email)?;
 
+    if let Some(sample_rate) = setup.verify_sample_rate {
+        pool_writer.set_verify_sample_rate(sample_rate);
+    }
+
     let mut group_list = BackupInfo::list_backup_groups(&datastore.base_path())?;
 
     group_list.sort_unstable();
@@ -518,6 +523,8 @@ fn backup_worker(
         }
     }
 
+    pool_writer.verify_chunk_archives(worker)?;
+
     if setup.export_media_set.unwrap_or(false) {
         pool_writer.export_media_set(worker)?;
     } else if setup.eject_media.unwrap_or(false) {
@@ -18,8 +18,10 @@ use crate::{
     },
     api2::types::{
         Authid,
+        parse_auth_id_checked,
         CHANGER_NAME_SCHEMA,
         ChangerListEntry,
+        ImportExportSlot,
         LtoTapeDrive,
         MtxEntryKind,
         MtxStatusEntry,
@@ -202,7 +204,7 @@ pub fn list_changers(
     _param: Value,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<ChangerListEntry>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, _digest) = config::drive::config()?;
@@ -226,7 +228,53 @@ pub fn list_changers(
     Ok(list)
 }
 
+#[api(
+    input: {
+        properties: {
+            name: {
+                schema: CHANGER_NAME_SCHEMA,
+            },
+            cache: {
+                description: "Use cached value.",
+                optional: true,
+                default: true,
+            },
+        },
+    },
+    returns: {
+        description: "A status entry for each import/export slot.",
+        type: Array,
+        items: {
+            type: ImportExportSlot,
+        },
+    },
+    access: {
+        permission: &Permission::Privilege(&["tape", "device", "{name}"], PRIV_TAPE_AUDIT, false),
+    },
+)]
+/// List import/export slots and their content
+pub async fn list_import_export_slots(
+    name: String,
+    cache: bool,
+) -> Result<Vec<ImportExportSlot>, Error> {
+
+    let (config, _digest) = config::drive::config()?;
+
+    let mut changer_config: ScsiTapeChanger = config.lookup("changer", &name)?;
+
+    let status = tokio::task::spawn_blocking(move || {
+        changer_config.status(cache)
+    }).await??;
+
+    Ok(status.import_export_slots())
+}
+
 const SUBDIRS: SubdirMap = &[
+    (
+        "import-export-slots",
+        &Router::new()
+            .get(&API_METHOD_LIST_IMPORT_EXPORT_SLOTS)
+    ),
     (
         "status",
         &Router::new()
@@ -1,7 +1,6 @@
 use std::panic::UnwindSafe;
 use std::path::Path;
 use std::sync::Arc;
-use std::collections::HashMap;
 
 use anyhow::{bail, format_err, Error};
 use serde_json::Value;
@@ -41,6 +40,7 @@ use crate::{
             MEDIA_LABEL_SCHEMA,
             MEDIA_POOL_NAME_SCHEMA,
             Authid,
+            parse_auth_id_checked,
             DriveListEntry,
             LtoTapeDrive,
             MediaIdFlat,
@@ -51,7 +51,7 @@ use crate::{
         },
         tape::restore::{
             fast_catalog_restore,
-            restore_media,
+            rebuild_media_catalog,
         },
     },
     server::WorkerTask,
@@ -82,7 +82,10 @@ use crate::{
             get_tape_device_state,
             tape_alert_flags_critical,
         },
-        changer::update_changer_online_status,
+        changer::{
+            CleaningCounters,
+            update_changer_online_status,
+        },
     },
 };
 
@@ -103,7 +106,7 @@ where
     let (config, _digest) = config::drive::config()?;
     let lock_guard = lock_tape_device(&config, &drive)?;
 
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let to_stdout = rpcenv.env_type() == RpcEnvironmentType::CLI;
 
     WorkerTask::new_thread(worker_type, job_id, auth_id, to_stdout, move |worker| {
@@ -789,7 +792,25 @@ pub fn clean_drive(
 
             worker.log("Starting drive clean");
 
-            changer.clean_drive()?;
+            let status = changer.clean_drive()?;
+
+            // the drive is clean, so counter problems are no reason to fail
+            let counters = CleaningCounters::load(Path::new(TAPE_STATUS_DIR)).and_then(|mut counters| {
+                if let Some((_slot, label_text)) = status.cleaning_cartridge() {
+                    counters.increment(&label_text).map_err(|err| {
+                        format_err!("unable to update cleaning counter for '{}' - {}", label_text, err)
+                    })?;
+                }
+                Ok(counters)
+            });
+            match counters {
+                Ok(counters) => {
+                    for warning in counters.check_changer_status(&status) {
+                        worker.warn(warning);
+                    }
+                }
+                Err(err) => worker.warn(format!("cleaning counters - {}", err)),
+            }
 
              if let Ok(drive_config) = config.lookup::<LtoTapeDrive>("lto", &drive) {
                  // Note: clean_drive unloads the cleaning media, so we cannot use drive_config.open
@@ -1335,8 +1356,7 @@ pub fn catalog_media(
             drive.rewind()?;
             drive.read_label()?; // skip over labels - we already read them above
 
-            let mut checked_chunks = HashMap::new();
-            restore_media(&worker, &mut drive, &media_id, None, &mut checked_chunks, verbose)?;
+            rebuild_media_catalog(&worker, &mut drive, &media_id, verbose)?;
 
             Ok(())
         },
@@ -1372,7 +1392,7 @@ pub fn list_drives(
     _param: Value,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<DriveListEntry>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, _) = config::drive::config()?;
@@ -19,6 +19,7 @@ use crate::{
     },
     api2::types::{
         Authid,
+        parse_auth_id_checked,
         BACKUP_ID_SCHEMA,
         BACKUP_TYPE_SCHEMA,
         MEDIA_POOL_NAME_SCHEMA,
@@ -82,7 +83,7 @@ pub async fn list_media(
     update_status_changer: Option<String>,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<MediaListEntry>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, _digest) = config::media_pool::config()?;
@@ -389,7 +390,7 @@ pub fn list_content(
     filter: MediaContentListFilter,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Vec<MediaContentEntry>, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let (config, _digest) = config::media_pool::config()?;
@@ -39,6 +39,7 @@ use crate::{
         DRIVE_NAME_SCHEMA,
         UPID_SCHEMA,
         Authid,
+        parse_auth_id_checked,
         Userid,
     },
     config::{
@@ -206,7 +207,7 @@ pub fn restore(
     owner: Option<Authid>,
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<Value, Error> {
-    let auth_id: Authid = rpcenv.get_auth_id().unwrap().parse()?;
+    let auth_id: Authid = parse_auth_id_checked(&rpcenv.get_auth_id().unwrap())?;
     let user_info = CachedUserInfo::new()?;
 
     let store_map = DataStoreMap::try_from(store)
@@ -465,6 +466,49 @@ pub fn restore_media(
     Ok(())
 }
 
+/// Rebuild the media catalog by reading the whole tape
+///
+/// The new catalog is written to `TAPE_STATUS_DIR`, and a content summary
+/// (including the file ranges of the chunk archives) is logged.
+pub fn rebuild_media_catalog(
+    worker: &WorkerTask,
+    drive: &mut Box<dyn TapeDriver>,
+    media_id: &MediaId,
+    verbose: bool,
+) -> Result<(), Error> {
+
+    let mut checked_chunks = HashMap::new();
+    restore_media(worker, drive, media_id, None, &mut checked_chunks, verbose)?;
+
+    let status_path = Path::new(TAPE_STATUS_DIR);
+    let catalog = MediaCatalog::open(status_path, media_id, false, false)?;
+
+    let mut stores: Vec<_> = catalog.content().iter().collect();
+    stores.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (store, content) in stores {
+        let ranges: Vec<String> = content.chunk_archive_ranges()
+            .into_iter()
+            .map(|(first, last)| if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            })
+            .collect();
+
+        task_log!(
+            worker,
+            "datastore '{}': {} snapshots, {} chunks (chunk archives at file {})",
+            store,
+            content.snapshot_index.len(),
+            content.chunk_index.len(),
+            if ranges.is_empty() { String::from("-") } else { ranges.join(", ") },
+        );
+    }
+
+    Ok(())
+}
+
 fn restore_archive<'a>(
     worker: &WorkerTask,
     mut reader: Box<dyn 'a + TapeRead>,
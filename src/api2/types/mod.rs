@@ -1,6 +1,6 @@
 //! API Type Definitions
 
-use anyhow::bail;
+use anyhow::{bail, format_err};
 use serde::{Deserialize, Serialize};
 
 use proxmox::api::{api, schema::*};
@@ -28,6 +28,7 @@ pub use userid::{Realm, RealmRef};
 pub use userid::{Tokenname, TokennameRef};
 pub use userid::{Username, UsernameRef};
 pub use userid::Userid;
+pub use userid::parse_auth_id_checked;
 pub use userid::Authid;
 pub use userid::{PROXMOX_TOKEN_ID_SCHEMA, PROXMOX_TOKEN_NAME_SCHEMA, PROXMOX_GROUP_ID_SCHEMA};
 
@@ -110,6 +111,10 @@ const_regex!{
 
     pub UUID_REGEX = r"^[0-9a-f]{8}(?:-[0-9a-f]{4}){3}-[0-9a-f]{12}$";
 
+    pub MAC_ADDRESS_REGEX = r"^[0-9a-fA-F]{2}(?::[0-9a-fA-F]{2}){5}$";
+
+    pub FILE_MODE_REGEX = r"^0?[0-7]{3}$";
+
     pub DATASTORE_MAP_REGEX = concat!(r"(:?", PROXMOX_SAFE_ID_REGEX_STR!(), r"=)?", PROXMOX_SAFE_ID_REGEX_STR!());
 }
 
@@ -221,6 +226,10 @@ pub const CHUNK_DIGEST_SCHEMA: Schema = StringSchema::new("Chunk digest (SHA256)
     .format(&CHUNK_DIGEST_FORMAT)
     .schema();
 
+/// Maximum number of digests per `known_chunks` backup protocol request,
+/// clients need to split larger lists.
+pub const KNOWN_CHUNKS_MAX_DIGESTS: usize = 256;
+
 pub const NODE_SCHEMA: Schema = StringSchema::new("Node name (or 'localhost')")
     .format(&ApiStringFormat::VerifyFn(|node| {
         if node == "localhost" || node == proxmox::tools::nodename() {
@@ -357,8 +366,16 @@ pub const BACKUP_TYPE_SCHEMA: Schema =
 pub const BACKUP_ID_SCHEMA: Schema =
     StringSchema::new("Backup ID.")
     .format(&BACKUP_ID_FORMAT)
+    .max_length(255) // used as directory name
     .schema();
 
+/// Check that `backup_id` is valid according to `BACKUP_ID_SCHEMA`.
+pub fn validate_backup_id(backup_id: &str) -> Result<(), anyhow::Error> {
+    parse_simple_value(backup_id, &BACKUP_ID_SCHEMA)
+        .map_err(|err| format_err!("invalid backup-id '{}' - {}", backup_id, err))?;
+    Ok(())
+}
+
 pub const BACKUP_TIME_SCHEMA: Schema =
     IntegerSchema::new("Backup time (Unix epoch.)")
     .minimum(1_547_797_308)
@@ -489,6 +506,15 @@ pub const BLOCKDEVICE_NAME_SCHEMA: Schema = StringSchema::new("Block device name
             optional: true,
             schema: SINGLE_LINE_COMMENT_SCHEMA,
         },
+        total: {
+            optional: true,
+        },
+        used: {
+            optional: true,
+        },
+        avail: {
+            optional: true,
+        },
     },
 )]
 #[derive(Serialize, Deserialize)]
@@ -497,6 +523,15 @@ pub const BLOCKDEVICE_NAME_SCHEMA: Schema = StringSchema::new("Block device name
 pub struct DataStoreListItem {
     pub store: String,
     pub comment: Option<String>,
+    /// Total space (bytes), if known.
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub total: Option<u64>,
+    /// Used space (bytes), if known.
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub used: Option<u64>,
+    /// Available space (bytes), if known.
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub avail: Option<u64>,
 }
 
 #[api(
@@ -978,6 +1013,26 @@ pub enum BondXmitHashPolicy {
     /// Layer 3+4
     #[serde(rename = "layer3+4")]
     layer3_4 = 2,
+    /// Encapsulated Layer 2+3 (inner headers of tunneled packets)
+    #[serde(rename = "encap2+3")]
+    encap2_3 = 3,
+    /// Encapsulated Layer 3+4 (inner headers of tunneled packets)
+    #[serde(rename = "encap3+4")]
+    encap3_4 = 4,
+}
+
+#[api()]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[allow(non_camel_case_types)]
+/// Bond MAC address policy for Active/Backup mode (fail_over_mac)
+pub enum BondFailOverMac {
+    /// Set the same MAC address on all slaves
+    none,
+    /// Use the MAC address of the currently active slave
+    active,
+    /// Keep slave MAC addresses, the bond follows the active slave
+    follow,
 }
 
 #[api()]
@@ -1001,12 +1056,24 @@ pub enum NetworkInterfaceType {
     Unknown,
 }
 
+pub const MAC_ADDRESS_FORMAT: ApiStringFormat =
+    ApiStringFormat::Pattern(&MAC_ADDRESS_REGEX);
+
+pub const MAC_ADDRESS_SCHEMA: Schema = StringSchema::new("MAC address (e.g. 00:11:22:33:44:55).")
+    .format(&MAC_ADDRESS_FORMAT)
+    .schema();
+
 pub const NETWORK_INTERFACE_NAME_SCHEMA: Schema = StringSchema::new("Network interface name.")
     .format(&NETWORK_INTERFACE_FORMAT)
     .min_length(1)
     .max_length(libc::IFNAMSIZ-1)
     .schema();
 
+pub const VLAN_ID_SCHEMA: Schema = IntegerSchema::new("VLAN ID.")
+    .minimum(1)
+    .maximum(4094)
+    .schema();
+
 pub const NETWORK_INTERFACE_ARRAY_SCHEMA: Schema = ArraySchema::new(
     "Network interface list.", &NETWORK_INTERFACE_NAME_SCHEMA)
     .schema();
@@ -1094,6 +1161,37 @@ pub const NETWORK_INTERFACE_LIST_SCHEMA: Schema = StringSchema::new(
             type: BondXmitHashPolicy,
             optional: true,
         },
+        bond_fail_over_mac: {
+            type: BondFailOverMac,
+            optional: true,
+        },
+        bond_miimon: {
+            description: "MII link monitoring frequency in milliseconds.",
+            type: Integer,
+            optional: true,
+        },
+        bond_downdelay: {
+            description: "Delay in milliseconds before disabling a slave after link failure.",
+            type: Integer,
+            optional: true,
+        },
+        bond_updelay: {
+            description: "Delay in milliseconds before enabling a slave after link recovery.",
+            type: Integer,
+            optional: true,
+        },
+        hwaddress: {
+            schema: MAC_ADDRESS_SCHEMA,
+            optional: true,
+        },
+        vlan_id: {
+            schema: VLAN_ID_SCHEMA,
+            optional: true,
+        },
+        vlan_raw_device: {
+            schema: NETWORK_INTERFACE_NAME_SCHEMA,
+            optional: true,
+        },
     }
 )]
 #[derive(Debug, Serialize, Deserialize)]
@@ -1154,6 +1252,21 @@ pub struct Interface {
     #[serde(rename = "bond-primary")]
     pub bond_primary: Option<String>,
     pub bond_xmit_hash_policy: Option<BondXmitHashPolicy>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub bond_fail_over_mac: Option<BondFailOverMac>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub bond_miimon: Option<u32>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub bond_downdelay: Option<u32>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub bond_updelay: Option<u32>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    /// Static MAC address override
+    pub hwaddress: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub vlan_id: Option<u16>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub vlan_raw_device: Option<String>,
 }
 
 // Regression tests
@@ -1243,6 +1356,43 @@ fn test_proxmox_user_id_schema() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[test]
+fn test_validate_backup_id() -> Result<(), anyhow::Error> {
+    let invalid_backup_ids = [
+        "", // empty
+        "a/b", // contains slash
+        "../a", // contains slash, leading dot
+        ".hidden", // leading dot
+        "a b", // contains space
+        "a\nb", // contains control character
+    ];
+
+    for id in invalid_backup_ids.iter() {
+        if validate_backup_id(id).is_ok() {
+            bail!("test backup-id '{}' failed - got Ok() while expecting an error.", id);
+        }
+    }
+
+    let overlong = "x".repeat(256);
+    if validate_backup_id(&overlong).is_ok() {
+        bail!("test backup-id with length {} failed - got Ok() while expecting an error.", overlong.len());
+    }
+
+    let valid_backup_ids = [
+        "100",
+        "host1",
+        "my-host.example.com",
+        "_test-host_1",
+    ];
+
+    for id in valid_backup_ids.iter() {
+        validate_backup_id(id)?;
+    }
+    validate_backup_id(&"x".repeat(255))?;
+
+    Ok(())
+}
+
 #[api()]
 #[derive(Copy, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
@@ -1299,6 +1449,23 @@ pub struct APTUpdateInfo {
     /// Custom extra field for additional package information
     #[serde(skip_serializing_if="Option::is_none")]
     pub extra_info: Option<String>,
+    /// Size of the package download in bytes (if known)
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub download_size: Option<u64>,
+}
+
+#[api()]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// State of an APT/dpkg lock file.
+pub struct APTLockInfo {
+    /// Path of the lock file
+    pub path: String,
+    /// The lock is currently held by another process
+    pub locked: bool,
+    /// PID of the process holding the lock (if known)
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub pid: Option<i32>,
 }
 
 #[api()]
@@ -1399,6 +1566,22 @@ pub const DATASTORE_NOTIFY_STRING_SCHEMA: Schema = StringSchema::new(
     .format(&ApiStringFormat::PropertyString(&DatastoreNotify::API_SCHEMA))
     .schema();
 
+pub const FILE_MODE_FORMAT: ApiStringFormat =
+    ApiStringFormat::Pattern(&FILE_MODE_REGEX);
+
+pub const DATASTORE_FILE_MODE_SCHEMA: Schema = StringSchema::new(
+    "Permissions (octal) for newly created backup data files, e.g. '0640'.")
+    .format(&FILE_MODE_FORMAT)
+    .schema();
+
+
+pub const DATASTORE_CHUNK_WRITE_RETRIES_SCHEMA: Schema = IntegerSchema::new(
+    "Number of retries (with increasing delay) on transient IO errors like a full disk \
+     while writing uploaded chunks.")
+    .minimum(0)
+    .maximum(10)
+    .default(0)
+    .schema();
 
 pub const PASSWORD_HINT_SCHEMA: Schema = StringSchema::new("Password hint.")
     .format(&SINGLE_LINE_COMMENT_FORMAT)
@@ -133,3 +133,26 @@ pub struct MtxStatusEntry {
     #[serde(skip_serializing_if="Option::is_none")]
     pub state: Option<String>,
 }
+
+#[api(
+    properties: {
+        "label-text": {
+            schema: MEDIA_LABEL_SCHEMA,
+            optional: true,
+        },
+    },
+)]
+#[derive(Serialize,Deserialize,Debug,PartialEq)]
+#[serde(rename_all = "kebab-case")]
+/// Import/Export Slot Status
+pub struct ImportExportSlot {
+    /// The slot number (starting at 1)
+    pub slot: u64,
+    /// Element address of the slot
+    pub element_address: u16,
+    /// Slot contains media
+    pub full: bool,
+    /// The media label (volume tag) if known
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub label_text: Option<String>,
+}
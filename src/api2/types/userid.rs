@@ -30,7 +30,7 @@ use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
 use proxmox::api::api;
-use proxmox::api::schema::{ApiStringFormat, Schema, StringSchema};
+use proxmox::api::schema::{parse_simple_value, ApiStringFormat, Schema, StringSchema};
 use proxmox::const_regex;
 
 // we only allow a limited set of characters
@@ -554,12 +554,30 @@ impl Authid {
         }
     }
 
+    /// Returns true if `self` is `owner`, or the user `owner` is an API token of.
+    ///
+    /// Users own everything owned by their API tokens, but tokens of the same user do not own
+    /// each other's objects.
+    pub fn owns(&self, owner: &Authid) -> bool {
+        self == owner || (owner.is_token() && !self.is_token() && self.user == owner.user)
+    }
+
     /// Get the "root@pam" auth id.
     pub fn root_auth_id() -> &'static Self {
         &*ROOT_AUTHID
     }
 }
 
+/// Parse and validate an `Authid` from untrusted input.
+///
+/// Checks the whole string against `Authid::API_SCHEMA` (including length limits) before
+/// parsing user, realm and token name, and reports all failures with the same error message.
+pub fn parse_auth_id_checked(auth_id: &str) -> Result<Authid, Error> {
+    parse_simple_value(auth_id, &Authid::API_SCHEMA)
+        .and_then(|_| auth_id.parse())
+        .map_err(|err| format_err!("invalid auth id '{}' - {}", auth_id, err))
+}
+
 lazy_static! {
     pub static ref ROOT_AUTHID: Authid = Authid::from(Userid::new("root@pam".to_string(), 4));
 }
@@ -677,6 +695,63 @@ fn test_token_id() {
     assert_eq!(auth_id.to_string(), "test@pam!bar".to_string());
 }
 
+#[test]
+fn test_authid_owns() {
+    let user: Authid = "test@pam".parse().unwrap();
+    let token: Authid = "test@pam!bar".parse().unwrap();
+    let token2: Authid = "test@pam!baz".parse().unwrap();
+    let other: Authid = "other@pam".parse().unwrap();
+    let other_token: Authid = "other@pam!bar".parse().unwrap();
+
+    assert!(user.owns(&user));
+    assert!(token.owns(&token));
+
+    // a user owns the objects of its tokens, but not vice versa
+    assert!(user.owns(&token));
+    assert!(!token.owns(&user));
+
+    // unrelated users and their tokens
+    assert!(!user.owns(&other));
+    assert!(!other.owns(&user));
+    assert!(!user.owns(&other_token));
+    assert!(!token.owns(&other_token));
+
+    // tokens of the same user
+    assert!(!token.owns(&token2));
+    assert!(!token2.owns(&token));
+}
+
+#[test]
+fn test_parse_auth_id_checked() {
+    let auth_id = parse_auth_id_checked("test@pam").expect("user auth id rejected");
+    assert!(!auth_id.is_token());
+    assert_eq!(auth_id.to_string(), "test@pam");
+
+    let auth_id = parse_auth_id_checked("test@pbs!backup-1").expect("token auth id rejected");
+    assert!(auth_id.is_token());
+    assert_eq!(auth_id.user().as_str(), "test@pbs");
+    assert_eq!(auth_id.tokenname().unwrap().as_str(), "backup-1");
+
+    let invalid = [
+        "", // empty
+        "test", // no realm
+        "test@", // empty realm
+        "@pam", // empty user name
+        "test@pam!", // empty token name
+        "test@pam!to:ken", // invalid token name
+        "test@p/am", // invalid realm
+        "te st@pam", // whitespace
+        "test@pam!a!b", // nested token
+    ];
+    for auth_id in invalid.iter() {
+        let err = parse_auth_id_checked(auth_id).expect_err(auth_id);
+        assert!(err.to_string().starts_with("invalid auth id"));
+    }
+
+    let overlong = format!("{}@pam", "x".repeat(64));
+    assert!(parse_auth_id_checked(&overlong).is_err());
+}
+
 proxmox::forward_deserialize_to_from_str!(Userid);
 proxmox::forward_serialize_to_display!(Userid);
 
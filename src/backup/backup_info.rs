@@ -1,13 +1,15 @@
 use crate::tools;
 
 use anyhow::{bail, format_err, Error};
+use std::convert::TryFrom;
 use std::os::unix::io::RawFd;
 
 use std::path::{Path, PathBuf};
 
 use proxmox::const_regex;
 
-use super::manifest::MANIFEST_BLOB_NAME;
+use super::manifest::{BackupManifest, MANIFEST_BLOB_NAME};
+use super::DataBlob;
 
 macro_rules! BACKUP_ID_RE {
     () => {
@@ -74,11 +76,14 @@ impl std::cmp::PartialOrd for BackupGroup {
 }
 
 impl BackupGroup {
-    pub fn new<T: Into<String>, U: Into<String>>(backup_type: T, backup_id: U) -> Self {
-        Self {
+    pub fn new<T: Into<String>, U: Into<String>>(backup_type: T, backup_id: U) -> Result<Self, Error> {
+        let backup_id = backup_id.into();
+        crate::api2::types::validate_backup_id(&backup_id)?;
+
+        Ok(Self {
             backup_type: backup_type.into(),
-            backup_id: backup_id.into(),
-        }
+            backup_id,
+        })
     }
 
     pub fn backup_type(&self) -> &str {
@@ -99,6 +104,37 @@ impl BackupGroup {
         relative_path
     }
 
+    /// Returns all finished snapshots (oldest first) together with the snapshot they are based
+    /// on, as recorded in their manifest. Full backups have no base.
+    pub fn incremental_chain(
+        &self,
+        base_path: &Path,
+    ) -> Result<Vec<(BackupDir, Option<BackupDir>)>, Error> {
+        let mut list = self.list_backups(base_path)?;
+        BackupInfo::sort_list(&mut list, true);
+
+        let mut chain = Vec::new();
+        for info in list.into_iter().filter(|info| info.is_finished()) {
+            let path = info.backup_dir.full_path(base_path).join(MANIFEST_BLOB_NAME);
+            let manifest = proxmox::try_block!({
+                let mut file = std::fs::File::open(&path)?;
+                BackupManifest::try_from(DataBlob::load_from_reader(&mut file)?)
+            });
+            // a single broken snapshot should not hide the rest of the group
+            let manifest = match manifest {
+                Ok(manifest) => manifest,
+                Err(err) => {
+                    log::warn!("skipping snapshot - unable to load manifest {:?} - {}", path, err);
+                    continue;
+                }
+            };
+
+            chain.push((info.backup_dir, manifest.base_snapshot()));
+        }
+
+        Ok(chain)
+    }
+
     pub fn list_backups(&self, base_path: &Path) -> Result<Vec<BackupInfo>, Error> {
         let mut list = vec![];
 
@@ -226,7 +262,7 @@ impl BackupDir {
         T: Into<String>,
         U: Into<String>,
     {
-        let group = BackupGroup::new(backup_type.into(), backup_id.into());
+        let group = BackupGroup::new(backup_type.into(), backup_id.into())?;
         BackupDir::with_group(group, backup_time)
     }
 
@@ -242,7 +278,7 @@ impl BackupDir {
     {
         let backup_time_string = backup_time_string.into();
         let backup_time = proxmox::tools::time::parse_rfc3339(&backup_time_string)?;
-        let group = BackupGroup::new(backup_type.into(), backup_id.into());
+        let group = BackupGroup::new(backup_type.into(), backup_id.into())?;
         Ok(Self {
             group,
             backup_time,
@@ -272,11 +308,30 @@ impl BackupDir {
     }
 
     pub fn relative_path(&self) -> PathBuf {
-        let mut relative_path = self.group.group_path();
+        self.full_path(Path::new(""))
+    }
 
-        relative_path.push(self.backup_time_string.clone());
+    /// Returns the path of the snapshot directory below `base_path`.
+    ///
+    /// This is the one place defining the on-disk snapshot layout (`<type>/<id>/<time>`).
+    pub fn full_path(&self, base_path: &Path) -> PathBuf {
+        let mut full_path = base_path.join(self.group.group_path());
+        full_path.push(&self.backup_time_string);
+        full_path
+    }
 
-        relative_path
+    /// Validate the snapshot components and return the snapshot path below `base_path`.
+    pub fn path_from_components(
+        base_path: &Path,
+        backup_type: &str,
+        backup_id: &str,
+        backup_time: i64,
+    ) -> Result<PathBuf, Error> {
+        if !BACKUP_TYPE_REGEX.is_match(backup_type) {
+            bail!("invalid backup-type '{}'", backup_type);
+        }
+        let backup_dir = BackupDir::new(backup_type, backup_id, backup_time)?;
+        Ok(backup_dir.full_path(base_path))
     }
 
     pub fn backup_time_to_string(backup_time: i64) -> Result<String, Error> {
@@ -383,7 +438,7 @@ impl BackupInfo {
                             return Ok(());
                         }
 
-                        list.push(BackupGroup::new(backup_type, backup_id));
+                        list.push(BackupGroup::new(backup_type, backup_id)?);
 
                         Ok(())
                     },
@@ -418,3 +473,75 @@ fn list_backup_files<P: ?Sized + nix::NixPath>(
 
     Ok(files)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_incremental_chain() -> Result<(), Error> {
+        let tmp = nix::unistd::mkdtemp("/tmp/pbs-incremental-chain-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let group = BackupGroup::new("vm", "100")?;
+            let full = BackupDir::with_group(group.clone(), 1592198313)?;
+            let incr1 = BackupDir::with_group(group.clone(), 1592198413)?;
+            let incr2 = BackupDir::with_group(group.clone(), 1592198513)?;
+            let unfinished = BackupDir::with_group(group.clone(), 1592198613)?;
+            let broken = BackupDir::with_group(group.clone(), 1592198713)?;
+
+            let snapshots = [(&full, None), (&incr1, Some(&full)), (&incr2, Some(&incr1))];
+            for (snapshot, base) in snapshots.iter() {
+                let path = snapshot.full_path(&tmp);
+                std::fs::create_dir_all(&path)?;
+
+                let mut manifest = BackupManifest::new((*snapshot).clone());
+                if let Some(base) = base {
+                    manifest.unprotected["base_snapshot"] = base.to_string().into();
+                }
+                let blob = DataBlob::encode(manifest.to_string(None)?.as_bytes(), None, true)?;
+                std::fs::write(path.join(MANIFEST_BLOB_NAME), blob.raw_data())?;
+            }
+            std::fs::create_dir_all(unfinished.full_path(&tmp))?;
+
+            // unreadable manifests are skipped
+            std::fs::create_dir_all(broken.full_path(&tmp))?;
+            std::fs::write(broken.full_path(&tmp).join(MANIFEST_BLOB_NAME), b"garbage")?;
+
+            let chain = group.incremental_chain(&tmp)?;
+            assert_eq!(chain, vec![
+                (full.clone(), None),
+                (incr1.clone(), Some(full)),
+                (incr2, Some(incr1)),
+            ]);
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&tmp)?;
+        result
+    }
+
+    #[test]
+    fn test_snapshot_path_from_components() -> Result<(), Error> {
+        let base = Path::new("/mnt/datastore/store1");
+
+        let path = BackupDir::path_from_components(base, "vm", "100", 1592198313)?;
+        assert_eq!(path, Path::new("/mnt/datastore/store1/vm/100/2020-06-15T05:18:33Z"));
+
+        let backup_dir = BackupDir::new("host", "elsa", 1592198313)?;
+        assert_eq!(backup_dir.relative_path(), Path::new("host/elsa/2020-06-15T05:18:33Z"));
+        assert_eq!(backup_dir.full_path(base), base.join(backup_dir.relative_path()));
+
+        // invalid backup type
+        assert!(BackupDir::path_from_components(base, "foo", "100", 1592198313).is_err());
+        assert!(BackupDir::path_from_components(base, "..", "100", 1592198313).is_err());
+        // invalid backup id
+        assert!(BackupDir::path_from_components(base, "vm", "../100", 1592198313).is_err());
+        assert!(BackupDir::path_from_components(base, "vm", "", 1592198313).is_err());
+        // backup time out of range
+        assert!(BackupDir::path_from_components(base, "vm", "100", i64::MAX).is_err());
+
+        Ok(())
+    }
+}
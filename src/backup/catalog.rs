@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 use std::ffi::{CStr, CString, OsStr};
 use std::fmt;
@@ -279,6 +280,41 @@ impl DirInfo {
     }
 }
 
+/// Metadata of a regular file as recorded in the catalog
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub size: u64,
+    pub mtime: i64,
+}
+
+impl FileMetadata {
+    /// A file is considered unchanged if both its size and its mtime (in
+    /// seconds, as stored in the catalog) are equal to the previous values.
+    ///
+    /// Any difference counts as change, including an mtime in the past.
+    pub fn is_unchanged(&self, previous: &FileMetadata) -> bool {
+        self.size == previous.size && self.mtime == previous.mtime
+    }
+}
+
+/// Returns the paths of all files in 'current' which are unchanged compared to
+/// 'previous' (see `FileMetadata::is_unchanged`)
+///
+/// Files missing in the previous metadata are always considered changed.
+pub fn find_unchanged_files(
+    previous: &BTreeMap<Vec<u8>, FileMetadata>,
+    current: &BTreeMap<Vec<u8>, FileMetadata>,
+) -> BTreeSet<Vec<u8>> {
+    current
+        .iter()
+        .filter(|(path, metadata)| match previous.get(*path) {
+            Some(previous) => metadata.is_unchanged(previous),
+            None => false,
+        })
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
 /// Write small catalog files
 ///
 /// A Catalogs simply contains list of files and directories
@@ -531,6 +567,40 @@ impl <R: Read + Seek> CatalogReader<R> {
         Ok(data)
     }
 
+    /// List the metadata of all regular files, indexed by their absolute path
+    pub fn list_metadata(&mut self) -> Result<BTreeMap<Vec<u8>, FileMetadata>, Error> {
+        let root = self.root()?;
+        let mut list = BTreeMap::new();
+        self.list_metadata_dir(&root, &mut Vec::new(), &mut list)?;
+        Ok(list)
+    }
+
+    fn list_metadata_dir(
+        &mut self,
+        parent: &DirEntry,
+        file_path: &mut Vec<u8>,
+        list: &mut BTreeMap<Vec<u8>, FileMetadata>,
+    ) -> Result<(), Error> {
+        let file_len = file_path.len();
+        for e in self.read_dir(parent)? {
+            file_path.truncate(file_len);
+            file_path.push(b'/');
+            file_path.extend(&e.name);
+            match e.attr {
+                DirEntryAttribute::Directory { .. } => {
+                    self.list_metadata_dir(&e, file_path, list)?;
+                }
+                DirEntryAttribute::File { size, mtime } => {
+                    list.insert(file_path.clone(), FileMetadata { size, mtime });
+                }
+                _ => (),
+            }
+        }
+        file_path.truncate(file_len);
+
+        Ok(())
+    }
+
     /// Print the content of a directory to stdout
     pub fn dump_dir(&mut self, prefix: &std::path::Path, start: u64) -> Result<(), Error> {
 
@@ -793,3 +863,47 @@ fn test_catalog_i64_compatibility() {
     test_encode_decode((1<<50)-1);
     test_encode_decode(u64::MAX);
 }
+
+#[test]
+fn test_catalog_unchanged_files() -> Result<(), Error> {
+
+    fn create_catalog(files: &[(&str, u64, i64)]) -> Result<BTreeMap<Vec<u8>, FileMetadata>, Error> {
+        let mut data = Vec::new();
+        {
+            let mut writer = CatalogWriter::new(&mut data)?;
+            writer.start_directory(&CString::new("etc")?)?;
+            for (name, size, mtime) in files {
+                writer.add_file(&CString::new(*name)?, *size, *mtime)?;
+            }
+            writer.add_symlink(&CString::new("link")?)?;
+            writer.end_directory()?;
+            writer.finish()?;
+        }
+        CatalogReader::new(std::io::Cursor::new(data)).list_metadata()
+    }
+
+    let previous = create_catalog(&[
+        ("same", 10, 1000),
+        ("size", 10, 1000),
+        ("mtime", 10, 1000),
+        ("older", 10, 1000),
+        ("removed", 10, 1000),
+    ])?;
+
+    assert_eq!(previous.len(), 5);
+    assert_eq!(previous.get(&b"/etc/same"[..]), Some(&FileMetadata { size: 10, mtime: 1000 }));
+
+    let current = create_catalog(&[
+        ("same", 10, 1000),
+        ("size", 11, 1000),
+        ("mtime", 10, 1001),
+        ("older", 10, 999),
+        ("new", 10, 1000),
+    ])?;
+
+    let unchanged = find_unchanged_files(&previous, &current);
+    let expected: BTreeSet<Vec<u8>> = vec![b"/etc/same".to_vec()].into_iter().collect();
+    assert_eq!(unchanged, expected);
+
+    Ok(())
+}
@@ -3,6 +3,7 @@ use anyhow::{bail, format_err, Error};
 use std::path::{Path, PathBuf};
 use std::io::Write;
 use std::sync::{Arc, Mutex};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::os::unix::io::AsRawFd;
 
 use proxmox::tools::fs::{CreateOptions, create_path, create_dir};
@@ -10,7 +11,7 @@ use proxmox::tools::fs::{CreateOptions, create_path, create_dir};
 use crate::tools;
 use crate::api2::types::GarbageCollectionStatus;
 
-use super::DataBlob;
+use super::{CryptConfig, DataBlob, ReadChunk};
 use crate::task::TaskState;
 
 /// File system based chunk store
@@ -20,6 +21,7 @@ pub struct ChunkStore {
     chunk_dir: PathBuf,
     mutex: Mutex<()>,
     locker: Arc<Mutex<tools::ProcessLocker>>,
+    file_mode: Option<u32>,
 }
 
 // TODO: what about sysctl setting vm.vfs_cache_pressure (0 - 100) ?
@@ -143,10 +145,16 @@ impl ChunkStore {
             base,
             chunk_dir,
             locker,
-            mutex: Mutex::new(())
+            mutex: Mutex::new(()),
+            file_mode: None,
         })
     }
 
+    /// Set the permissions for newly written chunk files, `None` means default permissions.
+    pub fn set_file_mode(&mut self, file_mode: Option<u32>) {
+        self.file_mode = file_mode;
+    }
+
     pub fn touch_chunk(&self, digest: &[u8; 32]) -> Result<(), Error> {
         self.cond_touch_chunk(digest, true)?;
         Ok(())
@@ -390,10 +398,74 @@ impl ChunkStore {
             }
         }
 
-        let mut tmp_path = chunk_path.clone();
+        let encoded_size = self.write_chunk_file(chunk, &chunk_path, &digest_str)?;
+
+        drop(lock);
+
+        Ok((false, encoded_size))
+    }
+
+    /// Replace a (corrupt) chunk with a copy read from 'source'
+    ///
+    /// The replacement is verified against 'digest' before it overwrites the
+    /// local chunk file, so mismatching data is never written. Returns the
+    /// encoded size of the new chunk.
+    pub fn repair_chunk(
+        &self,
+        digest: &[u8; 32],
+        source: &dyn ReadChunk,
+        config: Option<&CryptConfig>,
+    ) -> Result<u64, Error> {
+
+        let (chunk_path, digest_str) = self.chunk_path(digest);
+
+        let chunk = source.read_raw_chunk(digest)
+            .map_err(|err| format_err!("unable to read replacement for chunk {} - {}", digest_str, err))?;
+
+        chunk.verify_replacement(config, digest)
+            .map_err(|err| format_err!("replacement for chunk {} is invalid - {}", digest_str, err))?;
+
+        let _lock = self.mutex.lock();
+
+        self.write_chunk_file(&chunk, &chunk_path, &digest_str)
+    }
+
+    /// Like `insert_chunk`, but retry up to 'retries' times on transient IO errors
+    ///
+    /// The chunk store mutex is only held by `insert_chunk` itself, not while
+    /// waiting for the next retry.
+    pub fn insert_chunk_with_retry<L: FnMut(&str)>(
+        &self,
+        chunk: &DataBlob,
+        digest: &[u8; 32],
+        retries: u32,
+        log: L,
+    ) -> Result<(bool, u64), Error> {
+        retry_on_transient_io_error(retries, || self.insert_chunk(chunk, digest), log)
+    }
+
+    // atomically (over)write the chunk file, caller needs to hold the mutex
+    fn write_chunk_file(
+        &self,
+        chunk: &DataBlob,
+        chunk_path: &Path,
+        digest_str: &str,
+    ) -> Result<u64, Error> {
+
+        let mut tmp_path = chunk_path.to_path_buf();
         tmp_path.set_extension("tmp");
 
-        let mut file = std::fs::File::create(&tmp_path)?;
+        // keep plain IO errors here, see is_transient_io_error
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        if let Some(mode) = self.file_mode {
+            options.mode(mode);
+        }
+        let mut file = options.open(&tmp_path)?;
+        if let Some(mode) = self.file_mode {
+            // do not depend on the umask
+            file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+        }
 
         let raw_data = chunk.raw_data();
         let encoded_size = raw_data.len() as u64;
@@ -410,9 +482,7 @@ impl ChunkStore {
             );
         }
 
-        drop(lock);
-
-        Ok((false, encoded_size))
+        Ok(encoded_size)
     }
 
     pub fn chunk_path(&self, digest:&[u8; 32]) -> (PathBuf, String) {
@@ -449,6 +519,58 @@ impl ChunkStore {
 }
 
 
+// initial delay between retries, doubled after each retry
+const RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+// upper limit for the sum of all delays, so a full disk does not stall uploads for long
+const RETRY_MAX_TOTAL_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Returns true for IO errors which may go away by themselves (e.g. a full disk)
+pub fn is_transient_io_error(err: &Error) -> bool {
+    match err.downcast_ref::<std::io::Error>() {
+        Some(err) => {
+            err.kind() == std::io::ErrorKind::Interrupted
+                || matches!(
+                    err.raw_os_error(),
+                    Some(libc::ENOSPC) | Some(libc::EDQUOT) | Some(libc::EAGAIN) | Some(libc::EBUSY)
+                )
+        }
+        None => false,
+    }
+}
+
+/// Run 'op', retrying up to 'retries' times with exponential backoff if
+/// it fails with a transient IO error. Other errors are returned at once.
+///
+/// Gives up early once the delays would add up to more than 5 seconds. 'op'
+/// must not hold any locks across calls, as this sleeps between them.
+pub fn retry_on_transient_io_error<T, F, L>(retries: u32, mut op: F, mut log: L) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+    L: FnMut(&str),
+{
+    let mut delay = RETRY_BASE_DELAY;
+    let mut total_delay = std::time::Duration::from_secs(0);
+    let mut attempt = 0;
+
+    loop {
+        match op() {
+            Ok(res) => return Ok(res),
+            Err(err)
+                if attempt < retries
+                    && total_delay + delay <= RETRY_MAX_TOTAL_DELAY
+                    && is_transient_io_error(&err) =>
+            {
+                attempt += 1;
+                log(&format!("transient error: {} - retry {}/{} in {:?}", err, attempt, retries, delay));
+                std::thread::sleep(delay);
+                total_delay += delay;
+                delay *= 2;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
 #[test]
 fn test_chunk_store1() {
 
@@ -471,9 +593,131 @@ fn test_chunk_store1() {
     let (exists, _) = chunk_store.insert_chunk(&chunk, &digest).unwrap();
     assert!(exists);
 
+    // chunk files are created with the configured mode
+    let mut chunk_store = ChunkStore::open("test", &path).unwrap();
+    chunk_store.set_file_mode(Some(0o600));
+    let (chunk, digest) = super::DataChunkBuilder::new(&[2u8, 3u8]).build().unwrap();
+    chunk_store.insert_chunk(&chunk, &digest).unwrap();
+    let (chunk_path, _) = chunk_store.chunk_path(&digest);
+    let mode = std::fs::metadata(&chunk_path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o600);
 
     let chunk_store = ChunkStore::create("test", &path, user.uid, user.gid);
     assert!(chunk_store.is_err());
 
     if let Err(_e) = std::fs::remove_dir_all(".testdir") { /* ignore */ }
 }
+
+#[test]
+fn test_chunk_store_repair() {
+
+    struct TestSource(std::collections::HashMap<[u8; 32], DataBlob>);
+
+    impl ReadChunk for TestSource {
+        fn read_raw_chunk(&self, digest: &[u8; 32]) -> Result<DataBlob, Error> {
+            match self.0.get(digest) {
+                Some(chunk) => DataBlob::from_raw(chunk.raw_data().to_vec()),
+                None => bail!("no such chunk"),
+            }
+        }
+
+        fn read_chunk(&self, digest: &[u8; 32]) -> Result<Vec<u8>, Error> {
+            self.read_raw_chunk(digest)?.decode(None, Some(digest))
+        }
+    }
+
+    let mut path = std::fs::canonicalize(".").unwrap(); // we need absolute path
+    path.push(".testdir-repair");
+
+    if let Err(_e) = std::fs::remove_dir_all(&path) { /* ignore */ }
+
+    let user = nix::unistd::User::from_uid(nix::unistd::Uid::current()).unwrap().unwrap();
+    let chunk_store = ChunkStore::create("test", &path, user.uid, user.gid).unwrap();
+
+    let (chunk, digest) = super::DataChunkBuilder::new(b"repair me").build().unwrap();
+    let (other_chunk, _) = super::DataChunkBuilder::new(b"something else").build().unwrap();
+
+    chunk_store.insert_chunk(&chunk, &digest).unwrap();
+
+    let (chunk_path, _) = chunk_store.chunk_path(&digest);
+    std::fs::write(&chunk_path, b"corrupted").unwrap();
+
+    // mismatching replacement must not overwrite the local copy
+    let mut chunks = std::collections::HashMap::new();
+    chunks.insert(digest, other_chunk);
+    assert!(chunk_store.repair_chunk(&digest, &TestSource(chunks), None).is_err());
+    assert_eq!(std::fs::read(&chunk_path).unwrap(), b"corrupted");
+
+    // missing replacement
+    let source = TestSource(std::collections::HashMap::new());
+    assert!(chunk_store.repair_chunk(&digest, &source, None).is_err());
+
+    let mut chunks = std::collections::HashMap::new();
+    chunks.insert(digest, DataBlob::from_raw(chunk.raw_data().to_vec()).unwrap());
+    let size = chunk_store.repair_chunk(&digest, &TestSource(chunks), None).unwrap();
+    assert_eq!(size, chunk.raw_size());
+
+    let repaired = DataBlob::load_from_reader(&mut std::fs::File::open(&chunk_path).unwrap()).unwrap();
+    assert_eq!(repaired.decode(None, Some(&digest)).unwrap(), b"repair me");
+
+    if let Err(_e) = std::fs::remove_dir_all(&path) { /* ignore */ }
+}
+
+#[test]
+fn test_chunk_store_insert_retry() {
+
+    let mut path = std::fs::canonicalize(".").unwrap(); // we need absolute path
+    path.push(".testdir-retry");
+
+    if let Err(_e) = std::fs::remove_dir_all(&path) { /* ignore */ }
+
+    let user = nix::unistd::User::from_uid(nix::unistd::Uid::current()).unwrap().unwrap();
+    let chunk_store = ChunkStore::create("test", &path, user.uid, user.gid).unwrap();
+
+    let (chunk, digest) = super::DataChunkBuilder::new(b"retry me").build().unwrap();
+
+    // fails once with a full disk, then succeeds
+    let mut failures = 1;
+    let mut log = Vec::new();
+    let (is_duplicate, _) = retry_on_transient_io_error(
+        3,
+        || {
+            if failures > 0 {
+                failures -= 1;
+                return Err(std::io::Error::from_raw_os_error(libc::ENOSPC).into());
+            }
+            chunk_store.insert_chunk(&chunk, &digest)
+        },
+        |msg| log.push(msg.to_string()),
+    ).unwrap();
+
+    assert!(!is_duplicate);
+    assert_eq!(log.len(), 1);
+    let (chunk_path, _) = chunk_store.chunk_path(&digest);
+    let stored = DataBlob::load_from_reader(&mut std::fs::File::open(&chunk_path).unwrap()).unwrap();
+    assert_eq!(stored.decode(None, Some(&digest)).unwrap(), b"retry me");
+
+    // fatal errors are not retried
+    let mut calls = 0;
+    let result: Result<(), Error> = retry_on_transient_io_error(
+        3,
+        || { calls += 1; Err(std::io::Error::from_raw_os_error(libc::EACCES).into()) },
+        |_| {},
+    );
+    assert!(result.is_err());
+    assert_eq!(calls, 1);
+
+    // retries are bounded
+    let mut calls = 0;
+    let result: Result<(), Error> = retry_on_transient_io_error(
+        2,
+        || { calls += 1; Err(std::io::Error::from_raw_os_error(libc::ENOSPC).into()) },
+        |_| {},
+    );
+    assert!(result.is_err());
+    assert_eq!(calls, 3);
+
+    assert!(chunk_store.insert_chunk_with_retry(&chunk, &digest, 0, |_| {}).unwrap().0);
+
+    if let Err(_e) = std::fs::remove_dir_all(&path) { /* ignore */ }
+}
@@ -210,9 +210,40 @@ impl CryptConfig {
         tag: &[u8; 16],
     ) -> Result<Vec<u8>, Error> {
 
-        let dec = Vec::with_capacity(1024*1024);
+        let mut dec = Vec::with_capacity(1024*1024);
+        self.decode_compressed_chunk_into(data, iv, tag, &mut dec)?;
+        Ok(dec)
+    }
 
-        let mut decompressor = zstd::stream::write::Decoder::new(dec)?;
+    /// Like `decode_compressed_chunk`, but appends the decoded data to ``output``.
+    ///
+    /// On error, ``output`` is truncated to its original length, so no
+    /// unauthenticated data is left behind.
+    pub fn decode_compressed_chunk_into(
+        &self,
+        data: &[u8],
+        iv: &[u8; 16],
+        tag: &[u8; 16],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+
+        let start = output.len();
+        let result = self.do_decode_compressed_chunk_into(data, iv, tag, &mut *output);
+        if result.is_err() {
+            output.truncate(start);
+        }
+        result
+    }
+
+    fn do_decode_compressed_chunk_into(
+        &self,
+        data: &[u8],
+        iv: &[u8; 16],
+        tag: &[u8; 16],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+
+        let mut decompressor = zstd::stream::write::Decoder::new(output)?;
 
         let mut c = self.data_crypter(iv, Mode::Decrypt)?;
 
@@ -240,7 +271,7 @@ impl CryptConfig {
 
         decompressor.flush()?;
 
-        Ok(decompressor.into_inner())
+        Ok(())
     }
 
     /// Decrypt data, verify tag.
@@ -262,4 +293,39 @@ impl CryptConfig {
 
         Ok(decr_data)
     }
+
+    /// Like `decode_uncompressed_chunk`, but appends the decrypted data to ``output``.
+    ///
+    /// On error, ``output`` is truncated to its original length, so no
+    /// unauthenticated data is left behind.
+    pub fn decode_uncompressed_chunk_into(
+        &self,
+        data: &[u8],
+        iv: &[u8; 16],
+        tag: &[u8; 16],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+
+        let start = output.len();
+        output.resize(start + data.len() + self.cipher.block_size(), 0);
+
+        let result = (|| -> Result<usize, Error> {
+            let mut c = self.data_crypter(iv, Mode::Decrypt)?;
+            let count = c.update(data, &mut output[start..])?;
+            c.set_tag(tag)?;
+            let rest = c.finalize(&mut output[(start + count)..])?;
+            Ok(count + rest)
+        })();
+
+        match result {
+            Ok(len) => {
+                output.truncate(start + len);
+                Ok(())
+            }
+            Err(err) => {
+                output.truncate(start);
+                Err(err)
+            }
+        }
+    }
 }
@@ -8,6 +8,10 @@ use super::{CryptConfig, CryptMode};
 
 const MAX_BLOB_SIZE: usize = 128*1024*1024;
 
+// compute the CRC of bigger blobs using multiple threads
+const PARALLEL_CRC_THRESHOLD: usize = 8*1024*1024;
+const PARALLEL_CRC_THREADS: usize = 4;
+
 /// Encoded data chunk with digest and positional information
 pub struct ChunkInfo {
     pub chunk: DataBlob,
@@ -64,11 +68,18 @@ impl DataBlob {
     }
 
     /// compute the CRC32 checksum
+    ///
+    /// Blobs bigger than `PARALLEL_CRC_THRESHOLD` are processed in parallel.
     pub fn compute_crc(&self) -> u32 {
-        let mut hasher = crc32fast::Hasher::new();
         let start = header_size(self.magic()); // start after HEAD
-        hasher.update(&self.raw_data[start..]);
-        hasher.finalize()
+        let data = &self.raw_data[start..];
+        if data.len() >= PARALLEL_CRC_THRESHOLD {
+            compute_crc_parallel(data, PARALLEL_CRC_THREADS)
+        } else {
+            let mut hasher = crc32fast::Hasher::new();
+            hasher.update(data);
+            hasher.finalize()
+        }
     }
 
     // verify the CRC32 checksum
@@ -86,6 +97,19 @@ impl DataBlob {
         config: Option<&CryptConfig>,
         compress: bool,
     ) -> Result<Self, Error> {
+        Self::encode_with_level(data, config, compress, 1)
+    }
+
+    /// Like `encode`, but use zstd compression level 'level'
+    ///
+    /// Higher levels produce smaller blobs at the cost of CPU time. The
+    /// level is not stored in the blob, so decoding works for any level.
+    pub fn encode_with_level(
+        data: &[u8],
+        config: Option<&CryptConfig>,
+        compress: bool,
+        level: i32,
+    ) -> Result<Self, Error> {
 
         if data.len() > MAX_BLOB_SIZE {
             bail!("data blob too large ({} bytes).", data.len());
@@ -95,7 +119,7 @@ impl DataBlob {
 
             let compr_data;
             let (_compress, data, magic) = if compress {
-                compr_data = zstd::block::compress(data, 1)?;
+                compr_data = zstd::block::compress(data, level)?;
                 // Note: We only use compression if result is shorter
                 if compr_data.len() < data.len() {
                     (true, &compr_data[..], ENCR_COMPR_BLOB_MAGIC_1_0)
@@ -143,7 +167,7 @@ impl DataBlob {
                     comp_data.write_le_value(head)?;
                 }
 
-                zstd::stream::copy_encode(data, &mut comp_data, 1)?;
+                zstd::stream::copy_encode(data, &mut comp_data, level)?;
 
                 if comp_data.len() < max_data_len {
                     let mut blob = DataBlob { raw_data: comp_data };
@@ -186,26 +210,32 @@ impl DataBlob {
 
     /// Decode blob data
     pub fn decode(&self, config: Option<&CryptConfig>, digest: Option<&[u8; 32]>) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        self.decode_into(&mut data, config, digest)?;
+        Ok(data)
+    }
+
+    /// Decode blob data into ``buf``
+    ///
+    /// The buffer is cleared first, but keeps its capacity, so it can be reused to decode many
+    /// blobs without allocating new memory each time.
+    pub fn decode_into(
+        &self,
+        buf: &mut Vec<u8>,
+        config: Option<&CryptConfig>,
+        digest: Option<&[u8; 32]>,
+    ) -> Result<(), Error> {
+
+        buf.clear();
 
         let magic = self.magic();
 
-        if magic == &UNCOMPRESSED_BLOB_MAGIC_1_0 {
-            let data_start = std::mem::size_of::<DataBlobHeader>();
-            let data = self.raw_data[data_start..].to_vec();
-            if let Some(digest) = digest {
-                Self::verify_digest(&data, None, digest)?;
-            }
-            Ok(data)
-        } else if magic == &COMPRESSED_BLOB_MAGIC_1_0 {
-            let data_start = std::mem::size_of::<DataBlobHeader>();
-            let mut reader = &self.raw_data[data_start..];
-            let data = zstd::stream::decode_all(&mut reader)?;
-            // zstd::block::decompress is abou 10% slower
-            // let data = zstd::block::decompress(&self.raw_data[data_start..], MAX_BLOB_SIZE)?;
+        if magic == &UNCOMPRESSED_BLOB_MAGIC_1_0 || magic == &COMPRESSED_BLOB_MAGIC_1_0 {
+            self.decode_to_writer(None, &mut *buf)?;
             if let Some(digest) = digest {
-                Self::verify_digest(&data, None, digest)?;
+                Self::verify_digest(buf, None, digest)?;
             }
-            Ok(data)
+            Ok(())
         } else if magic == &ENCR_COMPR_BLOB_MAGIC_1_0 || magic == &ENCRYPTED_BLOB_MAGIC_1_0 {
             let header_len = std::mem::size_of::<EncryptedDataBlobHeader>();
             let head = unsafe {
@@ -213,15 +243,16 @@ impl DataBlob {
             };
 
             if let Some(config) = config  {
-                let data = if magic == &ENCR_COMPR_BLOB_MAGIC_1_0 {
-                    config.decode_compressed_chunk(&self.raw_data[header_len..], &head.iv, &head.tag)?
+                // the crypt config leaves ``buf`` empty if the tag does not match
+                if magic == &ENCR_COMPR_BLOB_MAGIC_1_0 {
+                    config.decode_compressed_chunk_into(&self.raw_data[header_len..], &head.iv, &head.tag, buf)?;
                 } else {
-                    config.decode_uncompressed_chunk(&self.raw_data[header_len..], &head.iv, &head.tag)?
-                };
+                    config.decode_uncompressed_chunk_into(&self.raw_data[header_len..], &head.iv, &head.tag, buf)?;
+                }
                 if let Some(digest) = digest {
-                    Self::verify_digest(&data, Some(config), digest)?;
+                    Self::verify_digest(buf, Some(config), digest)?;
                 }
-                Ok(data)
+                Ok(())
             } else {
                 bail!("unable to decrypt blob - missing CryptConfig");
             }
@@ -230,6 +261,53 @@ impl DataBlob {
         }
     }
 
+    /// Decode blob data and stream it to ``output``
+    ///
+    /// Compressed data is decompressed while writing, so the decoded data is
+    /// never kept in memory as a whole. Encrypted data is decrypted (and
+    /// authenticated) first. The digest is not verified.
+    pub fn decode_to_writer<W: std::io::Write>(
+        &self,
+        config: Option<&CryptConfig>,
+        mut output: W,
+    ) -> Result<(), Error> {
+
+        let magic = self.magic();
+
+        if magic == &UNCOMPRESSED_BLOB_MAGIC_1_0 {
+            let data_start = std::mem::size_of::<DataBlobHeader>();
+            output.write_all(&self.raw_data[data_start..])?;
+        } else if magic == &COMPRESSED_BLOB_MAGIC_1_0 {
+            let data_start = std::mem::size_of::<DataBlobHeader>();
+            let mut reader = &self.raw_data[data_start..];
+            // zstd::block::decompress is about 10% slower and needs a size limit
+            zstd::stream::copy_decode(&mut reader, &mut output)?;
+        } else if magic == &ENCR_COMPR_BLOB_MAGIC_1_0 || magic == &ENCRYPTED_BLOB_MAGIC_1_0 {
+            let config = match config {
+                Some(config) => config,
+                None => bail!("unable to decrypt blob - missing CryptConfig"),
+            };
+
+            let header_len = std::mem::size_of::<EncryptedDataBlobHeader>();
+            let head = unsafe {
+                (&self.raw_data[..header_len]).read_le_value::<EncryptedDataBlobHeader>()?
+            };
+
+            // do not write unauthenticated data, so decrypt everything first
+            let data = config.decode_uncompressed_chunk(&self.raw_data[header_len..], &head.iv, &head.tag)?;
+
+            if magic == &ENCR_COMPR_BLOB_MAGIC_1_0 {
+                zstd::stream::copy_decode(&data[..], &mut output)?;
+            } else {
+                output.write_all(&data)?;
+            }
+        } else {
+            bail!("Invalid blob magic number.");
+        }
+
+        Ok(())
+    }
+
     /// Load blob from ``reader``, verify CRC
     pub fn load_from_reader(reader: &mut dyn std::io::Read) -> Result<Self, Error> {
 
@@ -277,6 +355,12 @@ impl DataBlob {
         magic == &ENCR_COMPR_BLOB_MAGIC_1_0 || magic == &ENCRYPTED_BLOB_MAGIC_1_0
     }
 
+    /// Returns if chunk is compressed
+    pub fn is_compressed(&self) -> bool {
+        let magic = self.magic();
+        magic == &ENCR_COMPR_BLOB_MAGIC_1_0 || magic == &COMPRESSED_BLOB_MAGIC_1_0
+    }
+
     /// Verify digest and data length for unencrypted chunks.
     ///
     /// To do that, we need to decompress data first. Please note that
@@ -289,9 +373,7 @@ impl DataBlob {
         expected_digest: &[u8; 32],
     ) -> Result<(), Error> {
 
-        let magic = self.magic();
-
-        if magic == &ENCR_COMPR_BLOB_MAGIC_1_0 || magic == &ENCRYPTED_BLOB_MAGIC_1_0 {
+        if self.is_encrypted() {
             return Ok(());
         }
 
@@ -305,6 +387,27 @@ impl DataBlob {
         Ok(())
     }
 
+    /// Verify that this blob is a valid replacement for the chunk with 'expected_digest'.
+    ///
+    /// Checks the CRC and decodes the data to compare the digest. Encrypted
+    /// chunks can only be verified if the matching 'config' is given.
+    pub fn verify_replacement(
+        &self,
+        config: Option<&CryptConfig>,
+        expected_digest: &[u8; 32],
+    ) -> Result<(), Error> {
+        self.verify_crc()?;
+
+        if self.is_encrypted() && config.is_none() {
+            bail!("unable to verify encrypted replacement chunk without key");
+        }
+
+        // verifies digest!
+        self.decode(config, Some(expected_digest))?;
+
+        Ok(())
+    }
+
     fn verify_digest(
         data: &[u8],
         config: Option<&CryptConfig>,
@@ -323,6 +426,34 @@ impl DataBlob {
     }
 }
 
+// Compute the CRC32 of 'data' using up to 'threads' threads, combining the partial checksums.
+fn compute_crc_parallel(data: &[u8], threads: usize) -> u32 {
+    let threads = threads.max(1);
+    let part_size = ((data.len() + threads - 1) / threads).max(1);
+
+    let hashers: Vec<crc32fast::Hasher> = crossbeam_utils::thread::scope(|scope| {
+        let handles: Vec<_> = data
+            .chunks(part_size)
+            .map(|part| {
+                scope.spawn(move |_| {
+                    let mut hasher = crc32fast::Hasher::new();
+                    hasher.update(part);
+                    hasher
+                })
+            })
+            .collect();
+
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    })
+    .unwrap();
+
+    let mut hasher = crc32fast::Hasher::new();
+    for part in hashers.iter() {
+        hasher.combine(part);
+    }
+    hasher.finalize()
+}
+
 /// Builder for chunk DataBlobs
 ///
 /// Main purpose is to centralize digest computation. Digest
@@ -418,3 +549,166 @@ impl <'a, 'b> DataChunkBuilder<'a, 'b> {
     }
 
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compute_crc_parallel() -> Result<(), Error> {
+        let data: Vec<u8> = (0..(PARALLEL_CRC_THRESHOLD as u32 + 12345))
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&data);
+        let expected = hasher.finalize();
+
+        for threads in [1, 2, 3, 4, 7, 16].iter() {
+            assert_eq!(compute_crc_parallel(&data, *threads), expected);
+        }
+        assert_eq!(compute_crc_parallel(&data[..5], 16), crc32fast::hash(&data[..5]));
+        assert_eq!(compute_crc_parallel(&[], 4), crc32fast::hash(&[]));
+
+        // compute_crc uses the parallel path for blobs this big
+        let blob = DataBlob::encode(&data, None, false)?;
+        let start = header_size(blob.magic());
+        assert_eq!(blob.compute_crc(), crc32fast::hash(&blob.raw_data()[start..]));
+        blob.verify_crc()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_into() -> Result<(), Error> {
+        let config = CryptConfig::new([1u8; 32])?;
+
+        let inputs: Vec<Vec<u8>> = vec![
+            (0..100_000u32).map(|i| (i % 255) as u8).collect(),
+            b"small blob".to_vec(),
+            vec![0u8; 64 * 1024],
+            Vec::new(),
+        ];
+
+        let mut buf = Vec::new();
+
+        for crypt_config in [None, Some(&config)].iter() {
+            for compress in [false, true].iter() {
+                for data in inputs.iter() {
+                    let blob = DataBlob::encode(data, *crypt_config, *compress)?;
+                    let capacity = buf.capacity();
+
+                    blob.decode_into(&mut buf, *crypt_config, None)?;
+                    assert_eq!(&buf, data);
+                    assert!(buf.capacity() >= capacity);
+
+                    // must match the allocating variant
+                    assert_eq!(blob.decode(*crypt_config, None)?, buf);
+                }
+            }
+        }
+
+        // digest is verified
+        let data = &inputs[1];
+        let blob = DataBlob::encode(data, None, true)?;
+        blob.decode_into(&mut buf, None, Some(&openssl::sha::sha256(data)))?;
+        assert!(blob.decode_into(&mut buf, None, Some(&[0u8; 32])).is_err());
+
+        // missing crypt config
+        let blob = DataBlob::encode(data, Some(&config), false)?;
+        assert!(blob.decode_into(&mut buf, None, None).is_err());
+
+        // no unauthenticated data is left in the buffer
+        let wrong_config = CryptConfig::new([2u8; 32])?;
+        for compress in [false, true].iter() {
+            let blob = DataBlob::encode(&inputs[0], Some(&config), *compress)?;
+            assert!(blob.decode_into(&mut buf, Some(&wrong_config), None).is_err());
+            assert!(buf.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_to_writer() -> Result<(), Error> {
+        let config = CryptConfig::new([1u8; 32])?;
+
+        // decodes to more than the old 16MiB block decompression limit
+        let data: Vec<u8> = (0..20 * 1024 * 1024u32).map(|i| (i >> 16) as u8).collect();
+
+        for crypt_config in [None, Some(&config)].iter() {
+            for compress in [false, true].iter() {
+                let blob = DataBlob::encode(&data, *crypt_config, *compress)?;
+
+                let mut output = Vec::new();
+                blob.decode_to_writer(*crypt_config, &mut output)?;
+                assert!(output == data);
+            }
+        }
+
+        let blob = DataBlob::encode(&data[..1024], Some(&config), true)?;
+        assert!(blob.decode_to_writer(None, std::io::sink()).is_err());
+
+        // tampered data must not be written
+        let mut raw_data = blob.raw_data().to_vec();
+        let last = raw_data.len() - 1;
+        raw_data[last] ^= 1;
+        let blob = DataBlob::from_raw(raw_data)?;
+        let mut output = Vec::new();
+        assert!(blob.decode_to_writer(Some(&config), &mut output).is_err());
+        assert!(output.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_predicates() -> Result<(), Error> {
+        let config = CryptConfig::new([1u8; 32])?;
+        let data = vec![0u8; 64 * 1024];
+
+        let blob = DataBlob::encode(&data, None, false)?;
+        assert!(!blob.is_encrypted() && !blob.is_compressed());
+
+        let blob = DataBlob::encode(&data, None, true)?;
+        assert!(!blob.is_encrypted() && blob.is_compressed());
+
+        let blob = DataBlob::encode(&data, Some(&config), false)?;
+        assert!(blob.is_encrypted() && !blob.is_compressed());
+
+        let blob = DataBlob::encode(&data, Some(&config), true)?;
+        assert!(blob.is_encrypted() && blob.is_compressed());
+
+        // incompressible data is stored uncompressed
+        let blob = DataBlob::encode(b"x", None, true)?;
+        assert!(!blob.is_compressed());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_with_level() -> Result<(), Error> {
+        let config = CryptConfig::new([1u8; 32])?;
+
+        let data: Vec<u8> = (0..256 * 1024u32)
+            .map(|i| ((i / 7) % 13) as u8 ^ ((i >> 12) as u8))
+            .collect();
+
+        for crypt_config in [None, Some(&config)].iter() {
+            let blob = DataBlob::encode_with_level(&data, *crypt_config, true, 19)?;
+            assert!(blob.is_compressed());
+            assert!(blob.raw_size() < data.len() as u64);
+            blob.verify_crc()?;
+            let digest = match crypt_config {
+                Some(config) => config.compute_digest(&data),
+                None => openssl::sha::sha256(&data),
+            };
+            assert_eq!(blob.decode(*crypt_config, Some(&digest))?, data);
+
+            // default level encoding decodes the same way
+            let blob1 = DataBlob::encode(&data, *crypt_config, true)?;
+            assert_eq!(blob1.decode(*crypt_config, None)?, data);
+        }
+
+        Ok(())
+    }
+}
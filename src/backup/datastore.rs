@@ -18,7 +18,7 @@ use super::dynamic_index::{DynamicIndexReader, DynamicIndexWriter};
 use super::fixed_index::{FixedIndexReader, FixedIndexWriter};
 use super::manifest::{MANIFEST_BLOB_NAME, MANIFEST_LOCK_NAME, CLIENT_LOG_BLOB_NAME, BackupManifest};
 use super::index::*;
-use super::{DataBlob, ArchiveType, archive_type};
+use super::{CryptConfig, DataBlob, ReadChunk, ArchiveType, archive_type};
 use crate::config::datastore::{self, DataStoreConfig};
 use crate::task::TaskState;
 use crate::tools;
@@ -40,6 +40,8 @@ pub struct DataStore {
     gc_mutex: Mutex<()>,
     last_gc_status: Mutex<GarbageCollectionStatus>,
     verify_new: bool,
+    file_mode: Option<u32>,
+    chunk_write_retries: u32,
 }
 
 impl DataStore {
@@ -55,7 +57,9 @@ impl DataStore {
         if let Some(datastore) = map.get(name) {
             // Compare Config - if changed, create new Datastore object!
             if datastore.chunk_store.base == path &&
-                datastore.verify_new == config.verify_new.unwrap_or(false)
+                datastore.verify_new == config.verify_new.unwrap_or(false) &&
+                datastore.file_mode == config.file_mode()? &&
+                datastore.chunk_write_retries == config.chunk_write_retries.unwrap_or(0)
             {
                 return Ok(datastore.clone());
             }
@@ -70,7 +74,10 @@ impl DataStore {
     }
 
     fn open_with_path(store_name: &str, path: &Path, config: DataStoreConfig) -> Result<Self, Error> {
-        let chunk_store = ChunkStore::open(store_name, path)?;
+        let file_mode = config.file_mode()?;
+
+        let mut chunk_store = ChunkStore::open(store_name, path)?;
+        chunk_store.set_file_mode(file_mode);
 
         let mut gc_status_path = chunk_store.base_path();
         gc_status_path.push(".gc-status");
@@ -92,6 +99,8 @@ impl DataStore {
             gc_mutex: Mutex::new(()),
             last_gc_status: Mutex::new(gc_status),
             verify_new: config.verify_new.unwrap_or(false),
+            file_mode,
+            chunk_write_retries: config.chunk_write_retries.unwrap_or(0),
         })
     }
 
@@ -106,7 +115,8 @@ impl DataStore {
 
     pub fn create_fixed_writer<P: AsRef<Path>>(&self, filename: P, size: usize, chunk_size: usize) -> Result<FixedIndexWriter, Error> {
 
-        let index = FixedIndexWriter::create(self.chunk_store.clone(), filename.as_ref(), size, chunk_size)?;
+        let index = FixedIndexWriter::create(
+            self.chunk_store.clone(), filename.as_ref(), size, chunk_size, self.file_mode)?;
 
         Ok(index)
     }
@@ -125,7 +135,7 @@ impl DataStore {
     ) -> Result<DynamicIndexWriter, Error> {
 
         let index = DynamicIndexWriter::create(
-            self.chunk_store.clone(), filename.as_ref())?;
+            self.chunk_store.clone(), filename.as_ref(), self.file_mode)?;
 
         Ok(index)
     }
@@ -232,9 +242,15 @@ impl DataStore {
 
     /// Returns the absolute path for backup_dir
     pub fn snapshot_path(&self, backup_dir: &BackupDir) -> PathBuf {
-        let mut full_path = self.base_path();
-        full_path.push(backup_dir.relative_path());
-        full_path
+        backup_dir.full_path(&self.base_path())
+    }
+
+    /// Returns the snapshots of a group (oldest first) together with their incremental base.
+    pub fn incremental_chain(
+        &self,
+        backup_group: &BackupGroup,
+    ) -> Result<Vec<(BackupDir, Option<BackupDir>)>, Error> {
+        backup_group.incremental_chain(&self.base_path())
     }
 
     /// Remove a complete backup group including all snapshots
@@ -702,6 +718,27 @@ impl DataStore {
         self.chunk_store.insert_chunk(chunk, digest)
     }
 
+    /// Like `insert_chunk`, but retry on transient IO errors as configured
+    /// for this datastore. Each retry is reported to 'log'.
+    pub fn insert_chunk_with_retry<L: FnMut(&str)>(
+        &self,
+        chunk: &DataBlob,
+        digest: &[u8; 32],
+        log: L,
+    ) -> Result<(bool, u64), Error> {
+        self.chunk_store.insert_chunk_with_retry(chunk, digest, self.chunk_write_retries, log)
+    }
+
+    /// Replace a corrupt chunk with a verified copy from 'source'
+    pub fn repair_chunk(
+        &self,
+        digest: &[u8; 32],
+        source: &dyn ReadChunk,
+        config: Option<&CryptConfig>,
+    ) -> Result<u64, Error> {
+        self.chunk_store.repair_chunk(digest, source, config)
+    }
+
     pub fn load_blob(&self, backup_dir: &BackupDir, filename: &str) -> Result<DataBlob, Error> {
         let mut path = self.base_path();
         path.push(backup_dir.relative_path());
@@ -805,7 +842,7 @@ impl DataStore {
         path.push(MANIFEST_BLOB_NAME);
 
         // atomic replace invalidates flock - no other writes past this point!
-        replace_file(&path, raw_data, CreateOptions::new())?;
+        replace_file(&path, raw_data, self.file_create_options())?;
 
         Ok(())
     }
@@ -813,4 +850,18 @@ impl DataStore {
     pub fn verify_new(&self) -> bool {
         self.verify_new
     }
+
+    /// Permissions for newly created backup data files, `None` means default permissions.
+    pub fn file_mode(&self) -> Option<u32> {
+        self.file_mode
+    }
+
+    /// Options to create backup data files with the configured permissions.
+    pub fn file_create_options(&self) -> CreateOptions {
+        let options = CreateOptions::new();
+        match self.file_mode {
+            Some(mode) => options.perm(nix::sys::stat::Mode::from_bits_truncate(mode)),
+            None => options,
+        }
+    }
 }
@@ -497,19 +497,18 @@ impl Drop for DynamicIndexWriter {
 }
 
 impl DynamicIndexWriter {
-    pub fn create(store: Arc<ChunkStore>, path: &Path) -> Result<Self, Error> {
+    pub fn create(
+        store: Arc<ChunkStore>,
+        path: &Path,
+        file_mode: Option<u32>,
+    ) -> Result<Self, Error> {
         let shared_lock = store.try_shared_lock()?;
 
         let full_path = store.relative_path(path);
         let mut tmp_path = full_path.clone();
         tmp_path.set_extension("tmp_didx");
 
-        let file = std::fs::OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .read(true)
-            .write(true)
-            .open(&tmp_path)?;
+        let file = tools::fs::create_file_with_mode(&tmp_path, file_mode)?;
 
         let mut writer = BufWriter::with_capacity(1024 * 1024, file);
 
@@ -572,6 +571,14 @@ impl DynamicIndexWriter {
         Ok(index_csum)
     }
 
+    /// Returns the checksum of the digest list written so far.
+    pub fn current_csum(&self) -> Result<[u8; 32], Error> {
+        match self.csum {
+            Some(ref csum) if !self.closed => Ok(csum.clone().finish()),
+            _ => bail!("cannot get checksum of closed dynamic index file {:?}", self.filename),
+        }
+    }
+
     // fixme: rename to add_digest
     pub fn add_chunk(&mut self, offset: u64, digest: &[u8; 32]) -> Result<(), Error> {
         if self.closed {
@@ -592,6 +599,19 @@ impl DynamicIndexWriter {
         self.writer.write_all(digest)?;
         Ok(())
     }
+
+    /// Copy all chunks of 'reader' into this (empty) index
+    ///
+    /// Returns the number of chunks and the resulting end offset.
+    pub fn clone_data_from(&mut self, reader: &DynamicIndexReader) -> Result<(u64, u64), Error> {
+        let mut end = 0;
+        for pos in 0..reader.index_count() {
+            end = reader.chunk_end(pos);
+            self.add_chunk(end, reader.chunk_digest(pos))?;
+        }
+
+        Ok((reader.index_count() as u64, end))
+    }
 }
 
 /// Writer which splits a binary stream into dynamic sized chunks
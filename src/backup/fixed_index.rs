@@ -259,6 +259,7 @@ impl FixedIndexWriter {
         path: &Path,
         size: usize,
         chunk_size: usize,
+        file_mode: Option<u32>,
     ) -> Result<Self, Error> {
         let shared_lock = store.try_shared_lock()?;
 
@@ -266,12 +267,7 @@ impl FixedIndexWriter {
         let mut tmp_path = full_path.clone();
         tmp_path.set_extension("tmp_fidx");
 
-        let mut file = std::fs::OpenOptions::new()
-            .create(true)
-            .truncate(true)
-            .read(true)
-            .write(true)
-            .open(&tmp_path)?;
+        let mut file = tools::fs::create_file_with_mode(&tmp_path, file_mode)?;
 
         let header_size = std::mem::size_of::<FixedIndexHeader>();
 
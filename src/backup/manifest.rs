@@ -5,6 +5,7 @@ use std::path::Path;
 use serde_json::{json, Value};
 use ::serde::{Deserialize, Serialize};
 
+use crate::api2::types::SnapshotVerifyState;
 use crate::backup::{BackupDir, CryptMode, CryptConfig, Fingerprint};
 
 pub const MANIFEST_BLOB_NAME: &str = "index.json.blob";
@@ -197,6 +198,16 @@ impl BackupManifest {
         }
     }
 
+    /// Returns the state of the last verification, or None if there is no (valid) state stored.
+    pub fn verify_state(&self) -> Option<SnapshotVerifyState> {
+        serde_json::from_value(self.unprotected["verify_state"].clone()).ok()
+    }
+
+    /// Returns the snapshot this (incremental) backup is based on, or None for full backups.
+    pub fn base_snapshot(&self) -> Option<BackupDir> {
+        self.unprotected["base_snapshot"].as_str()?.parse().ok()
+    }
+
     /// Checks if a BackupManifest and a CryptConfig share a valid fingerprint combination.
     ///
     /// An unsigned manifest is valid with any or no CryptConfig.
@@ -315,3 +326,30 @@ fn test_manifest_signature() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_manifest_verify_state() -> Result<(), Error> {
+
+    use crate::api2::types::VerifyState;
+
+    let snapshot: BackupDir = "host/elsa/2020-06-26T13:56:05Z".parse()?;
+    let mut manifest = BackupManifest::new(snapshot);
+
+    assert!(manifest.verify_state().is_none());
+
+    let upid = "UPID:elsa:00000B6F:0000A47E:00000001:5EF5FDB5:verificationjob::root@pam:";
+
+    manifest.unprotected["verify_state"] = json!({ "upid": upid, "state": "ok" });
+    let verify_state = manifest.verify_state().expect("verify state missing");
+    assert_eq!(verify_state.state, VerifyState::Ok);
+    assert_eq!(verify_state.upid.to_string(), upid);
+
+    manifest.unprotected["verify_state"] = json!({ "upid": upid, "state": "failed" });
+    assert_eq!(manifest.verify_state().map(|v| v.state), Some(VerifyState::Failed));
+
+    // unparsable state
+    manifest.unprotected["verify_state"] = json!({ "state": "ok" });
+    assert!(manifest.verify_state().is_none());
+
+    Ok(())
+}
@@ -57,6 +57,7 @@ pub const DRIVE_OPTION_LIST_SCHEMA: Schema =
     .min_length(1)
     .schema();
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     config::{
         self,
@@ -262,7 +263,7 @@ fn bsr(count: usize, param: Value) -> Result<(), Error> {
 /// Read Cartridge Memory
 fn cartridge_memory(param: Value) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut handle = get_tape_handle(&param)?;
     let result = handle.cartridge_memory();
@@ -313,7 +314,7 @@ fn cartridge_memory(param: Value) -> Result<(), Error> {
 /// Read Tape Alert Flags
 fn tape_alert_flags(param: Value) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut handle = get_tape_handle(&param)?;
     let result = handle.tape_alert_flags()
@@ -622,7 +623,7 @@ fn rewind(param: Value) -> Result<(), Error> {
 /// Scan for existing tape changer devices
 fn scan(param: Value) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let list = lto_tape_device_list();
 
@@ -668,7 +669,7 @@ fn scan(param: Value) -> Result<(), Error> {
 /// Drive Status
 fn status(param: Value) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut handle = get_tape_handle(&param)?;
     let result = handle.get_drive_and_media_status();
@@ -743,7 +744,7 @@ fn unlock(param: Value) -> Result<(), Error> {
 /// Volume Statistics
 fn volume_statistics(param: Value) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut handle = get_tape_handle(&param)?;
     let result = handle.volume_statistics();
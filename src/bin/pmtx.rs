@@ -25,6 +25,7 @@ use proxmox::{
     },
 };
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     tools::sgutils2::{
         scsi_inquiry,
@@ -106,7 +107,7 @@ fn inquiry(
     param: Value,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let result: Result<_, Error> = proxmox::try_block!({
         let mut file = get_changer_handle(&param)?;
@@ -294,7 +295,7 @@ fn status(
     param: Value,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let result: Result<_, Error> = proxmox::try_block!({
         let mut file = get_changer_handle(&param)?;
@@ -400,7 +401,7 @@ fn transfer(
 /// Scan for existing tape changer devices
 fn scan(param: Value) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let list = linux_tape_changer_list();
 
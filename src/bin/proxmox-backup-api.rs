@@ -55,6 +55,11 @@ async fn run() -> Result<(), Error> {
     }
     let _ = csrf_secret(); // load with lazy_static
 
+    match proxmox_backup::config::node::config() {
+        Ok((node_config, _digest)) => node_config.setup_user_cache(),
+        Err(err) => log::warn!("unable to read node config - {}", err),
+    }
+
     let mut config = server::ApiConfig::new(
         buildcfg::JS_DIR,
         &proxmox_backup::api2::ROUTER,
@@ -30,6 +30,7 @@ use proxmox::{
 };
 use pxar::accessor::{MaybeReady, ReadAt, ReadAtOperation};
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::tools::{
     self,
     StdChannelWriter,
@@ -254,7 +255,7 @@ async fn backup_image<P: AsRef<Path>>(
 /// List backup groups.
 async fn list_backup_groups(param: Value) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let repo = extract_repository_from_value(&param)?;
 
@@ -268,7 +269,7 @@ async fn list_backup_groups(param: Value) -> Result<Value, Error> {
 
     let render_group_path = |_v: &Value, record: &Value| -> Result<String, Error> {
         let item: GroupListItem = serde_json::from_value(record.to_owned())?;
-        let group = BackupGroup::new(item.backup_type, item.backup_id);
+        let group = BackupGroup::new(item.backup_type, item.backup_id)?;
         Ok(group.group_path().to_str().unwrap().to_owned())
     };
 
@@ -404,7 +405,7 @@ fn api_logout(param: Value) -> Result<Value, Error> {
 /// Show client and optional server version
 async fn api_version(param: Value) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut version_info = json!({
         "client": {
@@ -456,7 +457,7 @@ async fn start_garbage_collection(param: Value) -> Result<Value, Error> {
 
     let repo = extract_repository_from_value(&param)?;
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut client = connect(&repo)?;
 
@@ -575,6 +576,11 @@ fn spawn_catalog_upload(
                schema: BACKUP_TIME_SCHEMA,
                optional: true,
            },
+           "ignore-clock-skew": {
+               type: Boolean,
+               description: "Only warn (instead of failing) if the backup-time is too far ahead of the server clock.",
+               optional: true,
+           },
            "chunk-size": {
                schema: CHUNK_SIZE_SCHEMA,
                optional: true,
@@ -621,6 +627,8 @@ async fn create_backup(
 
     let backup_time_opt = param["backup-time"].as_i64();
 
+    let ignore_clock_skew = param["ignore-clock-skew"].as_bool().unwrap_or(false);
+
     let chunk_size_opt = param["chunk-size"].as_u64().map(|v| (v*1024) as usize);
 
     if let Some(size) = chunk_size_opt {
@@ -772,7 +780,8 @@ async fn create_backup(
         &backup_id,
         backup_time,
         verbose,
-        false
+        false,
+        ignore_clock_skew,
     ).await?;
 
     let download_previous_manifest = match client.previous_backup_time().await {
@@ -1043,6 +1052,18 @@ We do not extract '.pxar' archives when writing to standard output.
                description: "Do not fail if directories already exists.",
                optional: true,
            },
+           "max-depth": {
+               type: Integer,
+               description: "Maximum directory depth to restore.",
+               minimum: 1,
+               optional: true,
+               default: proxmox_backup::pxar::DEFAULT_MAX_DEPTH as isize,
+           },
+           "ignore-path-length": {
+               type: Boolean,
+               description: "Do not fail on paths longer than PATH_MAX.",
+               optional: true,
+           },
            keyfile: {
                schema: KEYFILE_SCHEMA,
                optional: true,
@@ -1066,6 +1087,10 @@ async fn restore(param: Value) -> Result<Value, Error> {
 
     let allow_existing_dirs = param["allow-existing-dirs"].as_bool().unwrap_or(false);
 
+    let max_depth = param["max-depth"].as_u64().map(|depth| depth as usize);
+
+    let ignore_path_length = param["ignore-path-length"].as_bool().unwrap_or(false);
+
     let archive_name = tools::required_string_param(&param, "archive-name")?;
 
     let client = connect(&repo)?;
@@ -1144,7 +1169,7 @@ async fn restore(param: Value) -> Result<Value, Error> {
 
     if archive_type == ArchiveType::Blob {
 
-        let mut reader = client.download_blob(&manifest, &archive_name).await?;
+        let mut reader = client.download_blob_reader(&manifest, &archive_name).await?;
 
         if let Some(target) = target {
            let mut writer = std::fs::OpenOptions::new()
@@ -1175,7 +1200,11 @@ async fn restore(param: Value) -> Result<Value, Error> {
             match_list: &[],
             extract_match_default: true,
             allow_existing_dirs,
+            overwrite: false,
             on_error: None,
+            on_progress: None,
+            max_depth,
+            check_path_length: !ignore_path_length,
         };
 
         if let Some(target) = target {
@@ -1266,7 +1295,7 @@ async fn prune_async(mut param: Value) -> Result<Value, Error> {
     let group = tools::required_string_param(&param, "group")?;
     let group: BackupGroup = group.parse()?;
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let quiet = param["quiet"].as_bool().unwrap_or(false);
 
@@ -1343,7 +1372,7 @@ async fn status(param: Value) -> Result<Value, Error> {
 
     let repo = extract_repository_from_value(&param)?;
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let client = connect(&repo)?;
 
@@ -1496,5 +1525,6 @@ fn main() {
     let rpcenv = CliEnvironment::new();
     run_cli_command(cmd_def, rpcenv, Some(|future| {
         proxmox_backup::tools::runtime::main(future)
+            .map_err(proxmox_backup::tools::error_hint::with_hint)
     }));
 }
@@ -5,6 +5,7 @@ use anyhow::{format_err, Error};
 use serde_json::{json, Value};
 
 use proxmox::api::{api, cli::*, RpcEnvironment};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::tools;
 use proxmox_backup::config;
@@ -30,7 +31,7 @@ use proxmox_backup_manager::*;
 /// Start garbage collection for a specific datastore.
 async fn start_garbage_collection(param: Value) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let store = tools::required_string_param(&param, "store")?;
 
@@ -61,7 +62,7 @@ async fn start_garbage_collection(param: Value) -> Result<Value, Error> {
 /// Show garbage collection status for a specific datastore.
 async fn garbage_collection_status(param: Value) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let store = tools::required_string_param(&param, "store")?;
 
@@ -123,7 +124,7 @@ fn garbage_collection_commands() -> CommandLineInterface {
 /// List running server tasks.
 async fn task_list(param: Value) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let client = connect_to_localhost()?;
 
@@ -193,6 +194,50 @@ async fn task_stop(param: Value) -> Result<Value, Error> {
     Ok(Value::Null)
 }
 
+#[api(
+    input: {
+        properties: {
+            upid: {
+                schema: UPID_SCHEMA,
+            },
+        }
+    }
+)]
+/// Pause a running backup task.
+async fn task_pause(param: Value) -> Result<Value, Error> {
+
+    let upid_str = tools::required_string_param(&param, "upid")?;
+
+    let mut client = connect_to_localhost()?;
+
+    let path = format!("api2/json/nodes/localhost/tasks/{}/pause", tools::percent_encode_component(upid_str));
+    let _ = client.post(&path, None).await?;
+
+    Ok(Value::Null)
+}
+
+#[api(
+    input: {
+        properties: {
+            upid: {
+                schema: UPID_SCHEMA,
+            },
+        }
+    }
+)]
+/// Resume a paused task.
+async fn task_resume(param: Value) -> Result<Value, Error> {
+
+    let upid_str = tools::required_string_param(&param, "upid")?;
+
+    let mut client = connect_to_localhost()?;
+
+    let path = format!("api2/json/nodes/localhost/tasks/{}/resume", tools::percent_encode_component(upid_str));
+    let _ = client.post(&path, None).await?;
+
+    Ok(Value::Null)
+}
+
 fn task_mgmt_cli() -> CommandLineInterface {
 
     let task_log_cmd_def = CliCommand::new(&API_METHOD_TASK_LOG)
@@ -201,9 +246,17 @@ fn task_mgmt_cli() -> CommandLineInterface {
     let task_stop_cmd_def = CliCommand::new(&API_METHOD_TASK_STOP)
         .arg_param(&["upid"]);
 
+    let task_pause_cmd_def = CliCommand::new(&API_METHOD_TASK_PAUSE)
+        .arg_param(&["upid"]);
+
+    let task_resume_cmd_def = CliCommand::new(&API_METHOD_TASK_RESUME)
+        .arg_param(&["upid"]);
+
     let cmd_def = CliCommandMap::new()
         .insert("list", CliCommand::new(&API_METHOD_TASK_LIST))
         .insert("log", task_log_cmd_def)
+        .insert("pause", task_pause_cmd_def)
+        .insert("resume", task_resume_cmd_def)
         .insert("stop", task_stop_cmd_def);
 
     cmd_def.into()
@@ -242,7 +295,7 @@ async fn pull_datastore(
     param: Value,
 ) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut client = connect_to_localhost()?;
 
@@ -282,7 +335,7 @@ async fn verify(
     param: Value,
 ) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut client = connect_to_localhost()?;
 
@@ -323,7 +376,7 @@ async fn report() -> Result<Value, Error> {
 )]
 /// List package versions for important Proxmox Backup Server packages.
 async fn get_versions(verbose: bool, param: Value) -> Result<Value, Error> {
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let packages = crate::api2::node::apt::get_versions()?;
     let mut packages = json!(if verbose { &packages[..] } else { &packages[1..2] });
@@ -398,7 +451,7 @@ pub fn complete_remote_datastore_name(_arg: &str, param: &HashMap<String, String
         let remote = param.get("remote").ok_or_else(|| format_err!("no remote"))?;
 
         let data = crate::tools::runtime::block_on(async move {
-            crate::api2::config::remote::scan_remote_datastores(remote.clone()).await
+            crate::api2::config::remote::scan_remote_datastores(remote.clone(), Some(true), None).await
         })?;
 
         for item in data {
@@ -10,6 +10,7 @@ use tokio_stream::wrappers::ReceiverStream;
 
 use proxmox::try_block;
 use proxmox::api::RpcEnvironmentType;
+use proxmox::tools::fs::CreateOptions;
 
 use proxmox_backup::{
     backup::DataStore,
@@ -23,6 +24,10 @@ use proxmox_backup::{
             Job,
         },
         rotate_task_log_archive,
+        cleanup_task_logs,
+        worker_is_active_local,
+        TaskLogRetention,
+        PROXMOX_BACKUP_TASK_DIR,
     },
     tools::systemd::time::{
         parse_calendar_event,
@@ -85,6 +90,11 @@ async fn run() -> Result<(), Error> {
     let _ = public_auth_key(); // load with lazy_static
     let _ = csrf_secret(); // load with lazy_static
 
+    match proxmox_backup::config::node::config() {
+        Ok((node_config, _digest)) => node_config.setup_user_cache(),
+        Err(err) => log::warn!("unable to read node config - {}", err),
+    }
+
     let mut config = ApiConfig::new(
         buildcfg::JS_DIR,
         &proxmox_backup::api2::ROUTER,
@@ -643,12 +653,28 @@ async fn schedule_task_log_rotate() {
                     worker.log("task log archive was not rotated".to_string());
                 }
 
+                // a broken retention setting must not prevent the log rotation below
+                let cleanup = TaskLogRetention::load().and_then(|retention| {
+                    cleanup_task_logs(
+                        std::path::Path::new(PROXMOX_BACKUP_TASK_DIR),
+                        &retention,
+                        worker_is_active_local,
+                    )
+                });
+                match cleanup {
+                    Ok(0) => {}
+                    Ok(removed) => worker.log(format!("removed {} old task logs", removed)),
+                    Err(err) => worker.warn(format!("task log cleanup failed - {}", err)),
+                }
+
                 let max_size = 32 * 1024 * 1024 - 1;
                 let max_files = 14;
+                let backup_user = proxmox_backup::backup::backup_user()?;
+                let options = CreateOptions::new().owner(backup_user.uid).group(backup_user.gid);
                 let mut logrotate = LogRotate::new(buildcfg::API_ACCESS_LOG_FN, true)
                         .ok_or_else(|| format_err!("could not get API access log file names"))?;
 
-                if logrotate.rotate(max_size, None, Some(max_files))? {
+                if logrotate.rotate_if_bigger(max_size, options.clone(), Some(max_files))? {
                     println!("rotated access log, telling daemons to re-open log file");
                     proxmox_backup::tools::runtime::block_on(command_reopen_logfiles())?;
                     worker.log("API access log was rotated".to_string());
@@ -659,7 +685,7 @@ async fn schedule_task_log_rotate() {
                 let mut logrotate = LogRotate::new(buildcfg::API_AUTH_LOG_FN, true)
                         .ok_or_else(|| format_err!("could not get API auth log file names"))?;
 
-                if logrotate.rotate(max_size, None, Some(max_files))? {
+                if logrotate.rotate_if_bigger(max_size, options, Some(max_files))? {
                     worker.log("API authentication log was rotated".to_string());
                 } else {
                     worker.log("API authentication log was not rotated".to_string());
@@ -9,13 +9,14 @@ use serde_json::{json, Value};
 use proxmox::api::{
     api,
     cli::{
-        default_table_format_options, format_and_print_result_full, get_output_format,
+        default_table_format_options, format_and_print_result_full,
         run_cli_command, CliCommand, CliCommandMap, CliEnvironment, ColumnConfig, OUTPUT_FORMAT,
     },
 };
 use pxar::accessor::aio::Accessor;
 use pxar::decoder::aio::Decoder;
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::api2::{helpers, types::ArchiveEntry};
 use proxmox_backup::backup::{
     decrypt_key, BackupDir, BufferedDynamicReader, CatalogReader, CryptConfig, CryptMode,
@@ -242,7 +243,7 @@ async fn list(
         .column(ColumnConfig::new("mtime").header("last modified"))
         .column(ColumnConfig::new("size"));
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     format_and_print_result_full(
         &mut json!(result),
         &API_METHOD_LIST.returns,
@@ -11,13 +11,13 @@ use proxmox::api::{
     cli::{
         OUTPUT_FORMAT,
         ColumnConfig,
-        get_output_format,
         format_and_print_result_full,
         default_table_format_options,
     },
     router::ReturnType,
 };
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::backup::{
     load_and_decrypt_key,
     CryptConfig,
@@ -149,7 +149,7 @@ pub async fn benchmark(
 
     let verbose = param["verbose"].as_bool().unwrap_or(false);
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let crypt_config = match keyfile {
         None => None,
@@ -240,7 +240,8 @@ async fn test_upload_speed(
         "benchmark",
         backup_time,
         false,
-        true
+        true,
+        false,
     ).await?;
 
     if verbose { eprintln!("Start TLS speed test"); }
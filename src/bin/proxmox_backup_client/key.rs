@@ -6,13 +6,14 @@ use serde_json::Value;
 
 use proxmox::api::api;
 use proxmox::api::cli::{
-    format_and_print_result_full, get_output_format, CliCommand, CliCommandMap, ColumnConfig,
+    format_and_print_result_full, CliCommand, CliCommandMap, ColumnConfig,
     OUTPUT_FORMAT,
 };
 use proxmox::api::router::ReturnType;
 use proxmox::sys::linux::tty;
 use proxmox::tools::fs::{file_get_contents, replace_file, CreateOptions};
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     api2::types::{Kdf, KeyInfo, RsaPubKeyInfo, PASSWORD_HINT_SCHEMA},
     backup::{rsa_decrypt_key_config, KeyConfig},
@@ -264,7 +265,7 @@ fn show_key(path: Option<String>, param: Value) -> Result<(), Error> {
 
     let config: KeyConfig = serde_json::from_slice(&file_get_contents(path.clone())?)?;
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut info: KeyInfo = (&config).into();
     info.path = Some(format!("{:?}", path));
@@ -389,7 +390,7 @@ fn show_master_pubkey(path: Option<String>, param: Value) -> Result<(), Error> {
 
     let path = path.canonicalize()?;
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let pem_data = file_get_contents(path.clone())?;
     let rsa = openssl::rsa::Rsa::public_key_from_pem(&pem_data)?;
@@ -8,6 +8,7 @@ use proxmox::{
     tools::fs::file_get_contents,
 };
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     tools,
     api2::types::*,
@@ -61,7 +62,7 @@ async fn list_snapshots(param: Value) -> Result<Value, Error> {
 
     let repo = extract_repository_from_value(&param)?;
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let client = connect(&repo)?;
 
@@ -132,7 +133,7 @@ async fn list_snapshot_files(param: Value) -> Result<Value, Error> {
     let path = tools::required_string_param(&param, "snapshot")?;
     let snapshot: BackupDir = path.parse()?;
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let client = connect(&repo)?;
 
@@ -304,7 +305,7 @@ async fn show_notes(param: Value) -> Result<Value, Error> {
         "backup-time": snapshot.backup_time(),
     });
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let mut result = client.get(&path, Some(args)).await?;
 
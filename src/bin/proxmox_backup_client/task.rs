@@ -3,6 +3,7 @@ use serde_json::{json, Value};
 
 use proxmox::api::{api, cli::*};
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::tools;
 
 use proxmox_backup::client::*;
@@ -45,7 +46,7 @@ use crate::{
 /// List running server tasks for this repo user
 async fn task_list(param: Value) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let repo = extract_repository_from_value(&param)?;
     let client = connect(&repo)?;
@@ -2,6 +2,7 @@ use anyhow::Error;
 use serde_json::Value;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::config;
 use proxmox_backup::api2::{self, types::* };
@@ -19,7 +20,7 @@ use proxmox_backup::api2::{self, types::* };
 /// Datastore list.
 fn list_datastores(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::datastore::API_METHOD_LIST_DATASTORES;
     let mut data = match info.handler {
@@ -53,7 +54,7 @@ fn list_datastores(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Valu
 /// Show datastore configuration
 fn show_datastore(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::datastore::API_METHOD_READ_DATASTORE;
     let mut data = match info.handler {
@@ -2,6 +2,7 @@ use anyhow::{bail, Error};
 use serde_json::Value;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::tools::disks::{
     FileSystemType,
@@ -31,7 +32,7 @@ use proxmox_backup::api2::{self, types::* };
 /// Local disk list.
 fn list_disks(mut param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     param["node"] = "localhost".into();
 
@@ -87,7 +88,7 @@ fn list_disks(mut param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value
 /// Show SMART attributes.
 fn smart_attributes(mut param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     param["node"] = "localhost".into();
 
@@ -199,7 +200,7 @@ async fn create_zpool(
 /// Local zfs pools.
 fn list_zpools(mut param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     param["node"] = "localhost".into();
 
@@ -258,7 +259,7 @@ pub fn zpool_commands() -> CommandLineInterface {
 /// List systemd datastore mount units.
 fn list_datastore_mounts(mut param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     param["node"] = "localhost".into();
 
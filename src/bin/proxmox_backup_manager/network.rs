@@ -2,6 +2,7 @@ use anyhow::Error;
 use serde_json::Value;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::config;
 use proxmox_backup::api2;
@@ -19,7 +20,7 @@ use proxmox_backup::api2;
 /// Network device list.
 fn list_network_devices(mut param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     param["node"] = "localhost".into();
 
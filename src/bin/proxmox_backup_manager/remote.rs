@@ -2,6 +2,7 @@ use anyhow::Error;
 use serde_json::Value;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::config;
 use proxmox_backup::api2::{self, types::* };
@@ -19,7 +20,7 @@ use proxmox_backup::api2::{self, types::* };
 /// List configured remotes.
 fn list_remotes(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::remote::API_METHOD_LIST_REMOTES;
     let mut data = match info.handler {
@@ -55,7 +56,7 @@ fn list_remotes(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value,
 /// Show remote configuration
 fn show_remote(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::remote::API_METHOD_READ_REMOTE;
     let mut data = match info.handler {
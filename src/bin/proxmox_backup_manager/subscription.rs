@@ -2,6 +2,7 @@ use anyhow::Error;
 use serde_json::Value;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::api2;
 
@@ -18,7 +19,7 @@ use proxmox_backup::api2;
 /// Read subscription info.
 fn get(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::node::subscription::API_METHOD_GET_SUBSCRIPTION;
     let mut data = match info.handler {
@@ -2,6 +2,7 @@ use anyhow::Error;
 use serde_json::Value;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::config;
 use proxmox_backup::api2::{self, types::* };
@@ -19,7 +20,7 @@ use proxmox_backup::api2::{self, types::* };
 /// Sync job list.
 fn list_sync_jobs(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::sync::API_METHOD_LIST_SYNC_JOBS;
     let mut data = match info.handler {
@@ -56,7 +57,7 @@ fn list_sync_jobs(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value
 /// Show sync job configuration
 fn show_sync_job(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::sync::API_METHOD_READ_SYNC_JOB;
     let mut data = match info.handler {
@@ -4,6 +4,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::config;
 use proxmox_backup::tools;
@@ -40,7 +41,7 @@ fn render_expire(value: &Value, _record: &Value) -> Result<String, Error> {
 /// List configured users.
 fn list_users(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::access::user::API_METHOD_LIST_USERS;
     let mut data = match info.handler {
@@ -84,7 +85,7 @@ fn list_users(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Er
 /// List tokens associated with user.
 fn list_tokens(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::access::user::API_METHOD_LIST_TOKENS;
     let mut data = match info.handler {
@@ -130,7 +131,7 @@ fn list_tokens(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, E
 /// List permissions of user/token.
 fn list_permissions(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::access::API_METHOD_LIST_PERMISSIONS;
     let data = match info.handler {
@@ -2,6 +2,7 @@ use anyhow::Error;
 use serde_json::Value;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
+use proxmox_backup::tools::format::get_output_format;
 
 use proxmox_backup::config;
 use proxmox_backup::api2::{self, types::* };
@@ -19,7 +20,7 @@ use proxmox_backup::api2::{self, types::* };
 /// List all verification jobs
 fn list_verification_jobs(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::verify::API_METHOD_LIST_VERIFICATION_JOBS;
     let mut data = match info.handler {
@@ -56,7 +57,7 @@ fn list_verification_jobs(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Resu
 /// Show verification job configuration
 fn show_verification_job(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::verify::API_METHOD_READ_VERIFICATION_JOB;
     let mut data = match info.handler {
@@ -8,6 +8,7 @@ use std::future::Future;
 use std::hash::BuildHasher;
 use std::pin::Pin;
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::backup::{BackupDir, BackupManifest};
 use proxmox_backup::api2::types::ArchiveEntry;
 use proxmox_backup::client::BackupRepository;
@@ -120,7 +121,7 @@ pub async fn data_extract(
 )]
 /// Retrieve status information about currently running/mapped restore images
 pub async fn status(driver: Option<BlockDriverType>, param: Value) -> Result<(), Error> {
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let text = output_format == "text";
 
     let mut ret = json!({});
@@ -3,6 +3,7 @@ use serde_json::Value;
 
 use proxmox::api::{api, cli::*, RpcEnvironment, ApiHandler};
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     config,
     client::{
@@ -28,7 +29,7 @@ use proxmox_backup::{
 /// Tape backup job list.
 fn list_tape_backup_jobs(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::tape_backup_job::API_METHOD_LIST_TAPE_BACKUP_JOBS;
     let mut data = match info.handler {
@@ -65,7 +66,7 @@ fn list_tape_backup_jobs(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Resul
 /// Show tape backup job configuration
 fn show_tape_backup_job(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result<Value, Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::tape_backup_job::API_METHOD_READ_TAPE_BACKUP_JOB;
     let mut data = match info.handler {
@@ -91,7 +92,7 @@ fn show_tape_backup_job(param: Value, rpcenv: &mut dyn RpcEnvironment) -> Result
 /// Run THape Backup Job
 async fn run_tape_backup_job(mut param: Value) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let id = param["id"].take().as_str().unwrap().to_string();
 
@@ -11,6 +11,7 @@ use proxmox::{
     },
 };
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     api2::{
         self,
@@ -114,7 +115,7 @@ fn list_changers(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::tape::changer::API_METHOD_LIST_CHANGERS;
     let mut data = match info.handler {
         ApiHandler::Sync(handler) => (handler)(param, info, rpcenv)?,
@@ -150,7 +151,7 @@ fn scan_for_changers(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::tape::API_METHOD_SCAN_CHANGERS;
     let mut data = match info.handler {
         ApiHandler::Sync(handler) => (handler)(param, info, rpcenv)?,
@@ -188,7 +189,7 @@ fn get_config(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::config::changer::API_METHOD_GET_CONFIG;
     let mut data = match info.handler {
         ApiHandler::Sync(handler) => (handler)(param, info, rpcenv)?,
@@ -236,7 +237,7 @@ async fn get_status(
 
     param["name"] = lookup_changer_name(&param, &config)?.into();
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::tape::changer::API_METHOD_GET_STATUS;
     let mut data = match info.handler {
         ApiHandler::Async(handler) => (handler)(param, info, rpcenv).await?,
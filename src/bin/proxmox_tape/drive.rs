@@ -10,6 +10,7 @@ use proxmox::{
     },
 };
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     api2::{
         self,
@@ -78,7 +79,7 @@ fn list_drives(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::tape::drive::API_METHOD_LIST_DRIVES;
     let mut data = match info.handler {
         ApiHandler::Sync(handler) => (handler)(param, info, rpcenv)?,
@@ -115,7 +116,7 @@ fn scan_for_drives(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::tape::API_METHOD_SCAN_DRIVES;
     let mut data = match info.handler {
         ApiHandler::Sync(handler) => (handler)(param, info, rpcenv)?,
@@ -154,7 +155,7 @@ fn get_config(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::config::drive::API_METHOD_GET_CONFIG;
     let mut data = match info.handler {
         ApiHandler::Sync(handler) => (handler)(param, info, rpcenv)?,
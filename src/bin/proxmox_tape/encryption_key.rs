@@ -11,6 +11,7 @@ use proxmox::{
     sys::linux::tty,
 };
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     tools::{
         self,
@@ -134,7 +135,7 @@ fn show_key(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
 
     let info = &api2::config::tape_encryption_keys::API_METHOD_READ_KEY;
     let mut data = match info.handler {
@@ -290,7 +291,7 @@ fn list_keys(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::config::tape_encryption_keys::API_METHOD_LIST_KEYS;
     let mut data = match info.handler {
         ApiHandler::Sync(handler) => (handler)(param, info, rpcenv)?,
@@ -10,6 +10,7 @@ use proxmox::{
     },
 };
 
+use proxmox_backup::tools::format::get_output_format;
 use proxmox_backup::{
     api2::{
         self,
@@ -91,7 +92,7 @@ async fn list_media(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::tape::media::API_METHOD_LIST_MEDIA;
     let mut data = match info.handler {
         ApiHandler::Async(handler) => (handler)(param, info, rpcenv).await?,
@@ -167,7 +168,7 @@ fn list_content(
     rpcenv: &mut dyn RpcEnvironment,
 ) -> Result<(), Error> {
 
-    let output_format = get_output_format(&param);
+    let output_format = get_output_format(&param)?;
     let info = &api2::tape::media::API_METHOD_LIST_CONTENT;
     let mut data = match info.handler {
         ApiHandler::Sync(handler) => (handler)(param, info, rpcenv)?,
@@ -17,7 +17,7 @@ use proxmox::api::cli::*;
 use proxmox::api::api;
 
 use proxmox_backup::tools;
-use proxmox_backup::pxar::{fuse, format_single_line_entry, ENCODER_MAX_ENTRIES, Flags, PxarExtractOptions};
+use proxmox_backup::pxar::{fuse, dump_entries, list_entries, ENCODER_MAX_ENTRIES, DEFAULT_MAX_DEPTH, Flags, PxarExtractOptions};
 
 fn extract_archive_from_reader<R: std::io::Read>(
     reader: &mut R,
@@ -84,6 +84,11 @@ fn extract_archive_from_reader<R: std::io::Read>(
                 optional: true,
                 default: false,
             },
+            overwrite: {
+                description: "Overwrite existing regular files on restore.",
+                optional: true,
+                default: false,
+            },
             "files-from": {
                 description: "File containing match pattern for files to restore.",
                 optional: true,
@@ -108,6 +113,18 @@ fn extract_archive_from_reader<R: std::io::Read>(
                 optional: true,
                 default: false,
             },
+            "max-depth": {
+                description: "Maximum directory depth to extract.",
+                optional: true,
+                default: DEFAULT_MAX_DEPTH as isize,
+                minimum: 1,
+                maximum: std::isize::MAX,
+            },
+            "ignore-path-length": {
+                description: "Do not fail on paths longer than PATH_MAX.",
+                optional: true,
+                default: false,
+            },
         },
     },
 )]
@@ -122,11 +139,14 @@ fn extract_archive(
     no_fcaps: bool,
     no_acls: bool,
     allow_existing_dirs: bool,
+    overwrite: bool,
     files_from: Option<String>,
     no_device_nodes: bool,
     no_fifos: bool,
     no_sockets: bool,
     strict: bool,
+    max_depth: isize,
+    ignore_path_length: bool,
 ) -> Result<(), Error> {
     let mut feature_flags = Flags::DEFAULT;
     if no_xattrs {
@@ -189,8 +209,12 @@ fn extract_archive(
     let options = PxarExtractOptions {
         match_list: &match_list,
         allow_existing_dirs,
+        overwrite,
         extract_match_default,
         on_error,
+        on_progress: None,
+        max_depth: Some(max_depth as usize),
+        check_path_length: !ignore_path_length,
     };
 
     if archive == "-" {
@@ -441,21 +465,26 @@ async fn mount_archive(
                 optional: true,
                 default: false,
             },
+            json: {
+                description: "Output one JSON object per entry and line.",
+                optional: true,
+                default: false,
+            },
         },
     },
 )]
 /// List the contents of an archive.
-fn dump_archive(archive: String, verbose: bool) -> Result<(), Error> {
-    for entry in pxar::decoder::Decoder::open(archive)? {
-        let entry = entry?;
+fn dump_archive(archive: String, verbose: bool, json: bool) -> Result<(), Error> {
+    let mut decoder = pxar::decoder::Decoder::open(archive)?;
 
-        if verbose {
-            println!("{}", format_single_line_entry(&entry));
-        } else {
-            println!("{:?}", entry.path());
-        }
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    if json {
+        list_entries(&mut decoder, &mut out)
+    } else {
+        dump_entries(decoder, &mut out, verbose)
     }
-    Ok(())
 }
 
 fn main() {
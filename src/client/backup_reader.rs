@@ -4,8 +4,10 @@ use std::fs::File;
 use std::sync::Arc;
 use std::os::unix::fs::OpenOptionsExt;
 
-use futures::future::AbortHandle;
+use futures::future::{AbortHandle, Future};
+use futures::stream::{Stream, StreamExt};
 use serde_json::{json, Value};
+use tokio::sync::Semaphore;
 
 use proxmox::tools::digest_to_hex;
 
@@ -16,11 +18,33 @@ use crate::{
 
 use super::{HttpClient, H2Client};
 
+/// Default number of concurrent requests a `BackupReader` sends
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 16;
+
+/// Limits the number of concurrently running requests
+struct RequestLimit {
+    semaphore: Semaphore,
+}
+
+impl RequestLimit {
+
+    fn new(max_concurrent_requests: usize) -> Self {
+        Self { semaphore: Semaphore::new(max_concurrent_requests.max(1)) }
+    }
+
+    async fn run<F: Future>(&self, request: F) -> F::Output {
+        // we never close the semaphore
+        let _permit = self.semaphore.acquire().await.unwrap();
+        request.await
+    }
+}
+
 /// Backup Reader
 pub struct BackupReader {
     h2: H2Client,
     abort: AbortHandle,
     crypt_config: Option<Arc<CryptConfig>>,
+    request_limit: RequestLimit,
 }
 
 impl Drop for BackupReader {
@@ -32,8 +56,14 @@ impl Drop for BackupReader {
 
 impl BackupReader {
 
-    fn new(h2: H2Client, abort: AbortHandle, crypt_config: Option<Arc<CryptConfig>>) -> Arc<Self> {
-        Arc::new(Self { h2, abort, crypt_config})
+    fn new(
+        h2: H2Client,
+        abort: AbortHandle,
+        crypt_config: Option<Arc<CryptConfig>>,
+        max_concurrent_requests: usize,
+    ) -> Arc<Self> {
+        let request_limit = RequestLimit::new(max_concurrent_requests);
+        Arc::new(Self { h2, abort, crypt_config, request_limit })
     }
 
     /// Create a new instance by upgrading the connection at '/api2/json/reader'
@@ -46,6 +76,31 @@ impl BackupReader {
         backup_time: i64,
         debug: bool,
     ) -> Result<Arc<BackupReader>, Error> {
+        Self::start_with_request_limit(
+            client,
+            crypt_config,
+            datastore,
+            backup_type,
+            backup_id,
+            backup_time,
+            debug,
+            DEFAULT_MAX_CONCURRENT_REQUESTS,
+        ).await
+    }
+
+    /// Like `start`, but allow at most 'max_concurrent_requests' requests
+    /// (GET, downloads) to be in flight at the same time
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start_with_request_limit(
+        client: HttpClient,
+        crypt_config: Option<Arc<CryptConfig>>,
+        datastore: &str,
+        backup_type: &str,
+        backup_id: &str,
+        backup_time: i64,
+        debug: bool,
+        max_concurrent_requests: usize,
+    ) -> Result<Arc<BackupReader>, Error> {
 
         let param = json!({
             "backup-type": backup_type,
@@ -58,7 +113,7 @@ impl BackupReader {
 
         let (h2, abort) = client.start_h2_connection(req, String::from(PROXMOX_BACKUP_READER_PROTOCOL_ID_V1!())).await?;
 
-        Ok(BackupReader::new(h2, abort, crypt_config))
+        Ok(BackupReader::new(h2, abort, crypt_config, max_concurrent_requests))
     }
 
     /// Execute a GET request
@@ -67,7 +122,7 @@ impl BackupReader {
         path: &str,
         param: Option<Value>,
     ) -> Result<Value, Error> {
-        self.h2.get(path, param).await
+        self.request_limit.run(self.h2.get(path, param)).await
     }
 
     /// Execute a PUT request
@@ -96,7 +151,7 @@ impl BackupReader {
     ) -> Result<(), Error> {
         let path = "download";
         let param = json!({ "file-name": file_name });
-        self.h2.download(path, Some(param), output).await
+        self.request_limit.run(self.h2.download(path, Some(param), output)).await
     }
 
     /// Execute a special GET request and send output to a writer
@@ -106,7 +161,7 @@ impl BackupReader {
         &self,
         output: W,
     ) -> Result<(), Error> {
-        self.h2.download("speedtest", None, output).await
+        self.request_limit.run(self.h2.download("speedtest", None, output)).await
     }
 
     /// Download a specific chunk
@@ -117,21 +172,79 @@ impl BackupReader {
     ) -> Result<(), Error> {
         let path = "chunk";
         let param = json!({ "digest": digest_to_hex(digest) });
-        self.h2.download(path, Some(param), output).await
+        self.request_limit.run(self.h2.download(path, Some(param), output)).await
+    }
+
+    /// Download a specific chunk and verify it
+    ///
+    /// Checks the CRC, and for unencrypted chunks also that the decoded data
+    /// matches 'digest' (encrypted chunks can only be verified with the key).
+    pub async fn download_chunk_verified(&self, digest: &[u8; 32]) -> Result<DataBlob, Error> {
+
+        let mut raw_data = Vec::with_capacity(4 * 1024 * 1024);
+        self.download_chunk(digest, &mut raw_data).await?;
+
+        let digest_str = digest_to_hex(digest);
+
+        let chunk = DataBlob::from_raw(raw_data)
+            .map_err(|err| format_err!("unable to load chunk {} - {}", digest_str, err))?;
+        chunk.verify_crc()
+            .map_err(|err| format_err!("chunk {} failed CRC check - {}", digest_str, err))?;
+
+        if !chunk.is_encrypted() {
+            chunk.decode(None, Some(digest))
+                .map_err(|err| format_err!("chunk {} failed digest check - {}", digest_str, err))?;
+        }
+
+        Ok(chunk)
+    }
+
+    /// Download multiple chunks, with up to 'concurrency' requests in flight
+    ///
+    /// Chunks are yielded as soon as they are downloaded, so the order does
+    /// not match 'digests'. The CRC of each chunk is verified.
+    pub fn download_chunks<'a>(
+        &'a self,
+        digests: &'a [[u8; 32]],
+        concurrency: usize,
+    ) -> impl Stream<Item = Result<([u8; 32], DataBlob), Error>> + 'a {
+        futures::stream::iter(digests)
+            .map(move |digest| async move {
+                let mut raw_data = Vec::with_capacity(4 * 1024 * 1024);
+                self.download_chunk(digest, &mut raw_data).await?;
+                let chunk = DataBlob::load_from_reader(&mut &raw_data[..])
+                    .map_err(|err| format_err!("unable to load chunk {} - {}", digest_to_hex(digest), err))?;
+                Ok((*digest, chunk))
+            })
+            .buffer_unordered(concurrency.max(1))
     }
 
     pub fn force_close(self) {
         self.abort.abort();
     }
 
+    /// Download a blob into memory and verify its CRC
+    ///
+    /// The blob is returned as is (not decoded), so callers which only need
+    /// the raw (possibly encrypted) data do not pay for decryption.
+    pub async fn download_blob(&self, name: &str) -> Result<DataBlob, Error> {
+
+        let mut raw_data = Vec::with_capacity(64 * 1024);
+        self.download(name, &mut raw_data).await?;
+        let blob = DataBlob::from_raw(raw_data)
+            .map_err(|err| format_err!("unable to load blob '{}' - {}", name, err))?;
+        blob.verify_crc()
+            .map_err(|err| format_err!("blob '{}' failed CRC check - {}", name, err))?;
+
+        Ok(blob)
+    }
+
     /// Download backup manifest (index.json)
     ///
     /// The manifest signature is verified if we have a crypt_config.
     pub async fn download_manifest(&self) -> Result<(BackupManifest, Vec<u8>), Error> {
 
-        let mut raw_data = Vec::with_capacity(64 * 1024);
-        self.download(MANIFEST_BLOB_NAME, &mut raw_data).await?;
-        let blob = DataBlob::load_from_reader(&mut &raw_data[..])?;
+        let blob = self.download_blob(MANIFEST_BLOB_NAME).await?;
         // no expected digest available
         let data = blob.decode(None, None)?;
 
@@ -144,7 +257,7 @@ impl BackupReader {
     ///
     /// This creates a temporary file in /tmp (using O_TMPFILE). The data is verified using
     /// the provided manifest.
-    pub async fn download_blob(
+    pub async fn download_blob_reader(
         &self,
         manifest: &BackupManifest,
         name: &str,
@@ -222,3 +335,30 @@ impl BackupReader {
         Ok(index)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_request_limit() {
+        crate::tools::runtime::main(async {
+            let limit = RequestLimit::new(3);
+            let running = AtomicUsize::new(0);
+            let max_running = AtomicUsize::new(0);
+
+            let requests = (0..20).map(|_| limit.run(async {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+            }));
+            futures::future::join_all(requests).await;
+
+            assert_eq!(running.load(Ordering::SeqCst), 0);
+            assert_eq!(max_running.load(Ordering::SeqCst), 3);
+        });
+    }
+}
@@ -15,6 +15,7 @@ use tokio_stream::wrappers::ReceiverStream;
 use proxmox::tools::digest_to_hex;
 
 use super::merge_known_chunks::{MergeKnownChunks, MergedChunkInfo};
+use crate::api2::types::KNOWN_CHUNKS_MAX_DIGESTS;
 use crate::backup::*;
 use crate::tools::format::HumanByte;
 
@@ -86,8 +87,9 @@ impl BackupWriter {
         backup_time: i64,
         debug: bool,
         benchmark: bool,
+        ignore_clock_skew: bool,
     ) -> Result<Arc<BackupWriter>, Error> {
-        let param = json!({
+        let mut param = json!({
             "backup-type": backup_type,
             "backup-id": backup_id,
             "backup-time": backup_time,
@@ -96,6 +98,11 @@ impl BackupWriter {
             "benchmark": benchmark
         });
 
+        // only send if set, older servers do not know the parameter
+        if ignore_clock_skew {
+            param["ignore-clock-skew"] = true.into();
+        }
+
         let req = HttpClient::request_builder(
             client.server(),
             client.port(),
@@ -585,6 +592,39 @@ impl BackupWriter {
         Ok(index)
     }
 
+    /// Query which of the given chunks are already known to the server
+    ///
+    /// The server only reports chunks known to this backup session, i.e.
+    /// chunks from the previous snapshot or already uploaded ones. Large
+    /// lists are split into multiple requests.
+    pub async fn known_chunks(&self, digests: &[[u8; 32]]) -> Result<HashSet<[u8; 32]>, Error> {
+        let mut result = HashSet::new();
+
+        for batch in digests.chunks(KNOWN_CHUNKS_MAX_DIGESTS) {
+            let (request, body) = known_chunks_request(batch)?;
+            let data = self.h2.send_request(request, Some(body))
+                .and_then(|response| response.map_err(Error::from).and_then(H2Client::h2api_response))
+                .await?;
+            let known: Vec<String> = serde_json::from_value(data)
+                .map_err(|err| format_err!("Failed to parse known chunk list - {}", err))?;
+
+            for digest in known {
+                result.insert(proxmox::tools::hex_to_digest(&digest)?);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Estimate the deduplication savings for a list of (digest, size) chunks
+    ///
+    /// Returns `(total_bytes, dedup_bytes, new_bytes)`.
+    pub async fn dedup_estimate(&self, chunks: &[([u8; 32], u64)]) -> Result<(u64, u64, u64), Error> {
+        let digests: Vec<[u8; 32]> = chunks.iter().map(|(digest, _)| *digest).collect();
+        let known = self.known_chunks(&digests).await?;
+        Ok(compute_dedup_stats(chunks, &known))
+    }
+
     /// Retrieve backup time of last backup
     pub async fn previous_backup_time(&self) -> Result<Option<i64>, Error> {
         let data = self.h2.get("previous_backup_time", None).await?;
@@ -667,6 +707,8 @@ impl BackupWriter {
                 }
 
                 let mut known_chunks = known_chunks.lock().unwrap();
+                // Note: the digest is needed up front (known chunk check and the 'digest'
+                // parameter of the upload request), so it cannot be computed while streaming.
                 let digest = chunk_builder.digest();
 
                 let mut guard = index_csum.lock().unwrap();
@@ -834,3 +876,78 @@ impl BackupWriter {
         Ok(speed)
     }
 }
+
+// Build a 'known_chunks' request, the digest list is sent as JSON body (it
+// would easily exceed the query size limit of 'H2Client::request_builder').
+fn known_chunks_request(digests: &[[u8; 32]]) -> Result<(http::Request<()>, bytes::Bytes), Error> {
+    let digest_list: Vec<String> = digests.iter().map(|d| digest_to_hex(d)).collect();
+    let param = json!({ "digest-list": digest_list });
+
+    let request = H2Client::request_builder("localhost", "POST", "known_chunks", None, Some("application/json"))?;
+    let body = bytes::Bytes::from(param.to_string().into_bytes());
+
+    Ok((request, body))
+}
+
+// Sum up chunk sizes, counting each digest once. Chunks which are either known
+// to the server or repeated within the list are deduplicated.
+fn compute_dedup_stats(chunks: &[([u8; 32], u64)], known: &HashSet<[u8; 32]>) -> (u64, u64, u64) {
+    let mut seen = HashSet::new();
+    let mut total = 0;
+    let mut new = 0;
+
+    for (digest, size) in chunks {
+        total += size;
+        if !known.contains(digest) && seen.insert(*digest) {
+            new += size;
+        }
+    }
+
+    (total, total - new, new)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compute_dedup_stats() {
+        let chunks = [
+            ([1u8; 32], 100),
+            ([2u8; 32], 200),
+            ([3u8; 32], 400),
+            ([2u8; 32], 200), // duplicate of a known chunk
+            ([3u8; 32], 400), // duplicate of a new chunk
+        ];
+
+        // server reports a subset as known
+        let mut known = HashSet::new();
+        known.insert([2u8; 32]);
+        known.insert([9u8; 32]); // not part of the list
+
+        assert_eq!(compute_dedup_stats(&chunks, &known), (1300, 800, 500));
+        assert_eq!(compute_dedup_stats(&chunks, &HashSet::new()), (1300, 600, 700));
+        assert_eq!(compute_dedup_stats(&[], &known), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_known_chunks_request() -> Result<(), Error> {
+        // far more digests than fit into a query string
+        let digests: Vec<[u8; 32]> = (0..KNOWN_CHUNKS_MAX_DIGESTS as u32)
+            .map(|i| openssl::sha::sha256(&i.to_le_bytes()))
+            .collect();
+        assert!(digests.len() > 50);
+
+        let (request, body) = known_chunks_request(&digests)?;
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.uri().query(), None);
+        assert_eq!(request.headers()[http::header::CONTENT_TYPE], "application/json");
+
+        let param: Value = serde_json::from_slice(&body)?;
+        let digest_list = param["digest-list"].as_array().unwrap();
+        assert_eq!(digest_list.len(), digests.len());
+        assert_eq!(digest_list[1].as_str().unwrap(), digest_to_hex(&digests[1]));
+
+        Ok(())
+    }
+}
@@ -27,6 +27,7 @@ use crate::tools::{
     BroadcastFuture,
     DEFAULT_ENCODE_SET,
     http::HttpsConnector,
+    rate_limiter::RateLimiter,
 };
 
 /// Timeout used for several HTTP operations that are expected to finish quickly but may block in
@@ -48,6 +49,9 @@ pub struct HttpClientOptions {
     ticket_cache: bool,
     fingerprint_cache: bool,
     verify_cert: bool,
+    timeout: Option<Duration>,
+    rate_limit: Option<u64>,
+    read_limiter: Option<Arc<Mutex<RateLimiter>>>,
 }
 
 impl HttpClientOptions {
@@ -106,6 +110,28 @@ impl HttpClientOptions {
         self.verify_cert = verify_cert;
         self
     }
+
+    /// Timeout for HTTP requests (default: HTTP_TIMEOUT).
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Limit the download rate (bytes/second).
+    pub fn rate_limit(mut self, rate_limit: Option<u64>) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    pub fn get_rate_limit(&self) -> Option<u64> {
+        self.rate_limit
+    }
+
+    /// Share an existing read limiter (takes precedence over `rate_limit`).
+    pub fn read_limiter(mut self, read_limiter: Option<Arc<Mutex<RateLimiter>>>) -> Self {
+        self.read_limiter = read_limiter;
+        self
+    }
 }
 
 impl Default for HttpClientOptions {
@@ -118,6 +144,9 @@ impl Default for HttpClientOptions {
             ticket_cache: false,
             fingerprint_cache: false,
             verify_cert: true,
+            timeout: None,
+            rate_limit: None,
+            read_limiter: None,
         }
     }
 }
@@ -131,6 +160,8 @@ pub struct HttpClient {
     first_auth: Option<BroadcastFuture<()>>,
     auth: Arc<RwLock<AuthInfo>>,
     ticket_abort: futures::future::AbortHandle,
+    timeout: Duration,
+    read_limiter: Option<Arc<Mutex<RateLimiter>>>,
     _options: HttpClientOptions,
 }
 
@@ -323,8 +354,16 @@ impl HttpClient {
         httpc.set_nodelay(true); // important for h2 download performance!
         httpc.enforce_http(false); // we want https...
 
-        httpc.set_connect_timeout(Some(std::time::Duration::new(10, 0)));
-        let https = HttpsConnector::with_connector(httpc, ssl_connector_builder.build());
+        let timeout = options.timeout.unwrap_or(HTTP_TIMEOUT);
+
+        httpc.set_connect_timeout(Some(std::cmp::min(Duration::new(10, 0), timeout)));
+        let mut https = HttpsConnector::with_connector(httpc, ssl_connector_builder.build());
+        let read_limiter = match (&options.read_limiter, options.rate_limit) {
+            (Some(read_limiter), _) => Some(Arc::clone(read_limiter)),
+            (None, Some(rate_limit)) => Some(Arc::new(Mutex::new(RateLimiter::new(rate_limit)))),
+            (None, None) => None,
+        };
+        https.set_read_limiter(read_limiter.clone());
 
         let client = Client::builder()
         //.http2_initial_stream_window_size( (1 << 31) - 2)
@@ -371,7 +410,7 @@ impl HttpClient {
                     let authinfo = auth2.read().unwrap().clone();
                     (authinfo.auth_id, authinfo.ticket)
                 };
-                match Self::credentials(client2.clone(), server2.clone(), port, auth_id.user().clone(), ticket).await {
+                match Self::credentials(client2.clone(), server2.clone(), port, auth_id.user().clone(), ticket, timeout).await {
                     Ok(auth) => {
                         if use_ticket_cache && prefix2.is_some() {
                             let _ = store_ticket_info(prefix2.as_ref().unwrap(), &server2, &auth.auth_id.to_string(), &auth.ticket, &auth.token);
@@ -394,6 +433,7 @@ impl HttpClient {
             port,
             auth_id.user().clone(),
             password,
+            timeout,
         ).map_ok({
             let server = server.to_string();
             let prefix = options.prefix.clone();
@@ -423,6 +463,8 @@ impl HttpClient {
             auth,
             ticket_abort,
             first_auth,
+            timeout,
+            read_limiter,
             _options: options,
         })
     }
@@ -530,7 +572,7 @@ impl HttpClient {
             req.headers_mut().insert("CSRFPreventionToken", HeaderValue::from_str(&auth.token).unwrap());
         }
 
-        Self::api_request(client, req).await
+        Self::api_request(client, req, self.timeout).await
     }
 
     pub async fn get(
@@ -584,7 +626,7 @@ impl HttpClient {
         req.headers_mut().insert("Cookie", HeaderValue::from_str(&enc_ticket).unwrap());
 
         let resp = tokio::time::timeout(
-            HTTP_TIMEOUT,
+            self.timeout,
             client.request(req)
         )
             .await
@@ -656,7 +698,7 @@ impl HttpClient {
         req.headers_mut().insert("UPGRADE", HeaderValue::from_str(&protocol_name).unwrap());
 
         let resp = tokio::time::timeout(
-            HTTP_TIMEOUT,
+            self.timeout,
             client.request(req)
         )
             .await
@@ -702,10 +744,11 @@ impl HttpClient {
         port: u16,
         username: Userid,
         password: String,
+        timeout: Duration,
     ) -> Result<AuthInfo, Error> {
         let data = json!({ "username": username, "password": password });
         let req = Self::request_builder(&server, port, "POST", "/api2/json/access/ticket", Some(data))?;
-        let cred = Self::api_request(client, req).await?;
+        let cred = Self::api_request(client, req, timeout).await?;
         let auth = AuthInfo {
             auth_id: cred["data"]["username"].as_str().unwrap().parse()?,
             ticket: cred["data"]["ticket"].as_str().unwrap().to_owned(),
@@ -734,12 +777,13 @@ impl HttpClient {
 
     async fn api_request(
         client: Client<HttpsConnector>,
-        req: Request<Body>
+        req: Request<Body>,
+        timeout: Duration,
     ) -> Result<Value, Error> {
 
         Self::api_response(
             tokio::time::timeout(
-                HTTP_TIMEOUT,
+                timeout,
                 client.request(req)
             )
                 .await
@@ -756,6 +800,16 @@ impl HttpClient {
         self.port
     }
 
+    /// Timeout used for HTTP requests
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Download rate limiter shared by all connections of this client
+    pub fn read_limiter(&self) -> Option<Arc<Mutex<RateLimiter>>> {
+        self.read_limiter.clone()
+    }
+
     pub fn request_builder(server: &str, port: u16, method: &str, path: &str, data: Option<Value>) -> Result<Request<Body>, Error> {
         let path = path.trim_matches('/');
         let url: Uri = format!("https://{}:{}/{}", server, port, path).parse()?;
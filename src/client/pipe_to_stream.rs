@@ -10,6 +10,8 @@ use anyhow::{format_err, Error};
 use futures::{ready, Future};
 use h2::SendStream;
 
+use crate::backup::DataBlob;
+
 pub struct PipeToSendStream {
     body_tx: SendStream<Bytes>,
     data: Option<Bytes>,
@@ -22,6 +24,11 @@ impl PipeToSendStream {
             data: Some(data),
         }
     }
+
+    /// Sends the raw (encoded) data of 'blob', without copying it
+    pub fn from_blob(blob: DataBlob, tx: SendStream<Bytes>) -> PipeToSendStream {
+        Self::new(Bytes::from(blob.into_inner()), tx)
+    }
 }
 
 impl Future for PipeToSendStream {
@@ -68,3 +75,67 @@ impl Future for PipeToSendStream {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pipe_blob_to_send_stream() -> Result<(), Error> {
+        crate::tools::runtime::main(async {
+            // larger than the window size, so we need to wait for capacity
+            let data: Vec<u8> = (0..64 * 1024u32).map(|i| (i % 251) as u8).collect();
+            let blob = DataBlob::encode(&data, None, false)?;
+            let expected = blob.raw_data().to_vec();
+
+            let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+
+            let server = tokio::spawn(async move {
+                let mut conn = h2::server::Builder::new()
+                    .initial_window_size(4096)
+                    .handshake::<_, Bytes>(server_io)
+                    .await?;
+
+                let (request, mut respond) = conn.accept().await
+                    .ok_or_else(|| format_err!("connection closed"))??;
+
+                tokio::spawn(async move {
+                    let _ = futures::future::poll_fn(|cx| conn.poll_closed(cx)).await;
+                });
+
+                let mut body = request.into_body();
+                let mut received = Vec::new();
+                while let Some(chunk) = body.data().await {
+                    let chunk = chunk?;
+                    body.flow_control().release_capacity(chunk.len())?;
+                    received.extend_from_slice(&chunk);
+                }
+
+                respond.send_response(http::Response::new(()), true)?;
+
+                Ok::<_, Error>(received)
+            });
+
+            let (send_request, conn) = h2::client::handshake(client_io).await?;
+            tokio::spawn(async move {
+                let _ = conn.await;
+            });
+
+            let mut send_request = send_request.ready().await?;
+            let request = http::Request::builder()
+                .method("POST")
+                .uri("http://localhost/blob")
+                .body(())?;
+
+            let (response, stream) = send_request.send_request(request, false)?;
+
+            PipeToSendStream::from_blob(blob, stream).await?;
+            response.await?;
+
+            let received = server.await??;
+            assert_eq!(received, expected);
+
+            Ok(())
+        })
+    }
+}
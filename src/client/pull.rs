@@ -502,7 +502,9 @@ pub async fn pull_group(
         // get updated auth_info (new tickets)
         let auth_info = client.login().await?;
 
-        let options = HttpClientOptions::new_non_interactive(auth_info.ticket.clone(), fingerprint.clone());
+        let options = HttpClientOptions::new_non_interactive(auth_info.ticket.clone(), fingerprint.clone())
+            .timeout(Some(client.timeout()))
+            .read_limiter(client.read_limiter());
 
         let new_client = HttpClient::new(
             src_repo.host(),
@@ -590,7 +592,7 @@ pub async fn pull_store(
 
     let mut new_groups = std::collections::HashSet::new();
     for item in list.iter() {
-        new_groups.insert(BackupGroup::new(&item.backup_type, &item.backup_id));
+        new_groups.insert(BackupGroup::new(&item.backup_type, &item.backup_id)?);
     }
 
     let mut progress = StoreProgress::new(list.len() as u64);
@@ -600,7 +602,7 @@ pub async fn pull_store(
         progress.done_snapshots = 0;
         progress.group_snapshots = 0;
 
-        let group = BackupGroup::new(&item.backup_type, &item.backup_id);
+        let group = BackupGroup::new(&item.backup_type, &item.backup_id)?;
 
         let (owner, _lock_guard) = match tgt_store.create_locked_backup_group(&group, &auth_id) {
             Ok(result) => result,
@@ -63,8 +63,8 @@ pub async fn display_task_log(
             let path = format!("api2/json/nodes/localhost/tasks/{}/log", tools::percent_encode_component(upid_str));
             let result = client.get(&path, Some(param)).await?;
 
-            let active = result["active"].as_bool().unwrap();
-            let total = result["total"].as_u64().unwrap();
+            let active = tools::required_bool_param(&result, "active")?;
+            let total = tools::required_u64_param(&result, "total")?;
             let data = result["data"].as_array().unwrap();
 
             let lines = data.len();
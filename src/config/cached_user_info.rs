@@ -1,14 +1,18 @@
 //! Cached user info for fast ACL permission checks
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::os::unix::fs::MetadataExt;
 use std::sync::{RwLock, Arc};
 
 use anyhow::{Error, bail};
+use serde::Serialize;
 
 use proxmox::api::section_config::SectionConfigData;
 use lazy_static::lazy_static;
 use proxmox::api::UserInformation;
 
-use super::acl::{AclTree, ROLE_NAMES, ROLE_ADMIN};
+use super::acl::{AclTree, AclTreeNode, PRIVILEGES, ROLE_NAMES, ROLE_ADMIN};
 use super::user::{ApiToken, User};
 use crate::api2::types::{Authid, Userid};
 
@@ -16,6 +20,34 @@ use crate::api2::types::{Authid, Userid};
 pub struct CachedUserInfo {
     user_cfg: Arc<SectionConfigData>,
     acl_tree: Arc<AclTree>,
+    superuser: Userid,
+}
+
+/// File containing the userid of the superuser
+///
+/// Note: 'root@pam' is only a superuser if this file does not exist. The file
+/// is ignored unless it is owned by root with mode 0640 (like 'user.cfg').
+pub const SUPERUSER_FN: &str = configdir!("/superuser");
+
+/// Effective permissions of an authid on all accessible ACL paths
+#[derive(Debug, Serialize)]
+pub struct PermissionReport {
+    pub auth_id: Authid,
+    /// The superuser has all privileges everywhere.
+    pub superuser: bool,
+    /// Maps paths to privilege names and their propagate flag.
+    pub paths: BTreeMap<String, BTreeMap<String, bool>>,
+}
+
+// paths which are always checked, even if no ACL exists for them
+const DEFAULT_ACL_PATHS: &[&str] = &["/", "/access", "/datastore", "/remote", "/system"];
+
+fn collect_acl_paths(paths: &mut BTreeSet<String>, node: &AclTreeNode, path: &str) {
+    for (name, child) in node.children.iter() {
+        let child_path = format!("{}/{}", path, name);
+        collect_acl_paths(paths, child, &child_path);
+        paths.insert(child_path);
+    }
 }
 
 fn now() -> i64 { unsafe { libc::time(std::ptr::null_mut()) } }
@@ -29,6 +61,56 @@ lazy_static! {
     static ref CACHED_CONFIG: RwLock<ConfigCache> = RwLock::new(
         ConfigCache { data: None, last_update: 0 }
     );
+
+    static ref SUPERUSER: Userid = read_superuser(SUPERUSER_FN);
+}
+
+fn read_superuser(path: &str) -> Userid {
+    let root = Userid::root_userid().clone();
+
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return root,
+        Err(err) => {
+            log::warn!("unable to open {:?}, using {} - {}", path, root, err);
+            return root;
+        }
+    };
+
+    match file.metadata() {
+        Ok(stat) if stat.uid() == 0 && (stat.mode() & 0o7777) == 0o640 => (),
+        Ok(stat) => {
+            log::warn!(
+                "ignoring {:?} (owner {}, mode {:o}), must be owned by root with mode 0640 - using {}",
+                path, stat.uid(), stat.mode() & 0o7777, root,
+            );
+            return root;
+        }
+        Err(err) => {
+            log::warn!("unable to stat {:?}, using {} - {}", path, root, err);
+            return root;
+        }
+    }
+
+    let mut data = String::new();
+    if let Err(err) = file.read_to_string(&mut data) {
+        log::warn!("unable to read {:?}, using {} - {}", path, root, err);
+        return root;
+    }
+
+    match data.trim().parse::<Userid>() {
+        Ok(userid) => userid,
+        Err(err) => {
+            log::warn!("invalid superuser in {:?}, using {} - {}", path, root, err);
+            root
+        }
+    }
+}
+
+/// Returns the configured superuser (read once from `SUPERUSER_FN`),
+/// defaults to 'root@pam'.
+pub fn superuser() -> &'static Userid {
+    &SUPERUSER
 }
 
 impl CachedUserInfo {
@@ -48,6 +130,7 @@ impl CachedUserInfo {
         let config = Arc::new(CachedUserInfo {
             user_cfg: super::user::cached_config()?,
             acl_tree: super::acl::cached_config()?,
+            superuser: superuser().clone(),
         });
 
         let mut cache = CACHED_CONFIG.write().unwrap();
@@ -62,9 +145,16 @@ impl CachedUserInfo {
         Self {
             user_cfg: Arc::new(user_cfg),
             acl_tree: Arc::new(acl_tree),
+            superuser: Userid::root_userid().clone(),
         }
     }
 
+    #[cfg(test)]
+    pub(crate) fn test_with_superuser(mut self, superuser: Userid) -> Self {
+        self.superuser = superuser;
+        self
+    }
+
     /// Test if a authentication id is enabled and not expired
     pub fn is_active_auth_id(&self, auth_id: &Authid) -> bool {
         let userid = auth_id.user();
@@ -123,7 +213,7 @@ impl CachedUserInfo {
     }
 
     pub fn is_superuser(&self, auth_id: &Authid) -> bool {
-        !auth_id.is_token() && auth_id.user() == "root@pam"
+        !auth_id.is_token() && auth_id.user() == &self.superuser
     }
 
     pub fn is_group_member(&self, _userid: &Userid, _group: &str) -> bool {
@@ -140,17 +230,7 @@ impl CachedUserInfo {
             return (ROLE_ADMIN, ROLE_ADMIN);
         }
 
-        let roles = self.acl_tree.roles(auth_id, path);
-        let mut privs: u64 = 0;
-        let mut propagated_privs: u64 = 0;
-        for (role, propagate) in roles {
-            if let Some((role_privs, _)) = ROLE_NAMES.get(role.as_str()) {
-                if propagate {
-                    propagated_privs |= role_privs;
-                }
-                privs |= role_privs;
-            }
-        }
+        let (mut privs, mut propagated_privs) = self.acl_privs(auth_id, path);
 
         if auth_id.is_token() {
             // limit privs to that of owning user
@@ -164,11 +244,81 @@ impl CachedUserInfo {
         (privs, propagated_privs)
     }
 
+    /// Returns all ACL paths where 'auth_id' has any privilege.
+    pub fn accessible_paths(&self, auth_id: &Authid) -> Vec<String> {
+        let mut paths: BTreeSet<String> = DEFAULT_ACL_PATHS.iter().map(|p| p.to_string()).collect();
+        collect_acl_paths(&mut paths, &self.acl_tree.root, "");
+
+        paths
+            .into_iter()
+            .filter(|path| self.lookup_privs(auth_id, &super::acl::split_acl_path(path)) != 0)
+            .collect()
+    }
+
+    /// Generate a report of the effective privileges of 'auth_id'.
+    ///
+    /// Token privileges are limited to those of the owning user.
+    pub fn permission_report(&self, auth_id: &Authid) -> PermissionReport {
+        let mut paths = BTreeMap::new();
+
+        for path in self.accessible_paths(auth_id) {
+            let (privs, propagated_privs) =
+                self.lookup_privs_details(auth_id, &super::acl::split_acl_path(&path));
+
+            let priv_map = PRIVILEGES
+                .iter()
+                .filter(|(_, value)| privs & value != 0)
+                .map(|(name, value)| (name.to_string(), propagated_privs & value != 0))
+                .collect();
+
+            paths.insert(path, priv_map);
+        }
+
+        PermissionReport {
+            auth_id: auth_id.clone(),
+            superuser: self.is_superuser(auth_id),
+            paths,
+        }
+    }
+
+    /// Returns the privileges granted to a token on 'path', which are not
+    /// effective because its owning user lacks them.
+    ///
+    /// A non-zero result indicates a useless ACL entry. Always 0 for users.
+    pub fn token_privilege_anomalies(&self, token: &Authid, path: &[&str]) -> u64 {
+        if !token.is_token() {
+            return 0;
+        }
+
+        let (token_privs, _) = self.acl_privs(token, path);
+        let user_privs = self.lookup_privs(&Authid::from(token.user().clone()), path);
+
+        token_privs & !user_privs
+    }
+
+    // privileges as granted by the ACL tree, without token limits
+    fn acl_privs(&self, auth_id: &Authid, path: &[&str]) -> (u64, u64) {
+        let roles = self.acl_tree.roles(auth_id, path);
+        let mut privs: u64 = 0;
+        let mut propagated_privs: u64 = 0;
+        for (role, propagate) in roles {
+            if let Some((role_privs, _)) = ROLE_NAMES.get(role.as_str()) {
+                if propagate {
+                    propagated_privs |= role_privs;
+                }
+                privs |= role_privs;
+            }
+        }
+        (privs, propagated_privs)
+    }
 }
 
 impl UserInformation for CachedUserInfo {
     fn is_superuser(&self, userid: &str) -> bool {
-        userid == "root@pam"
+        match userid.parse::<Authid>() {
+            Ok(auth_id) => Self::is_superuser(self, &auth_id),
+            Err(_) => false,
+        }
     }
 
     fn is_group_member(&self, _userid: &str, _group: &str) -> bool {
@@ -182,3 +332,165 @@ impl UserInformation for CachedUserInfo {
         }
     }
 }
+
+#[test]
+fn test_token_privilege_anomalies() -> Result<(), Error> {
+    use super::acl::{ROLE_DATASTORE_AUDIT, ROLE_DATASTORE_BACKUP};
+
+    let (user_cfg, _) = super::user::test_cfg_from_str(r###"
+user: alice@pbs
+
+token: alice@pbs!backup
+
+token: alice@pbs!audit
+"###).expect("test user.cfg is not parsable");
+    let acl_tree = AclTree::from_raw(r###"
+acl:1:/datastore/store1:alice@pbs:DatastoreAudit
+acl:1:/datastore/store1:alice@pbs!backup:DatastoreBackup
+acl:1:/datastore/store1:alice@pbs!audit:DatastoreAudit
+"###).expect("test acl.cfg is not parsable");
+
+    let user_info = CachedUserInfo::test_new(user_cfg, acl_tree);
+
+    let path = ["datastore", "store1"];
+    let user: Authid = "alice@pbs".parse()?;
+    let backup_token: Authid = "alice@pbs!backup".parse()?;
+    let audit_token: Authid = "alice@pbs!audit".parse()?;
+
+    assert_eq!(
+        user_info.token_privilege_anomalies(&backup_token, &path),
+        ROLE_DATASTORE_BACKUP & !ROLE_DATASTORE_AUDIT,
+    );
+    assert_ne!(user_info.token_privilege_anomalies(&backup_token, &path), 0);
+
+    // effective privileges are still clamped to the user's
+    assert_eq!(
+        user_info.lookup_privs(&backup_token, &path),
+        ROLE_DATASTORE_BACKUP & ROLE_DATASTORE_AUDIT,
+    );
+
+    assert_eq!(user_info.token_privilege_anomalies(&audit_token, &path), 0);
+    assert_eq!(user_info.token_privilege_anomalies(&user, &path), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_custom_superuser() -> Result<(), Error> {
+    let (user_cfg, _) = super::user::test_cfg_from_str(r###"
+user: root@pam
+
+user: admin@pbs
+"###).expect("test user.cfg is not parsable");
+    let acl_tree = AclTree::from_raw("").expect("test acl.cfg is not parsable");
+
+    let admin: Userid = "admin@pbs".parse()?;
+    let user_info = CachedUserInfo::test_new(user_cfg, acl_tree)
+        .test_with_superuser(admin.clone());
+
+    let path = ["datastore", "store1"];
+    let admin_id = Authid::from(admin);
+    let root_id = Authid::root_auth_id();
+
+    assert!(user_info.is_superuser(&admin_id));
+    assert_eq!(user_info.lookup_privs(&admin_id, &path), ROLE_ADMIN);
+    assert!(UserInformation::is_superuser(&user_info, "admin@pbs"));
+
+    // root@pam is no superuser if a custom one is configured
+    assert!(!user_info.is_superuser(root_id));
+    assert_eq!(user_info.lookup_privs(root_id, &path), 0);
+    assert!(!UserInformation::is_superuser(&user_info, "root@pam"));
+
+    let user: Authid = "user@pbs".parse()?;
+    assert!(!user_info.is_superuser(&user));
+
+    // tokens of the superuser are never superuser
+    let token: Authid = "admin@pbs!token".parse()?;
+    assert!(!user_info.is_superuser(&token));
+
+    Ok(())
+}
+
+#[test]
+fn test_read_superuser() -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = nix::unistd::mkdtemp("/tmp/pbs-superuser-test-XXXXXX")?;
+
+    let result = (|| -> Result<(), Error> {
+        let path = tmp.join("superuser");
+        let path_str = path.to_str().unwrap();
+        let admin: Userid = "admin@pbs".parse()?;
+
+        assert_eq!(&read_superuser(path_str), Userid::root_userid());
+
+        std::fs::write(&path, "admin@pbs\n")?;
+
+        // world readable files are refused
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))?;
+        assert_eq!(&read_superuser(path_str), Userid::root_userid());
+
+        // only accepted if owned by root
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640))?;
+        if nix::unistd::Uid::current().is_root() {
+            assert_eq!(read_superuser(path_str), admin);
+        } else {
+            assert_eq!(&read_superuser(path_str), Userid::root_userid());
+        }
+
+        Ok(())
+    })();
+
+    std::fs::remove_dir_all(&tmp)?;
+    result
+}
+
+#[test]
+fn test_permission_report() -> Result<(), Error> {
+    let (user_cfg, _) = super::user::test_cfg_from_str(r###"
+user: root@pam
+
+user: alice@pbs
+
+token: alice@pbs!backup
+"###).expect("test user.cfg is not parsable");
+    let acl_tree = AclTree::from_raw(r###"
+acl:1:/datastore:alice@pbs:DatastoreAudit
+acl:0:/datastore/store1:alice@pbs:DatastoreBackup
+acl:1:/datastore/store1:alice@pbs!backup:DatastoreAdmin
+acl:1:/remote/remote1:alice@pbs!backup:RemoteAdmin
+"###).expect("test acl.cfg is not parsable");
+
+    let user_info = CachedUserInfo::test_new(user_cfg, acl_tree);
+
+    let privs = |list: &[(&str, bool)]| -> BTreeMap<String, bool> {
+        list.iter().map(|(name, propagate)| (name.to_string(), *propagate)).collect()
+    };
+
+    let user: Authid = "alice@pbs".parse()?;
+    let report = user_info.permission_report(&user);
+    assert!(!report.superuser);
+    assert_eq!(report.paths.keys().collect::<Vec<_>>(), vec!["/datastore", "/datastore/store1"]);
+    assert_eq!(report.paths["/datastore"], privs(&[("Datastore.Audit", true)]));
+    assert_eq!(
+        report.paths["/datastore/store1"],
+        privs(&[("Datastore.Backup", false)]),
+    );
+
+    // token privileges are limited to those of the user, ACLs of the user
+    // are not inherited by the token
+    let token: Authid = "alice@pbs!backup".parse()?;
+    let report = user_info.permission_report(&token);
+    assert_eq!(report.paths.keys().collect::<Vec<_>>(), vec!["/datastore/store1"]);
+    assert_eq!(
+        report.paths["/datastore/store1"],
+        privs(&[("Datastore.Backup", false)]),
+    );
+
+    let report = user_info.permission_report(Authid::root_auth_id());
+    assert!(report.superuser);
+    assert_eq!(report.paths.len(), 7);
+    assert_eq!(report.paths["/system"].len(), PRIVILEGES.len());
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use anyhow::{Error};
+use anyhow::{format_err, Error};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
@@ -80,6 +80,14 @@ pub const DIR_NAME_SCHEMA: Schema = StringSchema::new("Directory name").schema()
             optional: true,
             type: bool,
         },
+        "file-mode": {
+            optional: true,
+            schema: DATASTORE_FILE_MODE_SCHEMA,
+        },
+        "chunk-write-retries": {
+            optional: true,
+            schema: DATASTORE_CHUNK_WRITE_RETRIES_SCHEMA,
+        },
     }
 )]
 #[serde(rename_all="kebab-case")]
@@ -115,6 +123,24 @@ pub struct DataStoreConfig {
     /// Send notification only for job errors
     #[serde(skip_serializing_if="Option::is_none")]
     pub notify: Option<String>,
+    /// Permissions for newly created backup data files
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub file_mode: Option<String>,
+    /// Retries on transient errors while writing uploaded chunks
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub chunk_write_retries: Option<u32>,
+}
+
+impl DataStoreConfig {
+    /// Parse the configured file mode (octal).
+    pub fn file_mode(&self) -> Result<Option<u32>, Error> {
+        match self.file_mode {
+            Some(ref mode) => u32::from_str_radix(mode, 8)
+                .map(Some)
+                .map_err(|err| format_err!("invalid file-mode '{}' - {}", mode, err)),
+            None => Ok(None),
+        }
+    }
 }
 
 fn init() -> SectionConfig {
@@ -1,8 +1,11 @@
 use std::io::{Write};
+use std::path::Path;
 use std::collections::{HashSet, HashMap, BTreeMap};
 
 use anyhow::{Error, format_err, bail};
 use serde::de::{value, IntoDeserializer, Deserialize};
+use serde::Serialize;
+use serde_json::Value;
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -17,16 +20,25 @@ pub use lexer::*;
 mod parser;
 pub use parser::*;
 
-use crate::api2::types::{Interface, NetworkConfigMethod, NetworkInterfaceType, LinuxBondMode, BondXmitHashPolicy};
+use crate::api2::types::{Interface, NetworkConfigMethod, NetworkInterfaceType, LinuxBondMode, BondXmitHashPolicy, BondFailOverMac};
 
 lazy_static!{
     static ref PHYSICAL_NIC_REGEX: Regex = Regex::new(r"^(?:eth\d+|en[^:.]+|ib\d+)$").unwrap();
+    static ref VLAN_INTERFACE_REGEX: Regex = Regex::new(r"^(\S+)\.(\d+)$").unwrap();
 }
 
 pub fn is_physical_nic(iface: &str) -> bool {
     PHYSICAL_NIC_REGEX.is_match(iface)
 }
 
+/// Returns the underlying interface of VLAN port names ('eth0.100' => 'eth0')
+fn port_base_interface(port: &str) -> &str {
+    match VLAN_INTERFACE_REGEX.captures(port) {
+        Some(caps) => caps.get(1).unwrap().as_str(),
+        None => port,
+    }
+}
+
 pub fn bond_mode_from_str(s: &str) -> Result<LinuxBondMode, Error> {
     LinuxBondMode::deserialize(s.into_deserializer())
         .map_err(|_: value::Error| format_err!("invalid bond_mode '{}'", s))
@@ -54,6 +66,31 @@ pub fn bond_xmit_hash_policy_to_str(policy: &BondXmitHashPolicy) -> &'static str
         BondXmitHashPolicy::layer2 => "layer2",
         BondXmitHashPolicy::layer2_3 => "layer2+3",
         BondXmitHashPolicy::layer3_4 => "layer3+4",
+        BondXmitHashPolicy::encap2_3 => "encap2+3",
+        BondXmitHashPolicy::encap3_4 => "encap3+4",
+    }
+}
+
+pub fn bond_fail_over_mac_from_str(s: &str) -> Result<BondFailOverMac, Error> {
+    // the kernel also accepts the numeric values
+    match s {
+        "0" => return Ok(BondFailOverMac::none),
+        "1" => return Ok(BondFailOverMac::active),
+        "2" => return Ok(BondFailOverMac::follow),
+        _ => {}
+    }
+    BondFailOverMac::deserialize(s.into_deserializer())
+        .map_err(|_: value::Error| format_err!("invalid bond-fail-over-mac '{}' (expected none, active, follow or 0-2)", s))
+}
+
+/// Default key used to write the fail_over_mac policy
+pub const BOND_FAIL_OVER_MAC_KEY: &str = "bond-fail-over-mac";
+
+pub fn bond_fail_over_mac_to_str(policy: BondFailOverMac) -> &'static str {
+    match policy {
+        BondFailOverMac::none => "none",
+        BondFailOverMac::active => "active",
+        BondFailOverMac::follow => "follow",
     }
 }
 
@@ -82,9 +119,54 @@ impl Interface {
             bond_mode: None,
             bond_primary: None,
             bond_xmit_hash_policy: None,
+            bond_fail_over_mac: None,
+            bond_miimon: None,
+            bond_downdelay: None,
+            bond_updelay: None,
+            hwaddress: None,
+            vlan_id: None,
+            vlan_raw_device: None,
         }
     }
 
+    /// Returns the VLAN raw device and VLAN id
+    ///
+    /// Explicit 'vlan-raw-device' and 'vlan-id' options take precedence,
+    /// otherwise the values are derived from the interface name ('eth0.100'
+    /// or 'vlan100').
+    pub fn vlan_info(&self) -> Result<(String, u16), Error> {
+        lazy_static!{
+            static ref VLAN_NAME_REGEX: Regex = Regex::new(r"^vlan(\d+)$").unwrap();
+        }
+
+        let (name_raw_device, name_vlan_id) = if let Some(caps) = VLAN_INTERFACE_REGEX.captures(&self.name) {
+            (Some(caps[1].to_string()), Some(parse_vlan_id(&caps[2])?))
+        } else if let Some(caps) = VLAN_NAME_REGEX.captures(&self.name) {
+            (None, Some(parse_vlan_id(&caps[1])?))
+        } else {
+            (None, None)
+        };
+
+        let raw_device = match (&self.vlan_raw_device, name_raw_device) {
+            (Some(raw_device), Some(name_raw_device)) if *raw_device != name_raw_device => {
+                bail!("vlan '{}' - vlan-raw-device '{}' does not match interface name", self.name, raw_device);
+            }
+            (Some(raw_device), _) => raw_device.clone(),
+            (None, Some(name_raw_device)) => name_raw_device,
+            (None, None) => bail!("vlan '{}' - missing vlan-raw-device", self.name),
+        };
+
+        let vlan_id = match (self.vlan_id, name_vlan_id) {
+            (Some(vlan_id), Some(name_vlan_id)) if vlan_id != name_vlan_id => {
+                bail!("vlan '{}' - vlan-id {} does not match interface name", self.name, vlan_id);
+            }
+            (Some(vlan_id), _) | (None, Some(vlan_id)) => vlan_id,
+            (None, None) => bail!("vlan '{}' - missing vlan-id", self.name),
+        };
+
+        Ok((raw_device, vlan_id))
+    }
+
     fn set_method_v4(&mut self, method: NetworkConfigMethod) -> Result<(), Error> {
         if self.method.is_none() {
             self.method = Some(method);
@@ -165,7 +247,7 @@ impl Interface {
     }
 
     /// Write attributes not depending on address family
-    fn write_iface_attributes(&self, w: &mut dyn Write) -> Result<(), Error> {
+    fn write_iface_attributes(&self, w: &mut dyn Write, fail_over_mac_key: &str) -> Result<(), Error> {
 
         static EMPTY_LIST: Vec<String> = Vec::new();
 
@@ -190,6 +272,12 @@ impl Interface {
                     }
                 }
 
+                if let Some(fail_over_mac) = self.bond_fail_over_mac {
+                    if mode == LinuxBondMode::active_backup {
+                        writeln!(w, "\t{} {}", fail_over_mac_key, bond_fail_over_mac_to_str(fail_over_mac))?;
+                    }
+                }
+
                 if let Some(xmit_policy) = &self.bond_xmit_hash_policy {
                     if mode == LinuxBondMode::ieee802_3ad ||
                        mode == LinuxBondMode::balance_xor
@@ -198,6 +286,16 @@ impl Interface {
                     }
                 }
 
+                if let Some(miimon) = self.bond_miimon {
+                    writeln!(w, "\tbond-miimon {}", miimon)?;
+                }
+                if let Some(downdelay) = self.bond_downdelay {
+                    writeln!(w, "\tbond-downdelay {}", downdelay)?;
+                }
+                if let Some(updelay) = self.bond_updelay {
+                    writeln!(w, "\tbond-updelay {}", updelay)?;
+                }
+
                 let slaves = self.slaves.as_ref().unwrap_or(&EMPTY_LIST);
                 if slaves.is_empty() {
                     writeln!(w, "\tbond-slaves none")?;
@@ -205,6 +303,15 @@ impl Interface {
                     writeln!(w, "\tbond-slaves {}", slaves.join(" "))?;
                 }
             }
+            NetworkInterfaceType::Vlan => {
+                // only write explicit values, others are derived from the name
+                if let Some(raw_device) = &self.vlan_raw_device {
+                    writeln!(w, "\tvlan-raw-device {}", raw_device)?;
+                }
+                if let Some(vlan_id) = self.vlan_id {
+                    writeln!(w, "\tvlan-id {}", vlan_id)?;
+                }
+            }
             _ => {}
         }
 
@@ -212,6 +319,10 @@ impl Interface {
             writeln!(w, "\tmtu {}", mtu)?;
         }
 
+        if let Some(hwaddress) = &self.hwaddress {
+            writeln!(w, "\thwaddress ether {}", hwaddress)?;
+        }
+
         Ok(())
     }
 
@@ -263,7 +374,9 @@ impl Interface {
         Ok(())
     }
 
-    fn write_iface(&self, w: &mut dyn Write) -> Result<(), Error> {
+    // 'fail_over_mac_key' is the spelling of the bond fail_over_mac option,
+    // ifupdown accepts several variants
+    fn write_iface(&self, w: &mut dyn Write, fail_over_mac_key: &str) -> Result<(), Error> {
 
         fn method_to_str(method: NetworkConfigMethod) -> &'static str {
             match method {
@@ -283,7 +396,7 @@ impl Interface {
         if let Some(method) = self.method {
             writeln!(w, "iface {} inet {}", self.name, method_to_str(method))?;
             self.write_iface_attributes_v4(w, method)?;
-            self.write_iface_attributes(w)?;
+            self.write_iface_attributes(w, fail_over_mac_key)?;
             writeln!(w)?;
         }
 
@@ -301,7 +414,7 @@ impl Interface {
                 writeln!(w, "iface {} inet6 {}", self.name, method_to_str(method6))?;
                 self.write_iface_attributes_v6(w, method6)?;
                 if self.method.is_none() { // only write common attributes once
-                    self.write_iface_attributes(w)?;
+                    self.write_iface_attributes(w, fail_over_mac_key)?;
                 }
                 writeln!(w)?;
             }
@@ -311,6 +424,33 @@ impl Interface {
     }
 }
 
+/// Type of a change to an interface
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterfaceChangeType {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Changed attribute of an interface (Null if not set)
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AttributeChange {
+    pub name: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// Change to a single interface between two network configurations
+#[derive(Debug, PartialEq, Serialize)]
+pub struct InterfaceChange {
+    pub name: String,
+    pub change: InterfaceChangeType,
+    /// Changed attributes (only for modified interfaces)
+    #[serde(skip_serializing_if="Vec::is_empty")]
+    pub attributes: Vec<AttributeChange>,
+}
+
 #[derive(Debug)]
 enum NetworkOrderEntry {
     Iface(String),
@@ -322,6 +462,8 @@ enum NetworkOrderEntry {
 pub struct NetworkConfig {
     pub interfaces: BTreeMap<String, Interface>,
     order: Vec<NetworkOrderEntry>,
+    // non-default spelling of the fail_over_mac key per interface, kept when writing
+    fail_over_mac_keys: HashMap<String, String>,
 }
 
 use std::convert::TryFrom;
@@ -344,7 +486,60 @@ impl NetworkConfig {
         Self {
             interfaces: BTreeMap::new(),
             order: Vec::new(),
+            fail_over_mac_keys: HashMap::new(),
+        }
+    }
+
+    /// Create a minimal configuration for a fresh node
+    ///
+    /// Contains the loopback device and the physical management interface
+    /// 'mgmt_iface' with static address 'cidr' (IPv4 or IPv6).
+    pub fn minimal(mgmt_iface: &str, cidr: &str, gateway: Option<&str>) -> Result<Self, Error> {
+        if !is_physical_nic(mgmt_iface) {
+            bail!("management interface '{}' is not a physical network device", mgmt_iface);
+        }
+
+        let (_, _, is_v6) = parse_cidr(cidr)?;
+
+        if let Some(gateway) = gateway {
+            let (_, mask, gateway_is_v6) = parse_address_or_cidr(gateway)?;
+            if mask.is_some() {
+                bail!("gateway '{}' must not contain a netmask", gateway);
+            }
+            if gateway_is_v6 != is_v6 {
+                bail!("gateway '{}' does not match address family of '{}'", gateway, cidr);
+            }
+        }
+
+        let mut lo = Interface::new(String::from("lo"));
+        lo.set_method_v4(NetworkConfigMethod::Loopback)?;
+        lo.interface_type = NetworkInterfaceType::Loopback;
+        lo.autostart = true;
+
+        let mut mgmt = Interface::new(mgmt_iface.to_string());
+        mgmt.interface_type = NetworkInterfaceType::Eth;
+        mgmt.autostart = true;
+        if is_v6 {
+            mgmt.set_method_v6(NetworkConfigMethod::Static)?;
+            mgmt.set_cidr_v6(cidr.to_string())?;
+            if let Some(gateway) = gateway {
+                mgmt.set_gateway_v6(gateway.to_string())?;
+            }
+        } else {
+            mgmt.set_method_v4(NetworkConfigMethod::Static)?;
+            mgmt.set_cidr_v4(cidr.to_string())?;
+            if let Some(gateway) = gateway {
+                mgmt.set_gateway_v4(gateway.to_string())?;
+            }
         }
+
+        let mut config = Self::new();
+        config.order.push(NetworkOrderEntry::Iface(lo.name.clone()));
+        config.interfaces.insert(lo.name.clone(), lo);
+        config.order.push(NetworkOrderEntry::Iface(mgmt.name.clone()));
+        config.interfaces.insert(mgmt.name.clone(), mgmt);
+
+        Ok(config)
     }
 
     pub fn lookup(&self, name: &str) -> Result<&Interface, Error> {
@@ -363,21 +558,28 @@ impl NetworkConfig {
 
     /// Check if ports are used only once
     pub fn check_port_usage(&self) -> Result<(), Error> {
-        let mut used_ports = HashMap::new();
-        let mut check_port_usage = |iface, ports: &Vec<String>| {
-            for port in ports.iter() {
-                if let Some(prev_iface) = used_ports.get(port) {
-                    bail!("iface '{}' port '{}' is already used on interface '{}'",
-                          iface, port, prev_iface);
-                }
-                used_ports.insert(port.to_string(), iface);
-            }
-            Ok(())
-        };
-
         for (iface, interface) in self.interfaces.iter() {
-            if let Some(ports) = &interface.bridge_ports { check_port_usage(iface, ports)?; }
-            if let Some(slaves) = &interface.slaves { check_port_usage(iface, slaves)?; }
+            self.check_interface_port_usage(iface, interface)?;
+        }
+        Ok(())
+    }
+
+    fn check_interface_port_usage(&self, iface: &str, interface: &Interface) -> Result<(), Error> {
+        let mut own_ports = HashSet::new();
+        for port in interface.bridge_ports.iter().chain(interface.slaves.iter()).flatten() {
+            if !own_ports.insert(port) {
+                bail!("iface '{}' port '{}' is already used on interface '{}'",
+                      iface, port, iface);
+            }
+            let other = self.interfaces.iter()
+                .filter(|(name, _)| name.as_str() != iface)
+                .find(|(_, other)| {
+                    other.bridge_ports.iter().chain(other.slaves.iter()).flatten().any(|p| p == port)
+                });
+            if let Some((other_name, _)) = other {
+                bail!("iface '{}' port '{}' is already used on interface '{}'",
+                      iface, port, other_name);
+            }
         }
         Ok(())
     }
@@ -414,24 +616,81 @@ impl NetworkConfig {
         Ok(())
     }
 
+    /// Compute the effective MTU of an interface
+    ///
+    /// Walks down the chain of lower devices (bridge ports, bond
+    /// slaves and vlan raw devices) and returns the minimum configured
+    /// MTU. Bridges, bonds and vlans without explicit MTU inherit it
+    /// from their lower devices, everything else defaults to 1500.
+    pub fn effective_mtu(&self, iface: &str) -> u32 {
+        fn visit<'a>(
+            config: &'a NetworkConfig,
+            iface: &'a str,
+            visited: &mut HashSet<String>,
+        ) -> Option<u64> {
+            if !visited.insert(iface.to_string()) {
+                return None; // reference cycle, reported elsewhere
+            }
+
+            let interface = config.interfaces.get(iface)?;
+
+            let mut lower_devices: Vec<String> = interface.bridge_ports.iter()
+                .chain(interface.slaves.iter())
+                .flatten()
+                .map(|port| port_base_interface(port).to_string())
+                .collect();
+
+            if interface.interface_type == NetworkInterfaceType::Vlan {
+                if let Ok((raw_device, _vlan_id)) = interface.vlan_info() {
+                    lower_devices.push(raw_device);
+                }
+            }
+
+            let inherits_mtu = match interface.interface_type {
+                NetworkInterfaceType::Bridge |
+                NetworkInterfaceType::Bond |
+                NetworkInterfaceType::Vlan => true,
+                _ => false,
+            };
+
+            let own_mtu = match interface.mtu {
+                Some(mtu) => Some(mtu),
+                None if inherits_mtu => None,
+                None => Some(1500),
+            };
+
+            lower_devices.iter()
+                .filter_map(|lower| visit(config, lower, visited))
+                .chain(own_mtu)
+                .min()
+        }
+
+        visit(self, iface, &mut HashSet::new()).unwrap_or(1500) as u32
+    }
+
     /// Check if bond slaves exists
     pub fn check_bond_slaves(&self) -> Result<(), Error> {
         for (iface, interface) in self.interfaces.iter() {
-            if let Some(slaves) = &interface.slaves {
-                for slave in slaves.iter() {
-                    match self.interfaces.get(slave) {
-                        Some(entry) => {
-                            if entry.interface_type != NetworkInterfaceType::Eth {
-                                bail!("bond '{}' - wrong interface type on slave '{}' ({:?} != {:?})",
-                                      iface, slave, entry.interface_type, NetworkInterfaceType::Eth);
-                            }
-                        }
-                        None => {
-                            bail!("bond '{}' - unable to find slave '{}'", iface, slave);
+            self.check_interface_bond_slaves(iface, interface)?;
+        }
+        Ok(())
+    }
+
+    fn check_interface_bond_slaves(&self, iface: &str, interface: &Interface) -> Result<(), Error> {
+        if let Some(slaves) = &interface.slaves {
+            for slave in slaves.iter() {
+                match self.interfaces.get(slave) {
+                    Some(entry) => {
+                        if entry.interface_type != NetworkInterfaceType::Eth {
+                            bail!("bond '{}' - wrong interface type on slave '{}' ({:?} != {:?})",
+                                  iface, slave, entry.interface_type, NetworkInterfaceType::Eth);
                         }
                     }
-                    self.check_mtu(iface, slave)?;
+                    None => {
+                        bail!("bond '{}' - unable to find slave '{}'", iface, slave);
+                    }
                 }
+                self.check_mtu(iface, slave)?;
             }
         }
         Ok(())
@@ -439,31 +698,208 @@ impl NetworkConfig {
 
     /// Check if bridge ports exists
     pub fn check_bridge_ports(&self) -> Result<(), Error> {
-        lazy_static!{
-            static ref VLAN_INTERFACE_REGEX: Regex = Regex::new(r"^(\S+)\.(\d+)$").unwrap();
+        for (iface, interface) in self.interfaces.iter() {
+            self.check_interface_bridge_ports(iface, interface)?;
         }
+        Ok(())
+    }
 
+    fn check_interface_bridge_ports(&self, iface: &str, interface: &Interface) -> Result<(), Error> {
+        if let Some(ports) = &interface.bridge_ports {
+            for port in ports.iter() {
+                let port = port_base_interface(port);
+                if !self.interfaces.contains_key(port) {
+                    bail!("bridge '{}' - unable to find port '{}'", iface, port);
+                }
+                self.check_mtu(iface, port)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if vlan raw devices exists
+    pub fn check_vlan_raw_devices(&self) -> Result<(), Error> {
         for (iface, interface) in self.interfaces.iter() {
-            if let Some(ports) = &interface.bridge_ports {
-                for port in ports.iter() {
-                    let captures = VLAN_INTERFACE_REGEX.captures(port);
-                    let port = if let Some(ref caps) = captures { &caps[1] } else { port.as_str() };
-                    if !self.interfaces.contains_key(port) {
-                        bail!("bridge '{}' - unable to find port '{}'", iface, port);
-                    }
-                    self.check_mtu(iface, port)?;
+            self.check_interface_vlan_raw_device(iface, interface)?;
+        }
+        Ok(())
+    }
+
+    fn check_interface_vlan_raw_device(&self, iface: &str, interface: &Interface) -> Result<(), Error> {
+        if interface.interface_type != NetworkInterfaceType::Vlan { return Ok(()); }
+
+        let (raw_device, _vlan_id) = interface.vlan_info()?;
+        if !self.interfaces.contains_key(&raw_device) {
+            bail!("vlan '{}' - unable to find vlan-raw-device '{}'", iface, raw_device);
+        }
+        self.check_mtu(&raw_device, iface)
+    }
+
+    /// Returns all interfaces of the given type
+    pub fn interfaces_by_type(&self, interface_type: NetworkInterfaceType) -> Vec<&Interface> {
+        self.interfaces.values()
+            .filter(|interface| interface.interface_type == interface_type)
+            .collect()
+    }
+
+    /// Returns all interfaces using 'port' as bridge port or bond slave
+    ///
+    /// VLAN ports like 'eth0.100' are treated as references to 'eth0'.
+    pub fn interfaces_using_port(&self, port: &str) -> Vec<String> {
+        self.interfaces.iter()
+            .filter(|(_, interface)| {
+                interface.bridge_ports.iter().chain(interface.slaves.iter()).flatten()
+                    .any(|p| port_base_interface(p) == port)
+            })
+            .map(|(iface, _)| iface.to_string())
+            .collect()
+    }
+
+    /// Check for circular references between bridge ports and bond slaves
+    pub fn check_no_reference_cycles(&self) -> Result<(), Error> {
+        fn visit<'a>(
+            config: &'a NetworkConfig,
+            iface: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Result<(), Error> {
+            if let Some(pos) = path.iter().position(|name| *name == iface) {
+                let mut cycle = path[pos..].to_vec();
+                cycle.push(iface);
+                bail!("detected circular interface reference: {}", cycle.join(" -> "));
+            }
+            if done.contains(iface) { return Ok(()); }
+
+            let interface = match config.interfaces.get(iface) {
+                Some(interface) => interface,
+                None => return Ok(()), // missing ports are reported elsewhere
+            };
+
+            path.push(iface);
+            let ports = interface.bridge_ports.iter().chain(interface.slaves.iter()).flatten();
+            for port in ports {
+                visit(config, port_base_interface(port), path, done)?;
+            }
+            path.pop();
+
+            done.insert(iface);
+            Ok(())
+        }
+
+        let mut done = HashSet::new();
+        for iface in self.interfaces.keys() {
+            visit(self, iface, &mut Vec::new(), &mut done)?;
+        }
+
+        Ok(())
+    }
+
+    /// Compute the per interface changes from this to the 'new' configuration
+    pub fn diff(&self, new: &NetworkConfig) -> Result<Vec<InterfaceChange>, Error> {
+        let mut names: Vec<&String> = self.interfaces.keys().chain(new.interfaces.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        let mut changes = Vec::new();
+
+        for name in names {
+            let (old, new) = match (self.interfaces.get(name), new.interfaces.get(name)) {
+                (Some(old), Some(new)) => (old, new),
+                (old, new) => {
+                    let change = if old.is_none() { InterfaceChangeType::Added } else { InterfaceChangeType::Removed };
+                    changes.push(InterfaceChange { name: name.to_string(), change, attributes: Vec::new() });
+                    continue;
                 }
+            };
+
+            let old = serde_json::to_value(old)?;
+            let new = serde_json::to_value(new)?;
+            let (old, new) = match (old.as_object(), new.as_object()) {
+                (Some(old), Some(new)) => (old, new),
+                _ => bail!("unable to compare interface '{}'", name),
+            };
+
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            let mut attributes = Vec::new();
+            for key in keys {
+                if key == "active" { continue; } // runtime state, not configuration
+                let old_value = old.get(key).cloned().unwrap_or(Value::Null);
+                let new_value = new.get(key).cloned().unwrap_or(Value::Null);
+                if old_value != new_value {
+                    attributes.push(AttributeChange { name: key.to_string(), old: old_value, new: new_value });
+                }
+            }
+
+            if !attributes.is_empty() {
+                changes.push(InterfaceChange {
+                    name: name.to_string(),
+                    change: InterfaceChangeType::Modified,
+                    attributes,
+                });
             }
         }
+
+        Ok(changes)
+    }
+
+    /// Run all consistency checks, without writing anything
+    ///
+    /// This is done by `write_config` too (which only warns about missing
+    /// vlan raw devices), but allows to validate a modified configuration
+    /// before showing or applying it.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.check_no_reference_cycles()?;
+        self.check_port_usage()?;
+        self.check_bond_slaves()?;
+        self.check_bridge_ports()?;
+        self.check_vlan_raw_devices()?;
         Ok(())
     }
 
+    /// Write a single interface stanza
+    ///
+    /// Runs the checks relevant for this interface (port usage, bond
+    /// slaves, bridge ports, vlan raw device and reference cycles)
+    /// and writes it the same way `write_config` does.
+    pub fn write_interface(&self, name: &str, w: &mut dyn Write) -> Result<(), Error> {
+        let interface = self.lookup(name)?;
+
+        self.check_no_reference_cycles()?;
+
+        self.check_interface_port_usage(name, interface)?;
+        self.check_interface_bond_slaves(name, interface)?;
+        self.check_interface_bridge_ports(name, interface)?;
+        self.check_interface_vlan_raw_device(name, interface)?;
+
+        interface.write_iface(w, self.fail_over_mac_key(name))
+    }
+
+    fn fail_over_mac_key(&self, name: &str) -> &str {
+        self.fail_over_mac_keys.get(name).map(String::as_str).unwrap_or(BOND_FAIL_OVER_MAC_KEY)
+    }
+
     pub fn write_config(&self, w: &mut dyn Write) -> Result<(), Error> {
 
+        self.check_no_reference_cycles()?;
         self.check_port_usage()?;
         self.check_bond_slaves()?;
         self.check_bridge_ports()?;
 
+        // the raw device may be configured elsewhere, so do not refuse to
+        // write existing configurations
+        for (iface, interface) in self.interfaces.iter() {
+            if interface.interface_type != NetworkInterfaceType::Vlan { continue; }
+            let (raw_device, _vlan_id) = interface.vlan_info()?;
+            if self.interfaces.contains_key(&raw_device) {
+                self.check_mtu(&raw_device, iface)?;
+            } else {
+                log::warn!("vlan '{}' - unable to find vlan-raw-device '{}'", iface, raw_device);
+            }
+        }
+
         let mut done = HashSet::new();
 
         let mut last_entry_was_comment = false;
@@ -492,14 +928,14 @@ impl NetworkConfig {
                     if done.contains(name) { continue; }
                     done.insert(name);
 
-                    interface.write_iface(w)?;
+                    interface.write_iface(w, self.fail_over_mac_key(name))?;
                 }
             }
         }
 
         for (name, interface) in &self.interfaces {
             if done.contains(name) { continue; }
-            interface.write_iface(w)?;
+            interface.write_iface(w, self.fail_over_mac_key(name))?;
         }
         Ok(())
     }
@@ -519,11 +955,37 @@ pub fn config() -> Result<(NetworkConfig, [u8;32]), Error> {
         }
     };
 
-    let digest = openssl::sha::sha256(&content);
-
     let existing_interfaces = get_network_interfaces()?;
-    let mut parser = NetworkParser::new(&content[..]);
-    let data = parser.parse_interfaces(Some(&existing_interfaces))?;
+
+    parse_config(&content, Some(&existing_interfaces))
+}
+
+/// Read and parse the network configuration from `path`
+///
+/// Unlike `config()`, this does not fall back to an empty
+/// configuration if the file does not exist. If `existing_interfaces`
+/// is given (see `get_network_interfaces()`), it is used to detect
+/// physical interfaces and their state.
+pub fn config_from_path(
+    path: &Path,
+    existing_interfaces: Option<&HashMap<String, bool>>,
+) -> Result<(NetworkConfig, [u8;32]), Error> {
+
+    let content = proxmox::tools::fs::file_get_contents(path)?;
+
+    parse_config(&content, existing_interfaces)
+        .map_err(|err| format_err!("unable to parse {:?} - {}", path, err))
+}
+
+fn parse_config(
+    content: &[u8],
+    existing_interfaces: Option<&HashMap<String, bool>>,
+) -> Result<(NetworkConfig, [u8;32]), Error> {
+
+    let digest = openssl::sha::sha256(content);
+
+    let mut parser = NetworkParser::new(content);
+    let data = parser.parse_interfaces(existing_interfaces)?;
 
     Ok((data, digest))
 }
@@ -537,6 +999,23 @@ pub fn changes() -> Result<String, Error> {
     compute_file_diff(NETWORK_INTERFACES_FILENAME, NETWORK_INTERFACES_NEW_FILENAME)
 }
 
+/// Like `changes`, but returns the added, removed and modified interfaces
+pub fn changes_structured() -> Result<Vec<InterfaceChange>, Error> {
+
+    let new_content = match proxmox::tools::fs::file_get_optional_contents(NETWORK_INTERFACES_NEW_FILENAME)? {
+        Some(content) => content,
+        None => return Ok(Vec::new()),
+    };
+
+    let content = proxmox::tools::fs::file_get_optional_contents(NETWORK_INTERFACES_FILENAME)?
+        .unwrap_or_default();
+
+    let current = NetworkParser::new(&content[..]).parse_interfaces(None)?;
+    let new = NetworkParser::new(&new_content[..]).parse_interfaces(None)?;
+
+    current.diff(&new)
+}
+
 pub fn save_config(config: &NetworkConfig) -> Result<(), Error> {
 
     let mut raw = Vec::new();
@@ -565,14 +1044,12 @@ pub fn complete_interface_name(_arg: &str, _param: &HashMap<String, String>) ->
 
 
 pub fn complete_port_list(arg: &str, _param: &HashMap<String, String>) -> Vec<String> {
-    let mut ports = Vec::new();
-    match config() {
+    let ports: Vec<String> = match config() {
         Ok((data, _digest)) => {
-            for (iface, interface) in data.interfaces.iter() {
-                if interface.interface_type == NetworkInterfaceType::Eth {
-                    ports.push(iface.to_string());
-                }
-            }
+            data.interfaces_by_type(NetworkInterfaceType::Eth)
+                .iter()
+                .map(|interface| interface.name.clone())
+                .collect()
         }
         Err(_) => return vec![],
     };
@@ -729,4 +1206,737 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_network_config_bond_xmit_hash_policy() -> Result<(), Error> {
+
+        for policy in &["layer2", "layer2+3", "layer3+4", "encap2+3", "encap3+4"] {
+            let input = format!(
+                "iface eno1 inet manual\n\
+                 \n\
+                 iface eno2 inet manual\n\
+                 \n\
+                 auto bond0\n\
+                 iface bond0 inet manual\n\
+                 \tbond-slaves eno1 eno2\n\
+                 \tbond-mode 802.3ad\n\
+                 \tbond-xmit-hash-policy {}\n",
+                policy,
+            );
+
+            let mut parser = NetworkParser::new(input.as_bytes());
+            let config = parser.parse_interfaces(None)?;
+
+            let bond = config.lookup("bond0")?;
+            assert_eq!(
+                bond.bond_xmit_hash_policy.as_ref().map(bond_xmit_hash_policy_to_str),
+                Some(*policy),
+            );
+
+            let output = String::try_from(config)?;
+
+            let expected = format!(
+                "auto lo\n\
+                 iface lo inet loopback\n\
+                 \n\
+                 iface eno1 inet manual\n\
+                 \n\
+                 iface eno2 inet manual\n\
+                 \n\
+                 auto bond0\n\
+                 iface bond0 inet manual\n\
+                 \tbond-mode 802.3ad\n\
+                 \tbond_xmit_hash_policy {}\n\
+                 \tbond-slaves eno1 eno2\n\
+                 \n",
+                policy,
+            );
+            assert_eq!(output, expected);
+
+            // run again using output as input
+            let mut parser = NetworkParser::new(output.as_bytes());
+            let output2 = String::try_from(parser.parse_interfaces(None)?)?;
+            assert_eq!(output2, expected);
+        }
+
+        // the policy is not written for modes which do not use it
+        let input = "iface eno1 inet manual\n\
+                     auto bond0\n\
+                     iface bond0 inet manual\n\
+                     \tbond-slaves eno1\n\
+                     \tbond-mode active-backup\n\
+                     \tbond-xmit-hash-policy encap3+4\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let output = String::try_from(parser.parse_interfaces(None)?)?;
+        assert!(!output.contains("encap3+4"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_bond_fail_over_mac() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual\n\
+                     \n\
+                     iface eno2 inet manual\n\
+                     \n\
+                     auto bond0\n\
+                     iface bond0 inet manual\n\
+                     \tbond-slaves eno1 eno2\n\
+                     \tbond-mode active-backup\n\
+                     \tbond-primary eno1\n\
+                     \tbond-fail_over_mac follow\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+
+        assert_eq!(config.lookup("bond0")?.bond_fail_over_mac, Some(BondFailOverMac::follow));
+
+        let output = String::try_from(config)?;
+
+        let expected = "auto lo\n\
+                        iface lo inet loopback\n\
+                        \n\
+                        iface eno1 inet manual\n\
+                        \n\
+                        iface eno2 inet manual\n\
+                        \n\
+                        auto bond0\n\
+                        iface bond0 inet manual\n\
+                        \tbond-mode active-backup\n\
+                        \tbond-primary eno1\n\
+                        \tbond-fail_over_mac follow\n\
+                        \tbond-slaves eno1 eno2\n\
+                        \n";
+        assert_eq!(output, expected);
+
+        // run again using output as input
+        let mut parser = NetworkParser::new(output.as_bytes());
+        let output = String::try_from(parser.parse_interfaces(None)?)?;
+        assert_eq!(output, expected);
+
+        // numeric values are accepted too
+        let input = "auto bond0\n\
+                     iface bond0 inet manual\n\
+                     \tbond-mode active-backup\n\
+                     \tbond-fail-over-mac 1\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert_eq!(config.lookup("bond0")?.bond_fail_over_mac, Some(BondFailOverMac::active));
+        assert!(String::try_from(config)?.contains("\tbond-fail-over-mac active\n"));
+
+        // reject unknown values
+        let input = "auto bond0\n\
+                     iface bond0 inet manual\n\
+                     \tbond-mode active-backup\n\
+                     \tbond-fail-over-mac always\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        assert!(parser.parse_interfaces(None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_bond_miimon() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual\n\
+                     \n\
+                     iface eno2 inet manual\n\
+                     \n\
+                     auto bond0\n\
+                     iface bond0 inet manual\n\
+                     \tbond-slaves eno1 eno2\n\
+                     \tbond-miimon 100\n\
+                     \tbond-mode active-backup\n\
+                     \tbond_downdelay 200\n\
+                     \tbond-updelay 300\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+
+        let bond = config.lookup("bond0")?;
+        assert_eq!(bond.bond_miimon, Some(100));
+        assert_eq!(bond.bond_downdelay, Some(200));
+        assert_eq!(bond.bond_updelay, Some(300));
+        assert!(bond.options.is_empty());
+
+        let output = String::try_from(config)?;
+
+        let expected = "auto lo\n\
+                        iface lo inet loopback\n\
+                        \n\
+                        iface eno1 inet manual\n\
+                        \n\
+                        iface eno2 inet manual\n\
+                        \n\
+                        auto bond0\n\
+                        iface bond0 inet manual\n\
+                        \tbond-mode active-backup\n\
+                        \tbond-miimon 100\n\
+                        \tbond-downdelay 200\n\
+                        \tbond-updelay 300\n\
+                        \tbond-slaves eno1 eno2\n\
+                        \n";
+        assert_eq!(output, expected);
+
+        // run again using output as input
+        let mut parser = NetworkParser::new(output.as_bytes());
+        let output = String::try_from(parser.parse_interfaces(None)?)?;
+        assert_eq!(output, expected);
+
+        // passed through unchanged for other interface types
+        let input = "auto eno1\n\
+                     iface eno1 inet manual\n\
+                     \tbond_miimon 100\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert_eq!(config.lookup("eno1")?.bond_miimon, None);
+        let output = String::try_from(config)?;
+        assert!(output.contains("\tbond_miimon 100\n"));
+
+        // unparseable values are kept as they are
+        let input = "iface bond0 inet manual\n\
+                     \tbond-slaves none\n\
+                     \tbond-miimon fast\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert_eq!(config.lookup("bond0")?.bond_miimon, None);
+        let output = String::try_from(config)?;
+        assert!(output.contains("\tbond-miimon fast\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_interfaces_using_port() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual\n\
+                     \n\
+                     iface eno2 inet manual\n\
+                     \n\
+                     iface bond0 inet manual\n\
+                     \tbond-slaves eno1 eno2\n\
+                     \tbond-mode active-backup\n\
+                     \n\
+                     iface vmbr0 inet manual\n\
+                     \tbridge-ports bond0\n\
+                     \n\
+                     iface vmbr1 inet manual\n\
+                     \tbridge-ports eno2.100\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+
+        assert_eq!(config.interfaces_using_port("eno1"), vec!["bond0"]);
+        assert_eq!(config.interfaces_using_port("eno2"), vec!["bond0", "vmbr1"]);
+        assert_eq!(config.interfaces_using_port("bond0"), vec!["vmbr0"]);
+        assert!(config.interfaces_using_port("vmbr0").is_empty());
+        assert!(config.interfaces_using_port("eno").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_interfaces_by_type() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual\n\
+                     \n\
+                     iface eno2 inet manual\n\
+                     \n\
+                     iface bond0 inet manual\n\
+                     \tbond-slaves eno1 eno2\n\
+                     \tbond-mode active-backup\n\
+                     \n\
+                     iface vmbr0 inet manual\n\
+                     \tbridge-ports bond0\n\
+                     \n\
+                     iface vmbr1 inet static\n\
+                     \taddress 10.0.0.5/24\n\
+                     \tbridge-ports none\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+
+        let names = |interface_type| -> Vec<String> {
+            config.interfaces_by_type(interface_type)
+                .iter()
+                .map(|interface| interface.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names(NetworkInterfaceType::Eth), vec!["eno1", "eno2"]);
+        assert_eq!(names(NetworkInterfaceType::Bond), vec!["bond0"]);
+        assert_eq!(names(NetworkInterfaceType::Bridge), vec!["vmbr0", "vmbr1"]);
+        assert_eq!(names(NetworkInterfaceType::Loopback), vec!["lo"]);
+        assert!(names(NetworkInterfaceType::Vlan).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_diff() -> Result<(), Error> {
+
+        let input = "auto ens18\n\
+                     iface ens18 inet static\n\
+                     \taddress 10.0.0.5/24\n\
+                     \tgateway 10.0.0.1\n\
+                     \n\
+                     iface ens19 inet manual\n\
+                     \n\
+                     iface ens20 inet manual\n";
+        let current = NetworkParser::new(input.as_bytes()).parse_interfaces(None)?;
+
+        let input = "auto ens18\n\
+                     iface ens18 inet static\n\
+                     \taddress 10.0.0.5/24\n\
+                     \tgateway 10.0.0.2\n\
+                     \tmtu 9000\n\
+                     \n\
+                     iface ens20 inet manual\n\
+                     \n\
+                     iface ens21 inet manual\n";
+        let new = NetworkParser::new(input.as_bytes()).parse_interfaces(None)?;
+
+        let changes = current.diff(&new)?;
+
+        assert_eq!(changes, vec![
+            InterfaceChange {
+                name: "ens18".to_string(),
+                change: InterfaceChangeType::Modified,
+                attributes: vec![
+                    AttributeChange {
+                        name: "gateway".to_string(),
+                        old: Value::from("10.0.0.1"),
+                        new: Value::from("10.0.0.2"),
+                    },
+                    AttributeChange {
+                        name: "mtu".to_string(),
+                        old: Value::Null,
+                        new: Value::from(9000),
+                    },
+                ],
+            },
+            InterfaceChange {
+                name: "ens19".to_string(),
+                change: InterfaceChangeType::Removed,
+                attributes: Vec::new(),
+            },
+            InterfaceChange {
+                name: "ens21".to_string(),
+                change: InterfaceChangeType::Added,
+                attributes: Vec::new(),
+            },
+        ]);
+
+        assert!(current.diff(&current)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_script_hooks() -> Result<(), Error> {
+
+        let input = "auto lo\n\
+                     iface lo inet loopback\n\
+                     \n\
+                     iface eno1 inet manual\n\
+                     \n\
+                     auto vmbr0\n\
+                     iface vmbr0 inet static\n\
+                     \taddress 10.0.0.2/24\n\
+                     \tgateway 10.0.0.1\n\
+                     \tpost-up ip route add 192.168.0.0/24 via 10.0.0.254\n\
+                     \tpre-up ip link set eno1 up\n\
+                     \tup echo \"vmbr0  up\" > /run/vmbr0.state\n\
+                     \tpost-down ip link set eno1 down\n\
+                     \tdown ip route del 192.168.0.0/24\n\
+                     \tbridge-ports eno1\n\
+                     \n\
+                     iface vmbr0 inet6 static\n\
+                     \taddress fd00::2/64\n\
+                     \tpost-up ip -6 route add fd01::/64 via fd00::1\n\
+                     \tpre-up sysctl -w net.ipv6.conf.vmbr0.accept_ra=0\n\
+                     \n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+
+        let vmbr0 = config.lookup("vmbr0")?;
+        assert_eq!(vmbr0.options.len(), 5);
+        assert_eq!(vmbr0.options[2], "up echo \"vmbr0  up\" > /run/vmbr0.state");
+        assert_eq!(vmbr0.options6, vec![
+            "post-up ip -6 route add fd01::/64 via fd00::1",
+            "pre-up sysctl -w net.ipv6.conf.vmbr0.accept_ra=0",
+        ]);
+
+        let output = String::try_from(config)?;
+        assert_eq!(output, input);
+
+        // run again using output as input
+        let mut parser = NetworkParser::new(output.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert_eq!(String::try_from(config)?, input);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_minimal() -> Result<(), Error> {
+
+        let config = NetworkConfig::minimal("eno1", "192.168.1.10/24", Some("192.168.1.1"))?;
+
+        config.check_no_reference_cycles()?;
+        config.check_port_usage()?;
+        config.check_bond_slaves()?;
+        config.check_bridge_ports()?;
+        config.check_vlan_raw_devices()?;
+
+        let output = String::try_from(config)?;
+
+        let expected = "auto lo\n\
+                        iface lo inet loopback\n\
+                        \n\
+                        auto eno1\n\
+                        iface eno1 inet static\n\
+                        \taddress 192.168.1.10/24\n\
+                        \tgateway 192.168.1.1\n\
+                        \n";
+        assert_eq!(output, expected);
+
+        // output parses to the same config
+        let mut parser = NetworkParser::new(output.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert_eq!(String::try_from(config)?, expected);
+
+        let config = NetworkConfig::minimal("ens18", "fd00::10/64", None)?;
+        assert_eq!(
+            String::try_from(config)?,
+            "auto lo\niface lo inet loopback\n\nauto ens18\niface ens18 inet6 static\n\taddress fd00::10/64\n\n",
+        );
+
+        assert!(NetworkConfig::minimal("vmbr0", "192.168.1.10/24", None).is_err());
+        assert!(NetworkConfig::minimal("eno1", "192.168.1.10", None).is_err());
+        assert!(NetworkConfig::minimal("eno1", "192.168.1.10/33", None).is_err());
+        assert!(NetworkConfig::minimal("eno1", "192.168.1.10/24", Some("192.168.1.1/24")).is_err());
+        assert!(NetworkConfig::minimal("eno1", "192.168.1.10/24", Some("fd00::1")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_reference_cycle() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual\n\
+                     \n\
+                     iface vmbr0 inet manual\n\
+                     \tbridge-ports eno1 vmbr1\n\
+                     \n\
+                     iface vmbr1 inet manual\n\
+                     \tbridge-ports vmbr0.10\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+
+        let err = config.check_no_reference_cycles().unwrap_err().to_string();
+        assert_eq!(err, "detected circular interface reference: vmbr0 -> vmbr1 -> vmbr0");
+
+        assert!(String::try_from(config).is_err());
+
+        // self reference
+        let input = "iface vmbr0 inet manual\n\
+                     \tbridge-ports vmbr0\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert!(config.check_no_reference_cycles().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_hwaddress() -> Result<(), Error> {
+
+        let input = "auto lo\n\
+                     iface lo inet loopback\n\
+                     \n\
+                     auto eno1\n\
+                     iface eno1 inet static\n\
+                     \taddress 10.0.0.2/24\n\
+                     \thwaddress ether 00:11:22:33:44:55\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+
+        assert_eq!(config.lookup("eno1")?.hwaddress.as_deref(), Some("00:11:22:33:44:55"));
+        assert!(config.lookup("eno1")?.options.is_empty());
+
+        let output = String::try_from(config)?;
+
+        let expected = "auto lo\n\
+                        iface lo inet loopback\n\
+                        \n\
+                        auto eno1\n\
+                        iface eno1 inet static\n\
+                        \taddress 10.0.0.2/24\n\
+                        \thwaddress ether 00:11:22:33:44:55\n\
+                        \n";
+        assert_eq!(output, expected);
+
+        // run again using output as input
+        let mut parser = NetworkParser::new(output.as_bytes());
+        let output = String::try_from(parser.parse_interfaces(None)?)?;
+        assert_eq!(output, expected);
+
+        // invalid MAC addresses are kept as they are
+        let input = "iface eno1 inet manual\n\
+                     \thwaddress ether 00:11:22:33:44\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert_eq!(config.lookup("eno1")?.hwaddress, None);
+        assert!(String::try_from(config)?.contains("\thwaddress ether 00:11:22:33:44\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_vlan() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual\n\
+                     \n\
+                     auto eno1.100\n\
+                     iface eno1.100 inet static\n\
+                     \taddress 10.0.100.2/24\n\
+                     \n\
+                     auto vlan200\n\
+                     iface vlan200 inet manual\n\
+                     \tvlan_raw_device eno1\n";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+
+        let vlan = config.lookup("eno1.100")?;
+        assert_eq!(vlan.interface_type, NetworkInterfaceType::Vlan);
+        assert_eq!(vlan.vlan_info()?, ("eno1".to_string(), 100));
+
+        let vlan = config.lookup("vlan200")?;
+        assert_eq!(vlan.interface_type, NetworkInterfaceType::Vlan);
+        assert_eq!(vlan.vlan_info()?, ("eno1".to_string(), 200));
+
+        let output = String::try_from(config)?;
+
+        let expected = "auto lo\n\
+                        iface lo inet loopback\n\
+                        \n\
+                        iface eno1 inet manual\n\
+                        \n\
+                        auto eno1.100\n\
+                        iface eno1.100 inet static\n\
+                        \taddress 10.0.100.2/24\n\
+                        \n\
+                        auto vlan200\n\
+                        iface vlan200 inet manual\n\
+                        \tvlan-raw-device eno1\n\
+                        \n";
+        assert_eq!(output, expected);
+
+        // run again using output as input
+        let mut parser = NetworkParser::new(output.as_bytes());
+        let output = String::try_from(parser.parse_interfaces(None)?)?;
+        assert_eq!(output, expected);
+
+        // explicit vlan-id on a non-vlan name
+        let input = "iface eno1 inet manual\n\
+                     iface mgmt inet manual\n\
+                     \tvlan-raw-device eno1\n\
+                     \tvlan-id 10\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert_eq!(config.lookup("mgmt")?.vlan_info()?, ("eno1".to_string(), 10));
+        assert!(String::try_from(config)?.contains("\tvlan-raw-device eno1\n\tvlan-id 10\n"));
+
+        // missing raw device
+        let input = "iface vlan300 inet manual\n\
+                     \tvlan-raw-device eno2\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        let err = config.check_vlan_raw_devices().unwrap_err().to_string();
+        assert_eq!(err, "vlan 'vlan300' - unable to find vlan-raw-device 'eno2'");
+        assert!(config.validate().is_err());
+        // only a warning when writing
+        assert!(String::try_from(config)?.contains("\tvlan-raw-device eno2\n"));
+
+        // vlan-id does not match the interface name
+        let input = "iface eno1 inet manual\n\
+                     iface eno1.100 inet manual\n\
+                     \tvlan-id 200\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let config = parser.parse_interfaces(None)?;
+        assert!(String::try_from(config).is_err());
+
+        // vlan-id out of range
+        let input = "iface vlan5 inet manual\n\
+                     \tvlan-id 4095\n";
+        let mut parser = NetworkParser::new(input.as_bytes());
+        assert!(parser.parse_interfaces(None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_validate() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual
+                     
+                     auto vmbr0
+                     iface vmbr0 inet manual
+                     	bridge-ports eno1
+                     
+                     auto vmbr1
+                     iface vmbr1 inet manual
+                     	bridge-ports eno1
+";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let mut config = parser.parse_interfaces(None)?;
+
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("port 'eno1' is already used"));
+
+        config.interfaces.get_mut("vmbr1").unwrap().bridge_ports = None;
+        config.validate()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_effective_mtu() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual
+                     	mtu 9000
+
+                     iface eno2 inet manual
+                     	mtu 9000
+
+                     auto bond0
+                     iface bond0 inet manual
+                     	bond-slaves eno1 eno2
+                     	bond-mode active-backup
+
+                     auto vmbr0
+                     iface vmbr0 inet manual
+                     	bridge-ports bond0
+                     	mtu 9000
+
+                     auto vmbr0.100
+                     iface vmbr0.100 inet manual
+
+                     iface eno3 inet manual
+                     	mtu 9000
+
+                     auto vlan200
+                     iface vlan200 inet manual
+                     	vlan-raw-device eno3
+                     	mtu 8000
+";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let mut config = parser.parse_interfaces(None)?;
+
+        assert_eq!(config.effective_mtu("bond0"), 9000);
+        assert_eq!(config.effective_mtu("vmbr0"), 9000);
+        assert_eq!(config.effective_mtu("vmbr0.100"), 9000);
+        assert_eq!(config.effective_mtu("vlan200"), 8000);
+
+        // a bond slave with default mtu limits the whole stack
+        config.interfaces.get_mut("eno2").unwrap().mtu = None;
+        assert_eq!(config.effective_mtu("bond0"), 1500);
+        assert_eq!(config.effective_mtu("vmbr0.100"), 1500);
+
+        // the vlan itself may be lower than the stack below
+        config.interfaces.get_mut("vmbr0.100").unwrap().mtu = Some(1400);
+        assert_eq!(config.effective_mtu("vmbr0.100"), 1400);
+
+        assert_eq!(config.effective_mtu("unknown0"), 1500);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_from_path() -> Result<(), Error> {
+
+        let path = Path::new("tests/network_data/interfaces");
+
+        let (config, digest) = config_from_path(path, None)?;
+        assert_eq!(digest, openssl::sha::sha256(&std::fs::read(path)?));
+
+        assert_eq!(config.lookup("lo")?.interface_type, NetworkInterfaceType::Loopback);
+        assert_eq!(config.lookup("bond0")?.interface_type, NetworkInterfaceType::Bond);
+        assert_eq!(config.lookup("vmbr0")?.interface_type, NetworkInterfaceType::Bridge);
+        assert_eq!(config.lookup("vmbr0")?.cidr, Some(String::from("192.168.10.2/24")));
+        assert_eq!(config.lookup("vmbr0.20")?.interface_type, NetworkInterfaceType::Vlan);
+        assert_eq!(config.effective_mtu("vmbr0.20"), 9000);
+        config.validate()?;
+
+        assert_eq!(config.lookup("enp1s0")?.active, false);
+        assert!(config.lookup("enp3s0").is_err());
+
+        // cross-reference with existing interfaces
+        let mut existing_interfaces = HashMap::new();
+        existing_interfaces.insert(String::from("enp1s0"), true);
+        existing_interfaces.insert(String::from("enp3s0"), true);
+        let (config, _) = config_from_path(path, Some(&existing_interfaces))?;
+        assert_eq!(config.lookup("enp1s0")?.active, true);
+        assert_eq!(config.lookup("enp2s0")?.active, false);
+        assert_eq!(config.lookup("enp3s0")?.interface_type, NetworkInterfaceType::Eth);
+
+        assert!(config_from_path(Path::new("tests/network_data/does-not-exist"), None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_config_write_interface() -> Result<(), Error> {
+
+        let input = "iface eno1 inet manual
+
+                     iface eno2 inet manual
+
+                     auto vmbr0
+                     iface vmbr0 inet static
+                     	address 10.0.0.100/16
+                     	gateway 10.0.0.1
+                     	bridge-ports eno1
+
+                     auto vmbr1
+                     iface vmbr1 inet manual
+                     	bridge-ports eno2
+                     	mtu 1400
+";
+
+        let mut parser = NetworkParser::new(input.as_bytes());
+        let mut config = parser.parse_interfaces(None)?;
+
+        let mut full = Vec::new();
+        config.write_config(&mut full)?;
+        let full = String::from_utf8(full)?;
+
+        for name in &["eno1", "vmbr0", "vmbr1"] {
+            let mut single = Vec::new();
+            config.write_interface(name, &mut single)?;
+            let single = String::from_utf8(single)?;
+
+            assert!(single.contains(&format!("iface {} inet", name)));
+            assert!(full.contains(&single));
+        }
+
+        assert!(config.write_interface("vmbr2", &mut Vec::new()).is_err());
+
+        // per interface checks still apply
+        config.interfaces.get_mut("vmbr1").unwrap().bridge_ports = Some(vec!["eno1".to_string()]);
+        let err = config.write_interface("vmbr1", &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("port 'eno1' is already used"));
+
+        Ok(())
+    }
 }
@@ -82,6 +82,19 @@ pub fn check_netmask(mask: u8, is_v6: bool) -> Result<(), Error> {
     Ok(())
 }
 
+pub fn parse_vlan_id(text: &str) -> Result<u16, Error> {
+    let vlan_id = match u16::from_str_radix(text, 10) {
+        Ok(vlan_id) => vlan_id,
+        Err(err) => bail!("unable to parse vlan-id '{}' - {}", text, err),
+    };
+
+    if !(1..=4094).contains(&vlan_id) {
+        bail!("vlan-id '{}' is out of range (1..4094).", vlan_id);
+    }
+
+    Ok(vlan_id)
+}
+
 // parse ip address with optional cidr mask
 pub fn parse_address_or_cidr(cidr: &str) -> Result<(String, Option<u8>, bool), Error> {
 
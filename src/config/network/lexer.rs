@@ -1,6 +1,6 @@
 use std::io::BufRead;
 use std::iter::Iterator;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use lazy_static::lazy_static;
 
@@ -28,6 +28,13 @@ pub enum Token {
     BondMode,
     BondPrimary,
     BondXmitHashPolicy,
+    BondFailOverMac,
+    BondMiimon,
+    BondDowndelay,
+    BondUpdelay,
+    HwAddress,
+    VlanId,
+    VlanRawDevice,
     EOF,
 }
 
@@ -57,8 +64,27 @@ lazy_static! {
         map.insert("bond_primary", Token::BondPrimary);
         map.insert("bond_xmit_hash_policy", Token::BondXmitHashPolicy);
         map.insert("bond-xmit-hash-policy", Token::BondXmitHashPolicy);
+        map.insert("bond-fail-over-mac", Token::BondFailOverMac);
+        map.insert("bond-fail_over_mac", Token::BondFailOverMac);
+        map.insert("bond_fail_over_mac", Token::BondFailOverMac);
+        map.insert("bond-miimon", Token::BondMiimon);
+        map.insert("bond_miimon", Token::BondMiimon);
+        map.insert("bond-downdelay", Token::BondDowndelay);
+        map.insert("bond_downdelay", Token::BondDowndelay);
+        map.insert("bond-updelay", Token::BondUpdelay);
+        map.insert("bond_updelay", Token::BondUpdelay);
+        map.insert("hwaddress", Token::HwAddress);
+        map.insert("vlan-id", Token::VlanId);
+        map.insert("vlan_id", Token::VlanId);
+        map.insert("vlan-raw-device", Token::VlanRawDevice);
+        map.insert("vlan_raw_device", Token::VlanRawDevice);
         map
     };
+
+    // script hooks, the command is kept verbatim (including whitespace)
+    static ref HOOK_KEYWORDS: HashSet<&'static str> = {
+        ["pre-up", "up", "post-up", "pre-down", "down", "post-down"].iter().copied().collect()
+    };
 }
 
 pub struct Lexer<R> {
@@ -79,10 +105,15 @@ impl <R: BufRead> Lexer<R> {
             res.push_back((Token::Comment, comment.trim().to_string()));
             return res;
         }
-        let mut list: VecDeque<(Token, String)> = line.split_ascii_whitespace().map(|text| {
-            let token = KEYWORDS.get(text).unwrap_or(&Token::Text);
-            (*token, text.to_string())
-        }).collect();
+        let mut list: VecDeque<(Token, String)> = match line.split_ascii_whitespace().next() {
+            Some(first) if HOOK_KEYWORDS.contains(first) => {
+                std::iter::once((Token::Text, line.trim().to_string())).collect()
+            }
+            _ => line.split_ascii_whitespace().map(|text| {
+                let token = KEYWORDS.get(text).unwrap_or(&Token::Text);
+                (*token, text.to_string())
+            }).collect(),
+        };
 
         if line.starts_with(|c: char| c.is_ascii_whitespace() && c != '\n') {
             list.push_front((Token::Attribute, String::from("\t")));
@@ -9,7 +9,7 @@ use regex::Regex;
 use super::helper::*;
 use super::lexer::*;
 
-use super::{NetworkConfig, NetworkOrderEntry, Interface, NetworkConfigMethod, NetworkInterfaceType, bond_mode_from_str, bond_xmit_hash_policy_from_str};
+use super::{NetworkConfig, NetworkOrderEntry, Interface, NetworkConfigMethod, NetworkInterfaceType, bond_mode_from_str, bond_xmit_hash_policy_from_str, bond_fail_over_mac_from_str, BOND_FAIL_OVER_MAC_KEY};
 
 pub struct NetworkParser<R: BufRead> {
     input: Peekable<Lexer<R>>,
@@ -136,6 +136,23 @@ impl <R: BufRead> NetworkParser<R> {
         Ok(())
     }
 
+    // Returns the raw option line and the MAC address, if it is valid
+    fn parse_iface_hwaddress(&mut self) -> Result<(String, Option<String>), Error> {
+        let option = self.parse_to_eol()?;
+
+        // the address class 'ether' is optional
+        let hwaddress = match option.split_whitespace().collect::<Vec<_>>()[..] {
+            [_, "ether", hwaddress] | [_, hwaddress] => Some(hwaddress),
+            _ => None,
+        };
+
+        let hwaddress = hwaddress
+            .filter(|hwaddress| crate::api2::types::MAC_ADDRESS_REGEX.is_match(hwaddress))
+            .map(String::from);
+
+        Ok((option, hwaddress))
+    }
+
     fn parse_iface_mtu(&mut self) -> Result<u64, Error> {
         self.eat(Token::MTU)?;
 
@@ -152,6 +169,29 @@ impl <R: BufRead> NetworkParser<R> {
         Ok(mtu)
     }
 
+    // Returns the raw option line and its value, if it is a valid delay
+    fn parse_bond_delay(&mut self) -> Result<(String, Option<u32>), Error> {
+        let option = self.parse_to_eol()?;
+
+        let value = match option.split_whitespace().collect::<Vec<_>>()[..] {
+            [_, value] => u32::from_str_radix(value, 10).ok(),
+            _ => None,
+        };
+
+        Ok((option, value))
+    }
+
+    fn parse_vlan_id(&mut self) -> Result<u16, Error> {
+        self.eat(Token::VlanId)?;
+
+        let vlan_id = self.next_text()?;
+        let vlan_id = parse_vlan_id(&vlan_id)?;
+
+        self.eat(Token::Newline)?;
+
+        Ok(vlan_id)
+    }
+
     fn parse_yes_no(&mut self) -> Result<bool, Error> {
         let text = self.next_text()?;
         let value = match text.to_lowercase().as_str() {
@@ -204,10 +244,12 @@ impl <R: BufRead> NetworkParser<R> {
         interface: &mut Interface,
         address_family_v4: bool,
         address_family_v6: bool,
+        fail_over_mac_keys: &mut HashMap<String, String>,
     ) -> Result<(), Error> {
 
         let mut netmask = None;
         let mut address_list = Vec::new();
+        let mut bond_options = Vec::new();
 
         loop {
             match self.peek()? {
@@ -245,6 +287,25 @@ impl <R: BufRead> NetworkParser<R> {
                     let mtu = self.parse_iface_mtu()?;
                     interface.mtu = Some(mtu);
                 }
+                Token::HwAddress => {
+                    match self.parse_iface_hwaddress()? {
+                        (_, Some(hwaddress)) => interface.hwaddress = Some(hwaddress),
+                        // keep anything we do not understand as it is
+                        (option, None) => interface.options.push(option),
+                    }
+                }
+                Token::VlanId => {
+                    let vlan_id = self.parse_vlan_id()?;
+                    interface.vlan_id = Some(vlan_id);
+                    interface.set_interface_type(NetworkInterfaceType::Vlan)?;
+                }
+                Token::VlanRawDevice => {
+                    self.eat(Token::VlanRawDevice)?;
+                    let raw_device = self.next_text()?;
+                    interface.vlan_raw_device = Some(raw_device);
+                    interface.set_interface_type(NetworkInterfaceType::Vlan)?;
+                    self.eat(Token::Newline)?;
+                }
                 Token::BridgeVlanAware => {
                     self.eat(Token::BridgeVlanAware)?;
                     let bridge_vlan_aware = self.parse_yes_no()?;
@@ -280,6 +341,32 @@ impl <R: BufRead> NetworkParser<R> {
                     interface.bond_xmit_hash_policy = Some(policy);
                     self.eat(Token::Newline)?;
                 }
+                Token::BondFailOverMac => {
+                    let key = self.eat(Token::BondFailOverMac)?;
+                    let fail_over_mac = bond_fail_over_mac_from_str(&self.next_text()?)?;
+                    interface.bond_fail_over_mac = Some(fail_over_mac);
+                    if key != BOND_FAIL_OVER_MAC_KEY {
+                        fail_over_mac_keys.insert(interface.name.clone(), key);
+                    }
+                    self.eat(Token::Newline)?;
+                }
+                token @ Token::BondMiimon |
+                token @ Token::BondDowndelay |
+                token @ Token::BondUpdelay => {
+                    let (option, value) = self.parse_bond_delay()?;
+                    match value {
+                        Some(value) => {
+                            match token {
+                                Token::BondMiimon => interface.bond_miimon = Some(value),
+                                Token::BondDowndelay => interface.bond_downdelay = Some(value),
+                                _ => interface.bond_updelay = Some(value),
+                            }
+                            bond_options.push(option);
+                        }
+                        // keep unknown values as they are
+                        None => interface.options.push(option),
+                    }
+                }
                 _ => { // parse addon attributes
                     let option = self.parse_to_eol()?;
                     if !option.is_empty() {
@@ -293,6 +380,14 @@ impl <R: BufRead> NetworkParser<R> {
             }
         }
 
+        // only used for bonds, pass them through unchanged for other interface types
+        if interface.interface_type != NetworkInterfaceType::Bond && !bond_options.is_empty() {
+            interface.bond_miimon = None;
+            interface.bond_downdelay = None;
+            interface.bond_updelay = None;
+            interface.options.extend(bond_options);
+        }
+
         #[allow(clippy::comparison_chain)]
         if let Some(netmask) = netmask {
             if address_list.len() > 1 {
@@ -366,7 +461,12 @@ impl <R: BufRead> NetworkParser<R> {
                 interface.set_method_v6(config_method)?;
             }
 
-            self.parse_iface_attributes(&mut interface, address_family_v4, address_family_v6)?;
+            self.parse_iface_attributes(
+                &mut interface,
+                address_family_v4,
+                address_family_v6,
+                &mut config.fail_over_mac_keys,
+            )?;
         } else {
             let mut interface = Interface::new(iface.clone());
             if address_family_v4 {
@@ -376,7 +476,12 @@ impl <R: BufRead> NetworkParser<R> {
                 interface.set_method_v6(config_method)?;
             }
 
-            self.parse_iface_attributes(&mut interface, address_family_v4, address_family_v6)?;
+            self.parse_iface_attributes(
+                &mut interface,
+                address_family_v4,
+                address_family_v6,
+                &mut config.fail_over_mac_keys,
+            )?;
 
             config.interfaces.insert(interface.name.clone(), interface);
 
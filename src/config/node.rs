@@ -0,0 +1,257 @@
+//! Node specific configuration (`/etc/proxmox-backup/node.cfg`)
+
+use anyhow::{format_err, Error};
+use serde::{Serialize, Deserialize};
+
+use proxmox::api::{api, schema::*};
+use proxmox::const_regex;
+
+/// Default HTTP/2 window size used for backup/reader protocol connections
+pub const DEFAULT_HTTP2_WINDOW_SIZE: u32 = 32*1024*1024;
+/// Default HTTP/2 maximum frame size used for backup/reader protocol connections
+pub const DEFAULT_HTTP2_FRAME_SIZE: u32 = 4*1024*1024;
+/// Default for how far (in seconds) a backup-time may be ahead of the server clock
+pub const DEFAULT_MAX_BACKUP_TIME_SKEW: u32 = 300;
+
+pub const HTTP2_WINDOW_SIZE_SCHEMA: Schema = IntegerSchema::new(
+    "HTTP/2 stream and connection window size for backup and reader protocol connections (bytes).")
+    .minimum(65535)
+    .maximum((1 << 31) - 2)
+    .schema();
+
+pub const HTTP2_FRAME_SIZE_SCHEMA: Schema = IntegerSchema::new(
+    "HTTP/2 maximum frame size for backup and reader protocol connections (bytes).")
+    .minimum(16*1024)
+    .maximum((1 << 24) - 1)
+    .schema();
+
+pub const MAX_BACKUP_TIME_SKEW_SCHEMA: Schema = IntegerSchema::new(
+    "Maximum number of seconds a new backup's timestamp may be ahead of the server clock.")
+    .minimum(0)
+    .default(DEFAULT_MAX_BACKUP_TIME_SKEW as isize)
+    .schema();
+
+pub const USER_CACHE_MAX_AGE_SCHEMA: Schema = IntegerSchema::new(
+    "Maximum age (seconds) of the cached user configuration. By default, it is only reloaded when the file modification time changes.")
+    .minimum(1)
+    .schema();
+
+const_regex! {
+    CHANGELOG_MIRROR_REGEX = r"^[^\s=,;]+=https?://[^\s,;]+$";
+}
+
+pub const CHANGELOG_MIRROR_FORMAT: ApiStringFormat =
+    ApiStringFormat::Pattern(&CHANGELOG_MIRROR_REGEX);
+
+pub const CHANGELOG_MIRROR_ARRAY_SCHEMA: Schema = ArraySchema::new(
+    "Changelog mirror list.", &StringSchema::new("Changelog base URL for a package origin.")
+        .format(&CHANGELOG_MIRROR_FORMAT)
+        .type_text("<origin>=<base-url>")
+        .schema())
+    .schema();
+
+pub const CHANGELOG_MIRROR_LIST_SCHEMA: Schema = StringSchema::new(
+    "Comma separated list of changelog base URL overrides per package origin (e.g. 'Proxmox=http://mirror.example.com/proxmox'). \
+     Used instead of the origin's default host when downloading package changelogs.")
+    .format(&ApiStringFormat::PropertyString(&CHANGELOG_MIRROR_ARRAY_SCHEMA))
+    .schema();
+
+const_regex! {
+    TASK_LOG_RETENTION_REGEX = r"^[^\s=,;]+=\d+$";
+}
+
+pub const TASK_LOG_RETENTION_FORMAT: ApiStringFormat =
+    ApiStringFormat::Pattern(&TASK_LOG_RETENTION_REGEX);
+
+pub const TASK_LOG_RETENTION_ARRAY_SCHEMA: Schema = ArraySchema::new(
+    "Task log retention list.", &StringSchema::new("Maximum number of task logs for a worker type.")
+        .format(&TASK_LOG_RETENTION_FORMAT)
+        .type_text("<worker-type>=<count>")
+        .schema())
+    .schema();
+
+pub const TASK_LOG_RETENTION_LIST_SCHEMA: Schema = StringSchema::new(
+    "Comma separated list of the maximum number of task logs to keep per worker type (e.g. 'default=1000,backup=100'). \
+     The type 'default' applies to all worker types without an explicit entry.")
+    .format(&ApiStringFormat::PropertyString(&TASK_LOG_RETENTION_ARRAY_SCHEMA))
+    .schema();
+
+#[api(
+    properties: {
+        "http2-window-size": {
+            optional: true,
+            schema: HTTP2_WINDOW_SIZE_SCHEMA,
+        },
+        "http2-frame-size": {
+            optional: true,
+            schema: HTTP2_FRAME_SIZE_SCHEMA,
+        },
+        "max-backup-time-skew": {
+            optional: true,
+            schema: MAX_BACKUP_TIME_SKEW_SCHEMA,
+        },
+        "changelog-mirrors": {
+            optional: true,
+            schema: CHANGELOG_MIRROR_LIST_SCHEMA,
+        },
+        "task-log-retention": {
+            optional: true,
+            schema: TASK_LOG_RETENTION_LIST_SCHEMA,
+        },
+        "user-cache-max-age": {
+            optional: true,
+            schema: USER_CACHE_MAX_AGE_SCHEMA,
+        },
+    },
+)]
+#[serde(rename_all="kebab-case")]
+#[derive(Serialize,Deserialize,Default)]
+/// Node specific configuration
+pub struct NodeConfig {
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub http2_window_size: Option<u32>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub http2_frame_size: Option<u32>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub max_backup_time_skew: Option<u32>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub changelog_mirrors: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub task_log_retention: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub user_cache_max_age: Option<u32>,
+}
+
+impl NodeConfig {
+
+    /// Configured HTTP/2 window size, or the default.
+    pub fn http2_window_size(&self) -> u32 {
+        self.http2_window_size.unwrap_or(DEFAULT_HTTP2_WINDOW_SIZE)
+    }
+
+    /// Configured HTTP/2 maximum frame size, or the default.
+    pub fn http2_frame_size(&self) -> u32 {
+        self.http2_frame_size.unwrap_or(DEFAULT_HTTP2_FRAME_SIZE)
+    }
+
+    /// Configured maximum backup-time skew (seconds), or the default.
+    pub fn max_backup_time_skew(&self) -> u32 {
+        self.max_backup_time_skew.unwrap_or(DEFAULT_MAX_BACKUP_TIME_SKEW)
+    }
+
+    /// Configured changelog base URL for packages from `origin`, if any.
+    pub fn changelog_mirror(&self, origin: &str) -> Result<Option<String>, Error> {
+        let list = match &self.changelog_mirrors {
+            Some(list) => parse_property_string(list, &CHANGELOG_MIRROR_ARRAY_SCHEMA)?,
+            None => return Ok(None),
+        };
+
+        let mirror = list.as_array().unwrap().iter()
+            .filter_map(|entry| entry.as_str())
+            .filter_map(|entry| {
+                let mut parts = entry.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(name), Some(url)) if name == origin => Some(url.to_string()),
+                    _ => None,
+                }
+            })
+            .next();
+
+        Ok(mirror)
+    }
+
+    /// Apply the maximum age of the cached user configuration.
+    pub fn setup_user_cache(&self) {
+        crate::config::user::set_cached_config_max_age(self.user_cache_max_age.map(i64::from));
+    }
+
+    /// Apply the HTTP/2 settings to a server connection builder.
+    pub fn setup_http2<E>(&self, http: &mut hyper::server::conn::Http<E>) {
+        let window_size = self.http2_window_size();
+        http.http2_initial_stream_window_size(window_size);
+        http.http2_initial_connection_window_size(window_size);
+        http.http2_max_frame_size(self.http2_frame_size());
+    }
+}
+
+pub const NODE_CFG_FILENAME: &str = configdir!("/node.cfg");
+
+fn parse_config(content: &str) -> Result<NodeConfig, Error> {
+    crate::tools::config::from_str(content, &NodeConfig::API_SCHEMA)
+}
+
+/// Read the node configuration, returns defaults if the file does not exist.
+pub fn config() -> Result<(NodeConfig, [u8;32]), Error> {
+    let content = proxmox::tools::fs::file_read_optional_string(NODE_CFG_FILENAME)?;
+    let content = content.unwrap_or_else(String::new);
+
+    let digest = openssl::sha::sha256(content.as_bytes());
+    let data = parse_config(&content)
+        .map_err(|err| format_err!("unable to parse {} - {}", NODE_CFG_FILENAME, err))?;
+
+    Ok((data, digest))
+}
+
+#[test]
+fn test_node_config_http2() -> Result<(), Error> {
+    let config = parse_config("")?;
+    assert_eq!(config.http2_window_size(), DEFAULT_HTTP2_WINDOW_SIZE);
+    assert_eq!(config.http2_frame_size(), DEFAULT_HTTP2_FRAME_SIZE);
+
+    let config = parse_config("http2-window-size: 268435456\nhttp2-frame-size: 1048576\n")?;
+    assert_eq!(config.http2_window_size(), 256*1024*1024);
+    assert_eq!(config.http2_frame_size(), 1024*1024);
+
+    let max_window_size = (1u32 << 31) - 2;
+    let config = parse_config(&format!("http2-window-size: {}", max_window_size))?;
+    assert_eq!(config.http2_window_size(), max_window_size);
+
+    // oversized values are rejected, not clamped
+    assert!(parse_config(&format!("http2-window-size: {}", max_window_size + 1)).is_err());
+    assert!(parse_config("http2-window-size: 4294967296").is_err());
+    assert!(parse_config("http2-frame-size: 16777216").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_node_config_changelog_mirror() -> Result<(), Error> {
+    use crate::tools::apt::changelog_url_with_mirror;
+
+    let config = parse_config("")?;
+    assert_eq!(config.changelog_mirror("Proxmox")?, None);
+
+    let config = parse_config(
+        "changelog-mirrors: Proxmox=http://mirror.example.com/proxmox,Debian=https://deb.example.com\n")?;
+    let mirror = config.changelog_mirror("Proxmox")?.unwrap();
+    assert_eq!(mirror, "http://mirror.example.com/proxmox");
+    assert_eq!(config.changelog_mirror("Debian")?.unwrap(), "https://deb.example.com");
+    assert_eq!(config.changelog_mirror("Other")?, None);
+
+    // the overridden URL replaces the original host
+    let url = "http://download.proxmox.com/debian/pbs/dists/buster/pbs-no-subscription/binary-amd64/proxmox-backup-server_1.1.5-1.changelog";
+    assert_eq!(
+        changelog_url_with_mirror(url, &mirror)?,
+        "http://mirror.example.com/proxmox/debian/pbs/dists/buster/pbs-no-subscription/binary-amd64/proxmox-backup-server_1.1.5-1.changelog",
+    );
+
+    assert!(parse_config("changelog-mirrors: Proxmox=ftp://mirror.example.com\n").is_err());
+    assert!(parse_config("changelog-mirrors: mirror.example.com\n").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_node_config_task_log_retention() -> Result<(), Error> {
+    use crate::server::TaskLogRetention;
+
+    let config = parse_config("task-log-retention: default=1000,backup=100\n")?;
+    let retention = TaskLogRetention::parse(config.task_log_retention.as_deref().unwrap())?;
+    assert_eq!(retention.max_logs("backup"), Some(100));
+    assert_eq!(retention.max_logs("verify"), Some(1000));
+
+    assert!(parse_config("task-log-retention: backup=-1\n").is_err());
+    assert!(parse_config("task-log-retention: backup\n").is_err());
+
+    Ok(())
+}
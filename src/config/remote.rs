@@ -27,6 +27,17 @@ pub const REMOTE_PASSWORD_SCHEMA: Schema = StringSchema::new("Password or auth t
     .max_length(1024)
     .schema();
 
+pub const REMOTE_TIMEOUT_SCHEMA: Schema = IntegerSchema::new(
+    "HTTP request timeout for the remote (seconds).")
+    .minimum(1)
+    .maximum(3600)
+    .schema();
+
+pub const REMOTE_RATE_LIMIT_SCHEMA: Schema = IntegerSchema::new(
+    "Download rate limit for the remote (bytes/second).")
+    .minimum(1024)
+    .schema();
+
 #[api(
     properties: {
         name: {
@@ -54,6 +65,14 @@ pub const REMOTE_PASSWORD_SCHEMA: Schema = StringSchema::new("Password or auth t
             optional: true,
             schema: CERT_FINGERPRINT_SHA256_SCHEMA,
         },
+        timeout: {
+            optional: true,
+            schema: REMOTE_TIMEOUT_SCHEMA,
+        },
+        "rate-limit": {
+            optional: true,
+            schema: REMOTE_RATE_LIMIT_SCHEMA,
+        },
     }
 )]
 #[derive(Serialize,Deserialize)]
@@ -72,6 +91,10 @@ pub struct Remote {
     pub password: String,
     #[serde(skip_serializing_if="Option::is_none")]
     pub fingerprint: Option<String>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub rate_limit: Option<u64>,
 }
 
 fn init() -> SectionConfig {
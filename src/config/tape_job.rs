@@ -30,6 +30,13 @@ lazy_static! {
     pub static ref CONFIG: SectionConfig = init();
 }
 
+pub const TAPE_VERIFY_SAMPLE_RATE_SCHEMA: Schema = IntegerSchema::new(
+    "Read back and verify this percentage of written chunk archives (0 disables verification).")
+    .minimum(0)
+    .maximum(100)
+    .default(0)
+    .schema();
+
 #[api(
     properties: {
         store: {
@@ -60,6 +67,10 @@ lazy_static! {
             optional: true,
             type: Userid,
         },
+        "verify-sample-rate": {
+            schema: TAPE_VERIFY_SAMPLE_RATE_SCHEMA,
+            optional: true,
+        },
     }
 )]
 #[serde(rename_all="kebab-case")]
@@ -78,6 +89,8 @@ pub struct TapeBackupJobSetup {
     /// Send job email notification to this user
     #[serde(skip_serializing_if="Option::is_none")]
     pub notify_user: Option<Userid>,
+    #[serde(skip_serializing_if="Option::is_none")]
+    pub verify_sample_rate: Option<u64>,
 }
 
 #[api(
@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicI64, Ordering};
 
 use anyhow::{bail, Error};
 use lazy_static::lazy_static;
 use serde::{Serialize, Deserialize};
+use serde_json::Value;
 
 use proxmox::api::{
     api,
@@ -18,6 +20,7 @@ use proxmox::api::{
 use proxmox::tools::{fs::replace_file, fs::CreateOptions};
 
 use crate::api2::types::*;
+use crate::tools::sync::SingleFlight;
 
 lazy_static! {
     pub static ref CONFIG: SectionConfig = init();
@@ -179,46 +182,95 @@ pub fn config() -> Result<(SectionConfigData, [u8;32]), Error> {
     Ok((data, digest))
 }
 
-pub fn cached_config() -> Result<Arc<SectionConfigData>, Error> {
+// Maximum age (seconds) of the cached config, zero means no limit.
+static CACHED_CONFIG_MAX_AGE: AtomicI64 = AtomicI64::new(0);
+
+/// Set the maximum age (in seconds) of the config returned by `cached_config`.
+///
+/// By default (`None`), the cached config is only reloaded when the
+/// file modification time changes. Setting a maximum age also reloads
+/// older entries, which helps on file systems with coarse mtime
+/// granularity. The daemons set this from the `user-cache-max-age`
+/// node config option.
+pub fn set_cached_config_max_age(max_age: Option<i64>) {
+    CACHED_CONFIG_MAX_AGE.store(max_age.unwrap_or(0).max(0), Ordering::Relaxed);
+}
+
+struct ConfigCache {
+    data: Option<Arc<SectionConfigData>>,
+    last_mtime: i64,
+    last_mtime_nsec: i64,
+    last_load: i64,
+}
+
+impl ConfigCache {
+
+    // Test if the cached data is still valid for the given file mtime.
+    fn is_valid(&self, mtime: Option<(i64, i64)>, now: i64, max_age: i64) -> bool {
+        if self.data.is_none() {
+            return false;
+        }
 
-    struct ConfigCache {
-        data: Option<Arc<SectionConfigData>>,
-        last_mtime: i64,
-        last_mtime_nsec: i64,
+        if max_age > 0 && (now - self.last_load) >= max_age {
+            return false;
+        }
+
+        match mtime {
+            Some((mtime, mtime_nsec)) => {
+                mtime == self.last_mtime && mtime_nsec == self.last_mtime_nsec
+            }
+            None => self.last_mtime == 0 && self.last_mtime_nsec == 0,
+        }
     }
+}
+
+// Returns the (mtime, mtime_nsec) of the user config, or None if it does not exist.
+fn config_mtime() -> Result<Option<(i64, i64)>, Error> {
+    match nix::sys::stat::stat(USER_CFG_FILENAME) {
+        Ok(stat) => Ok(Some((stat.st_mtime, stat.st_mtime_nsec))),
+        Err(nix::Error::Sys(nix::errno::Errno::ENOENT)) => Ok(None),
+        Err(err) => bail!("unable to stat '{}' - {}", USER_CFG_FILENAME, err),
+    }
+}
+
+pub fn cached_config() -> Result<Arc<SectionConfigData>, Error> {
 
     lazy_static! {
         static ref CACHED_CONFIG: RwLock<ConfigCache> = RwLock::new(
-            ConfigCache { data: None, last_mtime: 0, last_mtime_nsec: 0 });
+            ConfigCache { data: None, last_mtime: 0, last_mtime_nsec: 0, last_load: 0 });
+        // coalesce concurrent reloads into a single read+parse
+        static ref CONFIG_RELOAD: SingleFlight<(Option<(i64, i64)>, Arc<SectionConfigData>)> =
+            SingleFlight::new();
     }
 
-    let stat = match nix::sys::stat::stat(USER_CFG_FILENAME) {
-        Ok(stat) => Some(stat),
-        Err(nix::Error::Sys(nix::errno::Errno::ENOENT)) => None,
-        Err(err) => bail!("unable to stat '{}' - {}", USER_CFG_FILENAME, err),
-    };
+    let mtime = config_mtime()?;
+    let now = proxmox::tools::time::epoch_i64();
+    let max_age = CACHED_CONFIG_MAX_AGE.load(Ordering::Relaxed);
 
     { // limit scope
         let cache = CACHED_CONFIG.read().unwrap();
-        if let Some(ref config) = cache.data {
-            if let Some(stat) = stat {
-                if stat.st_mtime == cache.last_mtime && stat.st_mtime_nsec == cache.last_mtime_nsec {
-                    return Ok(config.clone());
-                }
-            } else if cache.last_mtime == 0 && cache.last_mtime_nsec == 0 {
+        if cache.is_valid(mtime, now, max_age) {
+            if let Some(ref config) = cache.data {
                 return Ok(config.clone());
             }
         }
     }
 
-    let (config, _digest) = config()?;
-    let config = Arc::new(config);
+    // Stat again inside the shared reload, so that the cache is keyed by the
+    // mtime of the file the leader actually parsed (callers joining a running
+    // reload may have seen a different mtime). If the file changes between
+    // stat and read, the next call simply reloads it again.
+    let (mtime, config) = CONFIG_RELOAD.run(|| {
+        let mtime = config_mtime()?;
+        let (config, _digest) = config()?;
+        Ok((mtime, Arc::new(config)))
+    })?;
 
     let mut cache = CACHED_CONFIG.write().unwrap();
-    if let Some(stat) = stat {
-        cache.last_mtime = stat.st_mtime;
-        cache.last_mtime_nsec = stat.st_mtime_nsec;
-    }
+    let (last_mtime, last_mtime_nsec) = mtime.unwrap_or((0, 0));
+    cache.last_mtime = last_mtime;
+    cache.last_mtime_nsec = last_mtime_nsec;
+    cache.last_load = now;
     cache.data = Some(config.clone());
 
     Ok(config)
@@ -241,6 +293,93 @@ pub fn save_config(config: &SectionConfigData) -> Result<(), Error> {
     Ok(())
 }
 
+/// How to handle users existing in both configs when merging
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConflictPolicy {
+    /// Keep the existing user (and its tokens)
+    Skip,
+    /// Replace the existing user (and its tokens) with the incoming one
+    Overwrite,
+    /// Fail without modifying the base config
+    Error,
+}
+
+/// Merge users from `incoming` into `base`.
+///
+/// Tokens are merged together with their owning user, so skipped
+/// users also keep their existing tokens. Returns the list of merged
+/// user IDs.
+pub fn merge_user_configs(
+    base: &mut SectionConfigData,
+    incoming: &SectionConfigData,
+    on_conflict: ConflictPolicy,
+) -> Result<Vec<String>, Error> {
+
+    let mut user_list: Vec<&String> = incoming.sections.iter()
+        .filter(|(_, (section_type, _))| section_type == "user")
+        .map(|(id, _)| id)
+        .collect();
+    user_list.sort();
+
+    if on_conflict == ConflictPolicy::Error {
+        for id in user_list.iter() {
+            if base.sections.contains_key(*id) {
+                bail!("unable to merge user configs - user '{}' already exists", id);
+            }
+        }
+    }
+
+    let mut merged = Vec::new();
+
+    for id in user_list {
+        if base.sections.contains_key(id) && on_conflict == ConflictPolicy::Skip {
+            continue;
+        }
+
+        if let Some((_, data)) = incoming.sections.get(id) {
+            base.set_data(id, "user", data)?;
+        }
+
+        // remove tokens of the replaced user
+        let old_tokens: Vec<String> = base.sections.iter()
+            .filter(|(token_id, (section_type, _))| {
+                section_type == "token" && token_owner(token_id).as_deref() == Some(id.as_str())
+            })
+            .map(|(token_id, _)| token_id.to_string())
+            .collect();
+        for token_id in old_tokens {
+            base.sections.remove(&token_id);
+        }
+
+        merged.push(id.to_string());
+    }
+
+    let mut token_list: Vec<(&String, &Value)> = incoming.sections.iter()
+        .filter(|(token_id, (section_type, _))| {
+            section_type == "token" && match token_owner(token_id) {
+                Some(owner) => merged.contains(&owner),
+                None => false,
+            }
+        })
+        .map(|(token_id, (_, data))| (token_id, data))
+        .collect();
+    token_list.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (token_id, data) in token_list {
+        base.set_data(token_id, "token", data)?;
+    }
+
+    Ok(merged)
+}
+
+// Returns the user ID part of a token ID
+fn token_owner(token_id: &str) -> Option<String> {
+    match token_id.parse::<Authid>() {
+        Ok(auth_id) if auth_id.is_token() => Some(auth_id.user().as_str().to_string()),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 pub(crate) fn test_cfg_from_str(raw: &str) -> Result<(SectionConfigData, [u8;32]), Error> {
     let cfg = init();
@@ -274,33 +413,167 @@ pub fn complete_authid(_arg: &str, _param: &HashMap<String, String>) -> Vec<Stri
     }
 }
 
+/// List the token names of 'userid' (from the cached config)
+///
+/// Returns an empty list for unknown users.
+pub fn list_tokens_for_user(userid: &Userid) -> Result<Vec<String>, Error> {
+    let data = cached_config()?;
+    Ok(tokens_for_user(&data, userid))
+}
+
+fn tokens_for_user(data: &SectionConfigData, userid: &Userid) -> Vec<String> {
+    if data.lookup::<User>("user", userid.as_str()).is_err() {
+        return Vec::new();
+    }
+
+    data.sections.iter()
+        .filter(|(_, (section_type, _))| section_type == "token")
+        .filter_map(|(id, _)| id.parse::<Authid>().ok())
+        .filter(|tokenid| tokenid.is_token() && tokenid.user() == userid)
+        .map(|tokenid| tokenid.tokenname().unwrap().as_str().to_string())
+        .collect()
+}
+
 // shell completion helper
 pub fn complete_token_name(_arg: &str, param: &HashMap<String, String>) -> Vec<String> {
-    let data = match config() {
-        Ok((data, _digest)) => data,
-        Err(_) => return Vec::new(),
-    };
+    match param.get("userid").map(|userid| userid.parse::<Userid>()) {
+        Some(Ok(userid)) => list_tokens_for_user(&userid).unwrap_or_default(),
+        _ => vec![],
+    }
+}
 
-    match param.get("userid") {
-        Some(userid) => {
-            let user = data.lookup::<User>("user", userid);
-            let tokens = data.convert_to_typed_array("token");
-            match (user, tokens) {
-                (Ok(_), Ok(tokens)) => {
-                    tokens
-                        .into_iter()
-                        .filter_map(|token: ApiToken| {
-                            let tokenid = token.tokenid;
-                            if tokenid.is_token() && tokenid.user() == userid {
-                                Some(tokenid.tokenname().unwrap().as_str().to_string())
-                            } else {
-                                None
-                            }
-                        }).collect()
-                },
-                _ => vec![],
-            }
-        },
-        None => vec![],
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BASE_CFG: &str = "\
+user: root@pam
+	comment Superuser
+
+user: alice@pbs
+	comment base alice
+
+token: alice@pbs!backup
+	comment base token
+";
+
+    const INCOMING_CFG: &str = "\
+user: alice@pbs
+	comment incoming alice
+
+token: alice@pbs!sync
+	comment incoming token
+
+user: bob@pbs
+	comment incoming bob
+
+token: bob@pbs!backup
+	comment bob token
+";
+
+    fn comment(config: &SectionConfigData, id: &str) -> Option<String> {
+        config.sections.get(id)
+            .and_then(|(_, data)| data["comment"].as_str())
+            .map(String::from)
+    }
+
+    #[test]
+    fn test_merge_user_configs_skip() -> Result<(), Error> {
+        let (mut base, _) = test_cfg_from_str(BASE_CFG)?;
+        let (incoming, _) = test_cfg_from_str(INCOMING_CFG)?;
+
+        let merged = merge_user_configs(&mut base, &incoming, ConflictPolicy::Skip)?;
+        assert_eq!(merged, vec!["bob@pbs".to_string()]);
+
+        assert_eq!(comment(&base, "alice@pbs").as_deref(), Some("base alice"));
+        assert!(base.sections.contains_key("alice@pbs!backup"));
+        assert!(!base.sections.contains_key("alice@pbs!sync"));
+        assert_eq!(comment(&base, "bob@pbs").as_deref(), Some("incoming bob"));
+        assert!(base.sections.contains_key("bob@pbs!backup"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_user_configs_overwrite() -> Result<(), Error> {
+        let (mut base, _) = test_cfg_from_str(BASE_CFG)?;
+        let (incoming, _) = test_cfg_from_str(INCOMING_CFG)?;
+
+        let merged = merge_user_configs(&mut base, &incoming, ConflictPolicy::Overwrite)?;
+        assert_eq!(merged, vec!["alice@pbs".to_string(), "bob@pbs".to_string()]);
+
+        assert_eq!(comment(&base, "root@pam").as_deref(), Some("Superuser"));
+        assert_eq!(comment(&base, "alice@pbs").as_deref(), Some("incoming alice"));
+        assert!(!base.sections.contains_key("alice@pbs!backup"));
+        assert!(base.sections.contains_key("alice@pbs!sync"));
+        assert!(base.sections.contains_key("bob@pbs!backup"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_user_configs_error() -> Result<(), Error> {
+        let (mut base, _) = test_cfg_from_str(BASE_CFG)?;
+        let (incoming, _) = test_cfg_from_str(INCOMING_CFG)?;
+
+        assert!(merge_user_configs(&mut base, &incoming, ConflictPolicy::Error).is_err());
+
+        // base config is unchanged
+        assert_eq!(comment(&base, "alice@pbs").as_deref(), Some("base alice"));
+        assert!(!base.sections.contains_key("bob@pbs"));
+
+        // no conflict - no error
+        let (mut base, _) = test_cfg_from_str("user: root@pam\n")?;
+        let merged = merge_user_configs(&mut base, &incoming, ConflictPolicy::Error)?;
+        assert_eq!(merged.len(), 2);
+        assert!(base.sections.contains_key("alice@pbs!sync"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_config_max_age() -> Result<(), Error> {
+        let (data, _digest) = test_cfg_from_str("user: root@pam\n")?;
+
+        let loaded_at = 1_000_000;
+        let mtime = Some((loaded_at, 0));
+
+        let cache = ConfigCache {
+            data: Some(Arc::new(data)),
+            last_mtime: loaded_at,
+            last_mtime_nsec: 0,
+            last_load: loaded_at,
+        };
+
+        // same second modification - mtime does not change
+        let now = loaded_at + 10;
+
+        // default: only reload on mtime change
+        assert!(cache.is_valid(mtime, now, 0));
+
+        // max age forces a reload
+        assert!(!cache.is_valid(mtime, now, 5));
+        assert!(cache.is_valid(mtime, loaded_at + 4, 5));
+
+        // mtime change always reloads
+        assert!(!cache.is_valid(Some((loaded_at + 1, 0)), loaded_at, 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokens_for_user() -> Result<(), Error> {
+        let (data, _) = test_cfg_from_str(INCOMING_CFG)?;
+
+        let mut tokens = tokens_for_user(&data, &"bob@pbs".parse()?);
+        tokens.sort();
+        assert_eq!(tokens, vec!["backup".to_string()]);
+
+        assert_eq!(tokens_for_user(&data, &"alice@pbs".parse()?), vec!["sync".to_string()]);
+
+        // unknown user
+        assert!(tokens_for_user(&data, &"carol@pbs".parse()?).is_empty());
+
+        Ok(())
     }
 }
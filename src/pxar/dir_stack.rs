@@ -99,6 +99,11 @@ impl PxarDirStack {
         self.dirs.is_empty()
     }
 
+    /// Number of directories on the stack, including the root directory
+    pub fn len(&self) -> usize {
+        self.dirs.len()
+    }
+
     pub fn push(&mut self, file_name: OsString, metadata: Metadata) -> Result<(), Error> {
         assert_single_path_component(&file_name)?;
         self.path.push(&file_name);
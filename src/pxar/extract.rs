@@ -12,10 +12,10 @@ use std::pin::Pin;
 use futures::future::Future;
 use anyhow::{bail, format_err, Error};
 use nix::dir::Dir;
-use nix::fcntl::OFlag;
+use nix::fcntl::{AtFlags, OFlag};
 use nix::sys::stat::Mode;
 
-use pathpatterns::{MatchEntry, MatchList, MatchType};
+use pathpatterns::{MatchEntry, MatchList, MatchType, PatternFlag};
 use pxar::accessor::aio::{Accessor, FileContents, FileEntry};
 use pxar::decoder::aio::Decoder;
 use pxar::format::Device;
@@ -37,11 +37,24 @@ pub struct PxarExtractOptions<'a> {
     pub match_list: &'a[MatchEntry],
     pub extract_match_default: bool,
     pub allow_existing_dirs: bool,
+    pub overwrite: bool,
     pub on_error: Option<ErrorHandler>,
+    /// Byte progress for regular files, see `Extractor::set_progress_callback`. Only one of this
+    /// and the path-only callback of `extract_archive` is active: if this is set, the path-only
+    /// callback is not called.
+    pub on_progress: Option<ProgressHandler>,
+    /// Maximum directory depth, see `Extractor::set_max_depth`. Defaults to `DEFAULT_MAX_DEPTH`.
+    pub max_depth: Option<usize>,
+    /// Fail on paths exceeding `PATH_MAX`, see `Extractor::set_check_path_length`.
+    pub check_path_length: bool,
 }
 
 pub type ErrorHandler = Box<dyn FnMut(Error) -> Result<(), Error> + Send>;
 
+/// Called with the path of the current file, the bytes written to it so far and the total
+/// number of bytes restored.
+pub type ProgressHandler = Box<dyn FnMut(&Path, u64, u64) + Send>;
+
 pub fn extract_archive<T, F>(
     mut decoder: pxar::decoder::Decoder<T>,
     destination: &Path,
@@ -86,6 +99,22 @@ where
         feature_flags,
     );
 
+    extractor.set_overwrite(options.overwrite);
+    if let Some(max_depth) = options.max_depth {
+        extractor.set_max_depth(max_depth);
+    }
+    extractor.set_check_path_length(options.check_path_length);
+
+    let progress_active = options.on_progress.is_some();
+    if let Some(on_progress) = options.on_progress {
+        extractor.set_progress_callback(on_progress);
+    }
+    let mut callback = move |path: &Path| {
+        if !progress_active {
+            callback(path);
+        }
+    };
+
     if let Some(on_error) = options.on_error {
         extractor.on_error(on_error);
     }
@@ -214,10 +243,109 @@ where
     Ok(())
 }
 
+/// Extract only `subtree` (a directory or file path inside the archive, e.g. `/var/log`) and
+/// everything below it. Parent directories are created as needed, all other entries are skipped.
+///
+/// This works on a sequential decoder, so the whole archive is read. `options.match_list` must be
+/// empty, the match list is built from `subtree`.
+pub fn extract_subtree<T, F>(
+    decoder: pxar::decoder::Decoder<T>,
+    destination: &Path,
+    subtree: &Path,
+    feature_flags: Flags,
+    callback: F,
+    options: PxarExtractOptions,
+) -> Result<(), Error>
+where
+    T: pxar::decoder::SeqRead,
+    F: FnMut(&Path),
+{
+    if !options.match_list.is_empty() {
+        bail!("cannot combine subtree extraction with a match list");
+    }
+
+    let match_list = [subtree_match_entry(subtree)?];
+
+    let options = PxarExtractOptions {
+        match_list: &match_list,
+        extract_match_default: false,
+        ..options
+    };
+
+    extract_archive(decoder, destination, feature_flags, callback, options)
+}
+
+// Builds an anchored include pattern matching exactly `subtree`, with glob characters escaped.
+// Entries below a matching directory inherit the match.
+fn subtree_match_entry(subtree: &Path) -> Result<MatchEntry, Error> {
+    let subtree = subtree.as_os_str().as_bytes();
+    let subtree = subtree.strip_suffix(&b"/"[..]).unwrap_or(subtree);
+    if subtree.is_empty() {
+        bail!("empty subtree path");
+    }
+
+    let mut pattern = Vec::with_capacity(subtree.len() + 1);
+    if !subtree.starts_with(b"/") {
+        pattern.push(b'/');
+    }
+    for byte in subtree {
+        if b"\\*?[]".contains(byte) {
+            pattern.push(b'\\');
+        }
+        pattern.push(*byte);
+    }
+
+    MatchEntry::parse_pattern(pattern, PatternFlag::PATH_NAME, MatchType::Include)
+        .map_err(|err| format_err!("invalid subtree path {:?}: {}", OsStr::from_bytes(subtree), err))
+}
+
+/// Largest device major number representable in the kernel's `dev_t` (12 bits).
+const MAX_DEVICE_MAJOR: u64 = (1 << 12) - 1;
+/// Largest device minor number representable in the kernel's `dev_t` (20 bits).
+const MAX_DEVICE_MINOR: u64 = (1 << 20) - 1;
+
+/// Make sure the device numbers from an archive fit the kernel's `dev_t` encoding.
+fn check_device_numbers(device: &Device) -> Result<(), Error> {
+    if device.major > MAX_DEVICE_MAJOR {
+        bail!("device major number {} out of range (max {})", device.major, MAX_DEVICE_MAJOR);
+    }
+    if device.minor > MAX_DEVICE_MINOR {
+        bail!("device minor number {} out of range (max {})", device.minor, MAX_DEVICE_MINOR);
+    }
+    Ok(())
+}
+
+/// Reader wrapper calling the progress callback for all data read.
+struct ProgressReader<'a, R: ?Sized> {
+    inner: &'a mut R,
+    path: &'a Path,
+    file_bytes: u64,
+    total_bytes: &'a mut u64,
+    on_progress: &'a mut ProgressHandler,
+}
+
+impl<'a, R: io::Read + ?Sized> io::Read for ProgressReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let got = self.inner.read(buf)?;
+        if got > 0 {
+            self.file_bytes += got as u64;
+            *self.total_bytes += got as u64;
+            (self.on_progress)(self.path, self.file_bytes, *self.total_bytes);
+        }
+        Ok(got)
+    }
+}
+
+/// Default for the maximum directory depth an `Extractor` creates.
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
 /// Common state for file extraction.
 pub(crate) struct Extractor {
     feature_flags: Flags,
     allow_existing_dirs: bool,
+    overwrite: bool,
+    max_depth: usize,
+    check_path_length: bool,
     dir_stack: PxarDirStack,
 
     /// For better error output we need to track the current path in the Extractor state.
@@ -226,6 +354,10 @@ pub(crate) struct Extractor {
     /// Error callback. Includes `current_path` in the reformatted error, should return `Ok` to
     /// continue extracting or the passed error as `Err` to bail out.
     on_error: ErrorHandler,
+
+    /// Optional progress callback for regular file contents.
+    on_progress: Option<ProgressHandler>,
+    bytes_restored: u64,
 }
 
 impl Extractor {
@@ -239,9 +371,14 @@ impl Extractor {
         Self {
             dir_stack: PxarDirStack::new(root_dir, metadata),
             allow_existing_dirs,
+            overwrite: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            check_path_length: true,
             feature_flags,
             current_path: Arc::new(Mutex::new(OsString::new())),
             on_error: Box::new(Err),
+            on_progress: None,
+            bytes_restored: 0,
         }
     }
 
@@ -256,6 +393,46 @@ impl Extractor {
         });
     }
 
+    /// Allow existing regular files to be truncated and overwritten. By default, extracting a
+    /// file fails if anything already exists at its path. Other node types (symlinks, devices,
+    /// ...) are never replaced.
+    pub fn set_overwrite(&mut self, overwrite: bool) {
+        self.overwrite = overwrite;
+    }
+
+    /// Report byte progress while writing regular files (in `extract_file`). The callback gets
+    /// the current path, the bytes written to the current file and the total bytes restored by
+    /// this extractor.
+    pub fn set_progress_callback(&mut self, on_progress: ProgressHandler) {
+        self.on_progress = Some(on_progress);
+    }
+
+    /// Limit the directory depth (relative to the extraction root). Archives with deeper nesting
+    /// fail to extract, which protects against untrusted archives exhausting resources.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Fail early on entries whose path inside the archive reaches `PATH_MAX` (enabled by
+    /// default). Since the extractor works with directory file descriptors, such paths can still
+    /// be created, so this can be disabled to restore them anyway.
+    pub fn set_check_path_length(&mut self, check_path_length: bool) {
+        self.check_path_length = check_path_length;
+    }
+
+    // File names are limited in length by the archive format, but the accumulated path is not.
+    // We only know the path inside the archive, the target directory adds to this.
+    fn check_path_length(&self, file_name: &OsStr) -> Result<(), Error> {
+        if !self.check_path_length {
+            return Ok(());
+        }
+        let len = self.dir_stack.path().as_os_str().len() + 1 + file_name.len();
+        if len >= libc::PATH_MAX as usize {
+            bail!("path length {} exceeds maximum of {}", len, libc::PATH_MAX - 1);
+        }
+        Ok(())
+    }
+
     pub fn set_path(&mut self, path: OsString) {
         *self.current_path.lock().unwrap() = path;
     }
@@ -273,6 +450,12 @@ impl Extractor {
         metadata: Metadata,
         create: bool,
     ) -> Result<(), Error> {
+        // the root directory is on the stack, too
+        if self.dir_stack.len() > self.max_depth {
+            bail!("maximum directory depth of {} exceeded", self.max_depth);
+        }
+        self.check_path_length(&file_name)?;
+
         self.dir_stack.push(file_name, metadata)?;
 
         if create {
@@ -323,6 +506,7 @@ impl Extractor {
         metadata: &Metadata,
         link: &OsStr,
     ) -> Result<(), Error> {
+        self.check_path_length(OsStr::from_bytes(file_name.to_bytes()))?;
         let parent = self.parent_fd()?;
         nix::unistd::symlinkat(link, Some(parent), file_name)?;
         metadata::apply_at(
@@ -337,6 +521,7 @@ impl Extractor {
 
     pub fn extract_hardlink(&mut self, file_name: &CStr, link: &OsStr) -> Result<(), Error> {
         crate::pxar::tools::assert_relative_path(link)?;
+        self.check_path_length(OsStr::from_bytes(file_name.to_bytes()))?;
 
         let parent = self.parent_fd()?;
         let root = self.dir_stack.root_dir_fd()?;
@@ -358,6 +543,7 @@ impl Extractor {
         metadata: &Metadata,
         device: &Device,
     ) -> Result<(), Error> {
+        check_device_numbers(device)?;
         self.extract_special(file_name, metadata, device.to_dev_t())
     }
 
@@ -375,6 +561,7 @@ impl Extractor {
                 mode,
             )
         })?;
+        self.check_path_length(OsStr::from_bytes(file_name.to_bytes()))?;
         let parent = self.parent_fd()?;
         unsafe { c_result!(libc::mknodat(parent, file_name.as_ptr(), mode, device)) }
             .map_err(|err| format_err!("failed to create device node: {}", err))?;
@@ -389,6 +576,33 @@ impl Extractor {
         )
     }
 
+    /// Create (or, with `overwrite`, truncate) the regular file `file_name` in the current
+    /// directory.
+    fn create_file(&mut self, file_name: &CStr) -> Result<std::fs::File, Error> {
+        self.check_path_length(OsStr::from_bytes(file_name.to_bytes()))?;
+        let parent = self.parent_fd()?;
+
+        let mut oflags = OFlag::O_CREAT | OFlag::O_WRONLY | OFlag::O_CLOEXEC;
+        if self.overwrite {
+            match nix::sys::stat::fstatat(parent, file_name, AtFlags::AT_SYMLINK_NOFOLLOW) {
+                Ok(stat) if (stat.st_mode & libc::S_IFMT) != libc::S_IFREG => {
+                    bail!("refusing to overwrite {:?}: not a regular file", file_name);
+                }
+                Ok(_) | Err(nix::Error::Sys(nix::errno::Errno::ENOENT)) => (),
+                Err(err) => bail!("failed to stat file {:?}: {}", file_name, err),
+            }
+            // O_NOFOLLOW: don't get tricked by a symlink created after the check above
+            oflags |= OFlag::O_TRUNC | OFlag::O_NOFOLLOW;
+        } else {
+            oflags |= OFlag::O_EXCL;
+        }
+
+        let fd = nix::fcntl::openat(parent, file_name, oflags, Mode::from_bits(0o600).unwrap())
+            .map_err(|err| format_err!("failed to create file {:?}: {}", file_name, err))?;
+
+        Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+    }
+
     pub fn extract_file(
         &mut self,
         file_name: &CStr,
@@ -396,18 +610,7 @@ impl Extractor {
         size: u64,
         contents: &mut dyn io::Read,
     ) -> Result<(), Error> {
-        let parent = self.parent_fd()?;
-        let mut file = unsafe {
-            std::fs::File::from_raw_fd(
-                nix::fcntl::openat(
-                    parent,
-                    file_name,
-                    OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_WRONLY | OFlag::O_CLOEXEC,
-                    Mode::from_bits(0o600).unwrap(),
-                )
-                .map_err(|err| format_err!("failed to create file {:?}: {}", file_name, err))?,
-            )
-        };
+        let mut file = self.create_file(file_name)?;
 
         metadata::apply_initial_flags(
             self.feature_flags,
@@ -417,8 +620,21 @@ impl Extractor {
         )
         .map_err(|err| format_err!("failed to apply initial flags: {}", err))?;
 
-        let result = sparse_copy(&mut *contents, &mut file)
-            .map_err(|err| format_err!("failed to copy file contents: {}", err))?;
+        let result = match self.on_progress.as_mut() {
+            Some(on_progress) => {
+                let path = PathBuf::from(self.current_path.lock().unwrap().clone());
+                let mut reader = ProgressReader {
+                    inner: &mut *contents,
+                    path: &path,
+                    file_bytes: 0,
+                    total_bytes: &mut self.bytes_restored,
+                    on_progress,
+                };
+                sparse_copy(&mut reader, &mut file)
+            }
+            None => sparse_copy(&mut *contents, &mut file),
+        }
+        .map_err(|err| format_err!("failed to copy file contents: {}", err))?;
 
         if size != result.written {
             bail!(
@@ -452,18 +668,7 @@ impl Extractor {
         size: u64,
         contents: &mut T,
     ) -> Result<(), Error> {
-        let parent = self.parent_fd()?;
-        let mut file = tokio::fs::File::from_std(unsafe {
-            std::fs::File::from_raw_fd(
-                nix::fcntl::openat(
-                    parent,
-                    file_name,
-                    OFlag::O_CREAT | OFlag::O_EXCL | OFlag::O_WRONLY | OFlag::O_CLOEXEC,
-                    Mode::from_bits(0o600).unwrap(),
-                )
-                .map_err(|err| format_err!("failed to create file {:?}: {}", file_name, err))?,
-            )
-        });
+        let mut file = tokio::fs::File::from_std(self.create_file(file_name)?);
 
         metadata::apply_initial_flags(
             self.feature_flags,
@@ -862,3 +1067,267 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Error;
+    use nix::dir::Dir;
+    use nix::fcntl::OFlag;
+    use nix::sys::stat::Mode;
+
+    use pxar::format::Device;
+    use pxar::Metadata;
+
+    use crate::pxar::Flags;
+
+    use super::Extractor;
+
+    #[test]
+    fn test_extract_device_out_of_range() -> Result<(), Error> {
+        let target = nix::unistd::mkdtemp("/tmp/pxar-extract-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let dir = Dir::open(&target, OFlag::O_DIRECTORY | OFlag::O_CLOEXEC, Mode::empty())?;
+            let mut extractor = Extractor::new(
+                dir,
+                Metadata::dir_builder(0o700).build(),
+                false,
+                Flags::DEFAULT,
+            );
+
+            let metadata = Metadata::file_builder(0o600).build();
+            let file_name = std::ffi::CString::new("dev")?;
+
+            let device = Device { major: 1 << 12, minor: 0 };
+            assert!(extractor.extract_device(&file_name, &metadata, &device).is_err());
+
+            let device = Device { major: 0, minor: 1 << 20 };
+            assert!(extractor.extract_device(&file_name, &metadata, &device).is_err());
+
+            assert!(!target.join("dev").exists());
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&target)?;
+
+        result
+    }
+
+    #[test]
+    fn test_extract_subtree() -> Result<(), Error> {
+        let mut data = Vec::new();
+        let root_metadata = Metadata::dir_builder(0o755).build();
+        let dir_metadata = Metadata::dir_builder(0o755).build();
+        let file_metadata = Metadata::file_builder(0o644).build();
+
+        let mut encoder = pxar::encoder::sync::Encoder::from_std(&mut data, &root_metadata)?;
+        for dir_name in &["a", "var", "c1", "c[1]"] {
+            let mut dir = encoder.create_directory(dir_name, &dir_metadata)?;
+            if *dir_name == "var" {
+                let mut log = dir.create_directory("log", &dir_metadata)?;
+                log.add_file(&file_metadata, "syslog", 4, &mut &b"test"[..])?;
+                log.finish()?;
+                dir.add_file(&file_metadata, "other", 4, &mut &b"test"[..])?;
+            }
+            dir.add_file(&file_metadata, "file", 4, &mut &b"test"[..])?;
+            dir.finish()?;
+        }
+        encoder.finish()?;
+
+        let extract = |subtree: &str| -> Result<std::path::PathBuf, Error> {
+            let target = nix::unistd::mkdtemp("/tmp/pxar-extract-test-XXXXXX")?;
+            let options = super::PxarExtractOptions {
+                match_list: &[],
+                extract_match_default: true,
+                allow_existing_dirs: true,
+                overwrite: false,
+                // ownership may not be applicable when not running as root
+                on_error: Some(Box::new(|_| Ok(()))),
+                on_progress: None,
+                max_depth: None,
+                check_path_length: true,
+            };
+            let decoder = pxar::decoder::Decoder::from_std(&data[..])?;
+            let result = super::extract_subtree(
+                decoder,
+                &target,
+                Path::new(subtree),
+                Flags::DEFAULT,
+                |_| (),
+                options,
+            );
+            if let Err(err) = result {
+                std::fs::remove_dir_all(&target)?;
+                return Err(err);
+            }
+            Ok(target)
+        };
+
+        let target = extract("/var/log")?;
+        let check = || -> Result<(), Error> {
+            assert_eq!(std::fs::read(target.join("var/log/syslog"))?, b"test");
+            assert!(!target.join("var/other").exists());
+            assert!(!target.join("var/file").exists());
+            assert!(!target.join("a").exists());
+            Ok(())
+        };
+        let result = check();
+        std::fs::remove_dir_all(&target)?;
+        result?;
+
+        // glob characters in the path are matched literally
+        let target = extract("c[1]/")?;
+        let check = || -> Result<(), Error> {
+            assert!(target.join("c[1]/file").exists());
+            assert!(!target.join("c1").exists());
+            Ok(())
+        };
+        let result = check();
+        std::fs::remove_dir_all(&target)?;
+        result?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_limits() -> Result<(), Error> {
+        let target = nix::unistd::mkdtemp("/tmp/pxar-extract-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let dir = Dir::open(&target, OFlag::O_DIRECTORY | OFlag::O_CLOEXEC, Mode::empty())?;
+            let mut extractor = Extractor::new(
+                dir,
+                Metadata::dir_builder(0o700).build(),
+                false,
+                Flags::DEFAULT,
+            );
+            extractor.set_max_depth(2);
+
+            let metadata = Metadata::dir_builder(0o700).build();
+            extractor.enter_directory("a".into(), metadata.clone(), false)?;
+            extractor.enter_directory("b".into(), metadata.clone(), false)?;
+            let err = extractor.enter_directory("c".into(), metadata.clone(), false).unwrap_err();
+            assert!(err.to_string().contains("maximum directory depth of 2 exceeded"));
+
+            // depth is fine, but the accumulated path gets too long
+            extractor.set_max_depth(super::DEFAULT_MAX_DEPTH);
+            let long_name = "x".repeat(255);
+            let mut result = Ok(());
+            for _ in 0..20 {
+                result = extractor.enter_directory(long_name.clone().into(), metadata.clone(), false);
+                if result.is_err() {
+                    break;
+                }
+            }
+            assert!(result.unwrap_err().to_string().contains("path length"));
+
+            let file_name = std::ffi::CString::new(long_name)?;
+            let file_metadata = Metadata::file_builder(0o600).build();
+            assert!(extractor.extract_file(&file_name, &file_metadata, 0, &mut &b""[..]).is_err());
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&target)?;
+
+        result
+    }
+
+    #[test]
+    fn test_extract_file_progress() -> Result<(), Error> {
+        let target = nix::unistd::mkdtemp("/tmp/pxar-extract-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let dir = Dir::open(&target, OFlag::O_DIRECTORY | OFlag::O_CLOEXEC, Mode::empty())?;
+            let mut extractor = Extractor::new(
+                dir,
+                Metadata::dir_builder(0o700).build(),
+                false,
+                Flags::DEFAULT,
+            );
+            extractor.on_error(Box::new(|_| Ok(())));
+
+            let progress = Arc::new(Mutex::new(Vec::new()));
+            let progress2 = Arc::clone(&progress);
+            extractor.set_progress_callback(Box::new(move |path, file_bytes, total_bytes| {
+                progress2.lock().unwrap().push((path.to_owned(), file_bytes, total_bytes));
+            }));
+
+            let metadata = Metadata::file_builder(0o600).build();
+            let data = vec![1u8; 100_000];
+
+            for name in &["a", "b"] {
+                extractor.set_path(format!("/{}", name).into());
+                let file_name = std::ffi::CString::new(*name)?;
+                extractor.extract_file(&file_name, &metadata, data.len() as u64, &mut &data[..])?;
+            }
+
+            let progress = progress.lock().unwrap();
+            let (path, file_bytes, total_bytes) = progress.last().unwrap();
+            assert_eq!(path, Path::new("/b"));
+            assert_eq!(*file_bytes, 100_000);
+            assert_eq!(*total_bytes, 200_000);
+
+            let last_a = progress.iter().rev().find(|(path, _, _)| path == Path::new("/a")).unwrap();
+            assert_eq!((last_a.1, last_a.2), (100_000, 100_000));
+
+            // counters never go backwards
+            for pair in progress.windows(2) {
+                assert!(pair[1].2 > pair[0].2);
+            }
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&target)?;
+
+        result
+    }
+
+    #[test]
+    fn test_extract_file_overwrite() -> Result<(), Error> {
+        let target = nix::unistd::mkdtemp("/tmp/pxar-extract-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let dir = Dir::open(&target, OFlag::O_DIRECTORY | OFlag::O_CLOEXEC, Mode::empty())?;
+            let mut extractor = Extractor::new(
+                dir,
+                Metadata::dir_builder(0o700).build(),
+                false,
+                Flags::DEFAULT,
+            );
+            // metadata (ownership) may not be applicable when not running as root
+            extractor.on_error(Box::new(|_| Ok(())));
+
+            let metadata = Metadata::file_builder(0o600).build();
+            let file_name = std::ffi::CString::new("file")?;
+            let link_name = std::ffi::CString::new("link")?;
+
+            std::fs::write(target.join("file"), b"old and longer content")?;
+            std::os::unix::fs::symlink(target.join("file"), target.join("link"))?;
+
+            // default: existing files are not touched
+            assert!(extractor.extract_file(&file_name, &metadata, 3, &mut &b"new"[..]).is_err());
+            assert_eq!(std::fs::read(target.join("file"))?, b"old and longer content");
+
+            extractor.set_overwrite(true);
+            extractor.extract_file(&file_name, &metadata, 3, &mut &b"new"[..])?;
+            assert_eq!(std::fs::read(target.join("file"))?, b"new");
+
+            // never write through (or replace) a symlink
+            assert!(extractor.extract_file(&link_name, &metadata, 5, &mut &b"other"[..]).is_err());
+            assert_eq!(std::fs::read(target.join("file"))?, b"new");
+            assert!(std::fs::symlink_metadata(target.join("link"))?.file_type().is_symlink());
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&target)?;
+
+        result
+    }
+}
@@ -60,8 +60,8 @@ pub use flags::Flags;
 
 pub use create::{create_archive, PxarCreateOptions};
 pub use extract::{
-    create_zip, extract_archive, extract_sub_dir, extract_sub_dir_seq, ErrorHandler,
-    PxarExtractOptions,
+    create_zip, extract_archive, extract_sub_dir, extract_sub_dir_seq, extract_subtree, ErrorHandler,
+    ProgressHandler, PxarExtractOptions, DEFAULT_MAX_DEPTH,
 };
 
 /// The format requires to build sorted directory lookup tables in
@@ -69,4 +69,7 @@ pub use extract::{
 /// maximum memory usage.
 pub const ENCODER_MAX_ENTRIES: usize = 1024 * 1024;
 
-pub use tools::{format_multi_line_entry, format_single_line_entry};
+pub use tools::{
+    dump_entries, dump_json, format_json_entry, format_multi_line_entry, format_single_line_entry,
+    list_entries, read_goodbye_table, GoodbyeEntry,
+};
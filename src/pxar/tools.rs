@@ -2,13 +2,16 @@
 
 use std::convert::TryFrom;
 use std::ffi::OsStr;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 
 use anyhow::{bail, format_err, Error};
 use nix::sys::stat::Mode;
+use serde_json::{json, Value};
 
 use pxar::{mode, Entry, EntryKind, Metadata, format::StatxTimestamp};
+use pxar::format::{PXAR_GOODBYE, PXAR_GOODBYE_TAIL_MARKER};
 
 /// Get the file permissions as `nix::Mode`
 pub fn perms_from_metadata(meta: &Metadata) -> Result<Mode, Error> {
@@ -200,3 +203,365 @@ pub fn format_multi_line_entry(entry: &Entry) -> String {
         format_mtime(&meta.stat.mtime),
     )
 }
+
+fn entry_type_name(entry: &Entry) -> &'static str {
+    match entry.kind() {
+        EntryKind::File { .. } => "file",
+        EntryKind::Directory => "directory",
+        EntryKind::Symlink(_) => "symlink",
+        EntryKind::Hardlink(_) => "hardlink",
+        EntryKind::Device(_) => {
+            if entry.metadata().stat.is_chardev() {
+                "chardev"
+            } else {
+                "blockdev"
+            }
+        }
+        EntryKind::Fifo => "fifo",
+        EntryKind::Socket => "socket",
+        EntryKind::GoodbyeTable => "goodbye-table",
+    }
+}
+
+/// Format an entry as JSON object with `path`, `type`, `size`, `mode`, `uid` and `gid`.
+pub fn format_json_entry(entry: &Entry) -> Value {
+    let size = match entry.kind() {
+        EntryKind::File { size, .. } => *size,
+        _ => 0,
+    };
+
+    json!({
+        "path": entry.path().to_string_lossy(),
+        "type": entry_type_name(entry),
+        "size": size,
+        "mode": entry.metadata().stat.mode,
+        "uid": entry.metadata().stat.uid,
+        "gid": entry.metadata().stat.gid,
+    })
+}
+
+/// Dump archive entries to `out` in human readable form.
+///
+/// Prints the path of each entry, or a single line `ls -l` like
+/// description when `verbose` is set.
+pub fn dump_entries<I, W>(entries: I, out: &mut W, verbose: bool) -> Result<(), Error>
+where
+    I: IntoIterator<Item = std::io::Result<Entry>>,
+    W: Write,
+{
+    for entry in entries {
+        let entry = entry?;
+
+        if verbose {
+            writeln!(out, "{}", format_single_line_entry(&entry))?;
+        } else {
+            writeln!(out, "{:?}", entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Dump archive entries to `out`, one JSON object per line.
+pub fn dump_json<I, W>(entries: I, out: &mut W) -> Result<(), Error>
+where
+    I: IntoIterator<Item = std::io::Result<Entry>>,
+    W: Write,
+{
+    for entry in entries {
+        let entry = entry?;
+        if let EntryKind::GoodbyeTable = entry.kind() {
+            continue;
+        }
+        serde_json::to_writer(&mut *out, &format_json_entry(&entry))?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// List all entries of an archive to `output`, one JSON object per line (see
+/// `format_json_entry`).
+///
+/// Unlike `dump_json` this enables goodbye table entries on the decoder and
+/// checks that every directory is closed by exactly one goodbye table, so
+/// truncated or misnested archives are detected while listing. The goodbye
+/// table contents are not validated, use `read_goodbye_table` on seekable
+/// input for that.
+pub fn list_entries<T, W>(decoder: &mut pxar::decoder::Decoder<T>, output: &mut W) -> Result<(), Error>
+where
+    T: pxar::decoder::SeqRead,
+    W: Write,
+{
+    decoder.enable_goodbye_entries(true);
+
+    let mut depth = 0usize;
+    while let Some(entry) = decoder.next() {
+        let entry = entry.map_err(|err| format_err!("error reading pxar archive: {}", err))?;
+
+        match entry.kind() {
+            EntryKind::GoodbyeTable => {
+                depth = depth.checked_sub(1).ok_or_else(|| {
+                    format_err!("unexpected goodbye table at {:?}", entry.path())
+                })?;
+                continue;
+            }
+            EntryKind::Directory => depth += 1,
+            _ => (),
+        }
+
+        serde_json::to_writer(&mut *output, &format_json_entry(&entry))?;
+        writeln!(output)?;
+    }
+
+    if depth != 0 {
+        bail!("unexpected end of archive ({} unterminated directories)", depth);
+    }
+
+    Ok(())
+}
+
+/// Entry of a directory goodbye table (the directory's hash lookup table)
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoodbyeEntry {
+    /// Hash of the file name (see `pxar::format::hash_filename`)
+    pub hash: u64,
+    /// Archive offset of the child entry, pointing to its file name header
+    pub offset: u64,
+    /// Size of the child entry, including the file name header
+    pub size: u64,
+}
+
+const GOODBYE_HEADER_SIZE: u64 = 16;
+const GOODBYE_ITEM_SIZE: u64 = 24;
+
+fn read_u64_triple<R: Read + Seek>(input: &mut R, offset: u64) -> Result<(u64, u64, u64), Error> {
+    let mut buf = [0u8; GOODBYE_ITEM_SIZE as usize];
+    input.seek(SeekFrom::Start(offset))?;
+    input.read_exact(&mut buf)?;
+
+    let value = |n: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buf[n*8..(n+1)*8]);
+        u64::from_le_bytes(bytes)
+    };
+    Ok((value(0), value(1), value(2)))
+}
+
+/// Read the goodbye table of the directory ending at archive offset `dir_end`.
+///
+/// The tail marker is verified but not returned. Entries are returned in
+/// table order, and offsets are converted to absolute archive offsets, so
+/// they can be used to build a lookup table for random access.
+pub fn read_goodbye_table<R: Read + Seek>(input: &mut R, dir_end: u64) -> Result<Vec<GoodbyeEntry>, Error> {
+    if dir_end < GOODBYE_HEADER_SIZE + GOODBYE_ITEM_SIZE {
+        bail!("directory at offset {} too small for goodbye table", dir_end);
+    }
+
+    let (hash, tail_offset, table_size) = read_u64_triple(input, dir_end - GOODBYE_ITEM_SIZE)?;
+    if hash != PXAR_GOODBYE_TAIL_MARKER {
+        bail!("missing goodbye table tail marker at offset {}", dir_end - GOODBYE_ITEM_SIZE);
+    }
+
+    if table_size < GOODBYE_HEADER_SIZE + GOODBYE_ITEM_SIZE
+        || table_size > dir_end
+        || (table_size - GOODBYE_HEADER_SIZE) % GOODBYE_ITEM_SIZE != 0
+    {
+        bail!("invalid goodbye table size {}", table_size);
+    }
+
+    let table_start = dir_end - table_size;
+    if tail_offset > table_start {
+        bail!("goodbye table tail marker points outside of the archive");
+    }
+
+    let mut header = [0u8; GOODBYE_HEADER_SIZE as usize];
+    input.seek(SeekFrom::Start(table_start))?;
+    input.read_exact(&mut header)?;
+    let mut htype = [0u8; 8];
+    htype.copy_from_slice(&header[..8]);
+    let mut full_size = [0u8; 8];
+    full_size.copy_from_slice(&header[8..]);
+
+    if u64::from_le_bytes(htype) != PXAR_GOODBYE || u64::from_le_bytes(full_size) != table_size {
+        bail!("invalid goodbye table header at offset {}", table_start);
+    }
+
+    let count = (table_size - GOODBYE_HEADER_SIZE) / GOODBYE_ITEM_SIZE - 1;
+    let mut entries = Vec::with_capacity(count as usize);
+
+    for i in 0..count {
+        let item_offset = table_start + GOODBYE_HEADER_SIZE + i * GOODBYE_ITEM_SIZE;
+        let (hash, offset, size) = read_u64_triple(input, item_offset)?;
+        if offset > table_start {
+            bail!("goodbye table entry {} points outside of the archive", i);
+        }
+        entries.push(GoodbyeEntry { hash, offset: table_start - offset, size });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Error;
+    use serde_json::Value;
+
+    use pxar::Metadata;
+
+    use super::{dump_entries, dump_json, list_entries, read_goodbye_table};
+
+    // Create a small archive with a file inside a sub directory
+    fn create_test_archive() -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+
+        let root_metadata = Metadata::dir_builder(0o755).build();
+        let mut encoder = pxar::encoder::sync::Encoder::from_std(&mut data, &root_metadata)?;
+
+        let dir_metadata = Metadata::dir_builder(0o750).build();
+        let mut dir = encoder.create_directory("subdir", &dir_metadata)?;
+
+        let content = b"pxar dump test";
+        let file_metadata = Metadata::file_builder(0o644).build();
+        dir.add_file(&file_metadata, "file.txt", content.len() as u64, &mut &content[..])?;
+        dir.finish()?;
+
+        encoder.finish()?;
+
+        Ok(data)
+    }
+
+    #[test]
+    fn test_dump_entries() -> Result<(), Error> {
+        let data = create_test_archive()?;
+
+        for verbose in [false, true].iter() {
+            let decoder = pxar::decoder::Decoder::from_std(&data[..])?;
+
+            let mut output = Vec::new();
+            dump_entries(decoder, &mut output, *verbose)?;
+
+            assert!(!output.is_empty());
+
+            let output = String::from_utf8(output)?;
+            assert_eq!(output.lines().count(), 3);
+            assert!(output.contains("/subdir/file.txt"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_json() -> Result<(), Error> {
+        let data = create_test_archive()?;
+
+        let decoder = pxar::decoder::Decoder::from_std(&data[..])?;
+
+        let mut output = Vec::new();
+        dump_json(decoder, &mut output)?;
+
+        let output = String::from_utf8(output)?;
+        let lines: Vec<Value> = output
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+
+        assert_eq!(lines.len(), 3);
+
+        for line in lines.iter() {
+            assert!(line["path"].is_string());
+            assert!(line["type"].is_string());
+            assert!(line["size"].is_u64());
+            assert!(line["mode"].is_u64());
+        }
+
+        assert_eq!(lines[0]["type"], "directory");
+        assert_eq!(lines[1]["path"], "/subdir");
+        assert_eq!(lines[1]["type"], "directory");
+        assert_eq!(lines[2]["path"], "/subdir/file.txt");
+        assert_eq!(lines[2]["type"], "file");
+        assert_eq!(lines[2]["size"], 14);
+        assert_eq!(lines[2]["mode"].as_u64().unwrap() & 0o777, 0o644);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_entries() -> Result<(), Error> {
+        let data = create_test_archive()?;
+
+        let mut decoder = pxar::decoder::Decoder::from_std(&data[..])?;
+
+        let mut output = Vec::new();
+        list_entries(&mut decoder, &mut output)?;
+
+        let output = String::from_utf8(output)?;
+        let lines: Vec<Value> = output
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+
+        // goodbye tables are checked, but not listed
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2]["path"], "/subdir/file.txt");
+        assert_eq!(lines[2]["size"], 14);
+        assert_eq!(lines[2]["uid"], 0);
+        assert_eq!(lines[2]["gid"], 0);
+
+        // a truncated archive must not list successfully
+        let truncated = &data[..data.len() - 16];
+        let mut decoder = pxar::decoder::Decoder::from_std(truncated)?;
+        assert!(list_entries(&mut decoder, &mut Vec::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_goodbye_table() -> Result<(), Error> {
+        use std::convert::TryInto;
+        use std::io::Cursor;
+        use pxar::format::{hash_filename, PXAR_FILENAME};
+
+        let mut data = Vec::new();
+        let root_metadata = Metadata::dir_builder(0o755).build();
+        let mut encoder = pxar::encoder::sync::Encoder::from_std(&mut data, &root_metadata)?;
+
+        let file_metadata = Metadata::file_builder(0o644).build();
+        for name in ["a", "bb", "ccc"].iter() {
+            encoder.add_file(&file_metadata, name, 4, &mut &b"test"[..])?;
+        }
+        let mut dir = encoder.create_directory("subdir", &Metadata::dir_builder(0o750).build())?;
+        dir.add_file(&file_metadata, "file.txt", 4, &mut &b"test"[..])?;
+        dir.finish()?;
+        encoder.finish()?;
+
+        let mut input = Cursor::new(&data[..]);
+
+        // the root directory ends with the archive
+        let entries = read_goodbye_table(&mut input, data.len() as u64)?;
+        assert_eq!(entries.len(), 4);
+
+        let mut names = Vec::new();
+        for entry in entries.iter() {
+            // entries point to the file name header of the child
+            let header = &data[entry.offset as usize..];
+            assert_eq!(u64::from_le_bytes(header[..8].try_into()?), PXAR_FILENAME);
+            let name_len = u64::from_le_bytes(header[8..16].try_into()?) as usize - 16;
+            let name = &header[16..16 + name_len - 1]; // strip trailing zero byte
+            assert_eq!(entry.hash, hash_filename(name));
+            names.push(String::from_utf8(name.to_vec())?);
+
+            if name == b"subdir" {
+                let sub_entries = read_goodbye_table(&mut input, entry.offset + entry.size)?;
+                assert_eq!(sub_entries.len(), 1);
+                assert_eq!(sub_entries[0].hash, hash_filename(b"file.txt"));
+            }
+        }
+        names.sort();
+        assert_eq!(names, vec!["a", "bb", "ccc", "subdir"]);
+
+        // no tail marker at this position
+        assert!(read_goodbye_table(&mut input, data.len() as u64 - 1).is_err());
+        assert!(read_goodbye_table(&mut input, 10).is_err());
+
+        Ok(())
+    }
+}
@@ -31,10 +31,9 @@ pub fn do_verification_job(
             return true;
         }
 
-        let raw_verify_state = manifest.unprotected["verify_state"].clone();
-        match serde_json::from_value::<SnapshotVerifyState>(raw_verify_state) {
-            Err(_) => true, // no last verification, always include
-            Ok(last_verify) => {
+        match manifest.verify_state() {
+            None => true, // no last verification, always include
+            Some(last_verify) => {
                 match outdated_after {
                     None => false, // never re-verify if ignored and no max age
                     Some(max_age) => {
@@ -21,6 +21,7 @@ use super::UPID;
 use crate::buildcfg;
 use crate::server;
 use crate::tools::logrotate::{LogRotate, LogRotateFiles};
+use crate::tools::sync::PauseSignal;
 use crate::tools::{FileLogger, FileLogOptions};
 use crate::api2::types::{Authid, TaskStateType};
 
@@ -105,6 +106,22 @@ pub fn register_task_control_commands(
         }
         Ok(Value::Null)
     })?;
+    commando_sock.register_command("worker-task-pause".into(), move |args| {
+        let upid = get_upid(args)?;
+
+        if let Some(ref worker) = WORKER_TASK_LIST.lock().unwrap().get(&upid.task_id) {
+            worker.request_pause();
+        }
+        Ok(Value::Null)
+    })?;
+    commando_sock.register_command("worker-task-resume".into(), move |args| {
+        let upid = get_upid(args)?;
+
+        if let Some(ref worker) = WORKER_TASK_LIST.lock().unwrap().get(&upid.task_id) {
+            worker.request_resume();
+        }
+        Ok(Value::Null)
+    })?;
     commando_sock.register_command("worker-task-status".into(), move |args| {
         let upid = get_upid(args)?;
 
@@ -136,6 +153,31 @@ pub async fn abort_worker(upid: UPID) -> Result<(), Error> {
     super::send_command(sock, cmd).map_ok(|_| ()).await
 }
 
+/// Ask a worker to pause, see `WorkerTask::wait_resumed`.
+pub async fn pause_worker(upid: UPID) -> Result<(), Error> {
+
+    let sock = server::ctrl_sock_from_pid(upid.pid);
+    let cmd = json!({
+        "command": "worker-task-pause",
+        "args": {
+            "upid": upid.to_string(),
+        },
+    });
+    super::send_command(sock, cmd).map_ok(|_| ()).await
+}
+
+pub async fn resume_worker(upid: UPID) -> Result<(), Error> {
+
+    let sock = server::ctrl_sock_from_pid(upid.pid);
+    let cmd = json!({
+        "command": "worker-task-resume",
+        "args": {
+            "upid": upid.to_string(),
+        },
+    });
+    super::send_command(sock, cmd).map_ok(|_| ()).await
+}
+
 fn parse_worker_status_line(line: &str) -> Result<(String, UPID, Option<TaskState>), Error> {
 
     let data = line.splitn(3, ' ').collect::<Vec<&str>>();
@@ -329,7 +371,166 @@ pub fn rotate_task_log_archive(size_threshold: u64, compress: bool, max_files: O
     let mut logrotate = LogRotate::new(PROXMOX_BACKUP_ARCHIVE_TASK_FN, compress)
         .ok_or_else(|| format_err!("could not get archive file names"))?;
 
-    logrotate.rotate(size_threshold, None, max_files)
+    let backup_user = crate::backup::backup_user()?;
+    let options = CreateOptions::new().owner(backup_user.uid).group(backup_user.gid);
+
+    logrotate.rotate_if_bigger(size_threshold, options, max_files)
+}
+
+// worker types writing a backup snapshot, see `upgrade_to_backup_protocol`
+const BACKUP_WORKER_TYPES: [&str; 2] = ["backup", "benchmark"];
+
+/// Returns the datastore of a backup worker, parsed from the worker id (`<store>:<type>/<id>`)
+fn backup_worker_store(upid: &UPID) -> Option<&str> {
+    if !BACKUP_WORKER_TYPES.contains(&upid.worker_type.as_str()) {
+        return None;
+    }
+    let worker_id = upid.worker_id.as_deref()?;
+    let mut parts = worker_id.splitn(2, ':');
+    let store = parts.next()?;
+    let group = parts.next()?;
+    if store.is_empty() || !group.contains('/') {
+        return None;
+    }
+    Some(store)
+}
+
+/// Filter the backup workers writing to datastore `store` out of `upids`
+///
+/// Tasks with malformed worker ids are ignored.
+pub fn filter_backup_workers<I: IntoIterator<Item = UPID>>(upids: I, store: &str) -> Vec<UPID> {
+    upids
+        .into_iter()
+        .filter(|upid| backup_worker_store(upid) == Some(store))
+        .collect()
+}
+
+/// Returns the UPIDs of all currently running backup workers of datastore `store`
+pub fn active_backup_workers(store: &str) -> Result<Vec<UPID>, Error> {
+    let mut active = Vec::new();
+    for info in TaskListInfoIterator::new(true)? {
+        let info = info?;
+        if info.state.is_none() && worker_is_active_local(&info.upid) {
+            active.push(info.upid);
+        }
+    }
+    Ok(filter_backup_workers(active, store))
+}
+
+/// Maximum number of task logs to keep per worker type
+///
+/// Configured by the `task-log-retention` node config option, a list of
+/// `<worker-type>=<count>` entries. The special type `default` applies to
+/// all worker types without an explicit entry.
+#[derive(Debug, Default, PartialEq)]
+pub struct TaskLogRetention {
+    pub default: Option<usize>,
+    pub per_type: HashMap<String, usize>,
+}
+
+impl TaskLogRetention {
+    /// Parse a comma separated list of `<worker-type>=<count>` entries
+    pub fn parse(list: &str) -> Result<Self, Error> {
+        let mut retention = Self::default();
+
+        for entry in list.split(',') {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            let (worker_type, count) = match entry.find('=') {
+                Some(pos) => (&entry[..pos], &entry[(pos + 1)..]),
+                None => bail!("expected '<worker-type>=<count>', got '{}'", entry),
+            };
+            if worker_type.is_empty() {
+                bail!("missing worker type in '{}'", entry);
+            }
+            let count: usize = count
+                .parse()
+                .map_err(|err| format_err!("invalid count in '{}' - {}", entry, err))?;
+
+            if worker_type == "default" {
+                retention.default = Some(count);
+            } else {
+                retention.per_type.insert(worker_type.to_string(), count);
+            }
+        }
+
+        Ok(retention)
+    }
+
+    /// Load the retention settings from the node config, no setting means no limits
+    pub fn load() -> Result<Self, Error> {
+        let (node_config, _digest) = crate::config::node::config()?;
+        match node_config.task_log_retention {
+            Some(list) => Self::parse(&list)
+                .map_err(|err| format_err!("unable to parse task-log-retention - {}", err)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Returns the maximum number of logs to keep for `worker_type`, if limited
+    pub fn max_logs(&self, worker_type: &str) -> Option<usize> {
+        self.per_type.get(worker_type).copied().or(self.default)
+    }
+}
+
+/// Remove the oldest task logs exceeding the configured limit of their worker type
+///
+/// Logs of tasks for which `is_running` returns true are never removed. Returns the number of
+/// removed log files.
+pub fn cleanup_task_logs<F>(
+    task_dir: &std::path::Path,
+    retention: &TaskLogRetention,
+    is_running: F,
+) -> Result<usize, Error>
+where
+    F: Fn(&UPID) -> bool,
+{
+    let mut logs: HashMap<String, Vec<(UPID, std::path::PathBuf)>> = HashMap::new();
+
+    for entry in std::fs::read_dir(task_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        for file in std::fs::read_dir(entry.path())? {
+            let file = file?;
+            let upid: UPID = match file.file_name().to_str().map(str::parse) {
+                Some(Ok(upid)) => upid,
+                _ => continue, // not a task log
+            };
+            logs.entry(upid.worker_type.clone()).or_default().push((upid, file.path()));
+        }
+    }
+
+    let mut removed = 0;
+
+    for (worker_type, mut list) in logs {
+        let max_logs = match retention.max_logs(&worker_type) {
+            Some(max_logs) => max_logs,
+            None => continue,
+        };
+
+        // newest first
+        list.sort_unstable_by(|a, b| {
+            (b.0.starttime, b.0.task_id).cmp(&(a.0.starttime, a.0.task_id))
+        });
+
+        for (upid, path) in list.into_iter().skip(max_logs) {
+            if is_running(&upid) {
+                continue;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+                Err(err) => bail!("unable to remove task log {:?} - {}", path, err),
+            }
+        }
+    }
+
+    Ok(removed)
 }
 
 // atomically read/update the task list, update status of finished tasks
@@ -566,6 +767,7 @@ pub struct WorkerTask {
     upid: UPID,
     data: Mutex<WorkerTaskData>,
     abort_requested: AtomicBool,
+    pause: PauseSignal,
 }
 
 impl std::fmt::Display for WorkerTask {
@@ -612,6 +814,7 @@ impl WorkerTask {
         let worker = Arc::new(Self {
             upid: upid.clone(),
             abort_requested: AtomicBool::new(false),
+            pause: PauseSignal::new(),
             data: Mutex::new(WorkerTaskData {
                 logger,
                 progress: 0.0,
@@ -783,11 +986,50 @@ impl WorkerTask {
         rx
     }
 
+    /// Request pause
+    ///
+    /// Only has an effect on tasks which check `wait_resumed` regularly.
+    pub fn request_pause(&self) {
+        if self.pause.pause() {
+            self.log("received pause request ...");
+        }
+    }
+
+    /// Request resume of a paused task
+    pub fn request_resume(&self) {
+        if self.pause.resume() {
+            self.log("received resume request ...");
+        }
+    }
+
+    /// Test if pause was requested.
+    pub fn pause_requested(&self) -> bool {
+        self.pause.is_paused()
+    }
+
+    /// Wait while the task is paused, fails and aborts the task if paused longer than `timeout`.
+    pub async fn wait_resumed(&self, timeout: std::time::Duration) -> Result<(), Error> {
+        wait_resumed_or_abort(&self.pause, timeout, || self.request_abort()).await
+    }
+
     pub fn upid(&self) -> &UPID {
         &self.upid
     }
 }
 
+// wait until 'pause' is resumed, calls 'abort' if still paused after 'timeout'
+async fn wait_resumed_or_abort<F: FnOnce()>(
+    pause: &PauseSignal,
+    timeout: std::time::Duration,
+    abort: F,
+) -> Result<(), Error> {
+    if let Err(err) = pause.wait_resumed(timeout).await {
+        abort();
+        bail!("{} - aborting task", err);
+    }
+    Ok(())
+}
+
 impl crate::task::TaskState for WorkerTask {
     fn check_abort(&self) -> Result<(), Error> {
         self.fail_on_abort()
@@ -803,3 +1045,159 @@ impl crate::task::TaskState for WorkerTask {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_upid(worker_type: &str, task_id: usize, starttime: i64) -> UPID {
+        format!(
+            "UPID:testnode:00001234:00005678:{:08X}:{:08X}:{}::root@pam:",
+            task_id, starttime, worker_type,
+        ).parse().unwrap()
+    }
+
+    #[test]
+    fn test_filter_backup_workers() {
+        let worker = |worker_type: &str, task_id: usize, worker_id: Option<&str>| {
+            let mut upid = test_upid(worker_type, task_id, 1_600_000_000);
+            upid.worker_id = worker_id.map(String::from);
+            upid
+        };
+
+        let upids = vec![
+            worker("backup", 1, Some("store1:vm/100")),
+            worker("backup", 2, Some("store2:vm/100")),
+            worker("backup", 3, Some("store1:host/my:host")),
+            worker("benchmark", 4, Some("store1:host/benchmark")),
+            worker("reader", 5, Some("store1:vm/100")), // not a backup
+            worker("verify", 6, Some("store1")),
+            worker("backup", 7, None), // malformed worker ids
+            worker("backup", 8, Some("store1")),
+            worker("backup", 9, Some(":vm/100")),
+            worker("backup", 10, Some("store1-vm/100")),
+        ];
+
+        let task_ids = |store| -> Vec<usize> {
+            filter_backup_workers(upids.clone(), store).iter().map(|upid| upid.task_id).collect()
+        };
+
+        assert_eq!(task_ids("store1"), vec![1, 3, 4]);
+        assert_eq!(task_ids("store2"), vec![2]);
+        assert!(task_ids("store3").is_empty());
+    }
+
+    #[test]
+    fn test_pause_timeout_aborts() {
+        use std::sync::atomic::AtomicUsize;
+        use std::time::Duration;
+
+        let pause = Arc::new(PauseSignal::new());
+        let aborted = Arc::new(AtomicBool::new(false));
+        let processed = Arc::new(AtomicUsize::new(0));
+
+        // mock chunk upload loop, like upload_fixed_chunk/upload_dynamic_chunk
+        let receive_chunks = |timeout: Duration| {
+            let pause = Arc::clone(&pause);
+            let aborted = Arc::clone(&aborted);
+            let processed = Arc::clone(&processed);
+            tokio::spawn(async move {
+                for _ in 0..10 {
+                    wait_resumed_or_abort(&pause, timeout, || aborted.store(true, Ordering::SeqCst)).await?;
+                    processed.fetch_add(1, Ordering::SeqCst);
+                }
+                Ok::<(), Error>(())
+            })
+        };
+
+        crate::tools::runtime::main(async {
+            // paused backups do not accept chunks, and get aborted after the timeout
+            pause.pause();
+            let result = receive_chunks(Duration::from_millis(100)).await.unwrap();
+            let err = result.unwrap_err().to_string();
+            assert!(err.ends_with("aborting task"), "unexpected error: {}", err);
+            assert_eq!(processed.load(Ordering::SeqCst), 0);
+            assert!(aborted.load(Ordering::SeqCst));
+
+            // resumed in time - all chunks are processed
+            aborted.store(false, Ordering::SeqCst);
+            let receiver = receive_chunks(Duration::from_secs(10));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert_eq!(processed.load(Ordering::SeqCst), 0);
+            pause.resume();
+            receiver.await.unwrap().unwrap();
+            assert_eq!(processed.load(Ordering::SeqCst), 10);
+            assert!(!aborted.load(Ordering::SeqCst));
+        });
+    }
+
+    #[test]
+    fn test_task_log_retention() -> Result<(), Error> {
+        let retention = TaskLogRetention::parse("default=10,backup=2")?;
+        assert_eq!(retention.max_logs("backup"), Some(2));
+        assert_eq!(retention.max_logs("verify"), Some(10));
+        assert_eq!(TaskLogRetention::parse("")?.max_logs("backup"), None);
+
+        assert!(TaskLogRetention::parse("backup 2").is_err());
+        assert!(TaskLogRetention::parse("backup=-2").is_err());
+        assert!(TaskLogRetention::parse("=2").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cleanup_task_logs() -> Result<(), Error> {
+        let tmp = nix::unistd::mkdtemp("/tmp/pbs-task-log-cleanup-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let mut upids = Vec::new();
+            for i in 0..5 {
+                upids.push(test_upid("backup", i, 1_600_000_000 + i as i64));
+                upids.push(test_upid("verify", 100 + i, 1_600_000_000 + i as i64));
+            }
+            upids.push(test_upid("garbage_collection", 200, 1_600_000_000));
+
+            for (i, upid) in upids.iter().enumerate() {
+                // spread over multiple subdirectories, like real pstart values do
+                let dir = tmp.join(format!("{:02X}", i % 3));
+                std::fs::create_dir_all(&dir)?;
+                std::fs::write(dir.join(upid.to_string()), b"")?;
+            }
+            std::fs::write(tmp.join("00").join("not-a-upid"), b"")?;
+
+            let retention = TaskLogRetention::parse("backup=2,verify=3")?;
+            // the oldest verify task is still running
+            let running = test_upid("verify", 100, 1_600_000_000).to_string();
+
+            let removed = cleanup_task_logs(&tmp, &retention, |upid| upid.to_string() == running)?;
+            assert_eq!(removed, 3 + 1);
+
+            let mut remaining = Vec::new();
+            for entry in std::fs::read_dir(&tmp)? {
+                for file in std::fs::read_dir(entry?.path())? {
+                    remaining.push(file?.file_name().into_string().unwrap());
+                }
+            }
+            remaining.sort();
+
+            let mut expected: Vec<String> = vec![
+                test_upid("backup", 3, 1_600_000_003),
+                test_upid("backup", 4, 1_600_000_004),
+                test_upid("verify", 100, 1_600_000_000),
+                test_upid("verify", 102, 1_600_000_002),
+                test_upid("verify", 103, 1_600_000_003),
+                test_upid("verify", 104, 1_600_000_004),
+                test_upid("garbage_collection", 200, 1_600_000_000),
+            ].iter().map(|upid| upid.to_string()).collect();
+            expected.push("not-a-upid".to_string());
+            expected.sort();
+
+            assert_eq!(remaining, expected);
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&tmp)?;
+        result
+    }
+}
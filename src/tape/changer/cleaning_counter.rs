@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde_json::json;
+
+use proxmox::tools::fs::{
+    open_file_locked,
+    replace_file,
+    fchown,
+    file_get_json,
+    CreateOptions,
+};
+
+use crate::tape::changer::{
+    ElementStatus,
+    MtxStatus,
+};
+
+/// Number of cleaning cycles a cleaning cartridge usually supports
+pub const CLEANING_CARTRIDGE_MAX_CYCLES: u64 = 20;
+
+/// Warn if a cleaning cartridge has less cycles left
+pub const CLEANING_CARTRIDGE_WARN_REMAINING: u64 = 2;
+
+/// Persistent cleaning cartridge usage counters
+///
+/// Counts the number of cleaning cycles for each cleaning cartridge
+/// (by label text/barcode), because changers do not track that and
+/// a worn out cartridge silently does nothing.
+///
+/// Data is stored in `<base_path>/cleaning-counters.json`.
+pub struct CleaningCounters {
+    map: BTreeMap<String, u64>,
+    counter_path: PathBuf,
+    lockfile_path: PathBuf,
+}
+
+impl CleaningCounters {
+
+    pub const CLEANING_COUNTERS_FILENAME: &'static str = "cleaning-counters.json";
+    pub const CLEANING_COUNTERS_LOCKFILE: &'static str = ".cleaning-counters.lck";
+
+    /// Load counters from `base_path`
+    pub fn load(base_path: &Path) -> Result<Self, Error> {
+
+        let mut counter_path = base_path.to_owned();
+        counter_path.push(Self::CLEANING_COUNTERS_FILENAME);
+
+        let mut lockfile_path = base_path.to_owned();
+        lockfile_path.push(Self::CLEANING_COUNTERS_LOCKFILE);
+
+        let data = file_get_json(&counter_path, Some(json!({})))?;
+        let map = serde_json::from_value(data)?;
+
+        Ok(Self { map, counter_path, lockfile_path })
+    }
+
+    fn lock(&self) -> Result<std::fs::File, Error> {
+        let file = open_file_locked(&self.lockfile_path, std::time::Duration::new(10, 0), true)?;
+        if cfg!(test) {
+            // We cannot use chown inside test environment (no permissions)
+            return Ok(file);
+        }
+
+        let backup_user = crate::backup::backup_user()?;
+        fchown(file.as_raw_fd(), Some(backup_user.uid), Some(backup_user.gid))?;
+
+        Ok(file)
+    }
+
+    fn replace_file(&self) -> Result<(), Error> {
+        let raw = serde_json::to_string_pretty(&self.map)?;
+
+        let mode = nix::sys::stat::Mode::from_bits_truncate(0o0640);
+
+        let options = if cfg!(test) {
+            // We cannot use chown inside test environment (no permissions)
+            CreateOptions::new().perm(mode)
+        } else {
+            let backup_user = crate::backup::backup_user()?;
+            CreateOptions::new()
+                .perm(mode)
+                .owner(backup_user.uid)
+                .group(backup_user.gid)
+        };
+
+        replace_file(&self.counter_path, raw.as_bytes(), options)?;
+
+        Ok(())
+    }
+
+    /// Returns the number of cleaning cycles done with `label_text`
+    pub fn cleaning_count(&self, label_text: &str) -> u64 {
+        self.map.get(label_text).copied().unwrap_or(0)
+    }
+
+    /// Count a cleaning cycle, returns the new count
+    pub fn increment(&mut self, label_text: &str) -> Result<u64, Error> {
+        let _lock = self.lock()?;
+
+        // reload to avoid losing concurrent updates
+        let data = file_get_json(&self.counter_path, Some(json!({})))?;
+        self.map = serde_json::from_value(data)?;
+
+        let count = self.map.entry(label_text.to_string()).or_insert(0);
+        *count += 1;
+        let count = *count;
+
+        self.replace_file()?;
+
+        Ok(count)
+    }
+
+    /// Returns a warning if the cartridge is worn out or nearly so
+    pub fn check_cartridge(&self, label_text: &str) -> Option<String> {
+        let count = self.cleaning_count(label_text);
+        if count >= CLEANING_CARTRIDGE_MAX_CYCLES {
+            Some(format!(
+                "cleaning cartridge '{}' used {} times - probably worn out (max {} cycles)",
+                label_text, count, CLEANING_CARTRIDGE_MAX_CYCLES,
+            ))
+        } else if count + CLEANING_CARTRIDGE_WARN_REMAINING >= CLEANING_CARTRIDGE_MAX_CYCLES {
+            Some(format!(
+                "cleaning cartridge '{}' used {} times - nearly worn out (max {} cycles)",
+                label_text, count, CLEANING_CARTRIDGE_MAX_CYCLES,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Check all cleaning cartridges inside the changer
+    pub fn check_changer_status(&self, status: &MtxStatus) -> Vec<String> {
+        let drives = status.drives.iter().map(|d| &d.status);
+        let slots = status.slots.iter().map(|s| &s.status);
+
+        drives.chain(slots)
+            .filter_map(|status| match status {
+                ElementStatus::VolumeTag(tag) if tag.starts_with("CLN") => Some(tag),
+                _ => None,
+            })
+            .filter_map(|tag| self.check_cartridge(tag))
+            .collect()
+    }
+}
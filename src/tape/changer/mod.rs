@@ -7,6 +7,9 @@ pub mod mtx;
 mod online_status_map;
 pub use online_status_map::*;
 
+mod cleaning_counter;
+pub use cleaning_counter::*;
+
 use std::collections::HashSet;
 use std::path::PathBuf;
 
@@ -25,6 +28,7 @@ use proxmox::{
 
 use crate::api2::types::{
     SLOT_ARRAY_SCHEMA,
+    ImportExportSlot,
     ScsiTapeChanger,
     LtoTapeDrive,
 };
@@ -92,6 +96,20 @@ pub struct MtxStatus {
 
 impl MtxStatus {
 
+    /// Returns slot number and label text of the first cleaning
+    /// cartridge (label starting with `CLN`) in a storage slot.
+    pub fn cleaning_cartridge(&self) -> Option<(u64, String)> {
+        for (i, slot_info) in self.slots.iter().enumerate() {
+            if slot_info.import_export { continue; }
+            if let ElementStatus::VolumeTag(ref tag) = slot_info.status {
+                if tag.starts_with("CLN") {
+                    return Some((i as u64 + 1, tag.clone()));
+                }
+            }
+        }
+        None
+    }
+
     pub fn slot_address(&self, slot: u64) -> Result<u16, Error> {
         if slot == 0 {
             bail!("invalid slot number '{}' (slots numbers starts at 1)", slot);
@@ -136,6 +154,28 @@ impl MtxStatus {
         free_slot
     }
 
+    /// List all import/export slots with their content.
+    pub fn import_export_slots(&self) -> Vec<ImportExportSlot> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot_info)| slot_info.import_export)
+            .map(|(i, slot_info)| {
+                let (full, label_text) = match &slot_info.status {
+                    ElementStatus::Empty => (false, None),
+                    ElementStatus::Full => (true, None),
+                    ElementStatus::VolumeTag(tag) => (true, Some(tag.to_string())),
+                };
+                ImportExportSlot {
+                    slot: (i + 1) as u64,
+                    element_address: slot_info.element_address,
+                    full,
+                    label_text,
+                }
+            })
+            .collect()
+    }
+
     pub fn mark_import_export_slots(&mut self, config: &ScsiTapeChanger) -> Result<(), Error>{
         let mut export_slots: HashSet<u64> = HashSet::new();
 
@@ -172,6 +212,10 @@ pub trait ScsiMediaChange {
     fn transfer(&mut self, from_slot: u64, to_slot: u64) -> Result<MtxStatus, Error>;
 }
 
+/// Number of times `MediaChange::load_media` retries if the drive got
+/// loaded concurrently
+pub const LOAD_MEDIA_RETRIES: usize = 3;
+
 /// Interface to the media changer device for a single drive
 pub trait MediaChange {
 
@@ -199,6 +243,10 @@ pub trait MediaChange {
     /// Note: This refuses to load media inside import/export
     /// slots. Also, you cannot load cleaning units with this
     /// interface.
+    ///
+    /// If the load fails because the drive got loaded concurrently
+    /// (by another operation), we retry the unload/load sequence up
+    /// to `LOAD_MEDIA_RETRIES` times.
     fn load_media(&mut self, label_text: &str) -> Result<MtxStatus, Error> {
 
         if label_text.starts_with("CLN") {
@@ -207,50 +255,74 @@ pub trait MediaChange {
 
         let mut status = self.status()?;
 
-        let mut unload_drive = false;
+        let mut retries = 0;
 
-        // already loaded?
-        for (i, drive_status) in status.drives.iter().enumerate() {
-            if let ElementStatus::VolumeTag(ref tag) = drive_status.status {
-                if *tag == label_text {
-                    if i as u64 != self.drive_number() {
-                        bail!("unable to load media '{}' - media in wrong drive ({} != {})",
-                              label_text, i, self.drive_number());
+        loop {
+            let mut unload_drive = false;
+
+            // already loaded?
+            for (i, drive_status) in status.drives.iter().enumerate() {
+                if let ElementStatus::VolumeTag(ref tag) = drive_status.status {
+                    if *tag == label_text {
+                        if i as u64 != self.drive_number() {
+                            bail!("unable to load media '{}' - media in wrong drive ({} != {})",
+                                  label_text, i, self.drive_number());
+                        }
+                        return Ok(status) // already loaded
+                    }
+                }
+                if i as u64 == self.drive_number() {
+                    match drive_status.status {
+                        ElementStatus::Empty => { /* OK */ },
+                        _ => unload_drive = true,
                     }
-                    return Ok(status) // already loaded
                 }
             }
-            if i as u64 == self.drive_number() {
-                match drive_status.status {
-                    ElementStatus::Empty => { /* OK */ },
-                    _ => unload_drive = true,
-                 }
-            }
-        }
 
-        if unload_drive {
-            status = self.unload_to_free_slot(status)?;
-        }
+            if unload_drive {
+                status = self.unload_to_free_slot(status)?;
+            }
 
-        let mut slot = None;
-        for (i, slot_info) in status.slots.iter().enumerate() {
-            if let ElementStatus::VolumeTag(ref tag) = slot_info.status {
-                if tag == label_text {
-                    if slot_info.import_export {
-                        bail!("unable to load media '{}' - inside import/export slot", label_text);
+            let mut slot = None;
+            for (i, slot_info) in status.slots.iter().enumerate() {
+                if let ElementStatus::VolumeTag(ref tag) = slot_info.status {
+                    if tag == label_text {
+                        if slot_info.import_export {
+                            bail!("unable to load media '{}' - inside import/export slot", label_text);
+                        }
+                        slot = Some(i+1);
+                        break;
                     }
-                    slot = Some(i+1);
-                    break;
                 }
             }
-        }
 
-        let slot = match slot {
-            None => bail!("unable to find media '{}' (offline?)", label_text),
-            Some(slot) => slot,
-        };
+            let slot = match slot {
+                None => bail!("unable to find media '{}' (offline?)", label_text),
+                Some(slot) => slot,
+            };
+
+            let err = match self.load_media_from_slot(slot as u64) {
+                Ok(status) => return Ok(status),
+                Err(err) => err,
+            };
+
+            // only retry if the drive is (again) occupied
+            status = self.status()?;
+            let drive_full = match status.drives.get(self.drive_number() as usize) {
+                Some(drive_status) => !matches!(drive_status.status, ElementStatus::Empty),
+                None => false,
+            };
+
+            if !drive_full {
+                return Err(err);
+            }
 
-        self.load_media_from_slot(slot as u64)
+            retries += 1;
+            if retries > LOAD_MEDIA_RETRIES {
+                bail!("unable to load media '{}' - drive '{}' still occupied after {} attempts - {}",
+                      label_text, self.drive_name(), retries, err);
+            }
+        }
     }
 
     /// Unload media from drive (eject media if necessary)
@@ -285,7 +357,9 @@ pub trait MediaChange {
     /// Load/Unload cleaning cartridge
     ///
     /// This fail if there is no cleaning cartridge online. Any media
-    /// inside the drive is automatically unloaded.
+    /// inside the drive is automatically unloaded. The cartridge is
+    /// moved back to its slot, so `MtxStatus::cleaning_cartridge` on the
+    /// returned status tells which one was used.
     fn clean_drive(&mut self) -> Result<MtxStatus, Error> {
         let mut status = self.status()?;
 
@@ -297,24 +371,11 @@ pub trait MediaChange {
             }
         }
 
-        let mut cleaning_cartridge_slot = None;
-
-        for (i, slot_info) in status.slots.iter().enumerate() {
-            if slot_info.import_export { continue; }
-            if let ElementStatus::VolumeTag(ref tag) = slot_info.status {
-                if tag.starts_with("CLN") {
-                    cleaning_cartridge_slot = Some(i + 1);
-                    break;
-                }
-            }
-        }
-
-        let cleaning_cartridge_slot = match cleaning_cartridge_slot {
+        let cleaning_cartridge_slot = match status.cleaning_cartridge() {
             None => bail!("clean failed - unable to find cleaning cartridge"),
-            Some(cleaning_cartridge_slot) => cleaning_cartridge_slot as u64,
+            Some((slot, _label_text)) => slot,
         };
 
-
         self.load_media_from_slot(cleaning_cartridge_slot)?;
 
         self.unload_media(Some(cleaning_cartridge_slot))
@@ -325,52 +386,52 @@ pub trait MediaChange {
     /// By moving the media to an empty import-export slot. Returns
     /// Some(slot) if the media was exported. Returns None if the media is
     /// not online (already exported).
+    ///
+    /// Note: This fails if the media is loaded in another drive of
+    /// the changer.
     fn export_media(&mut self, label_text: &str) -> Result<Option<u64>, Error> {
         let status = self.status()?;
 
         let mut unload_from_drive = false;
-        if let Some(drive_status) = status.drives.get(self.drive_number() as usize) {
+        for (i, drive_status) in status.drives.iter().enumerate() {
             if let ElementStatus::VolumeTag(ref tag) = drive_status.status {
                 if tag == label_text {
+                    if i as u64 != self.drive_number() {
+                        bail!("unable to export media '{}' - media in wrong drive ({} != {})",
+                              label_text, i, self.drive_number());
+                    }
                     unload_from_drive = true;
                 }
             }
         }
 
         let mut from = None;
-        let mut to = None;
-
         for (i, slot_info) in status.slots.iter().enumerate() {
-            if slot_info.import_export {
-                if to.is_some() { continue; }
-                if let ElementStatus::Empty = slot_info.status {
-                    to = Some(i as u64 + 1);
-                }
-            } else if let ElementStatus::VolumeTag(ref tag) = slot_info.status {
+            if slot_info.import_export { continue; }
+            if let ElementStatus::VolumeTag(ref tag) = slot_info.status {
                 if tag == label_text {
                     from = Some(i as u64 + 1);
+                    break;
                 }
             }
         }
 
+        if !unload_from_drive && from.is_none() {
+            return Ok(None); // not online
+        }
+
+        let to = match status.find_free_slot(true) {
+            Some(to) => to,
+            None => bail!("unable to export media '{}' - no free import/export slot", label_text),
+        };
+
         if unload_from_drive {
-            match to {
-                Some(to) => {
-                    self.unload_media(Some(to))?;
-                    Ok(Some(to))
-                }
-                None =>  bail!("unable to find free export slot"),
-            }
-        } else {
-            match (from, to) {
-                (Some(from), Some(to)) => {
-                    self.transfer_media(from, to)?;
-                    Ok(Some(to))
-                }
-                (Some(_from), None) => bail!("unable to find free export slot"),
-                (None, _) => Ok(None), // not online
-            }
+            self.unload_media(Some(to))?;
+        } else if let Some(from) = from {
+            self.transfer_media(from, to)?;
         }
+
+        Ok(Some(to))
     }
 
     /// Unload media to a free storage slot
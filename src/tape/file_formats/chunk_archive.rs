@@ -1,6 +1,6 @@
 use std::io::Read;
 
-use anyhow::{bail, Error};
+use anyhow::{bail, format_err, Error};
 use endian_trait::Endian;
 
 use proxmox::tools::{
@@ -14,6 +14,7 @@ use crate::tape::{
     TapeWrite,
     file_formats::{
         PROXMOX_TAPE_BLOCK_SIZE,
+        PROXMOX_BACKUP_CONTENT_HEADER_MAGIC_1_0,
         PROXMOX_BACKUP_CHUNK_ARCHIVE_MAGIC_1_1,
         PROXMOX_BACKUP_CHUNK_ARCHIVE_ENTRY_MAGIC_1_0,
         MediaContentHeader,
@@ -207,3 +208,105 @@ impl <R: Read> ChunkArchiveDecoder<R> {
         Ok(Some((header.digest, blob)))
     }
 }
+
+/// Read back a whole chunk archive and verify all chunk CRCs.
+///
+/// The reader must be positioned at the start of the tape file (the
+/// archive `MediaContentHeader`). Returns the number of chunks found.
+pub fn verify_chunk_archive<R: Read>(mut reader: R) -> Result<usize, Error> {
+
+    let header: MediaContentHeader = unsafe { reader.read_le_value()? };
+    if header.magic != PROXMOX_BACKUP_CONTENT_HEADER_MAGIC_1_0 {
+        bail!("missing MediaContentHeader");
+    }
+
+    if header.content_magic != PROXMOX_BACKUP_CHUNK_ARCHIVE_MAGIC_1_1 {
+        bail!("unexpected content magic - not a chunk archive");
+    }
+
+    let header_data = reader.read_exact_allocated(header.size as usize)?;
+    let _archive_header: ChunkArchiveHeader = serde_json::from_slice(&header_data)
+        .map_err(|err| format_err!("unable to parse chunk archive header - {}", err))?;
+
+    let mut decoder = ChunkArchiveDecoder::new(reader);
+
+    let mut count = 0;
+    while decoder.next_chunk()
+        .map_err(|err| format_err!("chunk {} - {}", count, err))?
+        .is_some()
+    {
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Error;
+
+    use crate::backup::DataBlob;
+    use crate::tape::{
+        helpers::{EmulateTapeReader, EmulateTapeWriter},
+        file_formats::{
+            BlockedReader,
+            BlockedWriter,
+            ChunkArchiveWriter,
+            verify_chunk_archive,
+        },
+    };
+
+    const PATTERN: &[u8] = b"proxmox tape verify test data ";
+
+    fn write_test_archive(chunk_count: usize) -> Result<Vec<u8>, Error> {
+        let mut tape_data = Vec::new();
+
+        {
+            let writer = EmulateTapeWriter::new(&mut tape_data, 1024*1024*10);
+            let writer = Box::new(BlockedWriter::new(writer));
+            let (mut writer, _uuid) = ChunkArchiveWriter::new(writer, "store1", false)?;
+
+            for i in 0..chunk_count {
+                let data = PATTERN.repeat(100 + i);
+                let blob = DataBlob::encode(&data, None, false)?;
+                let digest = openssl::sha::sha256(&data);
+                assert!(writer.try_write_chunk(&digest, &blob)?);
+            }
+
+            writer.finish()?;
+        }
+
+        Ok(tape_data)
+    }
+
+    #[test]
+    fn verify_good_archive() -> Result<(), Error> {
+        let tape_data = write_test_archive(5)?;
+
+        let reader = EmulateTapeReader::new(&tape_data[..]);
+        let reader = BlockedReader::open(reader)?;
+
+        assert_eq!(verify_chunk_archive(reader)?, 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn detect_corrupted_archive() -> Result<(), Error> {
+        let mut tape_data = write_test_archive(5)?;
+
+        // flip a byte inside the data of the first chunk
+        let pos = tape_data
+            .windows(PATTERN.len())
+            .position(|window| window == PATTERN)
+            .expect("test pattern not found in tape data");
+        tape_data[pos] ^= 0xff;
+
+        let reader = EmulateTapeReader::new(&tape_data[..]);
+        let reader = BlockedReader::open(reader)?;
+
+        assert!(verify_chunk_archive(reader).is_err());
+
+        Ok(())
+    }
+}
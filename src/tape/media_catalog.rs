@@ -3,7 +3,7 @@ use std::fs::File;
 use std::io::{Write, Read, BufReader, Seek, SeekFrom};
 use std::os::unix::io::AsRawFd;
 use std::path::Path;
-use std::collections::{HashSet, HashMap};
+use std::collections::{BTreeSet, HashSet, HashMap};
 
 use anyhow::{bail, format_err, Error};
 use endian_trait::Endian;
@@ -43,6 +43,21 @@ impl DatastoreContent {
             snapshot_index: HashMap::new(),
         }
     }
+
+    /// Returns the file numbers of the chunk archives holding chunks of
+    /// this datastore, with consecutive numbers merged into (first, last) ranges
+    pub fn chunk_archive_ranges(&self) -> Vec<(u64, u64)> {
+        let file_numbers: BTreeSet<u64> = self.chunk_index.values().copied().collect();
+
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        for file_number in file_numbers {
+            match ranges.last_mut() {
+                Some((_, last)) if *last + 1 == file_number => *last = file_number,
+                _ => ranges.push((file_number, file_number)),
+            }
+        }
+        ranges
+    }
 }
 
 /// The Media Catalog
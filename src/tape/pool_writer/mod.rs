@@ -24,6 +24,7 @@ use crate::{
         MAX_CHUNK_ARCHIVE_SIZE,
         COMMIT_BLOCK_SIZE,
         TapeWrite,
+        BlockReadError,
         SnapshotReader,
         MediaPool,
         MediaId,
@@ -33,6 +34,7 @@ use crate::{
             ChunkArchiveWriter,
             tape_write_snapshot_archive,
             tape_write_catalog,
+            verify_chunk_archive,
         },
         drive::{
             TapeDriver,
@@ -53,6 +55,8 @@ struct PoolWriterState {
     at_eom: bool,
     // bytes written after the last tape fush/sync
     bytes_written: usize,
+    // file numbers of chunk archives written to the loaded media
+    chunk_archive_list: Vec<u64>,
 }
 
 /// Helper to manage a backup job, writing several tapes of a pool
@@ -62,6 +66,7 @@ pub struct PoolWriter {
     status: Option<PoolWriterState>,
     catalog_set: Arc<Mutex<CatalogSet>>,
     notify_email: Option<String>,
+    verify_sample_rate: u64,
 }
 
 impl PoolWriter {
@@ -107,9 +112,17 @@ impl PoolWriter {
             status: None,
             catalog_set: Arc::new(Mutex::new(catalog_set)),
             notify_email,
+            verify_sample_rate: 0,
          })
     }
 
+    /// Set the percentage of written chunk archives we read back for
+    /// verification (see `verify_chunk_archives`). Zero disables
+    /// verification.
+    pub fn set_verify_sample_rate(&mut self, sample_rate: u64) {
+        self.verify_sample_rate = sample_rate.min(100);
+    }
+
     pub fn pool(&mut self) -> &mut MediaPool {
         &mut self.pool
     }
@@ -217,6 +230,10 @@ impl PoolWriter {
 
         task_log!(worker, "allocated new writable media '{}'", media.label_text());
 
+        if last_media_uuid.is_some() {
+            self.verify_chunk_archives(worker)?;
+        }
+
         if let Some(PoolWriterState {mut drive, .. }) = self.status.take() {
             if last_media_uuid.is_some() {
                 task_log!(worker, "eject current media");
@@ -263,6 +280,7 @@ impl PoolWriter {
             media_uuid: media_uuid.clone(),
             at_eom: false,
             bytes_written: 0,
+            chunk_archive_list: Vec::new(),
         });
 
         if is_new_media {
@@ -496,6 +514,7 @@ impl PoolWriter {
         )?;
 
         status.bytes_written += bytes_written;
+        status.chunk_archive_list.push(current_file_number);
 
         let elapsed =  start_time.elapsed()?.as_secs_f64();
         worker.log(format!(
@@ -518,6 +537,77 @@ impl PoolWriter {
         Ok((leom, bytes_written))
     }
 
+    /// Read back a random sample of the chunk archives written to the
+    /// loaded media, and verify the CRC of all contained chunks.
+    ///
+    /// The sample size is controlled by `set_verify_sample_rate`.
+    /// This rewinds the tape, so the next write moves to EOM again.
+    /// Returns an error if any sampled archive is unreadable.
+    pub fn verify_chunk_archives(&mut self, worker: &WorkerTask) -> Result<(), Error> {
+
+        let sample_rate = self.verify_sample_rate;
+
+        let sample = match self.status {
+            Some(ref mut status) if sample_rate > 0 => {
+                let list = std::mem::replace(&mut status.chunk_archive_list, Vec::new());
+                select_verify_sample(&list, sample_rate)?
+            }
+            _ => return Ok(()),
+        };
+
+        if sample.is_empty() {
+            return Ok(());
+        }
+
+        self.commit()?; // make sure everything is on tape
+
+        let status = self.status.as_mut().unwrap();
+
+        task_log!(worker, "verify {} chunk archives (sample rate {}%)", sample.len(), sample_rate);
+
+        status.drive.rewind()?;
+        status.at_eom = false;
+
+        let last_file_number = *sample.last().unwrap();
+        let mut errors = 0;
+
+        loop {
+            let current_file_number = status.drive.current_file_number()?;
+            if current_file_number > last_file_number {
+                break;
+            }
+
+            let mut reader = match status.drive.read_next_file() {
+                Ok(reader) => reader,
+                Err(BlockReadError::EndOfFile) => continue,
+                Err(BlockReadError::EndOfStream) => {
+                    bail!("verify failed - got EOT before file {}", last_file_number);
+                }
+                Err(BlockReadError::Error(err)) => return Err(err.into()),
+            };
+
+            if sample.binary_search(&current_file_number).is_ok() {
+                match verify_chunk_archive(&mut reader) {
+                    Ok(count) => {
+                        task_log!(worker, "verified chunk archive {} ({} chunks)", current_file_number, count);
+                    }
+                    Err(err) => {
+                        errors += 1;
+                        worker.warn(format!("verify chunk archive {} failed - {}", current_file_number, err));
+                    }
+                }
+            }
+
+            reader.skip_data()?; // read remaining data
+        }
+
+        if errors > 0 {
+            bail!("verify failed for {} of {} chunk archives", errors, sample.len());
+        }
+
+        Ok(())
+    }
+
     pub fn spawn_chunk_reader_thread(
         &self,
         datastore: Arc<DataStore>,
@@ -531,6 +621,33 @@ impl PoolWriter {
     }
 }
 
+// Select a random subset (about sample_rate percent, but at least one
+// element) from a list of file numbers. The result is sorted.
+fn select_verify_sample(file_list: &[u64], sample_rate: u64) -> Result<Vec<u64>, Error> {
+
+    if file_list.is_empty() || sample_rate == 0 {
+        return Ok(Vec::new());
+    }
+
+    let random = proxmox::sys::linux::random_data(file_list.len())?;
+    let threshold = (sample_rate.min(100) * 256) / 100;
+
+    let mut sample: Vec<u64> = file_list
+        .iter()
+        .zip(random.iter())
+        .filter(|(_, r)| (**r as u64) < threshold)
+        .map(|(file_number, _)| *file_number)
+        .collect();
+
+    if sample.is_empty() {
+        sample.push(file_list[(random[0] as usize) % file_list.len()]);
+    }
+
+    sample.sort_unstable();
+
+    Ok(sample)
+}
+
 /// write up to <max_size> of chunks
 fn write_chunk_archive<'a>(
     _worker: &WorkerTask,
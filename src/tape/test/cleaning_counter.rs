@@ -0,0 +1,97 @@
+// Tape changer tests - test CleaningCounters
+//
+// # cargo test --release tape::test::cleaning_counter
+
+use std::path::PathBuf;
+use anyhow::Error;
+
+use crate::tape::changer::{
+    CLEANING_CARTRIDGE_MAX_CYCLES,
+    CleaningCounters,
+    DriveStatus,
+    ElementStatus,
+    MtxStatus,
+    StorageElementStatus,
+};
+
+fn create_testdir(name: &str) -> Result<PathBuf, Error> {
+    let mut testdir: PathBuf = String::from("./target/testout").into();
+    testdir.push(std::module_path!());
+    testdir.push(name);
+
+    let _ = std::fs::remove_dir_all(&testdir);
+    let _ = std::fs::create_dir_all(&testdir);
+
+    Ok(testdir)
+}
+
+#[test]
+fn test_cleaning_counter_persistent() -> Result<(), Error> {
+
+    let testdir = create_testdir("test_cleaning_counter_persistent")?;
+
+    let mut counters = CleaningCounters::load(&testdir)?;
+    assert_eq!(counters.cleaning_count("CLN001L1"), 0);
+
+    assert_eq!(counters.increment("CLN001L1")?, 1);
+    assert_eq!(counters.increment("CLN001L1")?, 2);
+    assert_eq!(counters.increment("CLN002L1")?, 1);
+
+    // reload from disk
+    let counters = CleaningCounters::load(&testdir)?;
+    assert_eq!(counters.cleaning_count("CLN001L1"), 2);
+    assert_eq!(counters.cleaning_count("CLN002L1"), 1);
+    assert_eq!(counters.cleaning_count("CLN003L1"), 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_cleaning_counter_warnings() -> Result<(), Error> {
+
+    let testdir = create_testdir("test_cleaning_counter_warnings")?;
+
+    let mut counters = CleaningCounters::load(&testdir)?;
+
+    for _ in 0..(CLEANING_CARTRIDGE_MAX_CYCLES - 3) {
+        counters.increment("CLN001L1")?;
+    }
+    assert!(counters.check_cartridge("CLN001L1").is_none());
+
+    counters.increment("CLN001L1")?;
+    assert!(counters.check_cartridge("CLN001L1").unwrap().contains("nearly worn out"));
+
+    counters.increment("CLN001L1")?;
+    counters.increment("CLN001L1")?;
+    assert!(counters.check_cartridge("CLN001L1").unwrap().contains("probably worn out"));
+
+    let status = MtxStatus {
+        drives: vec![DriveStatus {
+            loaded_slot: None,
+            status: ElementStatus::VolumeTag("tape1".to_string()),
+            drive_serial_number: None,
+            vendor: None,
+            model: None,
+            element_address: 0,
+        }],
+        slots: vec![
+            StorageElementStatus {
+                import_export: false,
+                status: ElementStatus::VolumeTag("CLN001L1".to_string()),
+                element_address: 1000,
+            },
+            StorageElementStatus {
+                import_export: false,
+                status: ElementStatus::VolumeTag("CLN002L1".to_string()),
+                element_address: 1001,
+            },
+        ],
+        transports: Vec::new(),
+    };
+
+    let warnings = counters.check_changer_status(&status);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("CLN001L1"));
+
+    Ok(())
+}
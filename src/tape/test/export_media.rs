@@ -0,0 +1,156 @@
+// Tape changer tests - test MediaChange::export_media()
+//
+// # cargo test --release tape::test::export_media
+
+use anyhow::{bail, Error};
+
+use crate::tape::changer::{
+    DriveStatus,
+    ElementStatus,
+    MediaChange,
+    MtxStatus,
+    StorageElementStatus,
+};
+
+// Simple in-memory changer: drives and slots contain an optional label
+struct TestChanger {
+    drive_number: u64,
+    drives: Vec<Option<String>>,
+    slots: Vec<(bool, Option<String>)>,
+}
+
+fn element_status(label: &Option<String>) -> ElementStatus {
+    match label {
+        Some(label) => ElementStatus::VolumeTag(label.clone()),
+        None => ElementStatus::Empty,
+    }
+}
+
+impl TestChanger {
+
+    fn new(drives: &[Option<&str>], slots: &[(bool, Option<&str>)]) -> Self {
+        Self {
+            drive_number: 0,
+            drives: drives.iter().map(|d| d.map(String::from)).collect(),
+            slots: slots.iter().map(|(ie, s)| (*ie, s.map(String::from))).collect(),
+        }
+    }
+
+    fn slot(&self, slot: u64) -> Option<&str> {
+        self.slots[slot as usize - 1].1.as_deref()
+    }
+}
+
+impl MediaChange for TestChanger {
+
+    fn drive_number(&self) -> u64 {
+        self.drive_number
+    }
+
+    fn drive_name(&self) -> &str {
+        "test"
+    }
+
+    fn status(&mut self) -> Result<MtxStatus, Error> {
+        let drives = self.drives.iter().enumerate().map(|(i, label)| DriveStatus {
+            loaded_slot: None,
+            status: element_status(label),
+            drive_serial_number: None,
+            vendor: None,
+            model: None,
+            element_address: i as u16,
+        }).collect();
+
+        let slots = self.slots.iter().enumerate().map(|(i, (import_export, label))| StorageElementStatus {
+            import_export: *import_export,
+            status: element_status(label),
+            element_address: (i + 100) as u16,
+        }).collect();
+
+        Ok(MtxStatus { drives, slots, transports: Vec::new() })
+    }
+
+    fn transfer_media(&mut self, from: u64, to: u64) -> Result<MtxStatus, Error> {
+        if self.slots[to as usize - 1].1.is_some() {
+            bail!("target slot {} not empty", to);
+        }
+        let label = self.slots[from as usize - 1].1.take();
+        self.slots[to as usize - 1].1 = label;
+        self.status()
+    }
+
+    fn load_media_from_slot(&mut self, slot: u64) -> Result<MtxStatus, Error> {
+        let label = self.slots[slot as usize - 1].1.take();
+        self.drives[self.drive_number as usize] = label;
+        self.status()
+    }
+
+    fn unload_media(&mut self, target_slot: Option<u64>) -> Result<MtxStatus, Error> {
+        let slot = match target_slot {
+            Some(slot) => slot,
+            None => bail!("no target slot"),
+        };
+        if self.slots[slot as usize - 1].1.is_some() {
+            bail!("target slot {} not empty", slot);
+        }
+        let label = self.drives[self.drive_number as usize].take();
+        self.slots[slot as usize - 1].1 = label;
+        self.status()
+    }
+}
+
+#[test]
+fn test_export_media_from_slot() -> Result<(), Error> {
+
+    let mut changer = TestChanger::new(
+        &[None],
+        &[(false, Some("tape1")), (false, None), (true, Some("tape3")), (true, None)],
+    );
+
+    assert_eq!(changer.export_media("tape1")?, Some(4));
+    assert_eq!(changer.slot(1), None);
+    assert_eq!(changer.slot(4), Some("tape1"));
+
+    // already exported (inside import/export slot) or unknown
+    assert_eq!(changer.export_media("tape1")?, None);
+    assert_eq!(changer.export_media("tape2")?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_export_media_from_drive() -> Result<(), Error> {
+
+    let mut changer = TestChanger::new(
+        &[Some("tape1")],
+        &[(false, None), (true, None)],
+    );
+
+    assert_eq!(changer.export_media("tape1")?, Some(2));
+    assert_eq!(changer.drives[0], None);
+    assert_eq!(changer.slot(2), Some("tape1"));
+
+    Ok(())
+}
+
+#[test]
+fn test_export_media_errors() -> Result<(), Error> {
+
+    // no free import/export slot
+    let mut changer = TestChanger::new(
+        &[None],
+        &[(false, Some("tape1")), (true, Some("tape2"))],
+    );
+    assert!(changer.export_media("tape1").is_err());
+    assert_eq!(changer.slot(1), Some("tape1"));
+
+    // media loaded in another drive
+    let mut changer = TestChanger::new(
+        &[None, Some("tape1")],
+        &[(false, None), (true, None)],
+    );
+    assert!(changer.export_media("tape1").is_err());
+    assert_eq!(changer.slot(2), None);
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+// Tape changer tests - test MtxStatus::import_export_slots()
+//
+// # cargo test --release tape::test::import_export_slots
+
+use crate::{
+    api2::types::ImportExportSlot,
+    tape::changer::{
+        ElementStatus,
+        MtxStatus,
+        StorageElementStatus,
+    },
+};
+
+fn slot(import_export: bool, status: ElementStatus, element_address: u16) -> StorageElementStatus {
+    StorageElementStatus { import_export, status, element_address }
+}
+
+#[test]
+fn test_import_export_slots() {
+
+    let status = MtxStatus {
+        drives: Vec::new(),
+        slots: vec![
+            slot(false, ElementStatus::VolumeTag("tape1".to_string()), 1000),
+            slot(false, ElementStatus::Empty, 1001),
+            slot(true, ElementStatus::VolumeTag("tape2".to_string()), 10),
+            slot(true, ElementStatus::Empty, 11),
+            slot(true, ElementStatus::Full, 12),
+        ],
+        transports: Vec::new(),
+    };
+
+    assert_eq!(status.import_export_slots(), vec![
+        ImportExportSlot { slot: 3, element_address: 10, full: true, label_text: Some("tape2".to_string()) },
+        ImportExportSlot { slot: 4, element_address: 11, full: false, label_text: None },
+        ImportExportSlot { slot: 5, element_address: 12, full: true, label_text: None },
+    ]);
+
+    let status = MtxStatus {
+        drives: Vec::new(),
+        slots: vec![slot(false, ElementStatus::Full, 1000)],
+        transports: Vec::new(),
+    };
+
+    assert!(status.import_export_slots().is_empty());
+}
@@ -0,0 +1,141 @@
+// Tape changer tests - test MediaChange::load_media() retries
+//
+// # cargo test --release tape::test::load_media
+
+use anyhow::{bail, Error};
+
+use crate::tape::changer::{
+    LOAD_MEDIA_RETRIES,
+    DriveStatus,
+    ElementStatus,
+    MediaChange,
+    MtxStatus,
+    StorageElementStatus,
+};
+
+// Single drive changer where another operation loads 'other' into
+// the drive right before our next 'racing_loads' load attempts.
+struct RacingChanger {
+    drive: Option<String>,
+    slots: Vec<Option<String>>,
+    racing_loads: usize,
+    load_attempts: usize,
+}
+
+fn element_status(label: &Option<String>) -> ElementStatus {
+    match label {
+        Some(label) => ElementStatus::VolumeTag(label.clone()),
+        None => ElementStatus::Empty,
+    }
+}
+
+impl RacingChanger {
+
+    fn new(racing_loads: usize) -> Self {
+        Self {
+            drive: None,
+            slots: vec![Some("tape1".to_string()), Some("other".to_string()), None, None],
+            racing_loads,
+            load_attempts: 0,
+        }
+    }
+}
+
+impl MediaChange for RacingChanger {
+
+    fn drive_number(&self) -> u64 {
+        0
+    }
+
+    fn drive_name(&self) -> &str {
+        "test"
+    }
+
+    fn status(&mut self) -> Result<MtxStatus, Error> {
+        let drives = vec![DriveStatus {
+            loaded_slot: None,
+            status: element_status(&self.drive),
+            drive_serial_number: None,
+            vendor: None,
+            model: None,
+            element_address: 0,
+        }];
+
+        let slots = self.slots.iter().enumerate().map(|(i, label)| StorageElementStatus {
+            import_export: false,
+            status: element_status(label),
+            element_address: (i + 100) as u16,
+        }).collect();
+
+        Ok(MtxStatus { drives, slots, transports: Vec::new() })
+    }
+
+    fn transfer_media(&mut self, _from: u64, _to: u64) -> Result<MtxStatus, Error> {
+        bail!("not implemented");
+    }
+
+    fn load_media_from_slot(&mut self, slot: u64) -> Result<MtxStatus, Error> {
+        self.load_attempts += 1;
+
+        if self.load_attempts <= self.racing_loads {
+            // somebody else was faster
+            let pos = self.slots.iter().position(|s| s.as_deref() == Some("other")).unwrap();
+            self.drive = self.slots[pos].take();
+        }
+
+        if self.drive.is_some() {
+            bail!("drive full");
+        }
+
+        self.drive = self.slots[slot as usize - 1].take();
+        self.status()
+    }
+
+    fn unload_media(&mut self, target_slot: Option<u64>) -> Result<MtxStatus, Error> {
+        let slot = match target_slot {
+            Some(slot) => slot,
+            None => bail!("no target slot"),
+        };
+        if self.slots[slot as usize - 1].is_some() {
+            bail!("target slot {} not empty", slot);
+        }
+        self.slots[slot as usize - 1] = self.drive.take();
+        self.status()
+    }
+}
+
+#[test]
+fn test_load_media_retry() -> Result<(), Error> {
+
+    let mut changer = RacingChanger::new(LOAD_MEDIA_RETRIES);
+    changer.load_media("tape1")?;
+
+    assert_eq!(changer.drive.as_deref(), Some("tape1"));
+    assert_eq!(changer.load_attempts, LOAD_MEDIA_RETRIES + 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_load_media_retry_exhausted() -> Result<(), Error> {
+
+    let mut changer = RacingChanger::new(LOAD_MEDIA_RETRIES + 1);
+
+    let err = changer.load_media("tape1").unwrap_err();
+    assert!(err.to_string().contains("still occupied"));
+    assert_eq!(changer.load_attempts, LOAD_MEDIA_RETRIES + 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_load_media_no_retry() -> Result<(), Error> {
+
+    // errors not caused by a full drive are not retried
+    let mut changer = RacingChanger::new(0);
+    assert!(changer.load_media("tape2").is_err());
+    assert!(changer.load_media("CLN001L1").is_err());
+    assert_eq!(changer.load_attempts, 0);
+
+    Ok(())
+}
@@ -0,0 +1,23 @@
+// Media catalog tests
+//
+// # cargo test --release tape::test::media_catalog
+
+use crate::tape::DatastoreContent;
+
+#[test]
+fn test_chunk_archive_ranges() {
+
+    let mut content = DatastoreContent::new();
+    assert!(content.chunk_archive_ranges().is_empty());
+
+    // chunks of the same archive share a file number
+    content.chunk_index.insert([1u8; 32], 3);
+    content.chunk_index.insert([2u8; 32], 3);
+    content.chunk_index.insert([3u8; 32], 4);
+    content.chunk_index.insert([4u8; 32], 5);
+    content.chunk_index.insert([5u8; 32], 8); // 6, 7 are snapshot archives
+    content.chunk_index.insert([6u8; 32], 10);
+    content.chunk_index.insert([7u8; 32], 11);
+
+    assert_eq!(content.chunk_archive_ranges(), vec![(3, 5), (8, 8), (10, 11)]);
+}
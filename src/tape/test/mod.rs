@@ -3,3 +3,8 @@ mod inventory;
 mod current_set_usable;
 mod compute_media_state;
 mod alloc_writable_media;
+mod media_catalog;
+mod import_export_slots;
+mod export_media;
+mod cleaning_counter;
+mod load_media;
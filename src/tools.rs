@@ -6,7 +6,7 @@ use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::hash::BuildHasher;
 use std::fs::File;
-use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::io::{BufRead, Read, Seek, SeekFrom};
 use std::os::unix::io::RawFd;
 use std::path::Path;
 
@@ -27,20 +27,24 @@ pub mod config;
 pub mod cpio;
 pub mod daemon;
 pub mod disks;
+pub mod error_hint;
 pub mod format;
 pub mod fs;
 pub mod fuse_loop;
 pub mod http;
+pub mod io;
 pub mod json;
 pub mod logrotate;
 pub mod loopdev;
 pub mod lru_cache;
 pub mod nom;
+pub mod rate_limiter;
 pub mod runtime;
 pub mod serde_filter;
 pub mod socket;
 pub mod statistics;
 pub mod subscription;
+pub mod sync;
 pub mod systemd;
 pub mod ticket;
 pub mod xattr;
@@ -64,7 +68,10 @@ mod tokio_writer_adapter;
 pub use tokio_writer_adapter::TokioWriterAdapter;
 
 mod process_locker;
-pub use process_locker::{ProcessLocker, ProcessLockExclusiveGuard, ProcessLockSharedGuard};
+pub use process_locker::{
+    ProcessLocker, ProcessLockExclusiveGuard, ProcessLockHolder, ProcessLockMode,
+    ProcessLockSharedGuard,
+};
 
 mod file_logger;
 pub use file_logger::{FileLogger, FileLogOptions};
@@ -167,6 +174,38 @@ pub fn required_array_property<'a>(param: &'a Value, name: &str) -> Result<&'a [
     }
 }
 
+pub fn required_bool_param(param: &Value, name: &str) -> Result<bool, Error> {
+    match optional_bool_param(param, name)? {
+        Some(b) => Ok(b),
+        None => bail!("missing parameter '{}'", name),
+    }
+}
+
+pub fn optional_bool_param(param: &Value, name: &str) -> Result<Option<bool>, Error> {
+    match &param[name] {
+        Value::Bool(b) => Ok(Some(*b)),
+        Value::Null => Ok(None),
+        _ => bail!("parameter '{}' has wrong type (expected boolean)", name),
+    }
+}
+
+pub fn required_u64_param(param: &Value, name: &str) -> Result<u64, Error> {
+    match optional_u64_param(param, name)? {
+        Some(n) => Ok(n),
+        None => bail!("missing parameter '{}'", name),
+    }
+}
+
+pub fn optional_u64_param(param: &Value, name: &str) -> Result<Option<u64>, Error> {
+    match &param[name] {
+        Value::Null => Ok(None),
+        value => match value.as_u64() {
+            Some(n) => Ok(Some(n)),
+            None => bail!("parameter '{}' has wrong type (expected unsigned integer)", name),
+        },
+    }
+}
+
 pub fn complete_file_name<S>(arg: &str, _param: &HashMap<String, String, S>) -> Vec<String>
 where
     S: BuildHasher,
@@ -498,10 +537,10 @@ pub const DEFAULT_ENCODE_SET: &AsciiSet = &percent_encoding::CONTROLS // 0..1f a
 /// `#`).
 pub fn file_get_non_comment_lines<P: AsRef<Path>>(
     path: P,
-) -> Result<impl Iterator<Item = io::Result<String>>, Error> {
+) -> Result<impl Iterator<Item = std::io::Result<String>>, Error> {
     let path = path.as_ref();
 
-    Ok(io::BufReader::new(
+    Ok(std::io::BufReader::new(
         File::open(path).map_err(|err| format_err!("error opening {:?}: {}", path, err))?,
     )
     .lines()
@@ -583,3 +622,36 @@ pub enum ControlFlow<B, C = ()> {
 impl<B> ControlFlow<B> {
     pub const CONTINUE: ControlFlow<B, ()> = ControlFlow::Continue(());
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_typed_params() {
+        let param = json!({
+            "flag": true,
+            "count": 42,
+            "negative": -1,
+            "name": "foo",
+        });
+
+        assert!(required_bool_param(&param, "flag").unwrap());
+        assert!(required_bool_param(&param, "missing").is_err());
+        let err = required_bool_param(&param, "name").unwrap_err();
+        assert!(err.to_string().contains("wrong type"));
+
+        assert_eq!(optional_bool_param(&param, "flag").unwrap(), Some(true));
+        assert_eq!(optional_bool_param(&param, "missing").unwrap(), None);
+        assert!(optional_bool_param(&param, "count").is_err());
+
+        assert_eq!(required_u64_param(&param, "count").unwrap(), 42);
+        assert!(required_u64_param(&param, "missing").is_err());
+
+        assert_eq!(optional_u64_param(&param, "count").unwrap(), Some(42));
+        assert_eq!(optional_u64_param(&param, "missing").unwrap(), None);
+        assert!(optional_u64_param(&param, "negative").is_err());
+        assert!(optional_u64_param(&param, "flag").is_err());
+    }
+}
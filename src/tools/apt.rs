@@ -1,5 +1,7 @@
 use std::collections::HashSet;
 use std::collections::HashMap;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
 
 use anyhow::{Error, bail, format_err};
 use apt_pkg_native::Cache;
@@ -7,10 +9,16 @@ use apt_pkg_native::Cache;
 use proxmox::const_regex;
 use proxmox::tools::fs::{file_read_optional_string, replace_file, CreateOptions};
 
-use crate::api2::types::APTUpdateInfo;
+use crate::api2::types::{APTLockInfo, APTUpdateInfo};
 
 const APT_PKG_STATE_FN: &str = "/var/lib/proxmox-backup/pkg-state.json";
 
+/// Lock files used by dpkg/apt frontends and by 'apt-get update'
+pub const APT_LOCK_FILES: &[&str] = &[
+    "/var/lib/dpkg/lock-frontend",
+    "/var/lib/apt/lists/lock",
+];
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 /// Some information we cache about the package (update) state, like what pending update version
 /// we already notfied an user about
@@ -57,10 +65,14 @@ pub fn pkg_cache_expired () -> Result<bool, Error> {
 
 pub fn update_cache() -> Result<PkgState, Error> {
         // update our cache
-        let all_upgradeable = list_installed_apt_packages(|data| {
-            data.candidate_version == data.active_version &&
-            data.installed_version != Some(data.candidate_version)
-        }, None);
+        let mut all_upgradeable = list_installed_apt_packages(upgradable_only(), None);
+
+        // download sizes are nice to have, so ignore errors here
+        let _ = update_download_sizes(&mut all_upgradeable);
+
+        if let Ok(held_back) = held_back_packages() {
+            flag_held_back_packages(&mut all_upgradeable, &held_back);
+        }
 
         let cache = match read_pkg_state() {
             Ok(Some(mut cache)) => {
@@ -76,6 +88,169 @@ pub fn update_cache() -> Result<PkgState, Error> {
         Ok(cache)
 }
 
+/// Fill in the `download_size` of the given packages (using 'apt-cache show').
+///
+/// Packages without size information keep `None`.
+pub fn update_download_sizes(packages: &mut [APTUpdateInfo]) -> Result<(), Error> {
+    if packages.is_empty() {
+        return Ok(());
+    }
+
+    let mut command = std::process::Command::new("apt-cache");
+    command.arg("show");
+    command.arg("--no-all-versions");
+    for pkg in packages.iter() {
+        command.arg(format!("{}={}", pkg.package, pkg.version));
+    }
+
+    let output = crate::tools::run_command(command, None)?;
+
+    set_download_sizes(packages, &parse_apt_cache_show_sizes(&output));
+
+    Ok(())
+}
+
+fn set_download_sizes(packages: &mut [APTUpdateInfo], sizes: &HashMap<(String, String), u64>) {
+    for pkg in packages.iter_mut() {
+        pkg.download_size = sizes
+            .get(&(pkg.package.clone(), pkg.version.clone()))
+            .copied();
+    }
+}
+
+// Parse 'apt-cache show' output into a (package, version) => size map
+fn parse_apt_cache_show_sizes(output: &str) -> HashMap<(String, String), u64> {
+    let mut sizes = HashMap::new();
+
+    for stanza in output.split("\n\n") {
+        let mut package = None;
+        let mut version = None;
+        let mut size = None;
+
+        for line in stanza.lines() {
+            if let Some(value) = line.strip_prefix("Package: ") {
+                package = Some(value.trim().to_string());
+            } else if let Some(value) = line.strip_prefix("Version: ") {
+                version = Some(value.trim().to_string());
+            } else if let Some(value) = line.strip_prefix("Size: ") {
+                size = value.trim().parse::<u64>().ok();
+            }
+        }
+
+        if let (Some(package), Some(version), Some(size)) = (package, version, size) {
+            sizes.insert((package, version), size);
+        }
+    }
+
+    sizes
+}
+
+/// Returns the names of packages which won't be upgraded.
+///
+/// This includes packages put on hold ('apt-mark showhold'), and
+/// packages kept back by a simulated 'apt-get dist-upgrade' (phased
+/// updates, dependency conflicts).
+pub fn held_back_packages() -> Result<HashSet<String>, Error> {
+    // we parse the output, so make sure it is not localized
+    let mut command = std::process::Command::new("apt-mark");
+    command.env("LC_ALL", "C");
+    command.arg("showhold");
+    let output = crate::tools::run_command(command, None)?;
+
+    let mut held_back = parse_apt_mark_showhold(&output);
+
+    let mut command = std::process::Command::new("apt-get");
+    command.env("LC_ALL", "C");
+    command.args(&["--simulate", "--quiet", "dist-upgrade"]);
+    let output = crate::tools::run_command(command, None)?;
+
+    held_back.extend(parse_kept_back_packages(&output));
+
+    Ok(held_back)
+}
+
+/// Mark held back packages using the `extra_info` field.
+pub fn flag_held_back_packages(packages: &mut [APTUpdateInfo], held_back: &HashSet<String>) {
+    for pkg in packages.iter_mut() {
+        if held_back.contains(&pkg.package) {
+            pkg.extra_info = Some("held back - will not be upgraded".to_string());
+        }
+    }
+}
+
+fn parse_apt_mark_showhold(output: &str) -> HashSet<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// Parse the "kept back" list from 'apt-get --simulate dist-upgrade' output
+fn parse_kept_back_packages(output: &str) -> HashSet<String> {
+    let mut packages = HashSet::new();
+    let mut in_list = false;
+
+    for line in output.lines() {
+        if line.starts_with("The following packages have been kept back:") {
+            in_list = true;
+        } else if in_list && line.starts_with(' ') {
+            packages.extend(line.split_whitespace().map(String::from));
+        } else {
+            in_list = false;
+        }
+    }
+
+    packages
+}
+
+/// Test if a (fcntl record) lock file is locked, and by which process.
+///
+/// We use an open file description lock query (F_OFD_GETLK), so this
+/// also detects locks held by our own process.
+pub fn query_lock_file<P: AsRef<Path>>(path: P) -> Result<APTLockInfo, Error> {
+    let path = path.as_ref();
+
+    let mut info = APTLockInfo {
+        path: path.to_string_lossy().into_owned(),
+        locked: false,
+        pid: None,
+    };
+
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(info),
+        Err(err) => bail!("unable to open lock file {:?} - {}", path, err),
+    };
+
+    let mut op = libc::flock {
+        l_type: libc::F_WRLCK as i16,
+        l_whence: libc::SEEK_SET as i16,
+        l_start: 0,
+        l_len: 0,
+        l_pid: 0,
+    };
+
+    // nix does not implement F_OFD_GETLK, so use libc directly
+    nix::errno::Errno::result(unsafe {
+        libc::fcntl(file.as_raw_fd(), libc::F_OFD_GETLK, &mut op)
+    }).map_err(|err| format_err!("unable to query lock file {:?} - {}", path, err))?;
+
+    if op.l_type != libc::F_UNLCK as i16 {
+        info.locked = true;
+        if op.l_pid > 0 {
+            info.pid = Some(op.l_pid);
+        }
+    }
+
+    Ok(info)
+}
+
+/// Query the state of all APT/dpkg lock files (see `APT_LOCK_FILES`).
+pub fn apt_lock_status() -> Result<Vec<APTLockInfo>, Error> {
+    APT_LOCK_FILES.iter().map(query_lock_file).collect()
+}
 
 const_regex! {
     VERSION_EPOCH_REGEX = r"^\d+:";
@@ -140,6 +315,21 @@ fn get_changelog_url(
     bail!("unknown origin ({}) or component ({})", origin, component)
 }
 
+/// Replace scheme and host of a changelog URL with the `mirror` base URL
+///
+/// For example 'http://download.proxmox.com/debian/...' with mirror
+/// 'http://mirror.local/proxmox' results in 'http://mirror.local/proxmox/debian/...'.
+pub fn changelog_url_with_mirror(changelog_url: &str, mirror: &str) -> Result<String, Error> {
+    let path = changelog_url
+        .splitn(2, "://")
+        .nth(1)
+        .and_then(|rest| rest.find('/').map(|pos| &rest[pos..]))
+        .ok_or_else(|| format_err!("unable to parse changelog URL '{}'", changelog_url))?;
+
+    Ok(format!("{}{}", mirror.trim_end_matches('/'), path))
+}
+
+#[derive(Clone, Copy)]
 pub struct FilterData<'a> {
     /// package name
     pub package: &'a str,
@@ -151,6 +341,24 @@ pub struct FilterData<'a> {
     pub active_version: &'a str,
 }
 
+/// Filter selecting the installed version of installed packages.
+pub fn installed_only() -> impl Fn(FilterData) -> bool {
+    |data| data.installed_version == Some(data.active_version)
+}
+
+/// Filter selecting the candidate version of packages with a pending update.
+pub fn upgradable_only() -> impl Fn(FilterData) -> bool {
+    |data| {
+        data.candidate_version == data.active_version
+            && data.installed_version != Some(data.candidate_version)
+    }
+}
+
+/// Filter selecting the candidate version of the package called `name`.
+pub fn by_name(name: &str) -> impl Fn(FilterData) -> bool + '_ {
+    move |data| data.package == name && data.active_version == data.candidate_version
+}
+
 enum PackagePreSelect {
     OnlyInstalled,
     OnlyNew,
@@ -362,9 +570,188 @@ where
                 priority: priority_res,
                 section: section_res,
                 extra_info: None,
+                download_size: None,
             });
         }
     }
 
     None
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_package(package: &str, version: &str) -> APTUpdateInfo {
+        APTUpdateInfo {
+            package: package.to_string(),
+            title: String::new(),
+            arch: "amd64".to_string(),
+            description: String::new(),
+            version: version.to_string(),
+            old_version: String::new(),
+            origin: "Debian".to_string(),
+            priority: "optional".to_string(),
+            section: "admin".to_string(),
+            change_log_url: String::new(),
+            extra_info: None,
+            download_size: None,
+        }
+    }
+
+    fn filter_data<'a>(
+        package: &'a str,
+        installed_version: Option<&'a str>,
+        candidate_version: &'a str,
+        active_version: &'a str,
+    ) -> FilterData<'a> {
+        FilterData { package, installed_version, candidate_version, active_version }
+    }
+
+    fn select<F: Fn(FilterData) -> bool>(list: &[FilterData], filter: F) -> Vec<(String, String)> {
+        list.iter()
+            .filter(|data| filter(**data))
+            .map(|data| (data.package.to_string(), data.active_version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_package_filters() {
+        let list = vec![
+            // installed and up-to-date
+            filter_data("up-to-date", Some("1.0"), "1.0", "1.0"),
+            // installed with pending update (one entry per available version)
+            filter_data("upgradable", Some("1.0"), "2.0", "1.0"),
+            filter_data("upgradable", Some("1.0"), "2.0", "2.0"),
+            // not installed (new dependency)
+            filter_data("new", None, "3.0", "3.0"),
+        ];
+
+        let to_vec = |items: &[(&str, &str)]| -> Vec<(String, String)> {
+            items.iter().map(|(p, v)| (p.to_string(), v.to_string())).collect()
+        };
+
+        assert_eq!(
+            select(&list, installed_only()),
+            to_vec(&[("up-to-date", "1.0"), ("upgradable", "1.0")]),
+        );
+
+        assert_eq!(
+            select(&list, upgradable_only()),
+            to_vec(&[("upgradable", "2.0"), ("new", "3.0")]),
+        );
+
+        assert_eq!(select(&list, by_name("upgradable")), to_vec(&[("upgradable", "2.0")]));
+        assert_eq!(select(&list, by_name("up-to-date")), to_vec(&[("up-to-date", "1.0")]));
+        assert!(select(&list, by_name("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_query_lock_file() -> Result<(), Error> {
+        let path = std::env::temp_dir()
+            .join(format!("proxmox-backup-apt-lock-test-{}", std::process::id()));
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .open(&path)?;
+
+        let info = query_lock_file(&path)?;
+        assert!(!info.locked);
+        assert_eq!(info.pid, None);
+
+        // simulate a running dpkg/apt (traditional record lock)
+        let op = libc::flock {
+            l_type: libc::F_WRLCK as i16,
+            l_whence: libc::SEEK_SET as i16,
+            l_start: 0,
+            l_len: 0,
+            l_pid: 0,
+        };
+        nix::fcntl::fcntl(file.as_raw_fd(), nix::fcntl::FcntlArg::F_SETLK(&op))?;
+
+        let info = query_lock_file(&path)?;
+        assert!(info.locked);
+        assert_eq!(info.pid, Some(std::process::id() as i32));
+
+        drop(file); // releases the lock
+
+        let info = query_lock_file(&path)?;
+        assert!(!info.locked);
+
+        std::fs::remove_file(&path)?;
+
+        let info = query_lock_file(&path)?;
+        assert!(!info.locked);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_held_back_packages() {
+        let showhold = "proxmox-backup-server\nzfsutils-linux\n\n";
+
+        let held_back = parse_apt_mark_showhold(showhold);
+        assert_eq!(held_back.len(), 2);
+
+        let simulate = "\
+Reading package lists...
+Building dependency tree...
+Calculating upgrade...
+The following packages have been kept back:
+  libfoo libbar
+  libbaz
+The following packages will be upgraded:
+  curl
+1 upgraded, 0 newly installed, 0 to remove and 3 not upgraded.
+";
+        let kept_back = parse_kept_back_packages(simulate);
+        assert_eq!(kept_back.len(), 3);
+        assert!(kept_back.contains("libbaz"));
+        assert!(!kept_back.contains("curl"));
+
+        let mut packages = vec![
+            test_package("proxmox-backup-server", "1.1.5-1"),
+            test_package("curl", "7.64.0-4"),
+            test_package("libbar", "1.0-1"),
+        ];
+
+        let mut held_back = held_back;
+        held_back.extend(kept_back);
+        flag_held_back_packages(&mut packages, &held_back);
+
+        assert!(packages[0].extra_info.is_some());
+        assert!(packages[1].extra_info.is_none());
+        assert!(packages[2].extra_info.is_some());
+    }
+
+    #[test]
+    fn test_download_size() {
+        let output = "\
+Package: proxmox-backup-server
+Version: 1.1.5-1
+Architecture: amd64
+Size: 4711
+Description: Proxmox Backup Server daemon with tools and GUI
+
+Package: libfoo
+Version: 2.0-1
+Architecture: amd64
+Description: package without size field
+";
+
+        let sizes = parse_apt_cache_show_sizes(output);
+        assert_eq!(sizes.len(), 1);
+
+        let mut packages = vec![
+            test_package("proxmox-backup-server", "1.1.5-1"),
+            test_package("libfoo", "2.0-1"),
+        ];
+
+        set_download_sizes(&mut packages, &sizes);
+
+        assert_eq!(packages[0].download_size, Some(4711));
+        assert_eq!(packages[1].download_size, None);
+    }
+}
@@ -1,6 +1,12 @@
 /// This ties two values T and U together, such that T does not move and cannot be used as long as
 /// there's an U. This essentially replaces the borrow checker's job for dependent values which
 /// need to be stored together in a struct {}, and is similar to what the 'rental' crate produces.
+///
+/// Note: there is no runtime check against reentrant mutable borrows here. The dependent value
+/// is only reachable via `as_mut`/`deref_mut`, which take `&mut self`, so the borrow checker
+/// already rules out a second live mutable reference. The raw owner pointer is only passed to
+/// the producer, and the dependent value keeps using it for its whole lifetime, so a scoped
+/// guard (panicking on a second borrow) would reject every valid use.
 pub struct Tied<T, U: ?Sized>(Option<Box<T>>, Option<Box<U>>);
 
 impl<T, U: ?Sized> Drop for Tied<T, U> {
@@ -527,6 +527,66 @@ pub fn disk_usage(path: &std::path::Path) -> Result<StorageStatus, Error> {
     })
 }
 
+/// Map a file system magic number (`statfs.f_type`) to a file system name.
+pub fn filesystem_type_name(magic: u32) -> Option<&'static str> {
+    let name = match magic {
+        0x0000_EF53 => "ext4", // also used by ext2 and ext3
+        0x5846_5342 => "xfs",
+        0x2FC1_2FC1 => "zfs",
+        0x9123_683E => "btrfs",
+        0xF2F5_2010 => "f2fs",
+        0x0102_1994 => "tmpfs",
+        0x794C_7630 => "overlay",
+        0x0000_6969 => "nfs",
+        0x0000_517B => "smb",
+        0xFF53_4D42 => "cifs",
+        0xFE53_4D42 => "smb2",
+        0x00C3_6400 => "ceph",
+        0x6573_5546 => "fuse",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Returns true if the file system magic number belongs to a network file system
+/// (NFS, CIFS/SMB, ceph).
+pub fn is_network_filesystem_magic(magic: u32) -> bool {
+    matches!(filesystem_type_name(magic), Some("nfs") | Some("smb") | Some("cifs") | Some("smb2") | Some("ceph"))
+}
+
+fn filesystem_magic(path: &std::path::Path) -> Result<u32, Error> {
+
+    let mut stat: libc::statfs64 = unsafe { std::mem::zeroed() };
+
+    use nix::NixPath;
+
+    let res = path.with_nix_path(|cstr| unsafe { libc::statfs64(cstr.as_ptr(), &mut stat) })?;
+    nix::errno::Errno::result(res)?;
+
+    // all magic numbers fit into 32 bits, f_type is a signed long
+    Ok(stat.f_type as u32)
+}
+
+/// Returns true if `path` is located on a network file system
+///
+/// Those have some caveats for datastores, e.g. `flock` may not be reliable.
+pub fn is_network_filesystem(path: &std::path::Path) -> Result<bool, Error> {
+    Ok(is_network_filesystem_magic(filesystem_magic(path)?))
+}
+
+/// Returns the type of the file system `path` is located on (e.g. "zfs" or "ext4")
+///
+/// Unknown file systems are reported as "unknown (0x<magic>)".
+pub fn filesystem_type(path: &std::path::Path) -> Result<String, Error> {
+
+    let magic = filesystem_magic(path)?;
+
+    Ok(match filesystem_type_name(magic) {
+        Some(name) => name.to_string(),
+        None => format!("unknown (0x{:x})", magic),
+    })
+}
+
 #[api()]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all="lowercase")]
@@ -998,3 +1058,29 @@ pub fn get_fs_uuid(disk: &Disk) -> Result<String, Error> {
 
     bail!("get_fs_uuid failed - missing UUID");
 }
+
+#[test]
+fn test_filesystem_type_name() {
+    assert_eq!(filesystem_type_name(0xEF53), Some("ext4"));
+    assert_eq!(filesystem_type_name(0x2FC12FC1), Some("zfs"));
+    assert_eq!(filesystem_type_name(0x58465342), Some("xfs"));
+    assert_eq!(filesystem_type_name(0x12345678), None);
+
+    // also works for magics with the high bit set, where f_type is negative
+    assert_eq!(filesystem_type_name(0xF2F52010_u32 as i32 as libc::c_long as u32), Some("f2fs"));
+
+    let fs_type = filesystem_type(std::path::Path::new("/")).expect("statfs on / failed");
+    assert!(!fs_type.is_empty());
+}
+
+#[test]
+fn test_is_network_filesystem() {
+    for magic in &[0x6969, 0x517B, 0xFF534D42, 0xFE534D42, 0x00C36400] {
+        assert!(is_network_filesystem_magic(*magic), "magic 0x{:x}", magic);
+    }
+    for magic in &[0xEF53, 0x58465342, 0x2FC12FC1, 0x01021994, 0x65735546, 0x12345678] {
+        assert!(!is_network_filesystem_magic(*magic), "magic 0x{:x}", magic);
+    }
+
+    assert!(is_network_filesystem(std::path::Path::new("/")).is_ok());
+}
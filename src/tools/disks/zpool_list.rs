@@ -4,6 +4,8 @@ use crate::tools::nom::{
     multispace0, multispace1, notspace1, IResult,
 };
 
+use super::zpool_status::expand_tab_length;
+
 use nom::{
     bytes::complete::{take_while1, take_till, take_till1},
     combinator::{map_res, all_consuming, recognize, opt},
@@ -21,12 +23,71 @@ pub struct ZFSPoolUsage {
     pub frag: u64,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum ZFSVdevKind {
+    /// A leaf device
+    Device,
+    Mirror,
+    /// RaidZ with parity level (1-3)
+    RaidZ(u8),
+    DRaid,
+    Replacing,
+    Spare,
+}
+
+/// A vdev from the `zpool list -v` output
+#[derive(Debug, PartialEq)]
+pub struct ZFSVdev {
+    /// Device path, or group name like `mirror-0` or `raidz2-1`
+    pub name: String,
+    pub kind: ZFSVdevKind,
+    /// Member devices (groups only)
+    pub children: Vec<ZFSVdev>,
+}
+
+impl ZFSVdev {
+
+    fn new(name: String) -> Self {
+        let kind = vdev_group_kind(&name).unwrap_or(ZFSVdevKind::Device);
+        Self { name, kind, children: Vec::new() }
+    }
+
+    /// Returns all leaf device names
+    pub fn devices(&self) -> Vec<&str> {
+        if self.kind == ZFSVdevKind::Device {
+            return vec![self.name.as_str()];
+        }
+        self.children.iter().flat_map(|child| child.devices()).collect()
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct ZFSPoolInfo {
     pub name: String,
     pub health: String,
     pub usage: Option<ZFSPoolUsage>,
+    /// All devices (`/dev/...`), flattened from `vdevs`
     pub devices: Vec<String>,
+    pub vdevs: Vec<ZFSVdev>,
+}
+
+// Detect group lines like 'mirror', 'mirror-0', 'raidz2-1' or 'draid1:2d:4c:0s-0'
+fn vdev_group_kind(name: &str) -> Option<ZFSVdevKind> {
+    let base = match name.rfind('-') {
+        Some(pos) if name[pos+1..].chars().all(|c| c.is_ascii_digit()) => &name[..pos],
+        _ => name,
+    };
+
+    match base {
+        "mirror" => Some(ZFSVdevKind::Mirror),
+        "raidz" | "raidz1" => Some(ZFSVdevKind::RaidZ(1)),
+        "raidz2" => Some(ZFSVdevKind::RaidZ(2)),
+        "raidz3" => Some(ZFSVdevKind::RaidZ(3)),
+        "replacing" => Some(ZFSVdevKind::Replacing),
+        "spare" => Some(ZFSVdevKind::Spare),
+        _ if base.starts_with("draid") => Some(ZFSVdevKind::DRaid),
+        _ => None,
+    }
 }
 
 
@@ -48,14 +109,55 @@ fn parse_optional_f64(i: &str) -> IResult<&str, Option<f64>> {
     }
 }
 
-fn parse_pool_device(i: &str) -> IResult<&str, String> {
-    let (i, (device, _, _rest)) = tuple((
-        preceded(multispace1, take_till1(|c| c == ' ' || c == '\t')),
+// returns the indentation and the device name
+fn parse_pool_device(i: &str) -> IResult<&str, (&str, String)> {
+    let (i, (indent, device, _, _rest)) = tuple((
+        multispace1,
+        take_till1(|c| c == ' ' || c == '\t'),
         multispace1,
         preceded(take_till(|c| c == '\n'), char('\n')),
     ))(i)?;
 
-    Ok((i, device.to_string()))
+    Ok((i, (indent, device.to_string())))
+}
+
+// Build the vdev tree from the (indent, name) list
+//
+// Group members are indented deeper than the group itself, so
+// 'zpool_list' does not use '-H' for verbose output. Note: With '-H',
+// all vdevs use the same (tab) indentation, so we cannot distinguish
+// group members from following top level devices. We simply add them
+// to the last group in that case.
+fn build_vdev_tree(list: Vec<(&str, String)>) -> Vec<ZFSVdev> {
+    let mut vdevs: Vec<ZFSVdev> = Vec::new();
+    let mut group_indent = None;
+
+    for (indent, name) in list {
+        let vdev = ZFSVdev::new(name);
+
+        if vdev.kind != ZFSVdevKind::Device {
+            group_indent = Some(indent);
+            vdevs.push(vdev);
+            continue;
+        }
+
+        let is_member = |group_indent: &str| {
+            let (len, group_len) = (expand_tab_length(indent), expand_tab_length(group_indent));
+            len > group_len || (len == group_len && indent.contains('\t'))
+        };
+
+        match (group_indent, vdevs.last_mut()) {
+            (Some(group_indent), Some(group)) if is_member(group_indent) => {
+                group.children.push(vdev);
+            }
+            _ => {
+                group_indent = None;
+                vdevs.push(vdev);
+            }
+        }
+    }
+
+    vdevs
 }
 
 fn parse_zpool_list_header(i: &str) -> IResult<&str, ZFSPoolInfo> {
@@ -84,6 +186,7 @@ fn parse_zpool_list_header(i: &str) -> IResult<&str, ZFSPoolInfo> {
             health: health.into(),
             usage: Some(ZFSPoolUsage { size, alloc, free, frag, dedup }),
             devices: Vec::new(),
+            vdevs: Vec::new(),
         }
     } else {
          ZFSPoolInfo {
@@ -91,6 +194,7 @@ fn parse_zpool_list_header(i: &str) -> IResult<&str, ZFSPoolInfo> {
              health: health.into(),
              usage: None,
              devices: Vec::new(),
+             vdevs: Vec::new(),
          }
     };
 
@@ -102,9 +206,13 @@ fn parse_zpool_list_item(i: &str) -> IResult<&str, ZFSPoolInfo> {
     let (i, mut stat) = parse_zpool_list_header(i)?;
     let (i, devices) = many0(parse_pool_device)(i)?;
 
-    for device_path in devices.into_iter().filter(|n| n.starts_with("/dev/")) {
-        stat.devices.push(device_path);
-    }
+    stat.vdevs = build_vdev_tree(devices);
+
+    stat.devices = stat.vdevs.iter()
+        .flat_map(|vdev| vdev.devices())
+        .filter(|name| name.starts_with("/dev/"))
+        .map(String::from)
+        .collect();
 
     let (i, _) = many0(tuple((multispace0, char('\n'))))(i)?; // skip empty lines
 
@@ -113,8 +221,9 @@ fn parse_zpool_list_item(i: &str) -> IResult<&str, ZFSPoolInfo> {
 
 /// Parse zpool list output
 ///
-/// Note: This does not reveal any details on how the pool uses the devices, because
-/// the zpool list output format is not really defined...
+/// Vdev groups (mirror, raidz, ...) are detected by name, see
+/// `build_vdev_tree` for limitations. Note: the zpool list output
+/// format is not really defined...
 fn parse_zpool_list(i: &str) -> Result<Vec<ZFSPoolInfo>, Error> {
     match all_consuming(many0(parse_zpool_list_item))(i) {
         Err(nom::Err::Error(err)) |
@@ -138,18 +247,43 @@ pub fn zpool_list(pool: Option<String>, verbose: bool) -> Result<Vec<ZFSPoolInfo
     // and maybe other things.
 
     let mut command = std::process::Command::new("zpool");
-    command.args(&["list", "-H", "-p", "-P"]);
+    command.args(&["list", "-p", "-P"]);
 
     // Note: We do not use -o to define output properties, because zpool command ignores
     // that completely for special vdevs and devices
 
-    if verbose { command.arg("-v"); }
+    // Note: '-H' replaces the vdev indentation with a single tab, which we
+    // need to build the vdev tree - so only use it without devices
+    if verbose { command.arg("-v"); } else { command.arg("-H"); }
 
     if let Some(pool) = pool { command.arg(pool); }
 
     let output = crate::tools::run_command(command, None)?;
 
-    parse_zpool_list(&output)
+    parse_zpool_list(strip_zpool_list_column_header(&output))
+}
+
+// without '-H', zpool list prints a column header line first
+fn strip_zpool_list_column_header(output: &str) -> &str {
+    if output.starts_with("NAME ") {
+        match output.find('\n') {
+            Some(pos) => &output[pos + 1..],
+            None => "",
+        }
+    } else {
+        output
+    }
+}
+
+#[cfg(test)]
+fn test_device(name: &str) -> ZFSVdev {
+    ZFSVdev { name: name.to_string(), kind: ZFSVdevKind::Device, children: Vec::new() }
+}
+
+#[cfg(test)]
+fn test_group(name: &str, kind: ZFSVdevKind, devices: &[&str]) -> ZFSVdev {
+    let children = devices.iter().map(|name| test_device(name)).collect();
+    ZFSVdev { name: name.to_string(), kind, children }
 }
 
 #[test]
@@ -169,6 +303,7 @@ fn test_zfs_parse_list() -> Result<(), Error> {
             name: "btest".to_string(),
             health: "ONLINE".to_string(),
             devices: Vec::new(),
+            vdevs: Vec::new(),
             usage: Some(ZFSPoolUsage {
                 size: 427349245952,
                 alloc: 405504,
@@ -196,6 +331,7 @@ logs
             name: String::from("rpool"),
             health: String::from("ONLINE"),
             devices: vec![String::from("/dev/disk/by-id/ata-Crucial_CT500MX200SSD1_154210EB4078-part3")],
+            vdevs: vec![test_device("/dev/disk/by-id/ata-Crucial_CT500MX200SSD1_154210EB4078-part3")],
             usage: Some(ZFSPoolUsage {
                 size: 535260299264,
                 alloc:402852388864 ,
@@ -208,12 +344,14 @@ logs
             name: String::from("special"),
             health: String::from("-"),
             devices: vec![String::from("/dev/sda2")],
+            vdevs: vec![test_device("/dev/sda2")],
             usage: None,
         },
         ZFSPoolInfo {
             name: String::from("logs"),
             health: String::from("-"),
             devices: vec![String::from("/dev/sda3")],
+            vdevs: vec![test_device("/dev/sda3")],
             usage: None,
         },
     ];
@@ -249,13 +387,18 @@ logs               -      -      -        -         -      -      -      -  -
                 String::from("/dev/sda2"),
                 String::from("/dev/sda3"),
                 String::from("/dev/sda4"),
-            ]
+            ],
+            vdevs: vec![
+                test_group("mirror", ZFSVdevKind::Mirror, &["/dev/sda1", "/dev/sda2"]),
+                test_group("mirror", ZFSVdevKind::Mirror, &["/dev/sda3", "/dev/sda4"]),
+            ],
         },
         ZFSPoolInfo {
             name: String::from("logs"),
             health: String::from("-"),
             usage: None,
             devices: vec![String::from("/dev/sda5")],
+            vdevs: vec![test_device("/dev/sda5")],
         },
     ];
 
@@ -281,7 +424,10 @@ b.test	427349245952	761856	427348484096	-	-	0	0	1.00	ONLINE	-
             }),
             devices: vec![
                 String::from("/dev/sda1"),
-            ]
+            ],
+            vdevs: vec![
+                test_group("mirror", ZFSVdevKind::Mirror, &["/dev/sda1"]),
+            ],
         },
     ];
 
@@ -289,3 +435,50 @@ b.test	427349245952	761856	427348484096	-	-	0	0	1.00	ONLINE	-
 
     Ok(())
 }
+
+#[test]
+fn test_zfs_parse_list_vdev_tree() -> Result<(), Error> {
+
+    // 'zpool list -v -H -p -P' output (all vdevs use the same indentation)
+    let output = "\
+tank	1996421627904	1048576	1996420579328	-	-	0	0	1.00	ONLINE	-
+	raidz2-0	1996421627904	1048576	1996420579328	-	-	0	0	-	ONLINE
+	/dev/sdb1	-	-	-	-	-	-	-	-	ONLINE
+	/dev/sdc1	-	-	-	-	-	-	-	-	ONLINE
+	/dev/sdd1	-	-	-	-	-	-	-	-	ONLINE
+	/dev/sde1	-	-	-	-	-	-	-	-	ONLINE
+	mirror-1	998210813952	0	998210813952	-	-	0	0	-	ONLINE
+	/dev/sdf1	-	-	-	-	-	-	-	-	ONLINE
+	/dev/sdg1	-	-	-	-	-	-	-	-	ONLINE
+";
+
+    let data = parse_zpool_list(&output)?;
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].vdevs, vec![
+        test_group("raidz2-0", ZFSVdevKind::RaidZ(2), &["/dev/sdb1", "/dev/sdc1", "/dev/sdd1", "/dev/sde1"]),
+        test_group("mirror-1", ZFSVdevKind::Mirror, &["/dev/sdf1", "/dev/sdg1"]),
+    ]);
+    assert_eq!(data[0].devices, vec![
+        "/dev/sdb1", "/dev/sdc1", "/dev/sdd1", "/dev/sde1", "/dev/sdf1", "/dev/sdg1",
+    ]);
+
+    // without '-H' the indentation tells group members from top level devices
+    let output = "\
+NAME         SIZE  ALLOC   FREE  CKPOINT  EXPANDSZ   FRAG    CAP  DEDUP    HEALTH  ALTROOT
+tank    1996421627904  1048576  1996420579328  -  -  0  0  1.00  ONLINE  -
+  raidz1-0    998210813952  1048576  998209765376  -  -  0  0  -  ONLINE
+    /dev/sdb1  -  -  -  -  -  -  -  -  ONLINE
+    /dev/sdc1  -  -  -  -  -  -  -  -  ONLINE
+    /dev/sdd1  -  -  -  -  -  -  -  -  ONLINE
+  /dev/sde1    998210813952  0  998210813952  -  -  0  0  -  ONLINE
+";
+
+    let data = parse_zpool_list(strip_zpool_list_column_header(&output))?;
+    assert_eq!(data[0].vdevs, vec![
+        test_group("raidz1-0", ZFSVdevKind::RaidZ(1), &["/dev/sdb1", "/dev/sdc1", "/dev/sdd1"]),
+        test_device("/dev/sde1"),
+    ]);
+    assert_eq!(data[0].vdevs[0].devices(), vec!["/dev/sdb1", "/dev/sdc1", "/dev/sdd1"]);
+
+    Ok(())
+}
@@ -34,7 +34,7 @@ pub struct ZFSPoolVDevState {
     pub msg: Option<String>,
 }
 
-fn expand_tab_length(input: &str) -> usize {
+pub(crate) fn expand_tab_length(input: &str) -> usize {
     input.chars().map(|c| if c == '\t' { 8 } else { 1 }).sum()
 }
 
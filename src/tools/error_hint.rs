@@ -0,0 +1,85 @@
+//! Append actionable hints to common errors
+//!
+//! This is meant to be used at the CLI boundary only (see
+//! `proxmox-backup-client`), API callers should get the unmodified error.
+
+use anyhow::{format_err, Error};
+
+// (error message pattern, hint)
+const ERROR_HINTS: &[(&str, &str)] = &[
+    (
+        "permission check failed",
+        "check the permissions of the user with 'proxmox-backup-manager acl list'",
+    ),
+    (
+        "no permissions on ",
+        "check the permissions of the user with 'proxmox-backup-manager acl list'",
+    ),
+    (
+        "Permission denied (os error 13)",
+        "this command needs to be run as root",
+    ),
+    (
+        "authentication failed",
+        "try logging in with 'proxmox-backup-client login'",
+    ),
+    (
+        "Connection refused",
+        "check that the proxmox-backup-proxy service is running",
+    ),
+];
+
+/// Returns the hint for the first known pattern contained in 'msg'
+pub fn error_hint(msg: &str) -> Option<&'static str> {
+    ERROR_HINTS
+        .iter()
+        .find(|(pattern, _)| msg.contains(pattern))
+        .map(|(_, hint)| *hint)
+}
+
+/// Append a hint to errors matching a known pattern, other errors are
+/// returned unchanged
+///
+/// The hint is added as context, so the original error stays available as
+/// its source.
+pub fn with_hint(err: Error) -> Error {
+    // use the full error chain, the pattern may be part of a cause
+    let msg = format!("{:#}", err);
+    match error_hint(&msg) {
+        Some(hint) => {
+            let msg = format!("{}\nhint: {}", err, hint);
+            err.context(msg)
+        }
+        None => err,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_hint() {
+        let err = with_hint(format_err!("permission check failed"));
+        assert_eq!(
+            err.to_string(),
+            "permission check failed\nhint: check the permissions of the user with 'proxmox-backup-manager acl list'",
+        );
+        assert_eq!(err.root_cause().to_string(), "permission check failed");
+
+        let io_err = std::io::Error::from_raw_os_error(libc::ECONNREFUSED);
+        let err = with_hint(Error::from(io_err));
+        assert!(err.to_string().ends_with("\nhint: check that the proxmox-backup-proxy service is running"));
+        assert!(err.downcast_ref::<std::io::Error>().is_some());
+
+        let err = with_hint(format_err!("no permissions on /datastore/store1"));
+        assert!(err.to_string().ends_with("acl list'"));
+
+        let err = with_hint(format_err!("error trying to connect: tcp connect error: Connection refused (os error 111)"));
+        assert!(err.to_string().ends_with("\nhint: check that the proxmox-backup-proxy service is running"));
+
+        // unknown errors are returned unchanged
+        let err = with_hint(format_err!("user 'foo@pbs' does not exist."));
+        assert_eq!(err.to_string(), "user 'foo@pbs' does not exist.");
+    }
+}
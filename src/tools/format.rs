@@ -1,6 +1,37 @@
-use anyhow::{Error};
+use anyhow::{format_err, Error};
 use serde_json::Value;
 
+use proxmox::api::cli::{ENV_VAR_PROXMOX_OUTPUT_FORMAT, OUTPUT_FORMAT};
+use proxmox::api::schema::parse_simple_value;
+
+/// Get the output format for CLI commands.
+///
+/// An explicit `output-format` parameter takes precedence over the
+/// `PROXMOX_OUTPUT_FORMAT` environment variable, which in turn takes
+/// precedence over the default (`text`). Unlike the `proxmox::api::cli`
+/// version, invalid environment values are rejected instead of being
+/// passed on to the formatter.
+pub fn get_output_format(param: &Value) -> Result<String, Error> {
+    let env_format = std::env::var(ENV_VAR_PROXMOX_OUTPUT_FORMAT).ok();
+    output_format_with_default(param, env_format.as_deref())
+}
+
+fn output_format_with_default(param: &Value, env_format: Option<&str>) -> Result<String, Error> {
+    if let Some(format) = param["output-format"].as_str() {
+        return Ok(format.to_string());
+    }
+
+    match env_format {
+        Some(format) => {
+            parse_simple_value(format, &OUTPUT_FORMAT).map_err(|err| {
+                format_err!("invalid {} '{}' - {}", ENV_VAR_PROXMOX_OUTPUT_FORMAT, format, err)
+            })?;
+            Ok(format.to_string())
+        }
+        None => Ok(String::from("text")),
+    }
+}
+
 pub fn strip_server_file_extension(name: &str) -> String {
 
     if name.ends_with(".didx") || name.ends_with(".fidx") || name.ends_with(".blob") {
@@ -147,3 +178,21 @@ fn correct_byte_convert() {
     assert_eq!(convert((1<<30) + 103 * (1<<20)), "1.10 GiB");
     assert_eq!(convert((2<<50) + 500 * (1<<40)), "2.49 PiB");
 }
+
+#[test]
+fn test_output_format_precedence() -> Result<(), Error> {
+    use serde_json::json;
+
+    assert_eq!(output_format_with_default(&json!({}), None)?, "text");
+    assert_eq!(output_format_with_default(&json!({}), Some("json"))?, "json");
+    assert_eq!(output_format_with_default(&json!({}), Some("json-pretty"))?, "json-pretty");
+
+    // the CLI parameter overrides the environment
+    let param = json!({ "output-format": "text" });
+    assert_eq!(output_format_with_default(&param, Some("json"))?, "text");
+    assert_eq!(output_format_with_default(&param, Some("invalid"))?, "text");
+
+    assert!(output_format_with_default(&json!({}), Some("invalid")).is_err());
+
+    Ok(())
+}
@@ -3,8 +3,9 @@
 use std::borrow::{Borrow, BorrowMut};
 use std::ops::{Deref, DerefMut};
 use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
 
-use anyhow::{format_err, Error};
+use anyhow::{bail, format_err, Error};
 use nix::dir;
 use nix::dir::Dir;
 use nix::fcntl::OFlag;
@@ -312,3 +313,108 @@ fn do_lock_dir_noblock(
 
     Ok(handle)
 }
+
+/// Create (or truncate) a file for reading and writing.
+///
+/// If `mode` is set, new files are created with it (so they are never more permissive), and
+/// the permissions are explicitly set to it afterwards, so they do not depend on the process
+/// umask and also apply to existing files.
+pub fn create_file_with_mode(path: &Path, mode: Option<u32>) -> Result<std::fs::File, Error> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).truncate(true).read(true).write(true);
+    if let Some(mode) = mode {
+        options.mode(mode);
+    }
+
+    let file = options.open(path)
+        .map_err(|err| format_err!("unable to create {:?} - {}", path, err))?;
+
+    if let Some(mode) = mode {
+        file.set_permissions(std::fs::Permissions::from_mode(mode))
+            .map_err(|err| format_err!("unable to set permissions on {:?} - {}", path, err))?;
+    }
+
+    Ok(file)
+}
+
+/// Canonicalize `candidate` and make sure the result is located below `base`.
+///
+/// Relative candidates are interpreted relative to `base`. This resolves `..` components and
+/// symlinks, so it can be used to reject client supplied names escaping a directory. Both paths
+/// need to exist.
+pub fn ensure_path_within(base: &Path, candidate: &Path) -> Result<PathBuf, Error> {
+    let base = std::fs::canonicalize(base)
+        .map_err(|err| format_err!("unable to canonicalize {:?} - {}", base, err))?;
+
+    // keep the io error, so callers can check for missing files
+    let path = std::fs::canonicalize(base.join(candidate)).map_err(|err| {
+        let msg = format!("unable to canonicalize {:?} - {}", candidate, err);
+        Error::from(err).context(msg)
+    })?;
+
+    if !path.starts_with(&base) {
+        bail!("path {:?} is outside of {:?}", candidate, base);
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_create_file_with_mode() -> Result<(), Error> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = nix::unistd::mkdtemp("/tmp/pbs-file-mode-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let path = tmp.join("index.fidx");
+            let file = create_file_with_mode(&path, Some(0o640))?;
+            assert_eq!(file.metadata()?.permissions().mode() & 0o7777, 0o640);
+
+            // existing files get the mode applied as well
+            let file = create_file_with_mode(&path, Some(0o600))?;
+            assert_eq!(file.metadata()?.permissions().mode() & 0o7777, 0o600);
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&tmp)?;
+        result
+    }
+
+    #[test]
+    fn test_ensure_path_within() -> Result<(), Error> {
+        let tmp = nix::unistd::mkdtemp("/tmp/pbs-path-within-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let base = tmp.join("base");
+            std::fs::create_dir(&base)?;
+            std::fs::write(base.join("index.didx"), b"")?;
+            std::fs::write(tmp.join("secret"), b"")?;
+
+            let path = ensure_path_within(&base, Path::new("index.didx"))?;
+            assert_eq!(path, std::fs::canonicalize(base.join("index.didx"))?);
+
+            assert!(ensure_path_within(&base, Path::new("../secret")).is_err());
+            assert!(ensure_path_within(&base, &tmp.join("secret")).is_err());
+
+            std::os::unix::fs::symlink("../secret", base.join("link"))?;
+            assert!(ensure_path_within(&base, Path::new("link")).is_err());
+
+            // missing files keep the io error kind
+            let err = ensure_path_within(&base, Path::new("missing.didx")).unwrap_err();
+            let io_err = err.downcast_ref::<std::io::Error>().unwrap();
+            assert_eq!(io_err.kind(), std::io::ErrorKind::NotFound);
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&tmp)?;
+        result
+    }
+}
@@ -3,7 +3,7 @@ use std::task::{Context, Poll};
 use std::os::unix::io::AsRawFd;
 use std::collections::HashMap;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use hyper::{Uri, Body};
 use hyper::client::{Client, HttpConnector};
@@ -22,6 +22,7 @@ use tokio_openssl::SslStream;
 
 use crate::tools::{
     async_io::MaybeTlsStream,
+    rate_limiter::{RateLimitedStream, RateLimiter},
     socket::{
         set_tcp_keepalive,
         PROXMOX_BACKUP_TCP_KEEPALIVE_TIME,
@@ -231,6 +232,7 @@ pub struct HttpsConnector {
     connector: HttpConnector,
     ssl_connector: Arc<SslConnector>,
     proxy: Option<ProxyConfig>,
+    read_limiter: Option<Arc<Mutex<RateLimiter>>>,
 }
 
 impl HttpsConnector {
@@ -240,6 +242,7 @@ impl HttpsConnector {
             connector,
             ssl_connector: Arc::new(ssl_connector),
             proxy: None,
+            read_limiter: None,
         }
     }
 
@@ -247,13 +250,18 @@ impl HttpsConnector {
         self.proxy = Some(proxy);
     }
 
+    /// Limit the download rate (bytes/second), shared by all connections.
+    pub fn set_read_limiter(&mut self, limiter: Option<Arc<Mutex<RateLimiter>>>) {
+        self.read_limiter = limiter;
+    }
+
     async fn secure_stream(
-        tcp_stream: TcpStream,
+        tcp_stream: RateLimitedStream<TcpStream>,
         ssl_connector: &SslConnector,
         host: &str,
-    ) -> Result<MaybeTlsStream<TcpStream>, Error> {
+    ) -> Result<MaybeTlsStream<RateLimitedStream<TcpStream>>, Error> {
         let config = ssl_connector.configure()?;
-        let mut conn: SslStream<TcpStream> = SslStream::new(config.into_ssl(host)?, tcp_stream)?;
+        let mut conn: SslStream<RateLimitedStream<TcpStream>> = SslStream::new(config.into_ssl(host)?, tcp_stream)?;
         Pin::new(&mut conn).connect().await?;
         Ok(MaybeTlsStream::Secured(conn))
     }
@@ -302,7 +310,7 @@ impl HttpsConnector {
 }
 
 impl hyper::service::Service<Uri> for HttpsConnector {
-    type Response = MaybeTlsStream<TcpStream>;
+    type Response = MaybeTlsStream<RateLimitedStream<TcpStream>>;
     type Error = Error;
     #[allow(clippy::type_complexity)]
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
@@ -324,6 +332,7 @@ impl hyper::service::Service<Uri> for HttpsConnector {
             }
         };
         let port = dst.port_u16().unwrap_or(if is_https { 443 } else { 80 });
+        let read_limiter = self.read_limiter.clone();
 
         if let Some(ref proxy) = self.proxy {
 
@@ -345,13 +354,15 @@ impl hyper::service::Service<Uri> for HttpsConnector {
             if use_connect {
                 async move {
 
-                    let mut tcp_stream = connector
+                    let tcp_stream = connector
                         .call(proxy_uri)
                         .await
                         .map_err(|err| format_err!("error connecting to {} - {}", proxy_url, err))?;
 
                     let _ = set_tcp_keepalive(tcp_stream.as_raw_fd(), PROXMOX_BACKUP_TCP_KEEPALIVE_TIME);
 
+                    let mut tcp_stream = RateLimitedStream::new(tcp_stream, read_limiter);
+
                     let mut connect_request = format!("CONNECT {0}:{1} HTTP/1.1\r\n", host, port);
                     if let Some(authorization) = authorization {
                         connect_request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
@@ -378,7 +389,7 @@ impl hyper::service::Service<Uri> for HttpsConnector {
 
                    let _ = set_tcp_keepalive(tcp_stream.as_raw_fd(), PROXMOX_BACKUP_TCP_KEEPALIVE_TIME);
 
-                   Ok(MaybeTlsStream::Proxied(tcp_stream))
+                   Ok(MaybeTlsStream::Proxied(RateLimitedStream::new(tcp_stream, read_limiter)))
                }.boxed()
             }
         } else {
@@ -391,6 +402,8 @@ impl hyper::service::Service<Uri> for HttpsConnector {
 
                 let _ = set_tcp_keepalive(tcp_stream.as_raw_fd(), PROXMOX_BACKUP_TCP_KEEPALIVE_TIME);
 
+                let tcp_stream = RateLimitedStream::new(tcp_stream, read_limiter);
+
                 if is_https {
                     Self::secure_stream(tcp_stream, &ssl_connector, &host).await
                 } else {
@@ -0,0 +1,122 @@
+//! Helpers for blocking I/O
+
+use std::io::{self, ErrorKind, Read};
+use std::time::{Duration, Instant};
+
+// delay range between retries of reads which would block
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Like `Read::read_exact`, but gives up once `timeout` has elapsed.
+///
+/// The deadline is checked between the individual `read` calls, so the underlying reader must not
+/// block forever on its own - for sockets, set a read timeout (e.g. `set_read_timeout`) so
+/// stalled reads return `WouldBlock`/`TimedOut`, which are retried (with an increasing delay, so
+/// non-blocking readers do not busy-loop) until the deadline passes.
+///
+/// Returns an error of kind `ErrorKind::TimedOut` if the buffer could not be filled in time.
+pub fn read_exact_timeout<R: Read>(
+    reader: &mut R,
+    mut buf: &mut [u8],
+    timeout: Duration,
+) -> Result<(), io::Error> {
+    let deadline = Instant::now() + timeout;
+    let mut retry_delay = MIN_RETRY_DELAY;
+
+    while !buf.is_empty() {
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                format!("read_exact_timeout: timed out after {:?}", timeout),
+            ));
+        }
+        match reader.read(buf) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "failed to fill whole buffer",
+                ));
+            }
+            Ok(n) => {
+                buf = &mut buf[n..];
+                retry_delay = MIN_RETRY_DELAY;
+            }
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => (),
+            Err(ref err)
+                if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut =>
+            {
+                std::thread::sleep(retry_delay.min(deadline - now));
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns one byte per read, sleeping `delay` before each.
+    struct SlowReader {
+        delay: Duration,
+    }
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            std::thread::sleep(self.delay);
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = 0xaa;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_read_exact_timeout() {
+        let mut reader = SlowReader { delay: Duration::from_millis(20) };
+        let mut buf = [0u8; 100];
+        let err = read_exact_timeout(&mut reader, &mut buf, Duration::from_millis(100))
+            .expect_err("slow reader should time out");
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        let mut reader = SlowReader { delay: Duration::from_millis(0) };
+        let mut buf = [0u8; 100];
+        read_exact_timeout(&mut reader, &mut buf, Duration::from_secs(5))
+            .expect("fast reader should succeed");
+        assert!(buf.iter().all(|b| *b == 0xaa));
+
+        let mut reader: &[u8] = &[1, 2, 3];
+        let mut buf = [0u8; 4];
+        let err = read_exact_timeout(&mut reader, &mut buf, Duration::from_secs(5))
+            .expect_err("short input should fail");
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    /// Never has data, like a non-blocking socket without incoming data.
+    struct BlockingReader {
+        reads: usize,
+    }
+
+    impl Read for BlockingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            Err(io::Error::from(ErrorKind::WouldBlock))
+        }
+    }
+
+    #[test]
+    fn test_read_exact_timeout_would_block() {
+        let mut reader = BlockingReader { reads: 0 };
+        let mut buf = [0u8; 10];
+        let err = read_exact_timeout(&mut reader, &mut buf, Duration::from_millis(200))
+            .expect_err("reader without data should time out");
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        // retries are delayed instead of spinning
+        assert!(reader.reads < 50, "too many reads: {}", reader.reads);
+    }
+}
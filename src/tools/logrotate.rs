@@ -1,17 +1,21 @@
 use std::path::{Path, PathBuf};
 use std::fs::{File, rename};
 use std::os::unix::io::{FromRawFd, IntoRawFd};
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 
-use anyhow::{bail, Error};
+use anyhow::{bail, format_err, Error};
 use nix::unistd;
 
 use proxmox::tools::fs::{CreateOptions, make_tmp_file};
 
+/// Highest compression level accepted by zstd (0 selects the zstd default)
+pub const ZSTD_MAX_COMPRESSION_LEVEL: i32 = 22;
+
 /// Used for rotating log files and iterating over them
 pub struct LogRotate {
     base_path: PathBuf,
     compress: bool,
+    compression_level: i32,
 }
 
 impl LogRotate {
@@ -24,12 +28,28 @@ impl LogRotate {
             Some(Self {
                 base_path: path.as_ref().to_path_buf(),
                 compress,
+                compression_level: 0,
             })
         } else {
             None
         }
     }
 
+    /// Sets the zstd compression level used for rotated files
+    ///
+    /// Valid levels are 0 (zstd default) up to `ZSTD_MAX_COMPRESSION_LEVEL`.
+    pub fn with_level(mut self, level: i32) -> Result<Self, Error> {
+        if level < 0 || level > ZSTD_MAX_COMPRESSION_LEVEL {
+            bail!(
+                "invalid zstd compression level {} (expected 0 - {})",
+                level,
+                ZSTD_MAX_COMPRESSION_LEVEL,
+            );
+        }
+        self.compression_level = level;
+        Ok(self)
+    }
+
     /// Returns an iterator over the logrotated file names that exist
     pub fn file_names(&self) -> LogRotateFileNames {
         LogRotateFileNames {
@@ -46,11 +66,24 @@ impl LogRotate {
         }
     }
 
-    fn compress(source_path: &PathBuf, target_path: &PathBuf, options: &CreateOptions) -> Result<(), Error> {
+    /// Returns an iterator over the logrotated file handles, including
+    /// their rotation index (0 for the live file) and file name
+    pub fn files_with_index(&self) -> LogRotateIndexedFiles {
+        LogRotateIndexedFiles {
+            file_names: self.file_names(),
+        }
+    }
+
+    fn compress(
+        source_path: &PathBuf,
+        target_path: &PathBuf,
+        options: &CreateOptions,
+        level: i32,
+    ) -> Result<(), Error> {
         let mut source = File::open(source_path)?;
         let (fd, tmp_path) = make_tmp_file(target_path, options.clone())?;
         let target = unsafe { File::from_raw_fd(fd.into_raw_fd()) };
-        let mut encoder = match zstd::stream::write::Encoder::new(target, 0) {
+        let mut encoder = match zstd::stream::write::Encoder::new(target, level) {
             Ok(encoder) => encoder,
             Err(err) => {
                 let _ = unistd::unlink(&tmp_path);
@@ -107,7 +140,7 @@ impl LogRotate {
                 && filenames[i].extension() != Some(std::ffi::OsStr::new("zst"))
                 && filenames[i+1].extension() == Some(std::ffi::OsStr::new("zst"))
             {
-                Self::compress(&filenames[i], &filenames[i+1], &options)?;
+                Self::compress(&filenames[i], &filenames[i+1], &options, self.compression_level)?;
             } else {
                 rename(&filenames[i], &filenames[i+1])?;
             }
@@ -124,25 +157,20 @@ impl LogRotate {
         Ok(())
     }
 
-    pub fn rotate(
+    /// Rotates the files (see `do_rotate`) only if the base file is bigger than 'max_size'
+    ///
+    /// A missing base file needs no rotation. Returns true if the files were rotated.
+    pub fn rotate_if_bigger(
         &mut self,
         max_size: u64,
-        options: Option<CreateOptions>,
-        max_files: Option<usize>
+        options: CreateOptions,
+        max_files: Option<usize>,
     ) -> Result<bool, Error> {
 
-        let options = match options {
-            Some(options) => options,
-            None => {
-                let backup_user = crate::backup::backup_user()?;
-                CreateOptions::new().owner(backup_user.uid).group(backup_user.gid)
-            },
-        };
-
         let metadata = match self.base_path.metadata() {
             Ok(metadata) => metadata,
             Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
-            Err(err) =>  bail!("unable to open task archive - {}", err),
+            Err(err) =>  bail!("unable to stat {:?} - {}", self.base_path, err),
         };
 
         if metadata.len() > max_size {
@@ -202,13 +230,197 @@ impl Iterator for LogRotateFiles {
 
     fn next(&mut self) -> Option<Self::Item> {
         let filename = self.file_names.next()?;
-        let file = File::open(&filename).ok()?;
+        open_rotated_file(&filename)
+    }
+}
 
-        if filename.extension() == Some(std::ffi::OsStr::new("zst")) {
-            let encoder = zstd::stream::read::Decoder::new(file).ok()?;
-            return Some(Box::new(encoder));
-        }
+/// Iterator over logrotated files, yielding the rotation index, the file
+/// name and a boxed reader, from the newest to the oldest file
+pub struct LogRotateIndexedFiles {
+    file_names: LogRotateFileNames,
+}
+
+impl Iterator for LogRotateIndexedFiles {
+    type Item = (usize, PathBuf, Box<dyn Read + Send>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.file_names.count;
+        let filename = self.file_names.next()?;
+        let reader = open_rotated_file(&filename)?;
+        Some((index, filename, reader))
+    }
+}
+
+// opens a rotated file, transparently decompressing '.zst' files
+fn open_rotated_file(filename: &Path) -> Option<Box<dyn Read + Send>> {
+    let file = File::open(filename).ok()?;
+
+    if filename.extension() == Some(std::ffi::OsStr::new("zst")) {
+        let encoder = zstd::stream::read::Decoder::new(file).ok()?;
+        return Some(Box::new(encoder));
+    }
+
+    Some(Box::new(file))
+}
+
+/// Returns the lines of all logrotated files of 'path', from the oldest
+/// rotated file up to the end of the live file
+///
+/// '.zst' files are decompressed transparently (if 'compress' is set they
+/// are also searched for, see `LogRotate::new`). Files are opened lazily,
+/// so only one file is open at a time.
+pub fn read_full_log_chronological<P: AsRef<Path>>(
+    path: P,
+    compress: bool,
+) -> Result<impl Iterator<Item = Result<String, Error>>, Error> {
+    let logrotate = LogRotate::new(path.as_ref(), compress)
+        .ok_or_else(|| format_err!("invalid log file path {:?}", path.as_ref()))?;
+
+    let mut file_names: Vec<PathBuf> = logrotate.file_names().collect();
+    file_names.reverse();
+
+    Ok(file_names.into_iter().flat_map(|filename| {
+        let lines: Box<dyn Iterator<Item = Result<String, Error>>> = match open_rotated_file(&filename) {
+            Some(reader) => Box::new(BufReader::new(reader).lines().map(move |line| {
+                line.map_err(|err| format_err!("reading {:?} failed - {}", filename, err))
+            })),
+            None => Box::new(std::iter::once(Err(format_err!("unable to open {:?}", filename)))),
+        };
+        lines
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rotate_if_bigger() -> Result<(), Error> {
+        let tmp = nix::unistd::mkdtemp("/tmp/pbs-logrotate-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let base = tmp.join("test.log");
+            let mut logrotate = LogRotate::new(&base, true).unwrap();
+
+            // missing file
+            assert!(!logrotate.rotate_if_bigger(10, CreateOptions::new(), None)?);
+
+            std::fs::write(&base, b"0123456789")?;
+            assert!(!logrotate.rotate_if_bigger(10, CreateOptions::new(), None)?);
+            assert_eq!(logrotate.file_names().count(), 1);
+
+            std::fs::write(&base, b"0123456789a")?;
+            assert!(logrotate.rotate_if_bigger(10, CreateOptions::new(), None)?);
+            assert!(!base.exists());
+            assert_eq!(std::fs::read(tmp.join("test.log.1"))?, b"0123456789a");
+
+            std::fs::write(&base, b"0123456789ab")?;
+            assert!(logrotate.rotate_if_bigger(10, CreateOptions::new(), None)?);
+            assert!(tmp.join("test.log.2.zst").is_file());
+            assert_eq!(std::fs::read(tmp.join("test.log.1"))?, b"0123456789ab");
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&tmp)?;
+        result
+    }
+
+    #[test]
+    fn test_files_with_index() -> Result<(), Error> {
+        let tmp = nix::unistd::mkdtemp("/tmp/pbs-logrotate-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let base = tmp.join("test.log");
+            let mut logrotate = LogRotate::new(&base, true).unwrap();
+
+            for content in &["oldest", "middle", "newest"] {
+                std::fs::write(&base, content)?;
+                logrotate.do_rotate(CreateOptions::new(), None)?;
+            }
+            std::fs::write(&base, "live")?;
+
+            let mut files = Vec::new();
+            for (index, path, mut reader) in logrotate.files_with_index() {
+                let mut content = String::new();
+                reader.read_to_string(&mut content)?;
+                files.push((index, path, content));
+            }
+
+            assert_eq!(files, vec![
+                (0, base.clone(), "live".to_string()),
+                (1, tmp.join("test.log.1"), "newest".to_string()),
+                (2, tmp.join("test.log.2.zst"), "middle".to_string()),
+                (3, tmp.join("test.log.3.zst"), "oldest".to_string()),
+            ]);
+
+            assert_eq!(logrotate.files().count(), 4);
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&tmp)?;
+        result
+    }
+
+    #[test]
+    fn test_compression_level() -> Result<(), Error> {
+        assert!(LogRotate::new("/tmp/test.log", true).unwrap().with_level(-1).is_err());
+        assert!(LogRotate::new("/tmp/test.log", true).unwrap().with_level(23).is_err());
+
+        let tmp = nix::unistd::mkdtemp("/tmp/pbs-logrotate-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let base = tmp.join("test.log");
+            let mut logrotate = LogRotate::new(&base, true).unwrap().with_level(19)?;
+
+            for content in &["first", "second", "third"] {
+                std::fs::write(&base, content)?;
+                logrotate.do_rotate(CreateOptions::new(), None)?;
+            }
+
+            let compressed = File::open(tmp.join("test.log.3.zst"))?;
+            let data = zstd::stream::decode_all(compressed)?;
+            assert_eq!(data, b"first");
+
+            Ok(())
+        })();
+
+        std::fs::remove_dir_all(&tmp)?;
+        result
+    }
+
+    #[test]
+    fn test_read_full_log_chronological() -> Result<(), Error> {
+        let tmp = nix::unistd::mkdtemp("/tmp/pbs-logrotate-test-XXXXXX")?;
+
+        let result = (|| -> Result<(), Error> {
+            let base = tmp.join("test.log");
+            let mut logrotate = LogRotate::new(&base, true).unwrap();
+
+            for content in &["line1\nline2\n", "line3\n", "line4\nline5\n"] {
+                std::fs::write(&base, content)?;
+                logrotate.do_rotate(CreateOptions::new(), None)?;
+            }
+            std::fs::write(&base, "line6\nline7")?;
+
+            // mixed set: test.log, test.log.1, test.log.2.zst, test.log.3.zst
+            assert!(tmp.join("test.log.1").is_file());
+            assert!(tmp.join("test.log.3.zst").is_file());
+
+            let lines = read_full_log_chronological(&base, true)?
+                .collect::<Result<Vec<String>, Error>>()?;
+            assert_eq!(lines, vec!["line1", "line2", "line3", "line4", "line5", "line6", "line7"]);
+
+            // without 'compress', the iteration stops before the first '.zst' file
+            let lines = read_full_log_chronological(&base, false)?
+                .collect::<Result<Vec<String>, Error>>()?;
+            assert_eq!(lines, vec!["line4", "line5", "line6", "line7"]);
+
+            Ok(())
+        })();
 
-        Some(Box::new(file))
+        std::fs::remove_dir_all(&tmp)?;
+        result
     }
 }
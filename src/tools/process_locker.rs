@@ -26,6 +26,21 @@ pub struct ProcessLocker {
     shared_guard_list: HashMap<u64, i64>, // guard_id => timestamp
 }
 
+/// Lock mode reported by `ProcessLocker::lock_holders`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProcessLockMode {
+    Shared,
+    Exclusive,
+}
+
+/// Process holding a lock, reported by `ProcessLocker::lock_holders`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProcessLockHolder {
+    /// Process ID, or `None` if the OS does not expose the holder
+    pub pid: Option<i32>,
+    pub mode: ProcessLockMode,
+}
+
 /// Lock guard for shared locks
 ///
 /// Release the lock when it goes out of scope.
@@ -170,6 +185,49 @@ impl ProcessLocker {
         result
     }
 
+    /// Report processes currently holding a lock on the lock file
+    ///
+    /// Locks held by our own process are known exactly. For other
+    /// processes, the kernel only reports one conflicting lock, so
+    /// this may miss additional shared lock holders.
+    pub fn lock_holders(locker: Arc<Mutex<Self>>) -> Result<Vec<ProcessLockHolder>, Error> {
+
+        let data = locker.lock().unwrap();
+
+        let mut holders = Vec::new();
+
+        let own_pid = Some(std::process::id() as i32);
+        if data.exclusive {
+            holders.push(ProcessLockHolder { pid: own_pid, mode: ProcessLockMode::Exclusive });
+        } else if data.writers > 0 {
+            holders.push(ProcessLockHolder { pid: own_pid, mode: ProcessLockMode::Shared });
+        }
+
+        // F_GETLK ignores our own locks, so this reports other processes
+        let mut op = libc::flock {
+            l_type: libc::F_WRLCK as i16,
+            l_whence: libc::SEEK_SET as i16,
+            l_start: 0,
+            l_len: 0,
+            l_pid: 0,
+        };
+
+        nix::fcntl::fcntl(data.file.as_raw_fd(), nix::fcntl::FcntlArg::F_GETLK(&mut op))?;
+
+        let mode = match op.l_type as i32 {
+            libc::F_RDLCK => Some(ProcessLockMode::Shared),
+            libc::F_WRLCK => Some(ProcessLockMode::Exclusive),
+            _ => None, // F_UNLCK - no other holder
+        };
+
+        if let Some(mode) = mode {
+            let pid = if op.l_pid > 0 { Some(op.l_pid) } else { None };
+            holders.push(ProcessLockHolder { pid, mode });
+        }
+
+        Ok(holders)
+    }
+
     /// Try to acquire a exclusive lock
     ///
     /// Make sure the we are the only process which has locks for this file (shared or exclusive).
@@ -190,3 +248,55 @@ impl ProcessLocker {
         Ok(ProcessLockExclusiveGuard { locker: locker.clone() })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use anyhow::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_lock_holders() -> Result<(), Error> {
+        let path = std::env::temp_dir()
+            .join(format!("proxmox-backup-process-locker-test-{}", std::process::id()));
+
+        let locker = ProcessLocker::new(&path)?;
+
+        assert!(ProcessLocker::lock_holders(locker.clone())?.is_empty());
+
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+
+        let thread_locker = locker.clone();
+        let handle = std::thread::spawn(move || {
+            let guard = ProcessLocker::try_shared_lock(thread_locker).unwrap();
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            drop(guard);
+        });
+
+        locked_rx.recv()?;
+
+        let holders = ProcessLocker::lock_holders(locker.clone())?;
+        assert_eq!(holders, vec![ProcessLockHolder {
+            pid: Some(std::process::id() as i32),
+            mode: ProcessLockMode::Shared,
+        }]);
+
+        release_tx.send(())?;
+        handle.join().unwrap();
+
+        assert!(ProcessLocker::lock_holders(locker.clone())?.is_empty());
+
+        {
+            let _guard = ProcessLocker::try_exclusive_lock(locker.clone())?;
+            let holders = ProcessLocker::lock_holders(locker.clone())?;
+            assert_eq!(holders.len(), 1);
+            assert_eq!(holders[0].mode, ProcessLockMode::Exclusive);
+        }
+
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+//! Token bucket rate limiter and rate limited streams
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::ready;
+use hyper::client::connect::{Connected, Connection};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+
+/// Token bucket based rate limiter
+pub struct RateLimiter {
+    rate: u64,        // tokens/second
+    bucket_size: u64, // maximum burst size
+    consumed_tokens: u64,
+    last_update: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a new instance, using `rate` as bucket size.
+    pub fn new(rate: u64) -> Self {
+        Self::with_bucket_size(rate, rate)
+    }
+
+    pub fn with_bucket_size(rate: u64, bucket_size: u64) -> Self {
+        Self {
+            rate: rate.max(1),
+            bucket_size,
+            consumed_tokens: 0,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Returns the configured rate (tokens/second)
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    fn refill_bucket(&mut self, current_time: Instant) {
+        let time_diff = match current_time.checked_duration_since(self.last_update) {
+            Some(diff) => diff.as_nanos(),
+            None => return, // time went backwards
+        };
+
+        self.last_update = current_time;
+
+        let allowed_traffic = (time_diff * self.rate as u128) / 1_000_000_000;
+        if allowed_traffic >= self.consumed_tokens as u128 {
+            self.consumed_tokens = 0;
+        } else {
+            self.consumed_tokens -= allowed_traffic as u64;
+        }
+    }
+
+    /// Account `data_len` bytes of traffic and return the time to wait before any further
+    /// traffic is allowed.
+    pub fn register_traffic(&mut self, current_time: Instant, data_len: u64) -> Duration {
+        self.refill_bucket(current_time);
+
+        self.consumed_tokens = self.consumed_tokens.saturating_add(data_len);
+
+        if self.consumed_tokens <= self.bucket_size {
+            return Duration::from_nanos(0);
+        }
+
+        let overflow = (self.consumed_tokens - self.bucket_size) as u128;
+        Duration::from_nanos(((overflow * 1_000_000_000) / self.rate as u128) as u64)
+    }
+}
+
+/// Stream wrapper limiting the read rate using a (shared) `RateLimiter`
+///
+/// Writes are passed through unchanged.
+pub struct RateLimitedStream<S> {
+    read_limiter: Option<Arc<Mutex<RateLimiter>>>,
+    read_delay: Option<Pin<Box<Sleep>>>,
+    stream: S,
+}
+
+impl<S> RateLimitedStream<S> {
+    pub fn new(stream: S, read_limiter: Option<Arc<Mutex<RateLimiter>>>) -> Self {
+        Self {
+            read_limiter,
+            read_delay: None,
+            stream,
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.stream
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for RateLimitedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+
+        if let Some(ref mut delay) = this.read_delay {
+            ready!(delay.as_mut().poll(cx));
+            this.read_delay = None;
+        }
+
+        let filled_before = buf.filled().len();
+        let result = ready!(Pin::new(&mut this.stream).poll_read(cx, buf));
+
+        if let Some(ref limiter) = this.read_limiter {
+            let len = (buf.filled().len() - filled_before) as u64;
+            let delay = limiter.lock().unwrap().register_traffic(Instant::now(), len);
+            if delay > Duration::from_nanos(0) {
+                this.read_delay = Some(Box::pin(tokio::time::sleep(delay)));
+            }
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for RateLimitedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+// we need this for the hyper http client
+impl<S: Connection> Connection for RateLimitedStream<S> {
+    fn connected(&self) -> Connected {
+        self.stream.connected()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_rate_limiter() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::with_bucket_size(1000, 100);
+        limiter.last_update = start;
+
+        // burst up to the bucket size
+        assert_eq!(limiter.register_traffic(start, 100), Duration::from_nanos(0));
+        // then we need to wait
+        assert_eq!(limiter.register_traffic(start, 100), Duration::from_millis(100));
+        // the bucket refills over time
+        let later = start + Duration::from_millis(200);
+        assert_eq!(limiter.register_traffic(later, 0), Duration::from_nanos(0));
+
+        // simulate a transfer and check that the average rate stays below the cap
+        let mut now = later;
+        let mut transferred = 0;
+        while transferred < 10_000 {
+            now += limiter.register_traffic(now, 50);
+            transferred += 50;
+        }
+        // 1000 bytes/second, so at least one millisecond per byte exceeding the burst size
+        let elapsed_ms = now.duration_since(later).as_millis() as u64;
+        assert!(elapsed_ms >= transferred - 100);
+    }
+
+    #[test]
+    fn test_rate_limited_stream() {
+        let data = vec![0u8; 64 * 1024];
+        let limiter = Arc::new(Mutex::new(RateLimiter::with_bucket_size(128 * 1024, 16 * 1024)));
+
+        let elapsed = crate::tools::runtime::main(async move {
+            let start = Instant::now();
+            let mut stream = RateLimitedStream::new(&data[..], Some(limiter));
+            let mut output = Vec::new();
+            stream.read_to_end(&mut output).await.unwrap();
+            assert_eq!(output.len(), data.len());
+            start.elapsed()
+        });
+
+        // (64K - 16K burst) at 128K/s takes at least 375ms
+        assert!(elapsed >= Duration::from_millis(300));
+    }
+}
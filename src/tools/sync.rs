@@ -0,0 +1,264 @@
+//! Synchronization helpers
+
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, format_err, Error};
+use tokio::sync::oneshot;
+
+struct SingleFlightState<T> {
+    running: bool,
+    generation: u64,
+    result: Option<Result<T, String>>,
+}
+
+/// Coalesce concurrent executions of the same operation.
+///
+/// If `run` is called while another thread is already executing the operation, the caller
+/// waits for that execution to finish and gets a copy of its result instead of running the
+/// operation itself. This is useful for reloading configuration files, where many
+/// simultaneous callers would otherwise all read and parse the same file.
+///
+/// Errors are shared as their string representation, since `anyhow::Error` cannot be cloned.
+pub struct SingleFlight<T> {
+    state: Mutex<SingleFlightState<T>>,
+    cond: Condvar,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(SingleFlightState {
+                running: false,
+                generation: 0,
+                result: None,
+            }),
+            cond: Condvar::new(),
+        }
+    }
+
+    /// Run `func`, or wait for and share the result of an already running execution.
+    pub fn run<F>(&self, func: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<T, Error>,
+    {
+        let mut state = self.state.lock().unwrap();
+
+        if state.running {
+            let generation = state.generation;
+            while state.generation == generation {
+                state = self.cond.wait(state).unwrap();
+            }
+            return match state.result {
+                Some(Ok(ref value)) => Ok(value.clone()),
+                Some(Err(ref msg)) => Err(format_err!("{}", msg)),
+                None => Err(format_err!("shared operation did not produce a result")),
+            };
+        }
+
+        state.running = true;
+        drop(state);
+
+        let mut finish = FinishGuard { flight: self, result: None };
+
+        let result = func();
+
+        finish.result = Some(match result {
+            Ok(ref value) => Ok(value.clone()),
+            Err(ref err) => Err(err.to_string()),
+        });
+
+        drop(finish);
+
+        result
+    }
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Publishes the result and wakes up waiters, also if the operation panicked.
+struct FinishGuard<'a, T> {
+    flight: &'a SingleFlight<T>,
+    result: Option<Result<T, String>>,
+}
+
+impl<'a, T> Drop for FinishGuard<'a, T> {
+    fn drop(&mut self) {
+        let mut state = match self.flight.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        state.result = Some(
+            self.result
+                .take()
+                .unwrap_or_else(|| Err("shared operation panicked".to_string())),
+        );
+        state.running = false;
+        state.generation = state.generation.wrapping_add(1);
+        self.flight.cond.notify_all();
+    }
+}
+
+#[derive(Debug)]
+struct PauseState {
+    paused: bool,
+    resume_listeners: Vec<oneshot::Sender<()>>,
+}
+
+/// Signal to temporarily pause an operation.
+///
+/// The operation itself has to check the signal at suitable points (for example between chunks)
+/// using `wait_resumed`.
+#[derive(Debug)]
+pub struct PauseSignal {
+    state: Mutex<PauseState>,
+}
+
+impl PauseSignal {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(PauseState {
+                paused: false,
+                resume_listeners: Vec::new(),
+            }),
+        }
+    }
+
+    /// Set the pause flag. Returns false if already paused.
+    pub fn pause(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        !std::mem::replace(&mut state.paused, true)
+    }
+
+    /// Clear the pause flag and wake up all waiters. Returns false if not paused.
+    pub fn resume(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let was_paused = std::mem::replace(&mut state.paused, false);
+        for listener in state.resume_listeners.drain(..) {
+            let _ = listener.send(()); // ignore errors here
+        }
+        was_paused
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    /// Get a future which resolves once not paused (immediately if not paused at all).
+    pub fn resume_future(&self) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+
+        let mut state = self.state.lock().unwrap();
+        if state.paused {
+            state.resume_listeners.push(tx);
+        } else {
+            let _ = tx.send(());
+        }
+        rx
+    }
+
+    /// Wait until resumed, fails if still paused after `timeout`.
+    pub async fn wait_resumed(&self, timeout: Duration) -> Result<(), Error> {
+        match tokio::time::timeout(timeout, self.resume_future()).await {
+            Ok(_) => Ok(()),
+            Err(_) => bail!("still paused after {} seconds", timeout.as_secs()),
+        }
+    }
+}
+
+impl Default for PauseSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::time::Duration;
+
+    #[test]
+    fn test_single_flight() {
+        const THREADS: usize = 8;
+
+        let flight = Arc::new(SingleFlight::new());
+        let counter = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(THREADS));
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let flight = Arc::clone(&flight);
+                let counter = Arc::clone(&counter);
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    flight.run(|| {
+                        let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                        // keep the operation running until all threads joined
+                        std::thread::sleep(Duration::from_millis(500));
+                        Ok(count)
+                    })
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), 1);
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        // later calls run the operation again
+        let value = flight.run(|| Ok(counter.fetch_add(1, Ordering::SeqCst) + 1)).unwrap();
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn test_pause_signal() {
+        use std::sync::atomic::AtomicBool;
+
+        let signal = Arc::new(PauseSignal::new());
+        let processed = Arc::new(AtomicUsize::new(0));
+        let finished = Arc::new(AtomicBool::new(false));
+
+        crate::tools::runtime::main(async move {
+            assert!(signal.pause());
+            assert!(!signal.pause());
+            assert!(signal.is_paused());
+
+            // mock chunk receive loop, checking the signal between chunks
+            let receiver = {
+                let signal = Arc::clone(&signal);
+                let processed = Arc::clone(&processed);
+                let finished = Arc::clone(&finished);
+                tokio::spawn(async move {
+                    for _ in 0..10 {
+                        signal.wait_resumed(Duration::from_secs(10)).await?;
+                        processed.fetch_add(1, Ordering::SeqCst);
+                    }
+                    finished.store(true, Ordering::SeqCst);
+                    Ok::<(), Error>(())
+                })
+            };
+
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            assert_eq!(processed.load(Ordering::SeqCst), 0);
+            assert!(!finished.load(Ordering::SeqCst));
+
+            assert!(signal.resume());
+            assert!(!signal.resume());
+            receiver.await.unwrap().unwrap();
+            assert_eq!(processed.load(Ordering::SeqCst), 10);
+            assert!(finished.load(Ordering::SeqCst));
+
+            // staying paused too long fails
+            signal.pause();
+            assert!(signal.wait_resumed(Duration::from_millis(50)).await.is_err());
+        });
+    }
+}
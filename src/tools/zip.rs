@@ -14,7 +14,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::SystemTime;
 
-use anyhow::{format_err, Error, Result};
+use anyhow::{bail, format_err, Error, Result};
 use endian_trait::Endian;
 use futures::ready;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
@@ -200,6 +200,14 @@ pub struct ZipEntry {
     compressed_size: u64,
     offset: u64,
     is_file: bool,
+    comment: Vec<u8>,
+}
+
+fn check_comment_len(comment: &[u8]) -> Result<(), Error> {
+    if comment.len() > u16::MAX as usize {
+        bail!("zip comment too long ({} > {} bytes)", comment.len(), u16::MAX);
+    }
+    Ok(())
 }
 
 impl ZipEntry {
@@ -229,9 +237,18 @@ impl ZipEntry {
             compressed_size: 0,
             offset: 0,
             is_file,
+            comment: Vec::new(),
         }
     }
 
+    /// Sets the file comment, which is stored in the central directory
+    pub fn set_comment<C: Into<Vec<u8>>>(&mut self, comment: C) -> Result<(), Error> {
+        let comment = comment.into();
+        check_comment_len(&comment)?;
+        self.comment = comment;
+        Ok(())
+    }
+
     async fn write_local_header<W>(&self, mut buf: &mut W) -> io::Result<usize>
     where
         W: AsyncWrite + Unpin + ?Sized,
@@ -306,7 +323,7 @@ impl ZipEntry {
         let filename_len = filename.len();
         let header_size = size_of::<CentralDirectoryFileHeader>();
         let zip_field_size = size_of::<Zip64FieldWithOffset>();
-        let mut size: usize = header_size + filename_len;
+        let mut size: usize = header_size + filename_len + self.comment.len();
 
         let (date, time) = epoch_to_dos(self.mtime);
 
@@ -341,7 +358,7 @@ impl ZipEntry {
                 uncompressed_size,
                 filename_len: filename_len as u16,
                 extra_field_len: if need_zip64 { zip_field_size as u16 } else { 0 },
-                comment_len: 0,
+                comment_len: self.comment.len() as u16,
                 start_disk: 0,
                 internal_flags: 0,
                 external_flags: (self.mode as u32) << 16 | (!self.is_file as u32) << 4,
@@ -367,6 +384,8 @@ impl ZipEntry {
             .await?;
         }
 
+        buf.write_all(&self.comment).await?;
+
         Ok(size)
     }
 }
@@ -447,6 +466,7 @@ where
     byte_count: usize,
     files: Vec<ZipEntry>,
     target: Option<W>,
+    comment: Vec<u8>,
 }
 
 impl<W: AsyncWrite + Unpin> ZipEncoder<W> {
@@ -455,9 +475,23 @@ impl<W: AsyncWrite + Unpin> ZipEncoder<W> {
             byte_count: 0,
             files: Vec::new(),
             target: Some(target),
+            comment: Vec::new(),
         }
     }
 
+    /// Sets the archive comment, written at the end of the archive by 'finish'
+    pub fn set_comment<C: Into<Vec<u8>>>(&mut self, comment: C) -> Result<(), Error> {
+        let comment = comment.into();
+        check_comment_len(&comment)?;
+        self.comment = comment;
+        Ok(())
+    }
+
+    /// Adds an entry, streaming and compressing 'content' until EOF
+    ///
+    /// The size of the content does not need to be known in advance: CRC and sizes are
+    /// written after the data in a data descriptor (general purpose flag bit 3), and
+    /// the central directory written by 'finish' contains the final values.
     pub async fn add_entry<R: AsyncRead + Unpin>(
         &mut self,
         mut entry: ZipEntry,
@@ -558,11 +592,13 @@ impl<W: AsyncWrite + Unpin> ZipEncoder<W> {
                 total_record_count: count,
                 directory_size,
                 directory_offset,
-                comment_len: 0,
+                comment_len: self.comment.len() as u16,
             },
         )
         .await?;
 
+        target.write_all(&self.comment).await?;
+
         self.target = Some(target);
 
         Ok(())
@@ -669,3 +705,128 @@ where
 
     encoder.finish().await
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read_u16(data: &[u8], pos: usize) -> usize {
+        u16::from_le_bytes([data[pos], data[pos + 1]]) as usize
+    }
+
+    fn read_u32(data: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+    }
+
+    // returns the data in small pieces, so the total size is only known at EOF
+    struct PieceReader {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    impl AsyncRead for PieceReader {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<Result<(), io::Error>> {
+            let this = self.get_mut();
+            let end = (this.pos + 7).min(this.data.len());
+            let len = (end - this.pos).min(buf.remaining());
+            buf.put_slice(&this.data[this.pos..(this.pos + len)]);
+            this.pos += len;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_zip_streaming_entry() -> Result<(), Error> {
+        use std::io::Read;
+
+        let content: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let mut data = Vec::new();
+
+        crate::tools::runtime::main(async {
+            let mut zip = ZipEncoder::new(&mut data);
+            let reader = PieceReader { data: content.clone(), pos: 0 };
+            zip.add_entry(ZipEntry::new("stream.bin", 0, 0o100644, true), Some(reader)).await?;
+            zip.finish().await
+        })?;
+
+        // local header: data descriptor flag set, crc and sizes unknown
+        assert_eq!(read_u32(&data, 0), LOCAL_FH_SIG);
+        assert_eq!(read_u16(&data, 6) & (1 << 3), 1 << 3);
+        assert_eq!(read_u32(&data, 14), 0);
+        let start = size_of::<LocalFileHeader>() + read_u16(&data, 26) + read_u16(&data, 28);
+
+        // central directory contains the real values
+        let sig = CENTRAL_DIRECTORY_FH_SIG.to_le_bytes();
+        let cd = data.windows(4).rposition(|w| w == sig).expect("no central directory");
+        let crc32 = read_u32(&data, cd + 16);
+        let compressed_size = read_u32(&data, cd + 20) as usize;
+        assert_eq!(read_u32(&data, cd + 24) as usize, content.len());
+        assert_eq!(read_u32(&data, cd + 42), 0); // offset of the local header
+
+        // data descriptor after the compressed data
+        let footer = start + compressed_size;
+        assert_eq!(read_u32(&data, footer), LOCAL_FF_SIG);
+        assert_eq!(read_u32(&data, footer + 4), crc32);
+
+        // extract
+        let mut decoder = flate2::read::DeflateDecoder::new(&data[start..footer]);
+        let mut extracted = Vec::new();
+        decoder.read_to_end(&mut extracted)?;
+        assert_eq!(extracted, content);
+
+        let mut hasher = Hasher::new();
+        hasher.update(&extracted);
+        assert_eq!(hasher.finalize(), crc32);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_comments() -> Result<(), Error> {
+        let mut data = Vec::new();
+
+        crate::tools::runtime::main(async {
+            let mut zip = ZipEncoder::new(&mut data);
+            zip.set_comment("host/elsa/2020-06-26T13:56:05Z")?;
+
+            let mut entry = ZipEntry::new("foo.txt", 0, 0o100644, true);
+            entry.set_comment("root.pxar.didx")?;
+            zip.add_entry(entry, Some(&b"some file content"[..])).await?;
+
+            zip.add_entry::<&[u8]>(ZipEntry::new("dir", 0, 0o40755, false), None).await?;
+
+            zip.finish().await
+        })?;
+
+        // central directory file header of the first entry
+        let sig = CENTRAL_DIRECTORY_FH_SIG.to_le_bytes();
+        let pos = data.windows(4).position(|w| w == sig).expect("no central directory");
+        let filename_len = read_u16(&data, pos + 28);
+        let extra_len = read_u16(&data, pos + 30);
+        let comment_len = read_u16(&data, pos + 32);
+        let start = pos + size_of::<CentralDirectoryFileHeader>() + filename_len + extra_len;
+        assert_eq!(&data[pos + 46..pos + 46 + filename_len], b"foo.txt");
+        assert_eq!(&data[start..start + comment_len], b"root.pxar.didx");
+
+        // the second entry has no comment
+        let pos = start + comment_len;
+        assert_eq!(&data[pos..pos + 4], &sig);
+        assert_eq!(read_u16(&data, pos + 32), 0);
+
+        // archive comment at the end of the 'end of central directory' record
+        let comment = b"host/elsa/2020-06-26T13:56:05Z";
+        let eocd = data.len() - comment.len() - size_of::<EndOfCentralDir>();
+        assert_eq!(&data[eocd..eocd + 4], &END_OF_CENTRAL_DIR.to_le_bytes());
+        assert_eq!(read_u16(&data, eocd + 20), comment.len());
+        assert!(data.ends_with(comment));
+
+        let mut entry = ZipEntry::new("bar", 0, 0o100644, true);
+        assert!(entry.set_comment(vec![b'x'; 65536]).is_err());
+
+        Ok(())
+    }
+}